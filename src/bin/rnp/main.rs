@@ -1,8 +1,7 @@
 use futures_intrusive::sync::ManualResetEvent;
-use rnp::{PingRunnerCore, RNP_ABOUT, RNP_AUTHOR, RNP_NAME, RNP_QUIET_LEVEL_NO_OUTPUT};
+use rnp::{PingRunnerCore, RnpThroughputRunnerConfig, ThroughputRunner, RNP_ABOUT, RNP_AUTHOR, RNP_NAME, RNP_QUIET_LEVEL_NO_OUTPUT};
 use rnp_cli_options::RnpCliOptions;
 use std::sync::Arc;
-use structopt::StructOpt;
 use tokio::runtime::Runtime;
 
 mod rnp_cli_options;
@@ -11,19 +10,41 @@ mod rnp_cli_options;
 fn main() {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
 
-    let mut opts = RnpCliOptions::from_args();
+    let mut opts = match RnpCliOptions::from_args_with_config_file() {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
     if opts.output_options.quiet_level < RNP_QUIET_LEVEL_NO_OUTPUT {
         println!("{} - {} - {}\n", RNP_NAME, RNP_AUTHOR, RNP_ABOUT);
     }
 
-    opts.prepare_to_use();
+    if let Err(e) = opts.prepare_to_use() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    if let Some(throughput_config) = opts.to_throughput_runner_config() {
+        run_throughput_test(throughput_config);
+        return;
+    }
+
     let runner_config = opts.to_ping_runner_config();
+    let quiet_level = opts.output_options.quiet_level;
 
     let rt = Runtime::new().unwrap();
     let result = rt.block_on(async {
         let stop_event = Arc::new(ManualResetEvent::new(false));
         let rnp_exit_failure_reason = runner_config.result_processor_config.exit_failure_reason.clone();
-        let mut runner = PingRunnerCore::new(runner_config, stop_event.clone());
+        let mut runner = match PingRunnerCore::new(runner_config, stop_event.clone()) {
+            Ok(runner) => runner,
+            Err(e) => {
+                eprintln!("{}", e);
+                return Err(e.to_string());
+            }
+        };
 
         ctrlc::set_handler(move || {
             tracing::debug!("Ctrl+C received. Stopping all ping workers.");
@@ -36,6 +57,16 @@ fn main() {
         runner.start_running_normal_pings();
         runner.join().await;
 
+        if let Some(stop_reason) = runner.stop_reason() {
+            if quiet_level < RNP_QUIET_LEVEL_NO_OUTPUT {
+                println!("\nStopped: {}.", stop_reason);
+            }
+
+            if stop_reason.is_failure() {
+                return Err(stop_reason.to_string());
+            }
+        }
+
         if let Some(rnp_exit_failure_reason) = rnp_exit_failure_reason {
             if rnp_exit_failure_reason.lock().unwrap().is_some() {
                 return Err("Ping failed!".to_string());
@@ -49,3 +80,29 @@ fn main() {
         std::process::exit(1);
     }
 }
+
+#[cfg(not(tarpaulin_include))]
+fn run_throughput_test(config: RnpThroughputRunnerConfig) {
+    let rt = Runtime::new().unwrap();
+    let result = rt.block_on(async {
+        let stop_event = Arc::new(ManualResetEvent::new(false));
+        let runner = ThroughputRunner::new(config, stop_event.clone());
+
+        ctrlc::set_handler(move || {
+            tracing::debug!("Ctrl+C received. Stopping throughput test.");
+            stop_event.set();
+        })
+        .expect("Error setting Ctrl-C handler");
+
+        return runner.run().await;
+    });
+
+    match result {
+        Ok(summary) if summary.failed_stream_count == 0 => (),
+        Ok(_) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
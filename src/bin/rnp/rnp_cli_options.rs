@@ -1,9 +1,11 @@
-use rand::Rng;
 use rnp::{
-    parse_ping_target, PingClientConfig, PingResultProcessorCommonConfig, PingResultProcessorConfig, PingWorkerConfig, PingWorkerSchedulerConfig,
-    PortRangeList, RnpPingRunnerConfig, RnpSupportedProtocol,
+    load_ping_runner_config_from_file, load_ping_targets_from_file, parse_http_header, parse_http_path, parse_ping_target_with_host_name,
+    parse_protocol_option, PingArrivalDistribution, PingClientConfig, PingClientRegistry, PingRate, PingResultProcessorCommonConfig,
+    PingResultProcessorConfig, PingTarget, PingWorkerConfig, PingWorkerSchedulerConfig, PortRangeList, RnpPingRunnerConfig, RnpSupportedProtocol,
+    RnpThroughputRunnerConfig, ThroughputDirection,
 };
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::ffi::OsString;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 
 use std::sync::{Arc, Mutex};
@@ -24,6 +26,12 @@ pub struct RnpCliOptions {
 
     #[structopt(flatten)]
     pub quic_options: RnpCliQuicPingOptions,
+
+    #[structopt(flatten)]
+    pub http_options: RnpCliHttpPingOptions,
+
+    #[structopt(flatten)]
+    pub throughput_options: RnpCliThroughputOptions,
 }
 
 #[derive(Debug, StructOpt, PartialEq)]
@@ -31,8 +39,41 @@ pub struct RnpCliCommonOptions {
     #[structopt(short = "m", long = "mode", default_value = "TCP", help = "Specify protocol to use.")]
     pub protocol: RnpSupportedProtocol,
 
-    #[structopt(parse(try_from_str = parse_ping_target), help = "Target endpoint. For IPv6, please use [] to wrap the address, such as [::1]:80.")]
-    pub target: SocketAddr,
+    #[structopt(
+        long = "protocol-option",
+        parse(try_from_str = parse_protocol_option),
+        number_of_values = 1,
+        help = "Option passed to the ping client of external protocols. Can be specified multiple times. Example: community=public."
+    )]
+    pub protocol_options: Vec<(String, String)>,
+
+    #[structopt(
+        parse(try_from_str = parse_ping_target_with_host_name),
+        required_unless_one = &["targets-file", "config-file"],
+        help = "Target endpoints. For IPv6, please use [] to wrap the address, such as [::1]:80.\nHost name is also supported, such as example.com:443. Port is 80 when not specified."
+    )]
+    pub targets: Vec<PingTarget>,
+
+    #[structopt(
+        long = "targets-file",
+        parse(from_os_str),
+        help = "Load more targets from a file, one target per line. Empty lines and lines starting with '#' are ignored."
+    )]
+    pub targets_file: Option<PathBuf>,
+
+    #[structopt(
+        long = "config",
+        parse(from_os_str),
+        help = "Load options from a TOML or YAML (.yaml/.yml) config file. Options specified in command line override the ones in the file."
+    )]
+    pub config_file: Option<PathBuf>,
+
+    #[structopt(
+        long = "profile",
+        requires = "config-file",
+        help = "Named profile in the config file, which is merged on top of the base options in the file. Example: lb-drain for [profiles.lb-drain]."
+    )]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, StructOpt, PartialEq)]
@@ -47,8 +88,14 @@ pub struct RnpCliPingCommonOptions {
     )]
     pub source_ports: Option<PortRangeList>,
 
-    #[structopt(short = "n", long = "count", default_value = "4", help = "Ping count.")]
-    pub ping_count: u32,
+    #[structopt(
+        long = "resolve-every-ping",
+        help = "Resolve the target host name before every ping instead of only once at start. Useful for catching DNS-driven failovers.\nIgnored when target is an IP address."
+    )]
+    pub resolve_on_every_ping: bool,
+
+    #[structopt(short = "n", long = "count", help = "Ping count. [default: 4]")]
+    pub ping_count: Option<u32>,
 
     #[structopt(short = "t", help = "Ping until stopped.")]
     pub ping_until_stopped: bool,
@@ -87,11 +134,53 @@ pub struct RnpCliPingCommonOptions {
     )]
     pub disconnect_timeout_in_ms: u64,
 
+    #[structopt(
+        long = "one-way-latency",
+        alias = "owl",
+        help = "Measure forward and reverse delay separately. Only available in TCP mode, and the target needs to be rnp_server.\nThe clock offset between both sides is estimated from the recent pings. [alias: --owl]"
+    )]
+    pub measure_one_way_latency: bool,
+
+    #[structopt(
+        long = "persistent",
+        help = "Keep the connection open and ping with small timestamped requests on it. Only available in TCP mode, and the target needs to be rnp_server.\nEach worker keeps its own connection, and reconnects are reported as separate results."
+    )]
+    pub use_persistent_connection: bool,
+
     #[structopt(short = "p", long = "parallel", default_value = "1", help = "Count of pings running in parallel.")]
     pub parallel_ping_count: u32,
 
     #[structopt(long, help = "Exit as soon as a ping failed and return a non-zero error code.")]
     pub exit_on_fail: bool,
+
+    #[structopt(
+        long,
+        parse(try_from_str = humantime::parse_duration),
+        help = "Stop pinging after the specified duration, such as 30s, 15m or 1h30m. Warmup pings are not counted.\nPings until stopped by the duration, unless -n is also specified."
+    )]
+    pub duration: Option<Duration>,
+
+    #[structopt(long = "stop-on-failures", help = "Stop pinging after N consecutive pings failed and return a non-zero error code.")]
+    pub stop_on_consecutive_failures: Option<u32>,
+
+    #[structopt(
+        long = "stop-on-recovery",
+        help = "Stop pinging after N consecutive pings succeeded following a failure, which means the target has recovered."
+    )]
+    pub stop_on_recovery: Option<u32>,
+
+    #[structopt(
+        long,
+        help = "Issue pings at a fixed rate regardless of how long each ping takes, instead of sleeping for the interval after each ping.\nFormat: count/unit, where unit is s, m or h. Example: 500/s. Parallel ping count limits the pings in flight."
+    )]
+    pub rate: Option<PingRate>,
+
+    #[structopt(
+        long = "arrival",
+        default_value = "constant",
+        help = "Distribution of the time between pings when rate is set. Can be constant, poisson or jitter (evenly picked between 50% and 150% of the interval)."
+    )]
+    pub arrival_distribution: PingArrivalDistribution,
 }
 
 #[derive(Debug, StructOpt, PartialEq)]
@@ -112,12 +201,31 @@ pub struct RnpCliOutputOptions {
     #[structopt(short = "o", long = "log-text", parse(from_os_str), help = "Log ping results to a text file.")]
     pub text_log_path: Option<PathBuf>,
 
+    #[structopt(
+        long = "report-html",
+        parse(from_os_str),
+        help = "Write a self-contained html report with charts of round trip time, latency buckets, failures and result scatter map after ping is done."
+    )]
+    pub html_report_path: Option<PathBuf>,
+
+    #[structopt(
+        long = "metrics-address",
+        help = "Serve live ping metrics for Prometheus to scrape on http://<address>/metrics. Example: 127.0.0.1:9100."
+    )]
+    pub metrics_address: Option<SocketAddr>,
+
     #[structopt(short = "r", long, help = "Show ping result scatter map after ping is done.")]
     pub show_result_scatter: bool,
 
     #[structopt(short = "l", long, help = "Show latency (round trip time) scatter map after ping is done.")]
     pub show_latency_scatter: bool,
 
+    #[structopt(
+        long = "dashboard",
+        help = "Show a live dashboard with success rate, recent latency, result scatter map and errors, refreshed every second in place.\nIt needs a terminal supporting ANSI escape codes. When output is redirected, a status line is written every 10 seconds instead."
+    )]
+    pub show_dashboard: bool,
+
     #[structopt(
         short = "b",
         long = "latency-buckets",
@@ -125,11 +233,22 @@ pub struct RnpCliOutputOptions {
         help = "If set, bucket ping latency (round trip time) after ping is done. Set to 0.0 to use the default one: [0.1,0.5,1.0,10.0,50.0,100.0,300.0,500.0]"
     )]
     pub latency_buckets: Option<Vec<f64>>,
+
+    #[structopt(
+        long = "latency-percentiles",
+        use_delimiter = true,
+        default_value = "50,90,99,99.9",
+        help = "Latency (round trip time) percentiles shown in the summary after ping is done, together with standard deviation and jitter."
+    )]
+    pub latency_percentiles: Vec<f64>,
 }
 
 #[derive(Debug, StructOpt, PartialEq)]
 pub struct RnpCliQuicPingOptions {
-    #[structopt(long, help = "Specify the server name in the QUIC pings. Example: localhost.")]
+    #[structopt(
+        long,
+        help = "Specify the server name in the QUIC, TLS or HTTPS pings. In TLS and HTTP pings, it is used as SNI and Host header. Example: localhost."
+    )]
     pub server_name: Option<String>,
 
     #[structopt(
@@ -140,10 +259,9 @@ pub struct RnpCliQuicPingOptions {
 
     #[structopt(
         long = "alpn",
-        default_value = "h3-29",
-        help = "ALPN protocol used in QUIC. Specify \"none\" to disable ALPN.\nIt is usually h3-<ver> for http/3 or hq-<ver> for specific version of QUIC.\nFor latest IDs, please check here: https://www.iana.org/assignments/tls-extensiontype-values/tls-extensiontype-values.xhtml#alpn-protocol-ids"
+        help = "ALPN protocol used in QUIC, TLS or HTTPS. Specify \"none\" to disable ALPN.\nDefaults to h3-29 in QUIC, http/1.1 in HTTPS and no ALPN in TLS.\nIt is usually h3-<ver> for http/3 or hq-<ver> for specific version of QUIC, and h2 or http/1.1 for TLS.\nFor latest IDs, please check here: https://www.iana.org/assignments/tls-extensiontype-values/tls-extensiontype-values.xhtml#alpn-protocol-ids"
     )]
-    pub alpn_protocol: String,
+    pub alpn_protocol: Option<String>,
 
     #[structopt(
         long,
@@ -152,24 +270,274 @@ pub struct RnpCliQuicPingOptions {
     pub use_timer_rtt: bool,
 }
 
+#[derive(Debug, StructOpt, PartialEq)]
+pub struct RnpCliHttpPingOptions {
+    #[structopt(long = "http-method", default_value = "GET", help = "HTTP method used in HTTP or HTTPS pings.")]
+    pub http_method: String,
+
+    #[structopt(
+        long = "http-path",
+        default_value = "/",
+        parse(try_from_str = parse_http_path),
+        help = "HTTP request path used in HTTP or HTTPS pings. Example: /health."
+    )]
+    pub http_path: String,
+
+    #[structopt(
+        long = "http-header",
+        parse(try_from_str = parse_http_header),
+        number_of_values = 1,
+        help = "Extra HTTP header sent in HTTP or HTTPS pings. Can be specified multiple times. Example: \"Accept: text/html\"."
+    )]
+    pub http_headers: Vec<(String, String)>,
+
+    #[structopt(
+        long = "http-expect-status",
+        help = "Expected HTTP status code. Other status codes will be reported as app handshake failure.\nIf not specified, all status codes below 400 are expected."
+    )]
+    pub http_expected_status: Option<u16>,
+
+    #[structopt(
+        long = "http-body-regex",
+        help = "Regex that the HTTP response body should match. Otherwise, it will be reported as app handshake failure."
+    )]
+    pub http_body_regex: Option<String>,
+}
+
+#[derive(Debug, StructOpt, PartialEq)]
+pub struct RnpCliThroughputOptions {
+    #[structopt(
+        long = "throughput",
+        help = "Run throughput test instead of pinging. Can be upload, download or bidirectional.\nOnly available in TCP mode with a single target, and the target needs to be rnp_server."
+    )]
+    pub throughput_direction: Option<ThroughputDirection>,
+
+    #[structopt(
+        long = "throughput-duration",
+        default_value = "10s",
+        parse(try_from_str = humantime::parse_duration),
+        help = "Duration of the throughput test, such as 10s or 1m."
+    )]
+    pub throughput_duration: Duration,
+
+    #[structopt(long = "throughput-streams", default_value = "1", help = "Count of TCP streams running in parallel in the throughput test.")]
+    pub throughput_stream_count: u32,
+
+    #[structopt(
+        long = "throughput-report-interval",
+        default_value = "1000",
+        help = "Report the throughput of the last interval every specified milliseconds in the throughput test."
+    )]
+    pub throughput_report_interval_in_ms: u64,
+}
+
 impl RnpCliOptions {
-    pub fn prepare_to_use(&mut self) {
-        self.ping_common_options.prepare_to_use(&self.common_options.target);
+    pub fn from_args_with_config_file() -> Result<RnpCliOptions, String> {
+        return RnpCliOptions::from_iter_with_config_file(std::env::args_os());
+    }
+
+    pub fn from_iter_with_config_file<I>(args: I) -> Result<RnpCliOptions, String>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString> + Clone,
+    {
+        let matches = RnpCliOptions::clap().get_matches_from(args);
+        let mut opts = RnpCliOptions::from_clap(&matches);
+
+        if let Some(config_file) = &opts.common_options.config_file {
+            let config = load_ping_runner_config_from_file(config_file, opts.common_options.profile.as_deref())?;
+            opts.apply_config_file(&config, |arg_name| matches.occurrences_of(arg_name) > 0);
+        }
+
+        return Ok(opts);
+    }
+
+    // Options from config file only fill the ones that are not specified in command line, so command line always wins. After this,
+    // the options go through the same normalization in prepare_to_use as the command line ones.
+    fn apply_config_file(&mut self, config: &RnpPingRunnerConfig, is_specified_in_command_line: impl Fn(&str) -> bool) {
+        let worker_config = &config.worker_config;
+        let client_config = &worker_config.ping_client_config;
+        let scheduler_config = &config.worker_scheduler_config;
+        let processor_config = &config.result_processor_config;
+
+        let common_options = &mut self.common_options;
+        if !is_specified_in_command_line("protocol") {
+            common_options.protocol = worker_config.protocol.clone();
+        }
+        if !is_specified_in_command_line("targets") {
+            common_options.targets = worker_config.targets.clone();
+        }
+        if !is_specified_in_command_line("protocol-options") {
+            common_options.protocol_options = client_config.protocol_options.clone().into_iter().collect();
+        }
+
+        let ping_common_options = &mut self.ping_common_options;
+        if !is_specified_in_command_line("source-ip") {
+            ping_common_options.source_ip = worker_config.source_ip;
+        }
+        if !is_specified_in_command_line("source-ports") && !scheduler_config.source_ports.ranges.is_empty() {
+            ping_common_options.source_ports = Some(scheduler_config.source_ports.clone());
+        }
+        if !is_specified_in_command_line("resolve-on-every-ping") {
+            ping_common_options.resolve_on_every_ping = worker_config.resolve_on_every_ping;
+        }
+        if !is_specified_in_command_line("ping-count") {
+            ping_common_options.ping_count = scheduler_config.ping_count;
 
-        if let Some(latency_buckets) = &mut self.output_options.latency_buckets {
-            tracing::debug!("Latency bucket set to 0. Use default one.");
-            if latency_buckets.len() == 0 || (latency_buckets.len() == 1 && latency_buckets[0] == 0.0) {
-                *latency_buckets = vec![0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 50.0, 100.0, 300.0, 500.0];
+            // Ping count in command line also overrides pinging until stopped in the file.
+            if !is_specified_in_command_line("ping-until-stopped") {
+                ping_common_options.ping_until_stopped = scheduler_config.ping_until_stopped;
             }
         }
+        if !is_specified_in_command_line("warmup-count") {
+            ping_common_options.warmup_count = scheduler_config.warmup_count;
+        }
+        if !is_specified_in_command_line("wait-timeout-in-ms") {
+            ping_common_options.wait_timeout_in_ms = client_config.wait_timeout.as_millis() as u32;
+        }
+        if !is_specified_in_command_line("ping-interval-in-ms") {
+            ping_common_options.ping_interval_in_ms = worker_config.ping_interval.as_millis() as u32;
+        }
+        if !is_specified_in_command_line("time-to-live") {
+            ping_common_options.time_to_live = client_config.time_to_live;
+        }
+        if !is_specified_in_command_line("check-disconnect") {
+            ping_common_options.check_disconnect = client_config.check_disconnect;
+        }
+        if !is_specified_in_command_line("wait-before-disconnect-in-ms") {
+            ping_common_options.wait_before_disconnect_in_ms = client_config.wait_before_disconnect.as_millis() as u64;
+        }
+        if !is_specified_in_command_line("disconnect-timeout-in-ms") {
+            ping_common_options.disconnect_timeout_in_ms = client_config.disconnect_timeout.as_millis() as u64;
+        }
+        if !is_specified_in_command_line("measure-one-way-latency") {
+            ping_common_options.measure_one_way_latency = client_config.measure_one_way_latency;
+        }
+        if !is_specified_in_command_line("use-persistent-connection") {
+            ping_common_options.use_persistent_connection = client_config.use_persistent_connection;
+        }
+        if !is_specified_in_command_line("parallel-ping-count") {
+            ping_common_options.parallel_ping_count = scheduler_config.parallel_ping_count;
+        }
+        if !is_specified_in_command_line("exit-on-fail") {
+            ping_common_options.exit_on_fail = processor_config.exit_on_fail;
+        }
+        if !is_specified_in_command_line("duration") {
+            ping_common_options.duration = scheduler_config.duration;
+        }
+        if !is_specified_in_command_line("stop-on-consecutive-failures") {
+            ping_common_options.stop_on_consecutive_failures = scheduler_config.stop_on_consecutive_failures;
+        }
+        if !is_specified_in_command_line("stop-on-recovery") {
+            ping_common_options.stop_on_recovery = scheduler_config.stop_on_recovery;
+        }
+        if !is_specified_in_command_line("rate") {
+            ping_common_options.rate = scheduler_config.rate;
+        }
+        if !is_specified_in_command_line("arrival-distribution") {
+            ping_common_options.arrival_distribution = scheduler_config.arrival_distribution;
+        }
+
+        let output_options = &mut self.output_options;
+        if !is_specified_in_command_line("quiet-level") {
+            output_options.quiet_level = processor_config.common_config.quiet_level;
+        }
+        if !is_specified_in_command_line("csv-log-path") {
+            output_options.csv_log_path = processor_config.csv_log_path.clone();
+        }
+        if !is_specified_in_command_line("json-log-path") {
+            output_options.json_log_path = processor_config.json_log_path.clone();
+        }
+        if !is_specified_in_command_line("text-log-path") {
+            output_options.text_log_path = processor_config.text_log_path.clone();
+        }
+        if !is_specified_in_command_line("html-report-path") {
+            output_options.html_report_path = processor_config.html_report_path.clone();
+        }
+        if !is_specified_in_command_line("metrics-address") {
+            output_options.metrics_address = processor_config.metrics_address;
+        }
+        if !is_specified_in_command_line("show-result-scatter") {
+            output_options.show_result_scatter = processor_config.show_result_scatter;
+        }
+        if !is_specified_in_command_line("show-latency-scatter") {
+            output_options.show_latency_scatter = processor_config.show_latency_scatter;
+        }
+        if !is_specified_in_command_line("show-dashboard") {
+            output_options.show_dashboard = processor_config.show_dashboard;
+        }
+        if !is_specified_in_command_line("latency-buckets") {
+            output_options.latency_buckets = processor_config.latency_buckets.clone();
+        }
+        if let Some(latency_percentiles) = &processor_config.latency_percentiles {
+            if !is_specified_in_command_line("latency-percentiles") {
+                output_options.latency_percentiles = latency_percentiles.clone();
+            }
+        }
+
+        let quic_options = &mut self.quic_options;
+        if !is_specified_in_command_line("server-name") {
+            quic_options.server_name = client_config.server_name.clone();
+        }
+        if !is_specified_in_command_line("log-tls-key") {
+            quic_options.log_tls_key = client_config.log_tls_key;
+        }
+        if !is_specified_in_command_line("alpn-protocol") {
+            quic_options.alpn_protocol = client_config.alpn_protocol.clone();
+        }
+        if !is_specified_in_command_line("use-timer-rtt") {
+            quic_options.use_timer_rtt = client_config.use_timer_rtt;
+        }
+
+        let http_options = &mut self.http_options;
+        if !is_specified_in_command_line("http-method") {
+            http_options.http_method = client_config.http_method.clone();
+        }
+        if !is_specified_in_command_line("http-path") {
+            http_options.http_path = client_config.http_path.clone();
+        }
+        if !is_specified_in_command_line("http-headers") {
+            http_options.http_headers = client_config.http_headers.clone();
+        }
+        if !is_specified_in_command_line("http-expected-status") {
+            http_options.http_expected_status = client_config.http_expected_status;
+        }
+        if !is_specified_in_command_line("http-body-regex") {
+            http_options.http_body_regex = client_config.http_body_regex.clone();
+        }
+    }
+
+    pub fn prepare_to_use(&mut self) -> Result<(), String> {
+        if let Some(targets_file) = &self.common_options.targets_file {
+            let mut targets = load_ping_targets_from_file(targets_file)?;
+            self.common_options.targets.append(&mut targets);
+        }
+
+        // The options are normalized and validated in the same way as the library, then the normalized values are written back, so the
+        // options we show and the config we use for pinging are always the same.
+        self.validate_throughput_options()?;
+
+        let mut config = self.to_ping_runner_config();
+        config.normalize();
+        config.validate().map_err(|e| e.to_string())?;
+
+        self.ping_common_options.source_ip = config.worker_config.source_ip;
+        self.ping_common_options.source_ports = Some(config.worker_scheduler_config.source_ports);
+        self.ping_common_options.ping_count = config.worker_scheduler_config.ping_count;
+        self.ping_common_options.ping_until_stopped = config.worker_scheduler_config.ping_until_stopped;
+        self.ping_common_options.parallel_ping_count = config.worker_scheduler_config.parallel_ping_count;
+        self.output_options.latency_buckets = config.result_processor_config.latency_buckets;
+
+        return Ok(());
     }
 
     pub fn to_ping_runner_config(&self) -> RnpPingRunnerConfig {
         let mut config = RnpPingRunnerConfig {
             worker_config: PingWorkerConfig {
                 protocol: self.common_options.protocol.clone(),
-                target: self.common_options.target,
+                targets: self.common_options.targets.clone(),
                 source_ip: self.ping_common_options.source_ip,
+                resolve_on_every_ping: self.ping_common_options.resolve_on_every_ping,
                 ping_interval: Duration::from_millis(self.ping_common_options.ping_interval_in_ms.into()),
                 ping_client_config: PingClientConfig {
                     wait_timeout: Duration::from_millis(self.ping_common_options.wait_timeout_in_ms.into()),
@@ -179,19 +547,29 @@ impl RnpCliOptions {
                     disconnect_timeout: Duration::from_millis(self.ping_common_options.disconnect_timeout_in_ms),
                     server_name: self.quic_options.server_name.as_ref().and_then(|s| Some(s.to_string())),
                     log_tls_key: self.quic_options.log_tls_key,
-                    alpn_protocol: if self.quic_options.alpn_protocol.to_uppercase() != String::from("NONE") {
-                        Some(self.quic_options.alpn_protocol.clone())
-                    } else {
-                        None
-                    },
+                    alpn_protocol: self.alpn_protocol(),
                     use_timer_rtt: self.quic_options.use_timer_rtt,
+                    http_method: self.http_options.http_method.clone(),
+                    http_path: self.http_options.http_path.clone(),
+                    http_headers: self.http_options.http_headers.clone(),
+                    http_expected_status: self.http_options.http_expected_status,
+                    http_body_regex: self.http_options.http_body_regex.clone(),
+                    measure_one_way_latency: self.ping_common_options.measure_one_way_latency,
+                    use_persistent_connection: self.ping_common_options.use_persistent_connection,
+                    protocol_options: self.common_options.protocol_options.iter().cloned().collect(),
                 },
             },
             worker_scheduler_config: PingWorkerSchedulerConfig {
-                source_ports: self.ping_common_options.source_ports.as_ref().unwrap().clone(),
-                ping_count: None,
+                source_ports: self.ping_common_options.source_ports.clone().unwrap_or(PortRangeList { ranges: vec![] }),
+                ping_count: self.ping_common_options.ping_count,
+                ping_until_stopped: self.ping_common_options.ping_until_stopped,
                 warmup_count: self.ping_common_options.warmup_count,
                 parallel_ping_count: self.ping_common_options.parallel_ping_count,
+                duration: self.ping_common_options.duration,
+                stop_on_consecutive_failures: self.ping_common_options.stop_on_consecutive_failures,
+                stop_on_recovery: self.ping_common_options.stop_on_recovery,
+                rate: self.ping_common_options.rate,
+                arrival_distribution: self.ping_common_options.arrival_distribution,
             },
             result_processor_config: PingResultProcessorConfig {
                 common_config: PingResultProcessorCommonConfig { quiet_level: self.output_options.quiet_level },
@@ -200,57 +578,69 @@ impl RnpCliOptions {
                 csv_log_path: self.output_options.csv_log_path.clone(),
                 json_log_path: self.output_options.json_log_path.clone(),
                 text_log_path: self.output_options.text_log_path.clone(),
+                html_report_path: self.output_options.html_report_path.clone(),
+                metrics_address: self.output_options.metrics_address,
                 show_result_scatter: self.output_options.show_result_scatter,
                 show_latency_scatter: self.output_options.show_latency_scatter,
+                show_dashboard: self.output_options.show_dashboard,
                 latency_buckets: self.output_options.latency_buckets.as_ref().and_then(|buckets| Some(buckets.clone())),
+                latency_percentiles: Some(self.output_options.latency_percentiles.clone()),
             },
-            external_ping_client_factory: None,
+            ping_client_registry: PingClientRegistry::new(),
+            external_ping_target_resolver: None,
             extra_ping_result_processors: vec![],
         };
 
-        if !self.ping_common_options.ping_until_stopped {
-            config.worker_scheduler_config.ping_count = Some(self.ping_common_options.ping_count);
-        }
-
         return config;
     }
-}
 
-impl RnpCliPingCommonOptions {
-    pub fn prepare_to_use(&mut self, target: &SocketAddr) {
-        if target.is_ipv4() != self.source_ip.is_ipv4() {
-            match &self.source_ip {
-                IpAddr::V4(source_ip_v4) if *source_ip_v4 == Ipv4Addr::UNSPECIFIED => self.source_ip = IpAddr::V6(Ipv6Addr::UNSPECIFIED),
-                IpAddr::V6(source_ip_v6) if *source_ip_v6 == Ipv6Addr::UNSPECIFIED => self.source_ip = IpAddr::V4(Ipv4Addr::UNSPECIFIED),
-                _ => panic!("Source IP and Target IP are not both IPv4 or IPv6!"),
-            }
+    fn validate_throughput_options(&self) -> Result<(), String> {
+        let throughput_options = &self.throughput_options;
+        if throughput_options.throughput_direction.is_none() {
+            return Ok(());
         }
 
-        if self.source_ports.is_none() {
-            let range_start = rand::thread_rng().gen_range(10000..30000);
-            let range_end = range_start + 2000;
-            self.source_ports = Some(PortRangeList { ranges: vec![(range_start..=range_end)] });
+        if self.common_options.protocol != RnpSupportedProtocol::TCP {
+            return Err(String::from("Throughput test is only available in TCP mode."));
         }
 
-        if !self.ping_until_stopped && self.ping_count < 1 {
-            tracing::warn!("Ping count cannot be less than 1, setting to 1 as minimum.");
-            self.ping_count = 1;
+        if self.common_options.targets.len() != 1 {
+            return Err(String::from("Throughput test only supports a single target."));
         }
 
-        let available_source_port_count = self.source_ports.as_ref().unwrap().calculate_total_port_count();
-        if self.parallel_ping_count > available_source_port_count as u32 {
-            tracing::warn!(
-                "Parallel ping count ({}) is larger than available source port count ({}), to avoid port conflict reducing parallel ping count down to the same as available source port count.",
-                self.parallel_ping_count,
-                available_source_port_count);
-
-            self.parallel_ping_count = available_source_port_count as u32;
+        if throughput_options.throughput_duration.is_zero()
+            || throughput_options.throughput_stream_count == 0
+            || throughput_options.throughput_report_interval_in_ms == 0
+        {
+            return Err(String::from("Throughput test duration, stream count and report interval should all be greater than 0."));
         }
 
-        if self.parallel_ping_count < 1 {
-            tracing::warn!("Parallel ping count cannot be 0. Setting to 1 as minimum.");
-            self.parallel_ping_count = 1;
-        }
+        return Ok(());
+    }
+
+    pub fn to_throughput_runner_config(&self) -> Option<RnpThroughputRunnerConfig> {
+        let throughput_options = &self.throughput_options;
+        return throughput_options.throughput_direction.map(|direction| RnpThroughputRunnerConfig {
+            target: self.common_options.targets[0].clone(),
+            source_ip: self.ping_common_options.source_ip,
+            direction,
+            duration: throughput_options.throughput_duration,
+            parallel_stream_count: throughput_options.throughput_stream_count,
+            report_interval: Duration::from_millis(throughput_options.throughput_report_interval_in_ms),
+            connect_timeout: Duration::from_millis(self.ping_common_options.wait_timeout_in_ms.into()),
+            quiet_level: self.output_options.quiet_level,
+        });
+    }
+
+    // ALPN defaults to h3-29 in QUIC and http/1.1 in HTTPS, while in TLS, no ALPN is sent unless specified, so plain TLS servers will also work.
+    fn alpn_protocol(&self) -> Option<String> {
+        return match &self.quic_options.alpn_protocol {
+            Some(alpn_protocol) if alpn_protocol.to_uppercase() == "NONE" => None,
+            Some(alpn_protocol) => Some(alpn_protocol.clone()),
+            None if self.common_options.protocol == RnpSupportedProtocol::QUIC => Some(String::from("h3-29")),
+            None if self.common_options.protocol == RnpSupportedProtocol::HTTPS => Some(String::from("http/1.1")),
+            None => None,
+        };
     }
 }
 
@@ -262,6 +652,8 @@ mod tests {
         PingClientConfig, PingResultProcessorCommonConfig, PingResultProcessorConfig, PingWorkerConfig, PingWorkerSchedulerConfig,
         RnpPingRunnerConfig, RnpSupportedProtocol, RNP_QUIET_LEVEL_NONE, RNP_QUIET_LEVEL_NO_OUTPUT, RNP_QUIET_LEVEL_NO_PING_RESULT,
     };
+    use std::collections::BTreeMap;
+    use std::net::{Ipv4Addr, Ipv6Addr};
     use std::path::PathBuf;
     use std::time::Duration;
     use structopt::StructOpt;
@@ -270,11 +662,19 @@ mod tests {
     fn parsing_default_options_should_work() {
         assert_eq!(
             RnpCliOptions {
-                common_options: RnpCliCommonOptions { target: "10.0.0.1:443".parse().unwrap(), protocol: RnpSupportedProtocol::TCP },
+                common_options: RnpCliCommonOptions {
+                    targets: vec!["10.0.0.1:443".parse().unwrap()],
+                    targets_file: None,
+                    config_file: None,
+                    profile: None,
+                    protocol_options: vec![],
+                    protocol: RnpSupportedProtocol::TCP
+                },
                 ping_common_options: RnpCliPingCommonOptions {
                     source_ip: "0.0.0.0".parse().unwrap(),
                     source_ports: None,
-                    ping_count: 4,
+                    resolve_on_every_ping: false,
+                    ping_count: None,
                     ping_until_stopped: false,
                     warmup_count: 0,
                     wait_timeout_in_ms: 2000,
@@ -283,23 +683,42 @@ mod tests {
                     check_disconnect: false,
                     wait_before_disconnect_in_ms: 0,
                     disconnect_timeout_in_ms: 2000,
+                    measure_one_way_latency: false,
+                    use_persistent_connection: false,
                     parallel_ping_count: 1,
                     exit_on_fail: false,
+                    duration: None,
+                    stop_on_consecutive_failures: None,
+                    stop_on_recovery: None,
+                    rate: None,
+                    arrival_distribution: PingArrivalDistribution::Constant,
                 },
-                quic_options: RnpCliQuicPingOptions {
-                    server_name: None,
-                    log_tls_key: false,
-                    alpn_protocol: String::from("h3-29"),
-                    use_timer_rtt: false,
+                quic_options: RnpCliQuicPingOptions { server_name: None, log_tls_key: false, alpn_protocol: None, use_timer_rtt: false },
+                http_options: RnpCliHttpPingOptions {
+                    http_method: String::from("GET"),
+                    http_path: String::from("/"),
+                    http_headers: vec![],
+                    http_expected_status: None,
+                    http_body_regex: None,
+                },
+                throughput_options: RnpCliThroughputOptions {
+                    throughput_direction: None,
+                    throughput_duration: Duration::from_secs(10),
+                    throughput_stream_count: 1,
+                    throughput_report_interval_in_ms: 1000,
                 },
                 output_options: RnpCliOutputOptions {
                     quiet_level: RNP_QUIET_LEVEL_NONE,
                     csv_log_path: None,
                     json_log_path: None,
                     text_log_path: None,
+                    html_report_path: None,
+                    metrics_address: None,
                     show_result_scatter: false,
                     show_latency_scatter: false,
+                    show_dashboard: false,
                     latency_buckets: None,
+                    latency_percentiles: vec![50.0, 90.0, 99.0, 99.9],
                 },
             },
             RnpCliOptions::from_iter(&["tp.exe", "10.0.0.1:443"])
@@ -310,11 +729,19 @@ mod tests {
     fn parsing_short_options_should_work() {
         assert_eq!(
             RnpCliOptions {
-                common_options: RnpCliCommonOptions { target: "10.0.0.1:443".parse().unwrap(), protocol: RnpSupportedProtocol::TCP },
+                common_options: RnpCliCommonOptions {
+                    targets: vec!["10.0.0.1:443".parse().unwrap()],
+                    targets_file: None,
+                    config_file: None,
+                    profile: None,
+                    protocol_options: vec![],
+                    protocol: RnpSupportedProtocol::TCP
+                },
                 ping_common_options: RnpCliPingCommonOptions {
                     source_ip: "10.0.0.2".parse().unwrap(),
                     source_ports: Some(PortRangeList { ranges: vec![(1024..=2048), (3096..=3096), (3097..=3097)] }),
-                    ping_count: 10,
+                    resolve_on_every_ping: false,
+                    ping_count: Some(10),
                     ping_until_stopped: true,
                     warmup_count: 0,
                     wait_timeout_in_ms: 1000,
@@ -323,23 +750,42 @@ mod tests {
                     check_disconnect: true,
                     wait_before_disconnect_in_ms: 0,
                     disconnect_timeout_in_ms: 1000,
+                    measure_one_way_latency: false,
+                    use_persistent_connection: false,
                     parallel_ping_count: 10,
                     exit_on_fail: false,
+                    duration: None,
+                    stop_on_consecutive_failures: None,
+                    stop_on_recovery: None,
+                    rate: None,
+                    arrival_distribution: PingArrivalDistribution::Constant,
                 },
-                quic_options: RnpCliQuicPingOptions {
-                    server_name: None,
-                    log_tls_key: false,
-                    alpn_protocol: String::from("h3-29"),
-                    use_timer_rtt: false,
+                quic_options: RnpCliQuicPingOptions { server_name: None, log_tls_key: false, alpn_protocol: None, use_timer_rtt: false },
+                http_options: RnpCliHttpPingOptions {
+                    http_method: String::from("GET"),
+                    http_path: String::from("/"),
+                    http_headers: vec![],
+                    http_expected_status: None,
+                    http_body_regex: None,
+                },
+                throughput_options: RnpCliThroughputOptions {
+                    throughput_direction: None,
+                    throughput_duration: Duration::from_secs(10),
+                    throughput_stream_count: 1,
+                    throughput_report_interval_in_ms: 1000,
                 },
                 output_options: RnpCliOutputOptions {
                     quiet_level: RNP_QUIET_LEVEL_NO_PING_RESULT,
                     csv_log_path: Some(PathBuf::from("log.csv")),
                     json_log_path: Some(PathBuf::from("log.json")),
                     text_log_path: Some(PathBuf::from("log.txt")),
+                    html_report_path: Some(PathBuf::from("report.html")),
+                    metrics_address: None,
                     show_result_scatter: true,
                     show_latency_scatter: true,
+                    show_dashboard: false,
                     latency_buckets: Some(vec![0.1, 0.5, 1.0, 10.0]),
+                    latency_percentiles: vec![50.0, 90.0, 99.0, 99.9],
                 },
             },
             RnpCliOptions::from_iter(&[
@@ -370,6 +816,8 @@ mod tests {
                 "log.json",
                 "-o",
                 "log.txt",
+                "--report-html",
+                "report.html",
                 "-r",
                 "-l",
                 "-b",
@@ -382,11 +830,19 @@ mod tests {
     fn parsing_long_options_should_work() {
         assert_eq!(
             RnpCliOptions {
-                common_options: RnpCliCommonOptions { target: "10.0.0.1:443".parse().unwrap(), protocol: RnpSupportedProtocol::QUIC },
+                common_options: RnpCliCommonOptions {
+                    targets: vec![PingTarget::HostName(String::from("example.com"), 443)],
+                    targets_file: Some(PathBuf::from("targets.txt")),
+                    config_file: Some(PathBuf::from("rnp.toml")),
+                    profile: Some(String::from("lb-drain")),
+                    protocol_options: vec![],
+                    protocol: RnpSupportedProtocol::QUIC
+                },
                 ping_common_options: RnpCliPingCommonOptions {
                     source_ip: "10.0.0.2".parse().unwrap(),
                     source_ports: Some(PortRangeList { ranges: vec![(1024..=2048), (3096..=3096), (3097..=3097)] }),
-                    ping_count: 10,
+                    resolve_on_every_ping: true,
+                    ping_count: Some(10),
                     ping_until_stopped: false,
                     warmup_count: 3,
                     wait_timeout_in_ms: 1000,
@@ -395,34 +851,65 @@ mod tests {
                     check_disconnect: true,
                     wait_before_disconnect_in_ms: 3000,
                     disconnect_timeout_in_ms: 4000,
+                    measure_one_way_latency: true,
+                    use_persistent_connection: true,
                     parallel_ping_count: 10,
                     exit_on_fail: true,
+                    duration: Some(Duration::from_secs(900)),
+                    stop_on_consecutive_failures: Some(3),
+                    stop_on_recovery: Some(5),
+                    rate: Some(PingRate { pings_per_second: 500.0 }),
+                    arrival_distribution: PingArrivalDistribution::Poisson,
                 },
                 quic_options: RnpCliQuicPingOptions {
                     server_name: Some(String::from("localhost")),
                     log_tls_key: true,
-                    alpn_protocol: String::from("hq-29"),
+                    alpn_protocol: Some(String::from("hq-29")),
                     use_timer_rtt: true,
                 },
+                http_options: RnpCliHttpPingOptions {
+                    http_method: String::from("HEAD"),
+                    http_path: String::from("/health"),
+                    http_headers: vec![(String::from("Accept"), String::from("text/html")), (String::from("X-Test"), String::from("1"))],
+                    http_expected_status: Some(204),
+                    http_body_regex: Some(String::from("OK")),
+                },
+                throughput_options: RnpCliThroughputOptions {
+                    throughput_direction: Some(ThroughputDirection::Bidirectional),
+                    throughput_duration: Duration::from_secs(30),
+                    throughput_stream_count: 4,
+                    throughput_report_interval_in_ms: 500,
+                },
                 output_options: RnpCliOutputOptions {
                     quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT,
                     csv_log_path: Some(PathBuf::from("log.csv")),
                     json_log_path: Some(PathBuf::from("log.json")),
                     text_log_path: Some(PathBuf::from("log.txt")),
+                    html_report_path: Some(PathBuf::from("report.html")),
+                    metrics_address: Some("127.0.0.1:9100".parse().unwrap()),
                     show_result_scatter: true,
                     show_latency_scatter: true,
+                    show_dashboard: true,
                     latency_buckets: Some(vec![0.1, 0.5, 1.0, 10.0]),
+                    latency_percentiles: vec![50.0, 99.0, 99.99],
                 },
             },
             RnpCliOptions::from_iter(&[
                 "rnp.exe",
-                "10.0.0.1:443",
+                "example.com:443",
+                "--targets-file",
+                "targets.txt",
+                "--config",
+                "rnp.toml",
+                "--profile",
+                "lb-drain",
                 "--mode",
                 "quic",
                 "--src-ip",
                 "10.0.0.2",
                 "--src-ports",
                 "1024-2048,3096,3097",
+                "--resolve-every-ping",
                 "--count",
                 "10",
                 "--warmup",
@@ -438,15 +925,39 @@ mod tests {
                 "3000",
                 "--disconnect-timeout",
                 "4000",
+                "--one-way-latency",
+                "--persistent",
                 "--parallel",
                 "10",
                 "--exit-on-fail",
+                "--duration",
+                "15m",
+                "--stop-on-failures",
+                "3",
+                "--stop-on-recovery",
+                "5",
+                "--rate",
+                "500/s",
+                "--arrival",
+                "poisson",
                 "--server-name",
                 "localhost",
                 "--log-tls-key",
                 "--alpn",
                 "hq-29",
                 "--use-timer-rtt",
+                "--http-method",
+                "HEAD",
+                "--http-path",
+                "/health",
+                "--http-header",
+                "Accept: text/html",
+                "--http-header",
+                "X-Test: 1",
+                "--http-expect-status",
+                "204",
+                "--http-body-regex",
+                "OK",
                 "-qqq",
                 "--log-csv",
                 "log.csv",
@@ -454,10 +965,25 @@ mod tests {
                 "log.json",
                 "--log-text",
                 "log.txt",
+                "--report-html",
+                "report.html",
+                "--metrics-address",
+                "127.0.0.1:9100",
                 "--show-result-scatter",
                 "--show-latency-scatter",
+                "--dashboard",
                 "--latency-buckets",
                 "0.1,0.5,1.0,10.0",
+                "--latency-percentiles",
+                "50,99,99.99",
+                "--throughput",
+                "bidir",
+                "--throughput-duration",
+                "30s",
+                "--throughput-streams",
+                "4",
+                "--throughput-report-interval",
+                "500",
             ])
         );
     }
@@ -468,8 +994,9 @@ mod tests {
             RnpPingRunnerConfig {
                 worker_config: PingWorkerConfig {
                     protocol: RnpSupportedProtocol::TCP,
-                    target: "10.0.0.1:443".parse().unwrap(),
+                    targets: vec!["10.0.0.1:443".parse().unwrap()],
                     source_ip: "10.0.0.2".parse().unwrap(),
+                    resolve_on_every_ping: false,
                     ping_interval: Duration::from_millis(1500),
                     ping_client_config: PingClientConfig {
                         wait_timeout: Duration::from_millis(1000),
@@ -481,13 +1008,27 @@ mod tests {
                         log_tls_key: false,
                         alpn_protocol: None,
                         use_timer_rtt: false,
+                        http_method: String::from("GET"),
+                        http_path: String::from("/"),
+                        http_headers: vec![],
+                        http_expected_status: None,
+                        http_body_regex: None,
+                        measure_one_way_latency: false,
+                        use_persistent_connection: false,
+                        protocol_options: BTreeMap::new(),
                     },
                 },
                 worker_scheduler_config: PingWorkerSchedulerConfig {
                     source_ports: PortRangeList { ranges: vec![(1024..=2048), (3096..=3096), (3097..=3097)] },
                     ping_count: Some(4),
+                    ping_until_stopped: false,
                     warmup_count: 1,
                     parallel_ping_count: 1,
+                    duration: None,
+                    stop_on_consecutive_failures: None,
+                    stop_on_recovery: None,
+                    rate: None,
+                    arrival_distribution: PingArrivalDistribution::Constant,
                 },
                 result_processor_config: PingResultProcessorConfig {
                     common_config: PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NONE },
@@ -496,44 +1037,81 @@ mod tests {
                     csv_log_path: None,
                     json_log_path: None,
                     text_log_path: None,
+                    html_report_path: None,
+                    metrics_address: None,
                     show_result_scatter: false,
                     show_latency_scatter: false,
+                    show_dashboard: false,
                     latency_buckets: None,
+                    latency_percentiles: Some(vec![50.0, 90.0, 99.0, 99.9]),
                 },
-                external_ping_client_factory: None,
+                ping_client_registry: PingClientRegistry::new(),
+                external_ping_target_resolver: None,
                 extra_ping_result_processors: vec![],
             },
             RnpCliOptions {
-                common_options: RnpCliCommonOptions { target: "10.0.0.1:443".parse().unwrap(), protocol: RnpSupportedProtocol::TCP },
+                common_options: RnpCliCommonOptions {
+                    targets: vec!["10.0.0.1:443".parse().unwrap()],
+                    targets_file: None,
+                    config_file: None,
+                    profile: None,
+                    protocol_options: vec![],
+                    protocol: RnpSupportedProtocol::TCP
+                },
                 ping_common_options: RnpCliPingCommonOptions {
-                    ping_count: 4,
+                    ping_count: Some(4),
                     ping_until_stopped: false,
                     warmup_count: 1,
                     source_ip: "10.0.0.2".parse().unwrap(),
                     source_ports: Some(PortRangeList { ranges: vec![(1024..=2048), (3096..=3096), (3097..=3097)] }),
+                    resolve_on_every_ping: false,
                     wait_timeout_in_ms: 1000,
                     ping_interval_in_ms: 1500,
                     time_to_live: Some(128),
                     check_disconnect: false,
                     wait_before_disconnect_in_ms: 2000,
                     disconnect_timeout_in_ms: 3000,
+                    measure_one_way_latency: false,
+                    use_persistent_connection: false,
                     parallel_ping_count: 1,
                     exit_on_fail: false,
+                    duration: None,
+                    stop_on_consecutive_failures: None,
+                    stop_on_recovery: None,
+                    rate: None,
+                    arrival_distribution: PingArrivalDistribution::Constant,
                 },
                 quic_options: RnpCliQuicPingOptions {
                     server_name: None,
                     log_tls_key: false,
-                    alpn_protocol: String::from("none"),
+                    alpn_protocol: Some(String::from("none")),
                     use_timer_rtt: false,
                 },
+                http_options: RnpCliHttpPingOptions {
+                    http_method: String::from("GET"),
+                    http_path: String::from("/"),
+                    http_headers: vec![],
+                    http_expected_status: None,
+                    http_body_regex: None,
+                },
+                throughput_options: RnpCliThroughputOptions {
+                    throughput_direction: None,
+                    throughput_duration: Duration::from_secs(10),
+                    throughput_stream_count: 1,
+                    throughput_report_interval_in_ms: 1000,
+                },
                 output_options: RnpCliOutputOptions {
                     quiet_level: RNP_QUIET_LEVEL_NONE,
                     csv_log_path: None,
                     json_log_path: None,
                     text_log_path: None,
+                    html_report_path: None,
+                    metrics_address: None,
                     show_result_scatter: false,
                     show_latency_scatter: false,
+                    show_dashboard: false,
                     latency_buckets: None,
+                    latency_percentiles: vec![50.0, 90.0, 99.0, 99.9],
                 },
             }
             .to_ping_runner_config()
@@ -543,8 +1121,9 @@ mod tests {
             RnpPingRunnerConfig {
                 worker_config: PingWorkerConfig {
                     protocol: RnpSupportedProtocol::QUIC,
-                    target: "10.0.0.1:443".parse().unwrap(),
+                    targets: vec!["10.0.0.1:443".parse().unwrap()],
                     source_ip: "10.0.0.2".parse().unwrap(),
+                    resolve_on_every_ping: true,
                     ping_interval: Duration::from_millis(1500),
                     ping_client_config: PingClientConfig {
                         wait_timeout: Duration::from_millis(2000),
@@ -556,13 +1135,27 @@ mod tests {
                         log_tls_key: true,
                         alpn_protocol: Some(String::from("h3")),
                         use_timer_rtt: true,
+                        http_method: String::from("GET"),
+                        http_path: String::from("/"),
+                        http_headers: vec![],
+                        http_expected_status: None,
+                        http_body_regex: None,
+                        measure_one_way_latency: true,
+                        use_persistent_connection: true,
+                        protocol_options: BTreeMap::new(),
                     },
                 },
                 worker_scheduler_config: PingWorkerSchedulerConfig {
                     source_ports: PortRangeList { ranges: vec![(1024..=2048), (3096..=3096), (3097..=3097)] },
-                    ping_count: None,
+                    ping_count: Some(4),
+                    ping_until_stopped: true,
                     warmup_count: 3,
                     parallel_ping_count: 1,
+                    duration: Some(Duration::from_secs(900)),
+                    stop_on_consecutive_failures: Some(3),
+                    stop_on_recovery: Some(5),
+                    rate: Some(PingRate { pings_per_second: 500.0 }),
+                    arrival_distribution: PingArrivalDistribution::Poisson,
                 },
                 result_processor_config: PingResultProcessorConfig {
                     common_config: PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_PING_RESULT },
@@ -571,44 +1164,81 @@ mod tests {
                     csv_log_path: Some(PathBuf::from("log.csv")),
                     json_log_path: Some(PathBuf::from("log.json")),
                     text_log_path: Some(PathBuf::from("log.txt")),
+                    html_report_path: Some(PathBuf::from("report.html")),
+                    metrics_address: Some("127.0.0.1:9100".parse().unwrap()),
                     show_result_scatter: true,
                     show_latency_scatter: true,
+                    show_dashboard: false,
                     latency_buckets: Some(vec![0.1, 0.5, 1.0, 10.0]),
+                    latency_percentiles: Some(vec![50.0, 90.0, 99.0, 99.9]),
                 },
-                external_ping_client_factory: None,
+                ping_client_registry: PingClientRegistry::new(),
+                external_ping_target_resolver: None,
                 extra_ping_result_processors: vec![],
             },
             RnpCliOptions {
-                common_options: RnpCliCommonOptions { target: "10.0.0.1:443".parse().unwrap(), protocol: RnpSupportedProtocol::QUIC },
+                common_options: RnpCliCommonOptions {
+                    targets: vec!["10.0.0.1:443".parse().unwrap()],
+                    targets_file: None,
+                    config_file: None,
+                    profile: None,
+                    protocol_options: vec![],
+                    protocol: RnpSupportedProtocol::QUIC
+                },
                 ping_common_options: RnpCliPingCommonOptions {
-                    ping_count: 4,
+                    ping_count: Some(4),
                     ping_until_stopped: true,
                     warmup_count: 3,
                     source_ip: "10.0.0.2".parse().unwrap(),
                     source_ports: Some(PortRangeList { ranges: vec![(1024..=2048), (3096..=3096), (3097..=3097)] }),
+                    resolve_on_every_ping: true,
                     wait_timeout_in_ms: 2000,
                     ping_interval_in_ms: 1500,
                     time_to_live: Some(128),
                     check_disconnect: true,
                     wait_before_disconnect_in_ms: 3000,
                     disconnect_timeout_in_ms: 4000,
+                    measure_one_way_latency: true,
+                    use_persistent_connection: true,
                     parallel_ping_count: 1,
                     exit_on_fail: true,
+                    duration: Some(Duration::from_secs(900)),
+                    stop_on_consecutive_failures: Some(3),
+                    stop_on_recovery: Some(5),
+                    rate: Some(PingRate { pings_per_second: 500.0 }),
+                    arrival_distribution: PingArrivalDistribution::Poisson,
                 },
                 quic_options: RnpCliQuicPingOptions {
                     server_name: Some(String::from("localhost")),
                     log_tls_key: true,
-                    alpn_protocol: String::from("h3"),
+                    alpn_protocol: Some(String::from("h3")),
                     use_timer_rtt: true,
                 },
+                http_options: RnpCliHttpPingOptions {
+                    http_method: String::from("GET"),
+                    http_path: String::from("/"),
+                    http_headers: vec![],
+                    http_expected_status: None,
+                    http_body_regex: None,
+                },
+                throughput_options: RnpCliThroughputOptions {
+                    throughput_direction: None,
+                    throughput_duration: Duration::from_secs(10),
+                    throughput_stream_count: 1,
+                    throughput_report_interval_in_ms: 1000,
+                },
                 output_options: RnpCliOutputOptions {
                     quiet_level: RNP_QUIET_LEVEL_NO_PING_RESULT,
                     csv_log_path: Some(PathBuf::from("log.csv")),
                     json_log_path: Some(PathBuf::from("log.json")),
                     text_log_path: Some(PathBuf::from("log.txt")),
+                    html_report_path: Some(PathBuf::from("report.html")),
+                    metrics_address: Some("127.0.0.1:9100".parse().unwrap()),
                     show_result_scatter: true,
                     show_latency_scatter: true,
+                    show_dashboard: false,
                     latency_buckets: Some(vec![0.1, 0.5, 1.0, 10.0]),
+                    latency_percentiles: vec![50.0, 90.0, 99.0, 99.9],
                 },
             }
             .to_ping_runner_config()
@@ -618,7 +1248,7 @@ mod tests {
     #[test]
     fn empty_source_port_in_options_should_be_fixed() {
         let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443"]);
-        opts.prepare_to_use();
+        opts.prepare_to_use().unwrap();
 
         assert!(opts.ping_common_options.source_ports.is_some());
         assert_eq!(1, opts.ping_common_options.source_ports.as_ref().unwrap().ranges.len());
@@ -633,33 +1263,318 @@ mod tests {
     fn invalid_options_for_ipv4_should_be_fixed() {
         let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443"]);
         opts.ping_common_options.source_ports = None;
-        opts.ping_common_options.ping_count = 0;
         opts.ping_common_options.parallel_ping_count = 0;
         opts.output_options.latency_buckets = Some(vec![0.0]);
-        opts.prepare_to_use();
+        opts.prepare_to_use().unwrap();
 
-        assert_eq!(1, opts.ping_common_options.ping_count);
         assert_eq!(1, opts.ping_common_options.parallel_ping_count);
         assert_eq!(Some(vec![0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 50.0, 100.0, 300.0, 500.0]), opts.output_options.latency_buckets);
 
         opts.ping_common_options.source_ports = Some(PortRangeList { ranges: vec![(1024..=1047)] });
         opts.ping_common_options.parallel_ping_count = 100;
-        opts.prepare_to_use();
+        opts.prepare_to_use().unwrap();
         assert_eq!(24, opts.ping_common_options.parallel_ping_count);
 
         opts.ping_common_options.source_ports = Some(PortRangeList { ranges: vec![(1024..=1024), (1025..=1025), (1026..=1026)] });
         opts.ping_common_options.parallel_ping_count = 100;
-        opts.prepare_to_use();
+        opts.prepare_to_use().unwrap();
         assert_eq!(3, opts.ping_common_options.parallel_ping_count);
     }
 
     #[test]
     fn invalid_options_for_ipv6_should_be_fixed() {
         let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "[2607:f8b0:400a:80a::200e]:443"]);
-        opts.prepare_to_use();
+        opts.prepare_to_use().unwrap();
 
         // If source ip is not set (unspecified/any), we update the IP accordingly to match our target.
         assert!(opts.ping_common_options.source_ip.is_ipv6());
         assert_eq!(Ipv6Addr::UNSPECIFIED, opts.ping_common_options.source_ip);
     }
+
+    #[test]
+    fn source_ip_for_host_name_target_should_not_be_changed() {
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "example.com:443"]);
+        opts.prepare_to_use().unwrap();
+
+        // Host name targets are resolved in ping workers, where the unspecified source IP will be adjusted to match the resolved address.
+        assert_eq!(Ipv4Addr::UNSPECIFIED, opts.ping_common_options.source_ip);
+    }
+
+    #[test]
+    fn invalid_latency_percentiles_should_be_rejected() {
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443", "--latency-percentiles", "50,100.5"]);
+        assert!(opts.prepare_to_use().is_err());
+
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443", "--latency-percentiles", "0"]);
+        assert!(opts.prepare_to_use().is_err());
+    }
+
+    #[test]
+    fn invalid_stop_conditions_should_be_rejected() {
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443", "--duration", "0s"]);
+        assert!(opts.prepare_to_use().is_err());
+
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443", "--stop-on-failures", "0"]);
+        assert!(opts.prepare_to_use().is_err());
+
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443", "--stop-on-recovery", "0"]);
+        assert!(opts.prepare_to_use().is_err());
+
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe", "10.0.0.1:443", "--duration", "15 minutes later"]).is_err());
+    }
+
+    #[test]
+    fn invalid_rate_options_should_be_rejected() {
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe", "10.0.0.1:443", "--rate", "0/s"]).is_err());
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe", "10.0.0.1:443", "--rate", "500/d"]).is_err());
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe", "10.0.0.1:443", "--rate", "500/s", "--arrival", "burst"]).is_err());
+    }
+
+    #[test]
+    fn new_throughput_runner_config_from_cli_options_should_work() {
+        let mut opts =
+            RnpCliOptions::from_iter(&["rnp.exe", "[::1]:443", "--throughput", "download", "--throughput-streams", "4", "-w", "500", "-q"]);
+        assert_eq!(Ok(()), opts.prepare_to_use());
+        assert_eq!(
+            Some(RnpThroughputRunnerConfig {
+                target: "[::1]:443".parse().unwrap(),
+                source_ip: "::".parse().unwrap(),
+                direction: ThroughputDirection::Download,
+                duration: Duration::from_secs(10),
+                parallel_stream_count: 4,
+                report_interval: Duration::from_millis(1000),
+                connect_timeout: Duration::from_millis(500),
+                quiet_level: RNP_QUIET_LEVEL_NO_PING_RESULT,
+            }),
+            opts.to_throughput_runner_config()
+        );
+
+        assert_eq!(None, RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443"]).to_throughput_runner_config());
+    }
+
+    #[test]
+    fn invalid_throughput_options_should_be_rejected() {
+        let invalid_args: [&[&str]; 5] = [
+            &["rnp.exe", "10.0.0.1:443", "--throughput", "upload", "-m", "udp"],
+            &["rnp.exe", "10.0.0.1:443", "10.0.0.2:443", "--throughput", "upload"],
+            &["rnp.exe", "10.0.0.1:443", "--throughput", "upload", "--throughput-streams", "0"],
+            &["rnp.exe", "10.0.0.1:443", "--throughput", "upload", "--throughput-duration", "0s"],
+            &["rnp.exe", "10.0.0.1:443", "--throughput", "upload", "--throughput-report-interval", "0"],
+        ];
+        for args in invalid_args {
+            assert!(RnpCliOptions::from_iter(args).prepare_to_use().is_err());
+        }
+
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe", "10.0.0.1:443", "--throughput", "sideways"]).is_err());
+    }
+
+    #[test]
+    fn parsing_external_protocol_should_work() {
+        let mut opts = RnpCliOptions::from_iter(&[
+            "rnp.exe",
+            "10.0.0.1:161",
+            "-m",
+            "snmp",
+            "--protocol-option",
+            "community=public",
+            "--protocol-option",
+            "version=2c",
+        ]);
+        assert_eq!(RnpSupportedProtocol::External(String::from("snmp")), opts.common_options.protocol);
+
+        let config = opts.to_ping_runner_config();
+        assert_eq!(Some(&String::from("public")), config.worker_config.ping_client_config.protocol_options.get("community"));
+        assert_eq!(Some(&String::from("2c")), config.worker_config.ping_client_config.protocol_options.get("version"));
+
+        // No ping client factory is registered in rnp itself, so external protocols can only be used by library users.
+        assert_eq!(Err(String::from("Protocol snmp is not supported. No ping client factory is registered with this name.")), opts.prepare_to_use());
+
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe", "10.0.0.1:161", "-m", "snmp/v2"]).is_err());
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe", "10.0.0.1:161", "--protocol-option", "community"]).is_err());
+    }
+
+    #[test]
+    fn parsing_multiple_targets_should_work() {
+        let opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443", "10.0.0.2:443", "example.com:443"]);
+        assert_eq!(
+            vec![
+                PingTarget::Address("10.0.0.1:443".parse().unwrap()),
+                PingTarget::Address("10.0.0.2:443".parse().unwrap()),
+                PingTarget::HostName(String::from("example.com"), 443),
+            ],
+            opts.common_options.targets
+        );
+
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe"]).is_err());
+        assert!(RnpCliOptions::from_iter_safe(&["rnp.exe", "--targets-file", "targets.txt"]).is_ok());
+    }
+
+    #[test]
+    fn targets_from_targets_file_should_be_appended() {
+        let test_targets_file_path = "tests_data/rnp_cli_options_tests/targets.txt";
+        std::fs::create_dir_all("tests_data/rnp_cli_options_tests").unwrap();
+        std::fs::write(test_targets_file_path, "10.0.0.2:443\n# Disabled: 10.0.0.3:443\n[2607:f8b0:400a:80a::200e]:443\n").unwrap();
+
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443", "--targets-file", test_targets_file_path]);
+        opts.prepare_to_use().unwrap();
+        assert_eq!(
+            vec![
+                PingTarget::Address("10.0.0.1:443".parse().unwrap()),
+                PingTarget::Address("10.0.0.2:443".parse().unwrap()),
+                PingTarget::Address("[2607:f8b0:400a:80a::200e]:443".parse().unwrap()),
+            ],
+            opts.common_options.targets
+        );
+
+        // Targets are in both IPv4 and IPv6, so the source IP is left to ping workers to adjust.
+        assert_eq!(Ipv4Addr::UNSPECIFIED, opts.ping_common_options.source_ip);
+
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "--targets-file", "tests_data/rnp_cli_options_tests/not_exist.txt"]);
+        assert!(opts.prepare_to_use().is_err());
+    }
+
+    #[test]
+    fn options_from_config_file_should_be_overridden_by_command_line() {
+        let test_config_file_path = "tests_data/rnp_cli_options_tests/config.toml";
+        std::fs::create_dir_all("tests_data/rnp_cli_options_tests").unwrap();
+        std::fs::write(
+            test_config_file_path,
+            r#"
+[worker_config]
+protocol = "https"
+targets = ["10.0.0.1:443"]
+ping_interval = "500ms"
+
+[worker_config.ping_client_config]
+wait_timeout = "1s"
+http_path = "/health"
+
+[worker_scheduler_config]
+source_ports = "1024-2048"
+ping_count = 100
+duration = "15m"
+stop_on_recovery = 5
+rate = "30/m"
+
+[result_processor_config]
+show_result_scatter = true
+latency_percentiles = [50, 99]
+
+[profiles.lb-drain.worker_scheduler_config]
+parallel_ping_count = 10
+
+[profiles.lb-drain.result_processor_config.common_config]
+quiet_level = 1
+"#,
+        )
+        .unwrap();
+
+        let opts = RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "--config", test_config_file_path]).unwrap();
+        assert_eq!(RnpSupportedProtocol::HTTPS, opts.common_options.protocol);
+        assert_eq!(vec![PingTarget::Address("10.0.0.1:443".parse().unwrap())], opts.common_options.targets);
+        assert_eq!(Some(PortRangeList { ranges: vec![(1024..=2048)] }), opts.ping_common_options.source_ports);
+        assert_eq!(Some(100), opts.ping_common_options.ping_count);
+        assert!(!opts.ping_common_options.ping_until_stopped);
+        assert_eq!(1, opts.ping_common_options.parallel_ping_count);
+        assert_eq!(500, opts.ping_common_options.ping_interval_in_ms);
+        assert_eq!(1000, opts.ping_common_options.wait_timeout_in_ms);
+        assert_eq!("/health", opts.http_options.http_path);
+        assert!(opts.output_options.show_result_scatter);
+        assert_eq!(vec![50.0, 99.0], opts.output_options.latency_percentiles);
+        assert_eq!(RNP_QUIET_LEVEL_NONE, opts.output_options.quiet_level);
+        assert_eq!(Some(Duration::from_secs(900)), opts.ping_common_options.duration);
+        assert_eq!(Some(5), opts.ping_common_options.stop_on_recovery);
+        assert_eq!(Some(PingRate { pings_per_second: 0.5 }), opts.ping_common_options.rate);
+
+        let opts = RnpCliOptions::from_iter_with_config_file(&[
+            "rnp.exe",
+            "10.0.0.2:443",
+            "--config",
+            test_config_file_path,
+            "--profile",
+            "lb-drain",
+            "-n",
+            "10",
+            "-w",
+            "3000",
+            "-m",
+            "tcp",
+            "--duration",
+            "1m",
+        ])
+        .unwrap();
+        assert_eq!(RnpSupportedProtocol::TCP, opts.common_options.protocol);
+        assert_eq!(Some(Duration::from_secs(60)), opts.ping_common_options.duration);
+        assert_eq!(vec![PingTarget::Address("10.0.0.2:443".parse().unwrap())], opts.common_options.targets);
+        assert_eq!(Some(10), opts.ping_common_options.ping_count);
+        assert_eq!(3000, opts.ping_common_options.wait_timeout_in_ms);
+        assert_eq!(500, opts.ping_common_options.ping_interval_in_ms);
+        assert_eq!(10, opts.ping_common_options.parallel_ping_count);
+        assert_eq!(RNP_QUIET_LEVEL_NO_PING_RESULT, opts.output_options.quiet_level);
+
+        assert!(RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "--config", test_config_file_path, "--profile", "not-exist"]).is_err());
+    }
+
+    #[test]
+    fn ping_until_stopped_in_config_file_should_work() {
+        let test_config_file_path = "tests_data/rnp_cli_options_tests/forever_config.toml";
+        std::fs::create_dir_all("tests_data/rnp_cli_options_tests").unwrap();
+        std::fs::write(
+            test_config_file_path,
+            "[worker_config]\ntargets = [\"10.0.0.1:443\"]\n\n[worker_scheduler_config]\nping_until_stopped = true\n",
+        )
+        .unwrap();
+
+        let opts = RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "--config", test_config_file_path]).unwrap();
+        assert!(opts.ping_common_options.ping_until_stopped);
+
+        let opts = RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "--config", test_config_file_path, "-n", "10"]).unwrap();
+        assert!(!opts.ping_common_options.ping_until_stopped);
+        assert_eq!(Some(10), opts.ping_common_options.ping_count);
+    }
+
+    #[test]
+    fn zero_ping_count_should_be_rejected() {
+        let mut opts = RnpCliOptions::from_iter(&["rnp.exe", "10.0.0.1:443", "-n", "0"]);
+        assert!(opts.prepare_to_use().is_err());
+
+        let test_config_file_path = "tests_data/rnp_cli_options_tests/zero_ping_count_config.toml";
+        std::fs::create_dir_all("tests_data/rnp_cli_options_tests").unwrap();
+        std::fs::write(test_config_file_path, "[worker_config]\ntargets = [\"10.0.0.1:443\"]\n\n[worker_scheduler_config]\nping_count = 0\n")
+            .unwrap();
+
+        let mut opts = RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "--config", test_config_file_path]).unwrap();
+        assert!(!opts.ping_common_options.ping_until_stopped);
+        assert!(opts.prepare_to_use().is_err());
+    }
+
+    #[test]
+    fn duration_without_ping_count_should_ping_until_stopped() {
+        let mut opts = RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "10.0.0.1:443", "--duration", "15m"]).unwrap();
+        opts.prepare_to_use().unwrap();
+        assert!(opts.ping_common_options.ping_until_stopped);
+        assert!(opts.to_ping_runner_config().worker_scheduler_config.ping_until_stopped);
+        assert_eq!(Some(Duration::from_secs(900)), opts.to_ping_runner_config().worker_scheduler_config.duration);
+
+        let mut opts = RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "10.0.0.1:443", "--duration", "15m", "-n", "4"]).unwrap();
+        opts.prepare_to_use().unwrap();
+        assert!(!opts.ping_common_options.ping_until_stopped);
+        assert_eq!(Some(4), opts.to_ping_runner_config().worker_scheduler_config.ping_count);
+
+        let mut opts = RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "10.0.0.1:443"]).unwrap();
+        opts.prepare_to_use().unwrap();
+        assert!(!opts.ping_common_options.ping_until_stopped);
+        assert_eq!(Some(4), opts.to_ping_runner_config().worker_scheduler_config.ping_count);
+
+        // Ping count from config file limits the pings in the same way as the one from command line.
+        let test_config_file_path = "tests_data/rnp_cli_options_tests/duration_config.toml";
+        std::fs::create_dir_all("tests_data/rnp_cli_options_tests").unwrap();
+        std::fs::write(test_config_file_path, "[worker_config]\ntargets = [\"10.0.0.1:443\"]\n\n[worker_scheduler_config]\nping_count = 100\n")
+            .unwrap();
+
+        let mut opts = RnpCliOptions::from_iter_with_config_file(&["rnp.exe", "--config", test_config_file_path, "--duration", "15m"]).unwrap();
+        opts.prepare_to_use().unwrap();
+        assert!(!opts.ping_common_options.ping_until_stopped);
+        assert_eq!(Some(100), opts.to_ping_runner_config().worker_scheduler_config.ping_count);
+    }
 }
@@ -0,0 +1,82 @@
+use rnp::{
+    load_ping_results_from_file, PingResult, PingResultAnalyzer, PingResultComparer, RNP_ABOUT, RNP_ANALYZE_NAME, RNP_AUTHOR,
+    RNP_QUIET_LEVEL_NO_OUTPUT,
+};
+use rnp_analyze_cli_options::RnpAnalyzeCliOptions;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+mod rnp_analyze_cli_options;
+
+#[cfg(not(tarpaulin_include))]
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let mut opts = RnpAnalyzeCliOptions::from_args();
+    if opts.output_options.quiet_level < RNP_QUIET_LEVEL_NO_OUTPUT {
+        println!("{} - {} - {}\n", RNP_ANALYZE_NAME, RNP_AUTHOR, RNP_ABOUT);
+    }
+
+    if let Err(e) = opts.prepare_to_use() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    if opts.compare_options.compare {
+        compare_ping_results(&opts);
+        return;
+    }
+
+    let ping_results = load_ping_results_or_exit(&opts.log_paths);
+    let config = opts.to_ping_result_processor_config();
+    match PingResultAnalyzer::new(&config, vec![]) {
+        Ok(analyzer) => analyzer.run(&ping_results),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Regressions over the thresholds fail the process, so the comparison can be used as a check in scripts.
+fn compare_ping_results(opts: &RnpAnalyzeCliOptions) {
+    let baseline_results = load_ping_results_or_exit(&opts.log_paths[0..1]);
+    let current_results = load_ping_results_or_exit(&opts.log_paths[1..2]);
+
+    let comparer = match PingResultComparer::new(opts.to_ping_result_comparison_config(), &baseline_results, &current_results) {
+        Ok(comparer) => comparer,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    comparer.output_to_console();
+
+    if !comparer.regressions().is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn load_ping_results_or_exit(log_paths: &[PathBuf]) -> Vec<PingResult> {
+    return match load_ping_results(log_paths) {
+        Ok(ping_results) => ping_results,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+}
+
+// Results from all logs are merged in the order of ping time, as if they came from a single run.
+fn load_ping_results(log_paths: &[PathBuf]) -> Result<Vec<PingResult>, String> {
+    let mut ping_results = Vec::new();
+    for log_path in log_paths {
+        for dto in load_ping_results_from_file(log_path)? {
+            let ping_result = PingResult::from_dto(&dto).map_err(|e| format!("Invalid ping result in {}: {}", log_path.display(), e))?;
+            ping_results.push(ping_result);
+        }
+    }
+
+    ping_results.sort_by_key(|ping_result| *ping_result.ping_time());
+    return Ok(ping_results);
+}
@@ -0,0 +1,274 @@
+use rnp::{PingResultComparisonConfig, PingResultProcessorCommonConfig, PingResultProcessorConfig};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+const DEFAULT_LATENCY_BUCKETS: [f64; 10] = [0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 50.0, 100.0, 300.0, 500.0];
+
+#[derive(Debug, StructOpt, PartialEq)]
+#[structopt(name = rnp::RNP_ANALYZE_NAME, author = rnp::RNP_AUTHOR, about = rnp::RNP_ABOUT)]
+pub struct RnpAnalyzeCliOptions {
+    #[structopt(
+        parse(from_os_str),
+        required = true,
+        help = "Ping result logs written by --log-csv or --log-json. Logs ending with .json are parsed as json, otherwise as csv. Results in all logs are analyzed together."
+    )]
+    pub log_paths: Vec<PathBuf>,
+
+    #[structopt(flatten)]
+    pub compare_options: RnpAnalyzeCliCompareOptions,
+
+    #[structopt(flatten)]
+    pub output_options: RnpAnalyzeCliOutputOptions,
+}
+
+#[derive(Debug, StructOpt, PartialEq)]
+pub struct RnpAnalyzeCliCompareOptions {
+    #[structopt(
+        long = "compare",
+        help = "Compare 2 logs instead of analyzing them together. The first log is the baseline and the second one is the current run."
+    )]
+    pub compare: bool,
+
+    #[structopt(
+        long = "max-failure-rate-increase",
+        help = "Exit with failure when the failure rate of any target increases by more than this many percentage points. Requires --compare."
+    )]
+    pub max_failure_rate_increase: Option<f64>,
+
+    #[structopt(
+        long = "max-latency-increase",
+        help = "Exit with failure when any latency percentile of any target increases by more than this many milliseconds. Requires --compare."
+    )]
+    pub max_latency_increase_in_ms: Option<f64>,
+}
+
+#[derive(Debug, StructOpt, PartialEq)]
+pub struct RnpAnalyzeCliOutputOptions {
+    #[structopt(
+        short = "q",
+        parse(from_occurrences),
+        help = "Quiet mode. -q = Don't output each ping result; -qq = Don't output final summaries; -qqq = Don't output anything."
+    )]
+    pub quiet_level: i32,
+
+    #[structopt(long = "log-csv", alias = "oc", parse(from_os_str), help = "Log ping results a csv file. [alias: --oc]")]
+    pub csv_log_path: Option<PathBuf>,
+
+    #[structopt(long = "log-json", alias = "oj", parse(from_os_str), help = "Log ping results to a json file. [alias: --oj]")]
+    pub json_log_path: Option<PathBuf>,
+
+    #[structopt(short = "o", long = "log-text", parse(from_os_str), help = "Log ping results to a text file.")]
+    pub text_log_path: Option<PathBuf>,
+
+    #[structopt(
+        long = "report-html",
+        parse(from_os_str),
+        help = "Write a self-contained html report with charts of round trip time, latency buckets, failures and result scatter map of the results."
+    )]
+    pub html_report_path: Option<PathBuf>,
+
+    #[structopt(short = "r", long, help = "Show ping result scatter map.")]
+    pub show_result_scatter: bool,
+
+    #[structopt(short = "l", long, help = "Show latency (round trip time) scatter map.")]
+    pub show_latency_scatter: bool,
+
+    #[structopt(
+        short = "b",
+        long = "latency-buckets",
+        use_delimiter = true,
+        help = "If set, bucket ping latency (round trip time). Set to 0.0 to use the default one: [0.1,0.5,1.0,10.0,50.0,100.0,300.0,500.0]"
+    )]
+    pub latency_buckets: Option<Vec<f64>>,
+
+    #[structopt(
+        long = "latency-percentiles",
+        use_delimiter = true,
+        default_value = "50,90,99,99.9",
+        help = "Latency (round trip time) percentiles shown in the summary, together with standard deviation and jitter."
+    )]
+    pub latency_percentiles: Vec<f64>,
+}
+
+impl RnpAnalyzeCliOptions {
+    pub fn prepare_to_use(&mut self) -> Result<(), String> {
+        if let Some(invalid_percentile) = self.output_options.latency_percentiles.iter().find(|p| !(**p > 0.0 && **p <= 100.0)) {
+            return Err(format!("Invalid latency percentile: {}. It should be in (0, 100].", invalid_percentile));
+        }
+
+        if self.compare_options.compare {
+            if self.log_paths.len() != 2 {
+                return Err(String::from("Comparing logs requires exactly 2 logs: the baseline first and then the current run."));
+            }
+
+            // Comparison only outputs the differences, so options that write or show each ping result don't apply.
+            let output_options = &self.output_options;
+            if output_options.csv_log_path.is_some()
+                || output_options.json_log_path.is_some()
+                || output_options.text_log_path.is_some()
+                || output_options.html_report_path.is_some()
+                || output_options.show_result_scatter
+                || output_options.show_latency_scatter
+            {
+                return Err(String::from("Logging or scatter map options cannot be used together with --compare."));
+            }
+        } else if self.compare_options.max_failure_rate_increase.is_some() || self.compare_options.max_latency_increase_in_ms.is_some() {
+            return Err(String::from("Regression thresholds can only be used together with --compare."));
+        }
+
+        if let Some(latency_buckets) = &mut self.output_options.latency_buckets {
+            if latency_buckets.is_empty() || (latency_buckets.len() == 1 && latency_buckets[0] == 0.0) {
+                tracing::debug!("Latency bucket set to 0. Use default one.");
+                *latency_buckets = DEFAULT_LATENCY_BUCKETS.to_vec();
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn to_ping_result_processor_config(&self) -> PingResultProcessorConfig {
+        return PingResultProcessorConfig {
+            common_config: PingResultProcessorCommonConfig { quiet_level: self.output_options.quiet_level },
+            exit_on_fail: false,
+            exit_failure_reason: None,
+            csv_log_path: self.output_options.csv_log_path.clone(),
+            json_log_path: self.output_options.json_log_path.clone(),
+            text_log_path: self.output_options.text_log_path.clone(),
+            html_report_path: self.output_options.html_report_path.clone(),
+            metrics_address: None,
+            show_result_scatter: self.output_options.show_result_scatter,
+            show_latency_scatter: self.output_options.show_latency_scatter,
+            show_dashboard: false,
+            latency_buckets: self.output_options.latency_buckets.clone(),
+            latency_percentiles: Some(self.output_options.latency_percentiles.clone()),
+        };
+    }
+
+    pub fn to_ping_result_comparison_config(&self) -> PingResultComparisonConfig {
+        return PingResultComparisonConfig {
+            common_config: PingResultProcessorCommonConfig { quiet_level: self.output_options.quiet_level },
+            latency_percentiles: self.output_options.latency_percentiles.clone(),
+            latency_buckets: self.output_options.latency_buckets.clone().unwrap_or_else(|| DEFAULT_LATENCY_BUCKETS.to_vec()),
+            max_failure_rate_increase: self.compare_options.max_failure_rate_increase,
+            max_latency_increase_in_ms: self.compare_options.max_latency_increase_in_ms,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rnp_analyze_cli_options::*;
+    use pretty_assertions::assert_eq;
+    use rnp::{RNP_QUIET_LEVEL_NONE, RNP_QUIET_LEVEL_NO_PING_RESULT};
+
+    #[test]
+    fn parsing_default_options_should_work() {
+        assert_eq!(
+            RnpAnalyzeCliOptions {
+                log_paths: vec![PathBuf::from("log.csv")],
+                compare_options: RnpAnalyzeCliCompareOptions { compare: false, max_failure_rate_increase: None, max_latency_increase_in_ms: None },
+                output_options: RnpAnalyzeCliOutputOptions {
+                    quiet_level: RNP_QUIET_LEVEL_NONE,
+                    csv_log_path: None,
+                    json_log_path: None,
+                    text_log_path: None,
+                    html_report_path: None,
+                    show_result_scatter: false,
+                    show_latency_scatter: false,
+                    latency_buckets: None,
+                    latency_percentiles: vec![50.0, 90.0, 99.0, 99.9],
+                },
+            },
+            RnpAnalyzeCliOptions::from_iter(&["rnp_analyze.exe", "log.csv"])
+        );
+
+        assert!(RnpAnalyzeCliOptions::from_iter_safe(&["rnp_analyze.exe"]).is_err());
+    }
+
+    #[test]
+    fn parsing_options_should_work() {
+        let mut opts = RnpAnalyzeCliOptions::from_iter(&[
+            "rnp_analyze.exe",
+            "before.csv",
+            "after.json",
+            "-q",
+            "--log-json",
+            "merged.json",
+            "--report-html",
+            "report.html",
+            "-r",
+            "-l",
+            "-b",
+            "0",
+            "--latency-percentiles",
+            "50,95",
+        ]);
+        opts.prepare_to_use().unwrap();
+
+        assert_eq!(vec![PathBuf::from("before.csv"), PathBuf::from("after.json")], opts.log_paths);
+        assert_eq!(
+            PingResultProcessorConfig {
+                common_config: PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_PING_RESULT },
+                exit_on_fail: false,
+                exit_failure_reason: None,
+                csv_log_path: None,
+                json_log_path: Some(PathBuf::from("merged.json")),
+                text_log_path: None,
+                html_report_path: Some(PathBuf::from("report.html")),
+                metrics_address: None,
+                show_result_scatter: true,
+                show_latency_scatter: true,
+                show_dashboard: false,
+                latency_buckets: Some(vec![0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 50.0, 100.0, 300.0, 500.0]),
+                latency_percentiles: Some(vec![50.0, 95.0]),
+            },
+            opts.to_ping_result_processor_config()
+        );
+    }
+
+    #[test]
+    fn invalid_latency_percentiles_should_be_rejected() {
+        let mut opts = RnpAnalyzeCliOptions::from_iter(&["rnp_analyze.exe", "log.csv", "--latency-percentiles", "50,100.5"]);
+        assert!(opts.prepare_to_use().is_err());
+    }
+
+    #[test]
+    fn parsing_compare_options_should_work() {
+        let mut opts = RnpAnalyzeCliOptions::from_iter(&[
+            "rnp_analyze.exe",
+            "before.csv",
+            "after.csv",
+            "--compare",
+            "--max-failure-rate-increase",
+            "1.5",
+            "--max-latency-increase",
+            "10",
+            "--latency-percentiles",
+            "50,99",
+        ]);
+        opts.prepare_to_use().unwrap();
+
+        assert_eq!(
+            PingResultComparisonConfig {
+                common_config: PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NONE },
+                latency_percentiles: vec![50.0, 99.0],
+                latency_buckets: vec![0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 50.0, 100.0, 300.0, 500.0],
+                max_failure_rate_increase: Some(1.5),
+                max_latency_increase_in_ms: Some(10.0),
+            },
+            opts.to_ping_result_comparison_config()
+        );
+    }
+
+    #[test]
+    fn invalid_compare_options_should_be_rejected() {
+        let mut opts = RnpAnalyzeCliOptions::from_iter(&["rnp_analyze.exe", "log.csv", "--compare"]);
+        assert!(opts.prepare_to_use().is_err());
+
+        let mut opts = RnpAnalyzeCliOptions::from_iter(&["rnp_analyze.exe", "before.csv", "after.csv", "--compare", "-r"]);
+        assert!(opts.prepare_to_use().is_err());
+
+        let mut opts = RnpAnalyzeCliOptions::from_iter(&["rnp_analyze.exe", "before.csv", "after.csv", "--max-latency-increase", "10"]);
+        assert!(opts.prepare_to_use().is_err());
+    }
+}
@@ -30,7 +30,7 @@ fn main() {
         })
         .expect("Error setting Ctrl-C handler");
 
-        rnp::stub_server_factory::run(&config, stop_event, server_started_event).await??;
+        rnp::stub_server_factory::run(&config, stop_event, server_started_event)?.await??;
 
         return Ok(());
     });
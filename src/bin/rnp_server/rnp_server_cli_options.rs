@@ -1,4 +1,4 @@
-use rnp::{parse_ping_target, RnpStubServerConfig, RnpSupportedProtocol};
+use rnp::{parse_ping_target, RnpStubServerConfig, RnpStubServerFaultRule, RnpSupportedProtocol};
 use std::net::SocketAddr;
 use std::time::Duration;
 use structopt::StructOpt;
@@ -8,6 +8,9 @@ use structopt::StructOpt;
 pub struct RnpServerCliOptions {
     #[structopt(flatten)]
     pub common_options: RnpServerCliCommonOptions,
+
+    #[structopt(flatten)]
+    pub quic_options: RnpServerCliQuicOptions,
 }
 
 #[derive(Debug, StructOpt, PartialEq)]
@@ -45,6 +48,33 @@ pub struct RnpServerCliCommonOptions {
         help = "When half shutdown is detected, wait specified milliseconds before fully shutdown the connection. [alias: --dd]"
     )]
     pub wait_before_disconnect_in_ms: u64,
+
+    #[structopt(
+        long = "fault-rule",
+        alias = "fr",
+        number_of_values = 1,
+        help = "Fault injected into TCP connections, in the format of \"<action>[=<value>][@<trigger>]\". Can be specified multiple times, \
+                and the first rule triggered is used for each connection. Actions: rst, hang, half-close, delay-accept=<ms>, \
+                close-after=<bytes>. Triggers: always (default), <percentage>%, every=<n>, nth=<n>. Example: rst@30%. [alias: --fr]"
+    )]
+    pub fault_rules: Vec<RnpStubServerFaultRule>,
+}
+
+#[derive(Debug, StructOpt, PartialEq)]
+pub struct RnpServerCliQuicOptions {
+    #[structopt(
+        long = "alpn",
+        default_value = "h3-29,hq-29",
+        use_delimiter = true,
+        help = "ALPN protocols accepted in QUIC handshakes. Handshakes with other ALPN protocols will fail."
+    )]
+    pub alpn_protocols: Vec<String>,
+
+    #[structopt(long, help = "Reject all QUIC handshakes with ALPN, which fail with no_application_protocol error.")]
+    pub reject_alpn: bool,
+
+    #[structopt(long, alias = "coh", help = "Close QUIC connection as soon as receiving the handshake. [alias: --coh]")]
+    pub close_on_handshake: bool,
 }
 
 impl RnpServerCliOptions {
@@ -55,11 +85,15 @@ impl RnpServerCliOptions {
             protocol: self.common_options.protocol,
             server_address: self.common_options.server_address,
             close_on_accept: self.common_options.close_on_accept,
+            close_on_handshake: self.quic_options.close_on_handshake,
+            alpn_protocols: self.quic_options.alpn_protocols,
+            reject_alpn: self.quic_options.reject_alpn,
             sleep_before_write: Duration::from_millis(self.common_options.sleep_before_write_in_ms),
             write_chunk_size: self.common_options.write_chunk_size,
             write_count_limit: self.common_options.write_count_limit,
             report_interval: Duration::from_millis(self.common_options.report_interval_in_ms),
             wait_before_disconnect: Duration::from_millis(self.common_options.wait_before_disconnect_in_ms),
+            fault_rules: self.common_options.fault_rules,
         };
     }
 }
@@ -68,7 +102,7 @@ impl RnpServerCliOptions {
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
-    use rnp::{RnpStubServerConfig, RnpSupportedProtocol};
+    use rnp::{RnpStubServerConfig, RnpStubServerFaultAction, RnpStubServerFaultTrigger, RnpSupportedProtocol};
     use structopt::StructOpt;
 
     #[test]
@@ -84,6 +118,12 @@ mod tests {
                     write_count_limit: 1,
                     sleep_before_write_in_ms: 0,
                     wait_before_disconnect_in_ms: 0,
+                    fault_rules: vec![],
+                },
+                quic_options: RnpServerCliQuicOptions {
+                    alpn_protocols: vec![String::from("h3-29"), String::from("hq-29")],
+                    reject_alpn: false,
+                    close_on_handshake: false,
                 },
             },
             RnpServerCliOptions::from_iter(&["rnp_server.exe", "10.0.0.1:443"])
@@ -103,6 +143,12 @@ mod tests {
                     write_count_limit: 10,
                     sleep_before_write_in_ms: 1000,
                     wait_before_disconnect_in_ms: 3000,
+                    fault_rules: vec![RnpStubServerFaultRule { action: RnpStubServerFaultAction::Reset, trigger: RnpStubServerFaultTrigger::Always }],
+                },
+                quic_options: RnpServerCliQuicOptions {
+                    alpn_protocols: vec![String::from("h3-29"), String::from("hq-29")],
+                    reject_alpn: false,
+                    close_on_handshake: true,
                 },
             },
             RnpServerCliOptions::from_iter(&[
@@ -113,6 +159,7 @@ mod tests {
                 "-r",
                 "2000",
                 "--coa",
+                "--coh",
                 "-w",
                 "1024",
                 "--wc",
@@ -121,6 +168,8 @@ mod tests {
                 "1000",
                 "--dd",
                 "3000",
+                "--fr",
+                "rst",
             ])
         );
     }
@@ -137,7 +186,22 @@ mod tests {
                     write_chunk_size: 2048,
                     write_count_limit: 20,
                     sleep_before_write_in_ms: 2000,
-                    wait_before_disconnect_in_ms: 3000
+                    wait_before_disconnect_in_ms: 3000,
+                    fault_rules: vec![
+                        RnpStubServerFaultRule {
+                            action: RnpStubServerFaultAction::CloseAfterBytes(1024),
+                            trigger: RnpStubServerFaultTrigger::EveryNthConnection(3)
+                        },
+                        RnpStubServerFaultRule {
+                            action: RnpStubServerFaultAction::DelayAccept(Duration::from_millis(500)),
+                            trigger: RnpStubServerFaultTrigger::Probability(0.25)
+                        },
+                    ],
+                },
+                quic_options: RnpServerCliQuicOptions {
+                    alpn_protocols: vec![String::from("hq-29"), String::from("h3")],
+                    reject_alpn: true,
+                    close_on_handshake: true,
                 },
             },
             RnpServerCliOptions::from_iter(&[
@@ -148,6 +212,10 @@ mod tests {
                 "--report-interval",
                 "3000",
                 "--close-on-accept",
+                "--alpn",
+                "hq-29,h3",
+                "--reject-alpn",
+                "--close-on-handshake",
                 "--write-chunk-size",
                 "2048",
                 "--write-count-limit",
@@ -156,6 +224,10 @@ mod tests {
                 "2000",
                 "--disconnect-delay",
                 "3000",
+                "--fault-rule",
+                "close-after=1024@every=3",
+                "--fault-rule",
+                "delay-accept=500@25%",
             ])
         );
     }
@@ -168,10 +240,17 @@ mod tests {
                 server_address: "10.0.0.1:443".parse().unwrap(),
                 report_interval: Duration::from_millis(1000),
                 close_on_accept: true,
+                close_on_handshake: true,
+                alpn_protocols: vec![String::from("hq-29")],
+                reject_alpn: true,
                 write_chunk_size: 2000,
                 write_count_limit: 3000,
                 sleep_before_write: Duration::from_millis(4000),
                 wait_before_disconnect: Duration::from_millis(5000),
+                fault_rules: vec![RnpStubServerFaultRule {
+                    action: RnpStubServerFaultAction::Hang,
+                    trigger: RnpStubServerFaultTrigger::NthConnection(2)
+                }],
             },
             RnpServerCliOptions {
                 common_options: RnpServerCliCommonOptions {
@@ -183,9 +262,38 @@ mod tests {
                     write_count_limit: 3000,
                     sleep_before_write_in_ms: 4000,
                     wait_before_disconnect_in_ms: 5000,
+                    fault_rules: vec![RnpStubServerFaultRule {
+                        action: RnpStubServerFaultAction::Hang,
+                        trigger: RnpStubServerFaultTrigger::NthConnection(2)
+                    }],
                 },
+                quic_options: RnpServerCliQuicOptions { alpn_protocols: vec![String::from("hq-29")], reject_alpn: true, close_on_handshake: true },
             }
             .to_stub_server_config()
         );
     }
+
+    #[test]
+    fn parsing_fault_rules_should_work() {
+        let rules: Vec<RnpStubServerFaultRule> =
+            ["half-close", "hang@always", "RST@nth=1", "close-after=10@100%"].iter().map(|rule| rule.parse().unwrap()).collect();
+        assert_eq!(
+            vec![
+                RnpStubServerFaultRule { action: RnpStubServerFaultAction::HalfClose, trigger: RnpStubServerFaultTrigger::Always },
+                RnpStubServerFaultRule { action: RnpStubServerFaultAction::Hang, trigger: RnpStubServerFaultTrigger::Always },
+                RnpStubServerFaultRule { action: RnpStubServerFaultAction::Reset, trigger: RnpStubServerFaultTrigger::NthConnection(1) },
+                RnpStubServerFaultRule {
+                    action: RnpStubServerFaultAction::CloseAfterBytes(10),
+                    trigger: RnpStubServerFaultTrigger::Probability(1.0)
+                },
+            ],
+            rules
+        );
+
+        for invalid_rule in
+            ["", "drop", "rst=1", "hang@", "hang@every=0", "hang@150%", "hang@-5%", "hang@NaN%", "close-after=0", "delay-accept=1s"].iter()
+        {
+            assert!(invalid_rule.parse::<RnpStubServerFaultRule>().is_err(), "Rule \"{}\" should be invalid", invalid_rule);
+        }
+    }
 }
@@ -1,22 +1,37 @@
 pub use ping_clients::ping_client::*;
 use ping_clients::ping_client_factory;
-pub use ping_clients::ping_client_factory::PingClientFactory;
+pub use ping_clients::ping_client_factory::{PingClientFactory, PingClientRegistry};
 use ping_port_picker::PingPortPicker;
+use ping_rate_scheduler::PingRateScheduler;
 pub use ping_result::PingResult;
+pub use ping_result_analyzer::PingResultAnalyzer;
+pub use ping_result_comparer::PingResultComparer;
 use ping_result_processing_worker::PingResultProcessingWorker;
 pub use ping_result_processors::ping_result_processor::*;
 pub use ping_runners::ping_runner_core::PingRunnerCore;
+pub use ping_runners::ping_runner_stream::{PingResultStream, PingRunHandle};
+pub use ping_runners::ping_stop_condition_checker::PingStopReason;
+pub use ping_runners::ping_target_resolver::*;
+pub use ping_runners::throughput_runner::{ThroughputRunner, ThroughputSummary};
 pub use ping_runners::*;
+use ping_stop_condition_checker::PingStopConditionChecker;
 pub use rnp_basic_types::*;
 pub use rnp_config::*;
+pub use rnp_config_builder::RnpPingRunnerConfigBuilder;
 pub use rnp_dto::*;
-pub use rnp_utils::parse_ping_target;
+pub use rnp_error::RnpError;
+pub use rnp_utils::{
+    load_ping_results_from_file, load_ping_runner_config_from_file, load_ping_targets_from_file, parse_http_header, parse_http_path,
+    parse_ping_target, parse_ping_target_with_host_name, parse_protocol_option,
+};
 pub use stub_servers::stub_server_factory;
 
 mod ping_runners;
 mod rnp_basic_types;
 mod rnp_config;
+mod rnp_config_builder;
 mod rnp_dto;
+mod rnp_error;
 mod rnp_utils;
 mod stub_servers;
 
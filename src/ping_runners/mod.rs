@@ -1,9 +1,16 @@
 pub mod ping_clients;
 pub mod ping_port_picker;
+pub mod ping_rate_scheduler;
 pub mod ping_result;
+pub mod ping_result_analyzer;
+pub mod ping_result_comparer;
 pub mod ping_result_processing_worker;
 pub mod ping_result_processors;
 pub mod ping_runner_core;
+pub mod ping_runner_stream;
+pub mod ping_stop_condition_checker;
+pub mod ping_target_resolver;
 pub mod ping_worker;
+pub mod throughput_runner;
 
-pub use ping_worker::PingWorker;
+pub use ping_worker::{PingWorker, PingWorkerContext};
@@ -1,17 +1,36 @@
 pub mod ping_client;
 pub mod ping_client_factory;
+pub(crate) mod ping_client_one_way_latency;
 mod ping_client_tcp;
+mod ping_client_tcp_persistent;
+mod ping_client_udp;
 
 // quinn cannot be built for windows.arm64, because it doesn't support uint128 and cause compile
 // failure in boringssl and ring. So before it is ready, we will have to ignore it.
 #[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
 mod ping_client_quic;
 
+#[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+mod ping_client_tls;
+
+// HTTP ping shares the TLS implementation with TLS ping for HTTPS, so it has the same platform limitation.
+#[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+mod ping_client_http;
+
 #[cfg(test)]
 mod ping_client_test_common;
 
 #[cfg(test)]
 mod ping_client_tcp_tests;
 
+#[cfg(test)]
+mod ping_client_udp_tests;
+
 #[cfg(test)]
 mod ping_client_quic_tests;
+
+#[cfg(test)]
+mod ping_client_tls_tests;
+
+#[cfg(test)]
+mod ping_client_http_tests;
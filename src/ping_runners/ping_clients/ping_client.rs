@@ -1,6 +1,10 @@
 use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::time::Duration;
+use std::{fmt, io};
 
 #[derive(thiserror::Error, Debug)]
 pub enum PingClientWarning {
@@ -9,6 +13,11 @@ pub enum PingClientWarning {
 
     #[error("{0}")]
     AppHandshakeFailed(Box<dyn std::error::Error + Send>),
+
+    // The persistent connection is established again, and the error is why the previous connection was dropped. It is reported as a
+    // separate result, which only contains the reconnect, and the RTT is the connect time.
+    #[error("{0}")]
+    Reconnected(Box<dyn std::error::Error + Send>),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -20,12 +29,146 @@ pub enum PingClientError {
     PingFailed(Box<dyn std::error::Error + Send>),
 }
 
+// Stable classification of what went wrong in a ping, so failures can be told apart without parsing the error messages, e.g. a
+// firewall silently dropping packets shows up as timeouts, while a stopped service shows up as refused connections.
+//
+// TTL exceeded can only be told apart when the OS reports it separately, which is only the case for UDP on Windows. Everywhere
+// else, ICMP time exceeded shows up as host unreachable or timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PingFailureClass {
+    Refused,
+    HostUnreachable,
+    NetworkUnreachable,
+    TtlExceeded,
+    Timeout,
+    LocalPortInUse,
+    TlsFailure,
+    Other,
+}
+
+// WSAENETRESET, which is returned on UDP sockets when an ICMP time exceeded message is received.
+#[cfg(target_os = "windows")]
+const WINDOWS_TTL_EXPIRED_ERROR: i32 = 10052;
+
+impl PingFailureClass {
+    pub fn from_error(error: &(dyn Error + 'static)) -> PingFailureClass {
+        if let Some(io_error) = error.downcast_ref::<io::Error>() {
+            return PingFailureClass::from_io_error(io_error);
+        }
+
+        #[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+        if let Some(failure_class) = PingFailureClass::from_tls_error(error) {
+            return failure_class;
+        }
+
+        return PingFailureClass::Other;
+    }
+
+    fn from_io_error(error: &io::Error) -> PingFailureClass {
+        // TLS streams report handshake failures as IO errors that wraps the TLS error.
+        #[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+        if let Some(failure_class) = error.get_ref().and_then(|inner| PingFailureClass::from_tls_error(inner)) {
+            return failure_class;
+        }
+
+        #[cfg(target_os = "windows")]
+        if error.raw_os_error() == Some(WINDOWS_TTL_EXPIRED_ERROR) {
+            return PingFailureClass::TtlExceeded;
+        }
+
+        return match error.kind() {
+            io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset => PingFailureClass::Refused,
+            io::ErrorKind::HostUnreachable => PingFailureClass::HostUnreachable,
+            io::ErrorKind::NetworkUnreachable => PingFailureClass::NetworkUnreachable,
+            io::ErrorKind::TimedOut => PingFailureClass::Timeout,
+
+            // Address not available is returned on connect, when the same source port is still used by another connection to the
+            // same target, e.g. in TIME_WAIT state.
+            io::ErrorKind::AddrInUse | io::ErrorKind::AddrNotAvailable => PingFailureClass::LocalPortInUse,
+            _ => PingFailureClass::Other,
+        };
+    }
+
+    #[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+    fn from_tls_error(error: &(dyn Error + 'static)) -> Option<PingFailureClass> {
+        if error.is::<rustls::Error>() {
+            return Some(PingFailureClass::TlsFailure);
+        }
+
+        // QUIC carries TLS alerts as crypto error codes, which are 0x100 to 0x1ff.
+        let is_crypto_error_code = |code: u64| (0x100..0x200).contains(&code);
+        return match error.downcast_ref::<quinn::ConnectionError>() {
+            Some(quinn::ConnectionError::TransportError(e)) if is_crypto_error_code(e.code.into()) => Some(PingFailureClass::TlsFailure),
+            Some(quinn::ConnectionError::ConnectionClosed(e)) if is_crypto_error_code(e.error_code.into()) => Some(PingFailureClass::TlsFailure),
+            _ => None,
+        };
+    }
+}
+
+impl FromStr for PingFailureClass {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<PingFailureClass, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "refused" => Ok(PingFailureClass::Refused),
+            "hostunreachable" => Ok(PingFailureClass::HostUnreachable),
+            "networkunreachable" => Ok(PingFailureClass::NetworkUnreachable),
+            "ttlexceeded" => Ok(PingFailureClass::TtlExceeded),
+            "timeout" => Ok(PingFailureClass::Timeout),
+            "localportinuse" => Ok(PingFailureClass::LocalPortInUse),
+            "tlsfailure" => Ok(PingFailureClass::TlsFailure),
+            "other" => Ok(PingFailureClass::Other),
+            _ => Err(String::from("Invalid failure class")),
+        }
+    }
+}
+
+impl fmt::Display for PingFailureClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let failure_class = match self {
+            PingFailureClass::Refused => "Refused",
+            PingFailureClass::HostUnreachable => "HostUnreachable",
+            PingFailureClass::NetworkUnreachable => "NetworkUnreachable",
+            PingFailureClass::TtlExceeded => "TtlExceeded",
+            PingFailureClass::Timeout => "Timeout",
+            PingFailureClass::LocalPortInUse => "LocalPortInUse",
+            PingFailureClass::TlsFailure => "TlsFailure",
+            PingFailureClass::Other => "Other",
+        };
+
+        write!(f, "{}", failure_class)
+    }
+}
+
+impl Serialize for PingFailureClass {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PingFailureClass {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let failure_class = String::deserialize(deserializer)?;
+        failure_class.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug)]
 pub struct PingClientPingResultDetails {
     pub actual_local_addr: Option<SocketAddr>,
     pub round_trip_time: Duration,
     pub is_timeout: bool,
     pub warning: Option<PingClientWarning>,
+
+    // Time spent on the app handshake after the connection is established, such as TLS handshake.
+    pub handshake_time: Option<Duration>,
+
+    // Time from sending the app request to receiving the first byte and the full response, such as HTTP request.
+    pub first_byte_time: Option<Duration>,
+    pub response_time: Option<Duration>,
+
+    // One-way delays measured with the timestamps returned by rnp_server.
+    pub one_way_latency: Option<PingOneWayLatency>,
 }
 
 impl PingClientPingResultDetails {
@@ -35,10 +178,43 @@ impl PingClientPingResultDetails {
         is_timeout: bool,
         warning: Option<PingClientWarning>,
     ) -> PingClientPingResultDetails {
-        PingClientPingResultDetails { actual_local_addr, round_trip_time, is_timeout, warning }
+        PingClientPingResultDetails {
+            actual_local_addr,
+            round_trip_time,
+            is_timeout,
+            warning,
+            handshake_time: None,
+            first_byte_time: None,
+            response_time: None,
+            one_way_latency: None,
+        }
+    }
+
+    pub fn with_handshake_time(mut self, handshake_time: Duration) -> PingClientPingResultDetails {
+        self.handshake_time = Some(handshake_time);
+        return self;
+    }
+
+    pub fn with_response_time(mut self, first_byte_time: Option<Duration>, response_time: Option<Duration>) -> PingClientPingResultDetails {
+        self.first_byte_time = first_byte_time;
+        self.response_time = response_time;
+        return self;
+    }
+
+    pub fn with_one_way_latency(mut self, one_way_latency: Option<PingOneWayLatency>) -> PingClientPingResultDetails {
+        self.one_way_latency = one_way_latency;
+        return self;
     }
 }
 
+// The clock offset is the estimated offset of the server clock against the local clock, which can be negative.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PingOneWayLatency {
+    pub forward_delay: Duration,
+    pub reverse_delay: Duration,
+    pub clock_offset_in_us: i64,
+}
+
 pub type PingClientResult<T, E = PingClientError> = std::result::Result<T, E>;
 
 #[async_trait]
@@ -49,3 +225,61 @@ pub trait PingClient {
 
     async fn ping(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn failure_class_should_be_classified_from_io_errors() {
+        let test_cases = vec![
+            (io::ErrorKind::ConnectionRefused, PingFailureClass::Refused),
+            (io::ErrorKind::ConnectionReset, PingFailureClass::Refused),
+            (io::ErrorKind::HostUnreachable, PingFailureClass::HostUnreachable),
+            (io::ErrorKind::NetworkUnreachable, PingFailureClass::NetworkUnreachable),
+            (io::ErrorKind::TimedOut, PingFailureClass::Timeout),
+            (io::ErrorKind::AddrInUse, PingFailureClass::LocalPortInUse),
+            (io::ErrorKind::AddrNotAvailable, PingFailureClass::LocalPortInUse),
+            (io::ErrorKind::PermissionDenied, PingFailureClass::Other),
+        ];
+
+        for (error_kind, expected_failure_class) in test_cases {
+            let error: Box<dyn Error + Send> = Box::new(io::Error::new(error_kind, "test error"));
+            assert_eq!(expected_failure_class, PingFailureClass::from_error(error.as_ref()), "Error kind: {:?}", error_kind);
+        }
+
+        let error: Box<dyn Error + Send> = Box::new(regex::Error::Syntax("test error".to_string()));
+        assert_eq!(PingFailureClass::Other, PingFailureClass::from_error(error.as_ref()));
+    }
+
+    #[test]
+    #[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+    fn failure_class_should_be_classified_from_tls_errors() {
+        let error: Box<dyn Error + Send> = Box::new(rustls::Error::AlertReceived(rustls::AlertDescription::HandshakeFailure));
+        assert_eq!(PingFailureClass::TlsFailure, PingFailureClass::from_error(error.as_ref()));
+
+        // TLS streams wrap the TLS errors in IO errors.
+        let error: Box<dyn Error + Send> = Box::new(io::Error::new(io::ErrorKind::InvalidData, rustls::Error::NoCertificatesPresented));
+        assert_eq!(PingFailureClass::TlsFailure, PingFailureClass::from_error(error.as_ref()));
+    }
+
+    #[test]
+    fn parsing_failure_class_should_work() {
+        for failure_class in [
+            PingFailureClass::Refused,
+            PingFailureClass::HostUnreachable,
+            PingFailureClass::NetworkUnreachable,
+            PingFailureClass::TtlExceeded,
+            PingFailureClass::Timeout,
+            PingFailureClass::LocalPortInUse,
+            PingFailureClass::TlsFailure,
+            PingFailureClass::Other,
+        ] {
+            assert_eq!(Ok(failure_class), failure_class.to_string().parse::<PingFailureClass>());
+        }
+
+        assert_eq!(Ok(PingFailureClass::TlsFailure), "tlsfailure".parse::<PingFailureClass>());
+        assert!("Unknown".parse::<PingFailureClass>().is_err());
+    }
+}
@@ -1,49 +1,152 @@
 use crate::ping_clients::ping_client_tcp::PingClientTcp;
+use crate::ping_clients::ping_client_tcp_persistent::PingClientTcpPersistent;
+use crate::ping_clients::ping_client_udp::PingClientUdp;
 use crate::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Debug;
+use std::sync::Arc;
 
 #[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
 use crate::ping_clients::ping_client_quic::PingClientQuic;
 
-pub type PingClientFactory = fn(protocol: &RnpSupportedProtocol, config: &PingClientConfig) -> Option<Box<dyn PingClient + Send + Sync>>;
+#[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+use crate::ping_clients::ping_client_tls::PingClientTls;
+
+#[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+use crate::ping_clients::ping_client_http::PingClientHttp;
+
+/// Factory of ping clients for the protocols that are not built into rnp. Closures with the same signature as `create_ping_client`
+/// are factories as well, so they can capture whatever configuration the plugin needs.
+pub trait PingClientFactory {
+    /// Create a ping client for the protocol, or return None if the protocol is not supported by this factory.
+    fn create_ping_client(&self, protocol: &RnpSupportedProtocol, config: &PingClientConfig) -> Option<Box<dyn PingClient + Send + Sync>>;
+}
+
+impl<F> PingClientFactory for F
+where
+    F: Fn(&RnpSupportedProtocol, &PingClientConfig) -> Option<Box<dyn PingClient + Send + Sync>>,
+{
+    fn create_ping_client(&self, protocol: &RnpSupportedProtocol, config: &PingClientConfig) -> Option<Box<dyn PingClient + Send + Sync>> {
+        return self(protocol, config);
+    }
+}
+
+/// Named ping client factories, which are used to create ping clients for protocols with the same name, such as `-m myproto`.
+/// Protocol names are case insensitive, same as the built-in ones. Registering a built-in protocol name replaces the built-in ping
+/// client, which is mostly useful for testing.
+///
+/// # Examples
+///
+/// ```
+/// use rnp::*;
+///
+/// let mut registry = PingClientRegistry::new();
+/// registry.register("myproto", |_protocol, config| {
+///     let _community = config.protocol_options.get("community");
+///     return None; // Create the ping client here.
+/// });
+///
+/// assert!(registry.contains(&"MyProto".parse().unwrap()));
+/// ```
+#[derive(Clone, Default)]
+pub struct PingClientRegistry {
+    factories: BTreeMap<String, Arc<dyn PingClientFactory + Send + Sync>>,
+}
+
+impl PingClientRegistry {
+    pub fn new() -> PingClientRegistry {
+        return PingClientRegistry { factories: BTreeMap::new() };
+    }
+
+    pub fn register<F>(&mut self, protocol_name: &str, factory: F)
+    where
+        F: Fn(&RnpSupportedProtocol, &PingClientConfig) -> Option<Box<dyn PingClient + Send + Sync>> + Send + Sync + 'static,
+    {
+        self.register_factory(protocol_name, Arc::new(factory));
+    }
+
+    pub fn register_factory(&mut self, protocol_name: &str, factory: Arc<dyn PingClientFactory + Send + Sync>) {
+        if self.factories.insert(protocol_name.to_uppercase(), factory).is_some() {
+            tracing::warn!("Ping client factory for protocol {} is registered more than once, replacing the previous one.", protocol_name);
+        }
+    }
+
+    pub fn contains(&self, protocol: &RnpSupportedProtocol) -> bool {
+        return self.factories.contains_key(&protocol.to_string().to_uppercase());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.factories.is_empty();
+    }
+
+    /// Upper cased names of all registered protocols, in alphabetical order.
+    pub fn protocol_names(&self) -> Vec<&str> {
+        return self.factories.keys().map(|name| name.as_str()).collect();
+    }
+
+    pub fn create_ping_client(&self, protocol: &RnpSupportedProtocol, config: &PingClientConfig) -> Option<Box<dyn PingClient + Send + Sync>> {
+        return self.factories.get(&protocol.to_string().to_uppercase()).and_then(|factory| factory.create_ping_client(protocol, config));
+    }
+}
+
+impl Debug for PingClientRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.protocol_names()).finish()
+    }
+}
+
+impl PartialEq for PingClientRegistry {
+    fn eq(&self, other: &PingClientRegistry) -> bool {
+        return self.protocol_names() == other.protocol_names();
+    }
+}
 
 pub fn new_ping_client(
     protocol: &RnpSupportedProtocol,
     config: &PingClientConfig,
-    external_ping_client_factory: Option<PingClientFactory>,
-) -> Box<dyn PingClient + Send + Sync> {
-    if let Some(factory) = external_ping_client_factory {
-        if let Some(ping_client) = factory(protocol, config) {
-            return ping_client;
-        }
+    ping_client_registry: &PingClientRegistry,
+) -> Result<Box<dyn PingClient + Send + Sync>, RnpError> {
+    if let Some(ping_client) = ping_client_registry.create_ping_client(protocol, config) {
+        return Ok(ping_client);
     }
 
     return new_inbox_ping_client(protocol, config);
 }
 
 #[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
-fn new_inbox_ping_client(protocol: &RnpSupportedProtocol, config: &PingClientConfig) -> Box<dyn PingClient + Send + Sync> {
+fn new_inbox_ping_client(protocol: &RnpSupportedProtocol, config: &PingClientConfig) -> Result<Box<dyn PingClient + Send + Sync>, RnpError> {
     match protocol {
-        RnpSupportedProtocol::TCP => return Box::new(PingClientTcp::new(config)),
-        RnpSupportedProtocol::QUIC => return Box::new(PingClientQuic::new(config)),
-        RnpSupportedProtocol::External(p) => panic!("Protocol {} is not supported!", p),
+        RnpSupportedProtocol::TCP if config.use_persistent_connection => return Ok(Box::new(PingClientTcpPersistent::new(config))),
+        RnpSupportedProtocol::TCP => return Ok(Box::new(PingClientTcp::new(config))),
+        RnpSupportedProtocol::UDP => return Ok(Box::new(PingClientUdp::new(config))),
+        RnpSupportedProtocol::QUIC => return Ok(Box::new(PingClientQuic::new(config))),
+        RnpSupportedProtocol::TLS => return Ok(Box::new(PingClientTls::new(config))),
+        RnpSupportedProtocol::HTTP => return Ok(Box::new(PingClientHttp::new(config, false))),
+        RnpSupportedProtocol::HTTPS => return Ok(Box::new(PingClientHttp::new(config, true))),
+        RnpSupportedProtocol::External(p) => return Err(RnpError::UnsupportedProtocol(p.clone())),
     }
 }
 
+// QUIC, TLS and HTTP pings are not supported yet for Windows ARM64.
 #[cfg(all(target_os = "windows", target_arch = "aarch64"))]
-fn new_inbox_ping_client(protocol: &RnpSupportedProtocol, config: &PingClientConfig) -> Box<dyn PingClient + Send + Sync> {
+fn new_inbox_ping_client(protocol: &RnpSupportedProtocol, config: &PingClientConfig) -> Result<Box<dyn PingClient + Send + Sync>, RnpError> {
     match protocol {
-        RnpSupportedProtocol::TCP => return Box::new(PingClientTcp::new(config)),
-        RnpSupportedProtocol::QUIC => {
-            panic!("Sorry, QUIC ping is not supported yet for Windows ARM64.")
-        }
-        RnpSupportedProtocol::External(p) => panic!(format!("Protocol {} is not supported!", p)),
+        RnpSupportedProtocol::TCP if config.use_persistent_connection => return Ok(Box::new(PingClientTcpPersistent::new(config))),
+        RnpSupportedProtocol::TCP => return Ok(Box::new(PingClientTcp::new(config))),
+        RnpSupportedProtocol::UDP => return Ok(Box::new(PingClientUdp::new(config))),
+        _ => return Err(RnpError::UnsupportedProtocol(protocol.to_string())),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::ping_clients::ping_client_factory::new_ping_client;
-    use crate::{PingClientConfig, RnpSupportedProtocol};
+    use crate::ping_clients::ping_client_tcp::PingClientTcp;
+    use crate::{PingClientConfig, PingClientRegistry, RnpError, RnpSupportedProtocol};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
     use std::time::Duration;
 
     #[test]
@@ -58,9 +161,131 @@ mod tests {
             log_tls_key: false,
             alpn_protocol: None,
             use_timer_rtt: false,
+            http_method: String::from("GET"),
+            http_path: String::from("/"),
+            http_headers: vec![],
+            http_expected_status: None,
+            http_body_regex: None,
+            measure_one_way_latency: false,
+            use_persistent_connection: false,
+            protocol_options: BTreeMap::new(),
         };
 
-        let ping_client = new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+        let ping_client = new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
         assert_eq!("TCP", ping_client.protocol());
     }
+
+    #[test]
+    fn create_udp_ping_client_should_work() {
+        let config = PingClientConfig {
+            wait_timeout: Duration::from_millis(100),
+            time_to_live: Some(128),
+            check_disconnect: false,
+            wait_before_disconnect: Duration::ZERO,
+            disconnect_timeout: Duration::from_millis(2000),
+            server_name: None,
+            log_tls_key: false,
+            alpn_protocol: None,
+            use_timer_rtt: false,
+            http_method: String::from("GET"),
+            http_path: String::from("/"),
+            http_headers: vec![],
+            http_expected_status: None,
+            http_body_regex: None,
+            measure_one_way_latency: false,
+            use_persistent_connection: false,
+            protocol_options: BTreeMap::new(),
+        };
+
+        let ping_client = new_ping_client(&RnpSupportedProtocol::UDP, &config, &PingClientRegistry::new()).unwrap();
+        assert_eq!("UDP", ping_client.protocol());
+    }
+
+    #[test]
+    fn create_tls_ping_client_should_work() {
+        let config = PingClientConfig {
+            wait_timeout: Duration::from_millis(100),
+            time_to_live: Some(128),
+            check_disconnect: false,
+            wait_before_disconnect: Duration::ZERO,
+            disconnect_timeout: Duration::from_millis(2000),
+            server_name: Some("localhost".to_string()),
+            log_tls_key: false,
+            alpn_protocol: Some("h2".to_string()),
+            use_timer_rtt: false,
+            http_method: String::from("GET"),
+            http_path: String::from("/"),
+            http_headers: vec![],
+            http_expected_status: None,
+            http_body_regex: None,
+            measure_one_way_latency: false,
+            use_persistent_connection: false,
+            protocol_options: BTreeMap::new(),
+        };
+
+        let ping_client = new_ping_client(&RnpSupportedProtocol::TLS, &config, &PingClientRegistry::new()).unwrap();
+        assert_eq!("TLS", ping_client.protocol());
+    }
+
+    #[test]
+    fn create_http_ping_client_should_work() {
+        let config = PingClientConfig {
+            wait_timeout: Duration::from_millis(100),
+            time_to_live: Some(128),
+            check_disconnect: false,
+            wait_before_disconnect: Duration::ZERO,
+            disconnect_timeout: Duration::from_millis(2000),
+            server_name: Some("localhost".to_string()),
+            log_tls_key: false,
+            alpn_protocol: None,
+            use_timer_rtt: false,
+            http_method: String::from("GET"),
+            http_path: String::from("/"),
+            http_headers: vec![],
+            http_expected_status: None,
+            http_body_regex: None,
+            measure_one_way_latency: false,
+            use_persistent_connection: false,
+            protocol_options: BTreeMap::new(),
+        };
+
+        let ping_client = new_ping_client(&RnpSupportedProtocol::HTTP, &config, &PingClientRegistry::new()).unwrap();
+        assert_eq!("HTTP", ping_client.protocol());
+
+        let ping_client = new_ping_client(&RnpSupportedProtocol::HTTPS, &config, &PingClientRegistry::new()).unwrap();
+        assert_eq!("HTTPS", ping_client.protocol());
+    }
+
+    #[test]
+    fn create_external_ping_client_with_registered_factory_should_work() {
+        let created_client_count = Arc::new(AtomicU32::new(0));
+        let created_client_count_in_factory = created_client_count.clone();
+
+        let mut registry = PingClientRegistry::new();
+        registry.register("MyProto", move |protocol, config| {
+            assert_eq!(RnpSupportedProtocol::External(String::from("myproto")), *protocol);
+            assert_eq!(Some(&String::from("public")), config.protocol_options.get("community"));
+            created_client_count_in_factory.fetch_add(1, Ordering::SeqCst);
+            return Some(Box::new(PingClientTcp::new(config)));
+        });
+        assert_eq!(vec!["MYPROTO"], registry.protocol_names());
+
+        let mut config = PingClientConfig::default();
+        config.protocol_options.insert(String::from("community"), String::from("public"));
+        let ping_client = new_ping_client(&"myproto".parse().unwrap(), &config, &registry).unwrap();
+        assert_eq!("TCP", ping_client.protocol());
+        assert_eq!(1, created_client_count.load(Ordering::SeqCst));
+
+        // Built-in protocols are not affected by the registered factories.
+        let ping_client = new_ping_client(&RnpSupportedProtocol::UDP, &config, &registry).unwrap();
+        assert_eq!("UDP", ping_client.protocol());
+        assert_eq!(1, created_client_count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn create_external_ping_client_without_factory_should_fail() {
+        let ping_client =
+            new_ping_client(&RnpSupportedProtocol::External(String::from("ICMP")), &PingClientConfig::default(), &PingClientRegistry::new());
+        assert!(matches!(ping_client, Err(RnpError::UnsupportedProtocol(p)) if p == "ICMP"));
+    }
 }
@@ -0,0 +1,375 @@
+use crate::ping_clients::ping_client_tcp::PingClientTcp;
+use crate::ping_clients::ping_client_tls::PingClientTls;
+use crate::*;
+use async_trait::async_trait;
+use regex::Regex;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::Instant;
+
+pub struct PingClientHttp {
+    config: PingClientConfig,
+    tcp_client: PingClientTcp,
+    tls_client: Option<PingClientTls>,
+    body_regex: Result<Option<Regex>, regex::Error>,
+}
+
+impl PingClientHttp {
+    pub fn new(config: &PingClientConfig, use_tls: bool) -> PingClientHttp {
+        return PingClientHttp {
+            config: config.clone(),
+            tcp_client: PingClientTcp::new(config),
+            tls_client: if use_tls { Some(PingClientTls::new(config)) } else { None },
+            body_regex: config.http_body_regex.as_ref().map(|r| Regex::new(r)).transpose(),
+        };
+    }
+
+    #[tracing::instrument(name = "Running HTTP ping in ping client", level = "debug", skip(self))]
+    async fn ping_target(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
+        let body_regex = self.body_regex.as_ref().map_err(|e| PingClientError::PreparationFailed(Box::new(e.clone())))?;
+
+        let (connection, rtt) = match self.tcp_client.connect_for_ping(source, target).await? {
+            (Some(connection), rtt) => (connection, rtt),
+            (None, rtt) => return Ok(PingClientPingResultDetails::new(None, rtt, true, None)),
+        };
+
+        // If getting local address failed, we ignore it, the same as TCP ping.
+        let local_addr = connection.local_addr().ok();
+
+        // Once TCP connection is established, the underlying network is reachable, so any failure after it, such as TLS handshake
+        // failure or unexpected status code, is reported as app handshake warning instead of failure.
+        let mut handshake_time: Option<Duration> = None;
+        let (response, first_byte_time, response_time) = match &self.tls_client {
+            Some(tls_client) => {
+                let (handshake_result, tls_handshake_time) = tls_client.handshake(connection, target).await?;
+                handshake_time = Some(tls_handshake_time);
+                match handshake_result {
+                    Ok(stream) => self.run_http_request(stream, target).await,
+                    Err(e) => {
+                        let warning = Some(PingClientWarning::AppHandshakeFailed(Box::new(e)));
+                        return Ok(PingClientPingResultDetails::new(local_addr, rtt, false, warning).with_handshake_time(tls_handshake_time));
+                    }
+                }
+            }
+
+            None => self.run_http_request(connection, target).await,
+        };
+
+        let warning = self.check_response(response, body_regex);
+        let mut details = PingClientPingResultDetails::new(local_addr, rtt, false, warning).with_response_time(first_byte_time, response_time);
+        if let Some(handshake_time) = handshake_time {
+            details = details.with_handshake_time(handshake_time);
+        }
+
+        return Ok(details);
+    }
+
+    // Send the HTTP request and read the full response within the wait timeout. Both first byte time and full response time
+    // are measured from the moment we start sending the request, and are returned even if the request failed.
+    async fn run_http_request<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        mut stream: S,
+        target: &SocketAddr,
+    ) -> (io::Result<HttpResponse>, Option<Duration>, Option<Duration>) {
+        let start_time = Instant::now();
+        let mut first_byte_time: Option<Duration> = None;
+
+        let request_future = self.send_request_and_read_response(&mut stream, target, start_time, &mut first_byte_time);
+        let response = match tokio::time::timeout(self.config.wait_timeout, request_future).await {
+            Ok(response) => response,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "HTTP request timed out.")),
+        };
+
+        let response_time = if response.is_ok() { Some(Instant::now().duration_since(start_time)) } else { None };
+        return (response, first_byte_time, response_time);
+    }
+
+    async fn send_request_and_read_response<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        stream: &mut S,
+        target: &SocketAddr,
+        start_time: Instant,
+        first_byte_time: &mut Option<Duration>,
+    ) -> io::Result<HttpResponse> {
+        stream.write_all(self.build_request(target).as_bytes()).await?;
+        stream.flush().await?;
+
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut read_buffer = vec![0u8; 4096];
+        loop {
+            let read_size = match stream.read(&mut read_buffer).await {
+                Ok(read_size) => read_size,
+
+                // Many servers close the TLS connection without sending close_notify, which should be treated as EOF.
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => 0,
+                Err(e) => return Err(e),
+            };
+
+            if read_size == 0 {
+                break;
+            }
+
+            if first_byte_time.is_none() {
+                *first_byte_time = Some(Instant::now().duration_since(start_time));
+            }
+
+            buffer.extend_from_slice(&read_buffer[..read_size]);
+            if let Some(head) = parse_http_response_head(&buffer)? {
+                if head.is_complete(&buffer, &self.config.http_method) {
+                    break;
+                }
+            }
+        }
+
+        return match parse_http_response_head(&buffer)? {
+            Some(head) => Ok(HttpResponse { status_code: head.status_code, body: head.decode_body(&buffer) }),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed before HTTP response is received.")),
+        };
+    }
+
+    fn build_request(&self, target: &SocketAddr) -> String {
+        let has_header = |name: &str| self.config.http_headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name));
+
+        let mut request = format!("{} {} HTTP/1.1\r\n", self.config.http_method, self.config.http_path);
+        if !has_header("Host") {
+            request.push_str(&format!("Host: {}\r\n", self.host_header_value(target)));
+        }
+        if !has_header("User-Agent") {
+            request.push_str(&format!("User-Agent: rnp/{}\r\n", env!("CARGO_PKG_VERSION")));
+        }
+        if !has_header("Accept") {
+            request.push_str("Accept: */*\r\n");
+        }
+
+        // We always close the connection after each ping, so every ping goes through the full connect and request.
+        request.push_str("Connection: close\r\n");
+        for (name, value) in &self.config.http_headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+
+        return request;
+    }
+
+    // Host header comes from the server name in config, same as SNI. If it is not specified, we fallback to the target IP.
+    fn host_header_value(&self, target: &SocketAddr) -> String {
+        let host = match &self.config.server_name {
+            Some(server_name) => server_name.clone(),
+            None if target.is_ipv6() => format!("[{}]", target.ip()),
+            None => target.ip().to_string(),
+        };
+
+        let default_port = if self.tls_client.is_some() { 443 } else { 80 };
+        if target.port() == default_port {
+            return host;
+        }
+
+        return format!("{}:{}", host, target.port());
+    }
+
+    fn check_response(&self, response: io::Result<HttpResponse>, body_regex: &Option<Regex>) -> Option<PingClientWarning> {
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => return Some(PingClientWarning::AppHandshakeFailed(Box::new(e))),
+        };
+
+        // Without expected status code, all status codes below 400 are treated as succeeded, since redirects are also valid responses.
+        let is_status_expected = match self.config.http_expected_status {
+            Some(expected_status) => response.status_code == expected_status,
+            None => response.status_code < 400,
+        };
+        if !is_status_expected {
+            let error = io::Error::other(format!("Unexpected HTTP status code: {}.", response.status_code));
+            return Some(PingClientWarning::AppHandshakeFailed(Box::new(error)));
+        }
+
+        if let Some(body_regex) = body_regex {
+            if !body_regex.is_match(&String::from_utf8_lossy(&response.body)) {
+                let error = io::Error::other(format!("HTTP response body doesn't match \"{}\".", body_regex));
+                return Some(PingClientWarning::AppHandshakeFailed(Box::new(error)));
+            }
+        }
+
+        return None;
+    }
+}
+
+#[async_trait]
+impl PingClient for PingClientHttp {
+    fn protocol(&self) -> &'static str {
+        if self.tls_client.is_some() {
+            "HTTPS"
+        } else {
+            "HTTP"
+        }
+    }
+
+    async fn prepare_ping(&mut self, _: &SocketAddr) -> Result<(), PingClientError> {
+        Ok(())
+    }
+
+    async fn ping(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
+        return self.ping_target(source, target).await;
+    }
+}
+
+#[derive(Debug)]
+struct HttpResponse {
+    status_code: u16,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct HttpResponseHead {
+    pub status_code: u16,
+    pub head_size: usize,
+    pub content_length: Option<usize>,
+    pub is_chunked: bool,
+}
+
+impl HttpResponseHead {
+    // Check if the full response is received, so we don't need to wait for the server to close the connection.
+    pub fn is_complete(&self, buffer: &[u8], method: &str) -> bool {
+        let body = &buffer[self.head_size..];
+
+        // Responses to HEAD requests and 101, 204 and 304 responses never have a body. Other 1xx heads are interim and skipped
+        // when parsing the head, so they never reach here.
+        if method.eq_ignore_ascii_case("HEAD") || self.status_code < 200 || self.status_code == 204 || self.status_code == 304 {
+            return true;
+        }
+
+        // The chunk framing needs to be walked through, because the last chunk can be followed by trailers, and the chunk data
+        // itself can contain anything, including the bytes of the last chunk.
+        if self.is_chunked {
+            return find_chunked_body_end(body).is_some();
+        }
+
+        return match self.content_length {
+            Some(content_length) => body.len() >= content_length,
+            None => false,
+        };
+    }
+
+    pub fn decode_body(&self, buffer: &[u8]) -> Vec<u8> {
+        let body = &buffer[self.head_size..];
+        if !self.is_chunked {
+            return match self.content_length {
+                Some(content_length) if content_length < body.len() => body[..content_length].to_vec(),
+                _ => body.to_vec(),
+            };
+        }
+
+        let mut decoded_body = Vec::new();
+        let mut remaining = body;
+        while let Some(size_line_end) = find_subsequence(remaining, b"\r\n") {
+            let size_line = String::from_utf8_lossy(&remaining[..size_line_end]);
+            let chunk_size = match usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16) {
+                Ok(chunk_size) if chunk_size > 0 => chunk_size,
+                _ => break,
+            };
+
+            let chunk_start = size_line_end + 2;
+            let chunk_end = std::cmp::min(chunk_start + chunk_size, remaining.len());
+            decoded_body.extend_from_slice(&remaining[chunk_start..chunk_end]);
+            remaining = &remaining[std::cmp::min(chunk_end + 2, remaining.len())..];
+        }
+
+        return decoded_body;
+    }
+}
+
+// Parse the status line and headers of the final HTTP response. Interim 1xx responses, such as 100 Continue, are skipped, since
+// the real response follows them. Returns None if the final head is not fully received yet.
+pub(crate) fn parse_http_response_head(buffer: &[u8]) -> io::Result<Option<HttpResponseHead>> {
+    let mut head_start = 0;
+    loop {
+        let head = match parse_single_http_response_head(&buffer[head_start..])? {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+
+        // 101 Switching Protocols is the last response on this connection, so it is the only 1xx response treated as final.
+        if head.status_code >= 200 || head.status_code == 101 {
+            return Ok(Some(HttpResponseHead { head_size: head_start + head.head_size, ..head }));
+        }
+
+        head_start += head.head_size;
+    }
+}
+
+fn parse_single_http_response_head(buffer: &[u8]) -> io::Result<Option<HttpResponseHead>> {
+    let head_end = match find_subsequence(buffer, b"\r\n\r\n") {
+        Some(head_end) => head_end,
+        None => return Ok(None),
+    };
+
+    let invalid_response = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let head = String::from_utf8_lossy(&buffer[..head_end]);
+    let mut lines = head.split("\r\n");
+
+    let status_line = lines.next().unwrap_or("");
+    let mut status_line_parts = status_line.split(' ');
+    if !status_line_parts.next().unwrap_or("").starts_with("HTTP/") {
+        return Err(invalid_response("Invalid HTTP response status line."));
+    }
+    let status_code =
+        status_line_parts.next().and_then(|code| code.parse::<u16>().ok()).ok_or_else(|| invalid_response("Invalid HTTP response status code."))?;
+
+    let mut content_length: Option<usize> = None;
+    let mut is_chunked = false;
+    for line in lines {
+        let (name, value) = match line.find(':') {
+            Some(colon_index) => (line[..colon_index].trim(), line[(colon_index + 1)..].trim()),
+            None => continue,
+        };
+
+        if name.eq_ignore_ascii_case("Content-Length") {
+            content_length = Some(value.parse::<usize>().map_err(|_| invalid_response("Invalid HTTP response content length."))?);
+        } else if name.eq_ignore_ascii_case("Transfer-Encoding") {
+            is_chunked = value.to_ascii_lowercase().contains("chunked");
+        }
+    }
+
+    return Ok(Some(HttpResponseHead { status_code, head_size: head_end + 4, content_length, is_chunked }));
+}
+
+// Find the end of the chunked body, including the last chunk and the trailers after it. Returns None if the body is not fully
+// received yet. Malformed chunk sizes end the body right away, since no more data can be decoded after them anyway.
+fn find_chunked_body_end(body: &[u8]) -> Option<usize> {
+    let mut position = 0;
+    loop {
+        let size_line_end = position + find_subsequence(&body[position..], b"\r\n")?;
+        let size_line = String::from_utf8_lossy(&body[position..size_line_end]);
+        let chunk_size = match usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16) {
+            Ok(chunk_size) => chunk_size,
+            Err(_) => return Some(body.len()),
+        };
+
+        position = size_line_end + 2;
+        if chunk_size == 0 {
+            break;
+        }
+
+        // Chunk data is always followed by CRLF.
+        position = position.checked_add(chunk_size)?.checked_add(2)?;
+        if position > body.len() {
+            return None;
+        }
+    }
+
+    // The last chunk is followed by optional trailer lines and then an empty line.
+    loop {
+        let line_end = position + find_subsequence(&body[position..], b"\r\n")?;
+        if line_end == position {
+            return Some(line_end + 2);
+        }
+
+        position = line_end + 2;
+    }
+}
+
+fn find_subsequence(buffer: &[u8], pattern: &[u8]) -> Option<usize> {
+    return buffer.windows(pattern.len()).position(|window| window == pattern);
+}
@@ -0,0 +1,263 @@
+use crate::ping_clients::ping_client_http::*;
+use crate::ping_clients::ping_client_test_common::*;
+use crate::{
+    ping_clients::ping_client_factory, rnp_test_common, PingClientConfig, PingClientRegistry, PingResult, PingResultDto, RnpSupportedProtocol,
+};
+use chrono::{TimeZone, Utc};
+use futures_intrusive::sync::ManualResetEvent;
+use pretty_assertions::assert_eq;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::runtime::Runtime;
+
+#[test]
+fn ping_client_http_should_work_when_pinging_good_host() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11637".parse::<SocketAddr>().unwrap();
+    start_run_http_test_server(&rt, server_address, "HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nHealthy");
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_http_default_config();
+        config.http_body_regex = Some(String::from("^Health"));
+        let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::HTTP, &config, &PingClientRegistry::new()).unwrap();
+
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+        assert!(!ping_result.is_timeout);
+        assert!(ping_result.warning.is_none());
+        assert!(ping_result.first_byte_time.is_some());
+        assert!(ping_result.response_time.is_some());
+        assert!(ping_result.handshake_time.is_none());
+    });
+}
+
+#[test]
+fn ping_client_http_should_warn_when_status_code_is_unexpected() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11638".parse::<SocketAddr>().unwrap();
+    start_run_http_test_server(&rt, server_address, "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n");
+
+    rt.block_on(async move {
+        let config = create_ping_client_http_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::HTTP, &config, &PingClientRegistry::new()).unwrap();
+
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("Unexpected HTTP status code: 503.");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_http_should_warn_when_body_does_not_match() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11639".parse::<SocketAddr>().unwrap();
+    start_run_http_test_server(&rt, server_address, "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nDown\r\n0\r\n\r\n");
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_http_default_config();
+        config.http_expected_status = Some(200);
+        config.http_body_regex = Some(String::from("Healthy"));
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::HTTP, &config, &PingClientRegistry::new()).unwrap();
+
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("HTTP response body doesn't match \"Healthy\".");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_http_body_mismatch_warning_should_round_trip_through_log() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11640".parse::<SocketAddr>().unwrap();
+    start_run_http_test_server(&rt, server_address, "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nDown");
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_http_default_config();
+        config.http_body_regex = Some(String::from(r#"^\{"status":\s*"ok"\}$"#));
+        let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::HTTP, &config, &PingClientRegistry::new()).unwrap();
+
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+        // Time and RTT are rounded in the logs, so fixed values are used here to compare the results loaded back.
+        let result = PingResult::new(
+            &Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap(),
+            1,
+            "HTTP",
+            server_address,
+            source,
+            false,
+            true,
+            Duration::from_millis(10),
+            false,
+            ping_result.warning,
+            None,
+        );
+        let dto = result.create_dto();
+        assert_eq!(r#"HTTP response body doesn't match "^\{"status":\s*"ok"\}$"."#, dto.handshake_error);
+
+        let json_dto: PingResultDto = serde_json::from_str(&result.format_as_json_string()).unwrap();
+        assert_eq!(dto, PingResult::from_dto(&json_dto).unwrap().create_dto());
+
+        let csv_string = result.format_as_csv_string();
+        let mut csv_reader = csv::ReaderBuilder::new().has_headers(false).from_reader(csv_string.as_bytes());
+        let csv_dto: PingResultDto = csv_reader.deserialize().next().unwrap().unwrap();
+        assert_eq!(dto, PingResult::from_dto(&csv_dto).unwrap().create_dto());
+    });
+}
+
+#[test]
+fn ping_client_http_should_fail_when_pinging_non_existing_port() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async move {
+        let config = create_ping_client_http_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::HTTP, &config, &PingClientRegistry::new()).unwrap();
+
+        let expected_result = if cfg!(windows) { ExpectedTestCaseResult::Timeout } else { ExpectedTestCaseResult::Failed("connection refused") };
+        ping_client_should_fail_when_pinging_non_existing_port(&mut ping_client, &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_http_should_fail_when_body_regex_is_invalid() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_http_default_config();
+        config.http_body_regex = Some(String::from("("));
+        let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::HTTP, &config, &PingClientRegistry::new()).unwrap();
+
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let target = "127.0.0.1:56789".parse::<SocketAddr>().unwrap();
+        let ping_result = ping_client.ping(&source, &target).await;
+        assert!(matches!(ping_result, Err(crate::PingClientError::PreparationFailed(_))));
+    });
+}
+
+#[test]
+fn parsing_http_response_head_should_work() {
+    assert_eq!(None, parse_http_response_head(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n").unwrap());
+
+    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nOK";
+    let head = parse_http_response_head(response).unwrap().unwrap();
+    assert_eq!(HttpResponseHead { status_code: 200, head_size: 38, content_length: Some(2), is_chunked: false }, head);
+    assert!(head.is_complete(response, "GET"));
+    assert!(!head.is_complete(&response[..39], "GET"));
+    assert_eq!(b"OK".to_vec(), head.decode_body(response));
+
+    let response = b"HTTP/1.1 404 Not Found\r\ntransfer-encoding: chunked\r\n\r\n4\r\nWiki\r\n5;ext=1\r\npedia\r\n0\r\n\r\n";
+    let head = parse_http_response_head(response).unwrap().unwrap();
+    assert_eq!(404, head.status_code);
+    assert!(head.is_chunked);
+    assert!(head.is_complete(response, "GET"));
+    assert_eq!(b"Wikipedia".to_vec(), head.decode_body(response));
+
+    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n";
+    let head = parse_http_response_head(response).unwrap().unwrap();
+    assert!(head.is_complete(response, "HEAD"));
+    assert!(!head.is_complete(response, "GET"));
+
+    assert!(parse_http_response_head(b"SSH-2.0-OpenSSH\r\n\r\n").is_err());
+    assert!(parse_http_response_head(b"HTTP/1.1 abc OK\r\n\r\n").is_err());
+}
+
+#[test]
+fn parsing_http_response_head_should_skip_interim_responses() {
+    let response = b"HTTP/1.1 100 Continue\r\n\r\n";
+    assert_eq!(None, parse_http_response_head(response).unwrap());
+
+    let response = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: </a.css>\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nOK";
+    let head = parse_http_response_head(response).unwrap().unwrap();
+    assert_eq!(HttpResponseHead { status_code: 200, head_size: response.len() - 2, content_length: Some(2), is_chunked: false }, head);
+    assert!(head.is_complete(response, "GET"));
+    assert!(!head.is_complete(&response[..response.len() - 1], "GET"));
+    assert_eq!(b"OK".to_vec(), head.decode_body(response));
+
+    let response = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n";
+    let head = parse_http_response_head(response).unwrap().unwrap();
+    assert_eq!(101, head.status_code);
+    assert!(head.is_complete(response, "GET"));
+}
+
+#[test]
+fn checking_chunked_http_response_completion_should_follow_chunk_framing() {
+    // Trailers after the last chunk.
+    let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n0\r\nExpires: never\r\n\r\n";
+    let head = parse_http_response_head(response).unwrap().unwrap();
+    assert!(head.is_complete(response, "GET"));
+    assert!(!head.is_complete(&response[..response.len() - 2], "GET"));
+    assert_eq!(b"Wiki".to_vec(), head.decode_body(response));
+
+    // Chunk data which ends with the same bytes as the last chunk.
+    let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n10\r\nabcdefghijk0\r\n\r\n\r\n0\r\n\r\n";
+    let head = parse_http_response_head(response).unwrap().unwrap();
+    let first_chunk_end = head.head_size + 20;
+    assert!(response[..first_chunk_end].ends_with(b"0\r\n\r\n"));
+    assert!(!head.is_complete(&response[..first_chunk_end], "GET"));
+    assert!(!head.is_complete(&response[..first_chunk_end + 2], "GET"));
+    assert!(head.is_complete(response, "GET"));
+    assert_eq!(b"abcdefghijk0\r\n\r\n".to_vec(), head.decode_body(response));
+}
+
+// A minimal HTTP server which reads the request head and writes the same canned response to every connection.
+fn start_run_http_test_server(rt: &Runtime, server_address: SocketAddr, response: &'static str) {
+    let ready_event = Arc::new(ManualResetEvent::new(false));
+    let ready_event_clone = ready_event.clone();
+    rt.spawn(async move {
+        let listener = TcpListener::bind(server_address).await.unwrap();
+        ready_event_clone.set();
+
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut read_buffer = vec![0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut read_buffer).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&read_buffer[..n]),
+                    }
+                }
+
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    rt.block_on(ready_event.wait());
+}
+
+fn create_ping_client_http_default_config() -> PingClientConfig {
+    return PingClientConfig {
+        wait_timeout: Duration::from_millis(300),
+        time_to_live: None,
+        check_disconnect: false,
+        wait_before_disconnect: Duration::ZERO,
+        disconnect_timeout: Duration::ZERO,
+        server_name: Some("localhost".to_string()),
+        log_tls_key: false,
+        alpn_protocol: None,
+        use_timer_rtt: false,
+        http_method: String::from("GET"),
+        http_path: String::from("/health"),
+        http_headers: vec![(String::from("X-Test"), String::from("1"))],
+        http_expected_status: None,
+        http_body_regex: None,
+        measure_one_way_latency: false,
+        use_persistent_connection: false,
+        protocol_options: BTreeMap::new(),
+    };
+}
@@ -0,0 +1,197 @@
+use crate::PingOneWayLatency;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// The timestamp exchange between rnp and rnp_server for measuring one-way latency.
+//
+// Request sent by rnp after the connection is established:
+// - 4 bytes: Magic "RNPT".
+// - 4 bytes: Sequence number.
+// - 8 bytes: Client send timestamp (T1) in microseconds since UNIX epoch.
+//
+// Response sent by rnp_server, all fields in the request are echoed back, followed by:
+// - 8 bytes: Server receive timestamp (T2) in microseconds since UNIX epoch.
+// - 8 bytes: Server send timestamp (T3) in microseconds since UNIX epoch.
+//
+// The client receive timestamp (T4) is taken by rnp when the response is received.
+pub const ONE_WAY_LATENCY_PROBE_MAGIC: &[u8; 4] = b"RNPT";
+pub const ONE_WAY_LATENCY_REQUEST_SIZE: usize = 16;
+pub const ONE_WAY_LATENCY_RESPONSE_SIZE: usize = 32;
+
+// Same as the clock filter in NTP, we only use the most recent samples to estimate the clock offset.
+const ONE_WAY_LATENCY_SAMPLE_WINDOW_SIZE: usize = 8;
+
+pub fn now_in_us() -> u64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_micros() as u64);
+}
+
+pub fn build_one_way_latency_request(sequence: u32, client_send_time_in_us: u64) -> Vec<u8> {
+    let mut request = Vec::with_capacity(ONE_WAY_LATENCY_REQUEST_SIZE);
+    request.extend_from_slice(ONE_WAY_LATENCY_PROBE_MAGIC);
+    request.extend_from_slice(&sequence.to_be_bytes());
+    request.extend_from_slice(&client_send_time_in_us.to_be_bytes());
+    return request;
+}
+
+// The stub server only answers the data that looks exactly like a request, and all other data is drained as before.
+pub fn build_one_way_latency_response(request: &[u8], server_receive_time_in_us: u64, server_send_time_in_us: u64) -> Option<Vec<u8>> {
+    if request.len() != ONE_WAY_LATENCY_REQUEST_SIZE || &request[..4] != ONE_WAY_LATENCY_PROBE_MAGIC {
+        return None;
+    }
+
+    let mut response = Vec::with_capacity(ONE_WAY_LATENCY_RESPONSE_SIZE);
+    response.extend_from_slice(request);
+    response.extend_from_slice(&server_receive_time_in_us.to_be_bytes());
+    response.extend_from_slice(&server_send_time_in_us.to_be_bytes());
+    return Some(response);
+}
+
+pub fn parse_one_way_latency_response(response: &[u8], sequence: u32, client_receive_time_in_us: u64) -> Option<OneWayLatencySample> {
+    if response.len() != ONE_WAY_LATENCY_RESPONSE_SIZE || &response[..4] != ONE_WAY_LATENCY_PROBE_MAGIC {
+        return None;
+    }
+
+    let read_u64 = |offset: usize| -> u64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&response[offset..(offset + 8)]);
+        return u64::from_be_bytes(bytes);
+    };
+
+    let mut sequence_bytes = [0u8; 4];
+    sequence_bytes.copy_from_slice(&response[4..8]);
+    if u32::from_be_bytes(sequence_bytes) != sequence {
+        return None;
+    }
+
+    return Some(OneWayLatencySample {
+        client_send_time_in_us: read_u64(8) as i64,
+        server_receive_time_in_us: read_u64(16) as i64,
+        server_send_time_in_us: read_u64(24) as i64,
+        client_receive_time_in_us: client_receive_time_in_us as i64,
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OneWayLatencySample {
+    pub client_send_time_in_us: i64,
+    pub server_receive_time_in_us: i64,
+    pub server_send_time_in_us: i64,
+    pub client_receive_time_in_us: i64,
+}
+
+impl OneWayLatencySample {
+    // Offset of the server clock against the client clock, assuming the path is symmetric: ((T2 - T1) + (T3 - T4)) / 2.
+    pub fn clock_offset_in_us(&self) -> i64 {
+        return ((self.server_receive_time_in_us - self.client_send_time_in_us) + (self.server_send_time_in_us - self.client_receive_time_in_us)) / 2;
+    }
+
+    // Round trip delay without the time spent in the server: (T4 - T1) - (T3 - T2).
+    pub fn round_trip_delay_in_us(&self) -> i64 {
+        return (self.client_receive_time_in_us - self.client_send_time_in_us) - (self.server_send_time_in_us - self.server_receive_time_in_us);
+    }
+}
+
+// The clock offset can't be told apart from the path asymmetry in a single sample, so we follow the NTP clock filter and trust the
+// offset from the sample with the lowest round trip delay in the recent samples, because it is the least likely to be queued.
+// The one-way delays of every sample are then calculated with this offset, which shows the queueing on each direction separately.
+#[derive(Debug, Default)]
+pub struct OneWayLatencyEstimator {
+    samples: VecDeque<OneWayLatencySample>,
+}
+
+impl OneWayLatencyEstimator {
+    pub fn add_sample(&mut self, sample: OneWayLatencySample) -> PingOneWayLatency {
+        if self.samples.len() >= ONE_WAY_LATENCY_SAMPLE_WINDOW_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+
+        let clock_offset_in_us = self.samples.iter().min_by_key(|s| s.round_trip_delay_in_us()).map_or(0, |s| s.clock_offset_in_us());
+
+        // Delays can go below 0 with clock jitters, which are not meaningful, so we cap them at 0.
+        let forward_delay_in_us = (sample.server_receive_time_in_us - sample.client_send_time_in_us - clock_offset_in_us).max(0);
+        let reverse_delay_in_us = (sample.client_receive_time_in_us - sample.server_send_time_in_us + clock_offset_in_us).max(0);
+
+        return PingOneWayLatency {
+            forward_delay: Duration::from_micros(forward_delay_in_us as u64),
+            reverse_delay: Duration::from_micros(reverse_delay_in_us as u64),
+            clock_offset_in_us,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn create_sample(
+        client_send_time_in_us: i64,
+        forward_delay_in_us: i64,
+        reverse_delay_in_us: i64,
+        clock_offset_in_us: i64,
+    ) -> OneWayLatencySample {
+        let server_receive_time_in_us = client_send_time_in_us + forward_delay_in_us + clock_offset_in_us;
+        let server_send_time_in_us = server_receive_time_in_us + 100;
+        return OneWayLatencySample {
+            client_send_time_in_us,
+            server_receive_time_in_us,
+            server_send_time_in_us,
+            client_receive_time_in_us: server_send_time_in_us - clock_offset_in_us + reverse_delay_in_us,
+        };
+    }
+
+    #[test]
+    fn one_way_latency_request_and_response_should_round_trip() {
+        let request = build_one_way_latency_request(12345, 1000);
+        assert_eq!(ONE_WAY_LATENCY_REQUEST_SIZE, request.len());
+        assert_eq!(None, build_one_way_latency_response(&request[..8], 2000, 3000));
+        assert_eq!(None, build_one_way_latency_response(&[0u8; ONE_WAY_LATENCY_REQUEST_SIZE], 2000, 3000));
+
+        let response = build_one_way_latency_response(&request, 2000, 3000).unwrap();
+        assert_eq!(
+            Some(OneWayLatencySample {
+                client_send_time_in_us: 1000,
+                server_receive_time_in_us: 2000,
+                server_send_time_in_us: 3000,
+                client_receive_time_in_us: 4000
+            }),
+            parse_one_way_latency_response(&response, 12345, 4000)
+        );
+        assert_eq!(None, parse_one_way_latency_response(&response, 12346, 4000));
+        assert_eq!(None, parse_one_way_latency_response(&response[..16], 12345, 4000));
+    }
+
+    #[test]
+    fn one_way_latency_sample_should_calculate_offset_and_delay() {
+        let sample = create_sample(1_000_000, 3000, 1000, 50_000);
+        assert_eq!(51_000, sample.clock_offset_in_us());
+        assert_eq!(4000, sample.round_trip_delay_in_us());
+    }
+
+    #[test]
+    fn one_way_latency_estimator_should_use_offset_from_lowest_delay_sample() {
+        let mut estimator = OneWayLatencyEstimator::default();
+
+        // The first sample is symmetric, so the offset is estimated correctly.
+        assert_eq!(
+            PingOneWayLatency { forward_delay: Duration::from_micros(1000), reverse_delay: Duration::from_micros(1000), clock_offset_in_us: 50_000 },
+            estimator.add_sample(create_sample(1_000_000, 1000, 1000, 50_000))
+        );
+
+        // Upload congestion shows up in the forward delay only, since the offset is still taken from the first sample.
+        assert_eq!(
+            PingOneWayLatency { forward_delay: Duration::from_micros(9000), reverse_delay: Duration::from_micros(1000), clock_offset_in_us: 50_000 },
+            estimator.add_sample(create_sample(2_000_000, 9000, 1000, 50_000))
+        );
+
+        // Once the symmetric sample moves out of the window, the offset is taken from the lowest delay sample left.
+        for i in 0..ONE_WAY_LATENCY_SAMPLE_WINDOW_SIZE {
+            estimator.add_sample(create_sample(3_000_000 + i as i64 * 1_000_000, 9000, 1000, 50_000));
+        }
+        assert_eq!(
+            PingOneWayLatency { forward_delay: Duration::from_micros(5000), reverse_delay: Duration::from_micros(5000), clock_offset_in_us: 54_000 },
+            estimator.add_sample(create_sample(20_000_000, 9000, 1000, 50_000))
+        );
+    }
+}
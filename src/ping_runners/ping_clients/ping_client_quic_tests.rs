@@ -1,8 +1,69 @@
 use crate::ping_clients::ping_client_test_common::*;
-use crate::{ping_clients::ping_client_factory, rnp_test_common, PingClientConfig, RnpSupportedProtocol};
+use crate::stub_servers::stub_server_factory;
+use crate::{ping_clients::ping_client_factory, rnp_test_common, PingClientConfig, PingClientRegistry, RnpStubServerConfig, RnpSupportedProtocol};
+use futures_intrusive::sync::ManualResetEvent;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::runtime::Runtime;
 
+#[test]
+fn ping_client_quic_should_work_when_pinging_good_host() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11837".parse::<SocketAddr>().unwrap();
+    let server_config = create_quic_stub_server_default_config(&server_address);
+    start_run_quic_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let config = create_ping_client_quic_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, &PingClientRegistry::new()).unwrap();
+        ping_client_should_work_when_pinging_good_host(&mut ping_client, &server_address).await;
+    });
+}
+
+#[test]
+fn ping_client_quic_should_fail_when_server_rejects_alpn() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11838".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_quic_stub_server_default_config(&server_address);
+    server_config.reject_alpn = true;
+    start_run_quic_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let config = create_ping_client_quic_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, &PingClientRegistry::new()).unwrap();
+
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("aborted by peer: the cryptographic handshake failed: error 120");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::ZERO, &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_quic_should_fail_when_server_closes_connection_on_handshake() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11839".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_quic_stub_server_default_config(&server_address);
+    server_config.close_on_handshake = true;
+    start_run_quic_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let config = create_ping_client_quic_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, &PingClientRegistry::new()).unwrap();
+
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("aborted by peer: the cryptographic handshake failed: error 40");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::ZERO, &expected_result).await;
+    });
+}
+
 #[test]
 fn ping_client_quic_should_fail_when_pinging_non_existing_host() {
     rnp_test_common::initialize();
@@ -10,7 +71,7 @@ fn ping_client_quic_should_fail_when_pinging_non_existing_host() {
 
     rt.block_on(async move {
         let config = create_ping_client_quic_default_config();
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, &PingClientRegistry::new()).unwrap();
         ping_client_should_fail_when_pinging_non_existing_host(&mut ping_client, &ExpectedTestCaseResult::Timeout).await;
     });
 }
@@ -22,7 +83,7 @@ fn ping_client_quic_should_fail_when_pinging_non_existing_port() {
 
     rt.block_on(async move {
         let config = create_ping_client_quic_default_config();
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, &PingClientRegistry::new()).unwrap();
         ping_client_should_fail_when_pinging_non_existing_port(&mut ping_client, &ExpectedTestCaseResult::Timeout).await;
     });
 }
@@ -34,14 +95,39 @@ fn ping_client_quic_should_fail_when_binding_invalid_source_ip() {
 
     rt.block_on(async move {
         let config = create_ping_client_quic_default_config();
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::QUIC, &config, &PingClientRegistry::new()).unwrap();
 
-        let expected_result =
-            ExpectedTestCaseResult::Failed("The requested address is not valid in its context. (os error 10049)");
+        let expected_result = ExpectedTestCaseResult::Failed("The requested address is not valid in its context. (os error 10049)");
         ping_client_should_fail_when_binding_invalid_source_ip(&mut ping_client, &expected_result).await;
     });
 }
 
+fn create_quic_stub_server_default_config(server_address: &SocketAddr) -> RnpStubServerConfig {
+    return RnpStubServerConfig {
+        protocol: RnpSupportedProtocol::QUIC,
+        server_address: *server_address,
+        close_on_accept: false,
+        close_on_handshake: false,
+        alpn_protocols: vec!["hq-29".to_string()],
+        reject_alpn: false,
+        sleep_before_write: Duration::ZERO,
+        write_chunk_size: 0,
+        write_count_limit: 0,
+        report_interval: Duration::from_secs(1),
+        wait_before_disconnect: Duration::ZERO,
+        fault_rules: vec![],
+    };
+}
+
+fn start_run_quic_stub_server(rt: &Runtime, stub_server_config: RnpStubServerConfig) {
+    let ready_event = Arc::new(ManualResetEvent::new(false));
+    let ready_event_clone = ready_event.clone();
+    rt.spawn(async move {
+        let _ = stub_server_factory::run(&stub_server_config, Arc::new(ManualResetEvent::new(false)), ready_event_clone).unwrap().await;
+    });
+    rt.block_on(ready_event.wait());
+}
+
 fn create_ping_client_quic_default_config() -> PingClientConfig {
     return PingClientConfig {
         wait_timeout: Duration::from_millis(300),
@@ -53,5 +139,13 @@ fn create_ping_client_quic_default_config() -> PingClientConfig {
         log_tls_key: false,
         alpn_protocol: Some("hq-29".to_string()),
         use_timer_rtt: false,
+        http_method: String::from("GET"),
+        http_path: String::from("/"),
+        http_headers: vec![],
+        http_expected_status: None,
+        http_body_regex: None,
+        measure_one_way_latency: false,
+        use_persistent_connection: false,
+        protocol_options: BTreeMap::new(),
     };
 }
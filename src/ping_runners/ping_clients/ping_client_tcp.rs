@@ -1,53 +1,107 @@
+use crate::ping_clients::ping_client_one_way_latency::*;
 use crate::*;
 use async_trait::async_trait;
 use socket2::{Domain, SockAddr, Socket, Type};
+use std::collections::HashMap;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use tokio::net::{TcpSocket, TcpStream};
 use tokio::time::Instant;
 
 pub struct PingClientTcp {
     config: PingClientConfig,
+    next_sequence: AtomicU32,
+
+    // The clock offset is estimated from the recent pings, and it is different on each target, so we keep an estimator per target.
+    one_way_latency_estimators: Mutex<HashMap<SocketAddr, OneWayLatencyEstimator>>,
 }
 
 impl PingClientTcp {
     pub fn new(config: &PingClientConfig) -> PingClientTcp {
-        return PingClientTcp { config: config.clone() };
+        return PingClientTcp { config: config.clone(), next_sequence: AtomicU32::new(0), one_way_latency_estimators: Mutex::new(HashMap::new()) };
     }
 
     #[tracing::instrument(name = "Running TCP ping in ping client", level = "debug", skip(self))]
     async fn ping_target(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
-        let socket = self.prepare_socket_for_ping(source).map_err(|e| PingClientError::PreparationFailed(Box::new(e)))?;
+        let (mut connection, rtt) = match self.connect_for_ping(source, target).await? {
+            (Some(connection), rtt) => (connection, rtt),
+            (None, rtt) => return Ok(PingClientPingResultDetails::new(None, rtt, true, None)),
+        };
+        let local_addr = connection.local_addr();
 
-        let start_time = Instant::now();
-        let connect_result = socket.connect_timeout(&SockAddr::from(target.clone()), self.config.wait_timeout);
-        let rtt = Instant::now().duration_since(start_time);
-        match connect_result {
-            // Timeout is an expected value instead of an actual failure, so here we should return Ok.
-            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(PingClientPingResultDetails::new(None, rtt, true, None)),
-            Err(e) => return Err(PingClientError::PingFailed(Box::new(e))),
-            Ok(()) => (),
+        let mut warning: Option<PingClientWarning> = None;
+        let mut one_way_latency: Option<PingOneWayLatency> = None;
+        if self.config.measure_one_way_latency {
+            match self.measure_one_way_latency(&mut connection, target).await {
+                Ok(latency) => one_way_latency = Some(latency),
+                Err(e) => warning = Some(PingClientWarning::AppHandshakeFailed(Box::new(e))),
+            }
         }
-        let local_addr = socket.local_addr();
 
         // Check closing connection as well as opening connection
-        let mut warning: Option<PingClientWarning> = None;
         if self.config.check_disconnect {
-            warning = match self.shutdown_connection(socket, &target).await {
-                Err(e) => Some(PingClientWarning::DisconnectFailed(Box::new(e))),
-                Ok(_) => None,
+            let disconnect_result = self.shutdown_connection(connection, &target).await;
+            if let (None, Err(e)) = (&warning, disconnect_result) {
+                warning = Some(PingClientWarning::DisconnectFailed(Box::new(e)));
             }
         } else {
-            drop(socket);
+            drop(connection);
         }
 
         // If getting local address failed, we ignore it.
         // The worse case we can get is to output a 0.0.0.0 as source IP, which is not critical to what we are trying to do.
-        return match local_addr {
-            Ok(addr) => Ok(PingClientPingResultDetails::new(Some(addr.as_socket().unwrap()), rtt, false, warning)),
-            Err(_) => Ok(PingClientPingResultDetails::new(None, rtt, false, warning)),
+        return Ok(PingClientPingResultDetails::new(local_addr.ok(), rtt, false, warning).with_one_way_latency(one_way_latency));
+    }
+
+    // Exchange the timestamps with rnp_server. Any failure here is returned as error, which will be reported as app handshake
+    // failure, since the target is already reached.
+    #[tracing::instrument(name = "Measuring one-way latency", level = "debug", skip(self, connection))]
+    async fn measure_one_way_latency(&self, connection: &mut TcpStream, target: &SocketAddr) -> io::Result<PingOneWayLatency> {
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        let exchange = async {
+            let request = build_one_way_latency_request(sequence, now_in_us());
+            connection.write_all(&request).await?;
+
+            let mut response = [0u8; ONE_WAY_LATENCY_RESPONSE_SIZE];
+            connection.read_exact(&mut response).await?;
+            return Ok::<_, io::Error>(parse_one_way_latency_response(&response, sequence, now_in_us()));
+        };
+
+        let sample = match tokio::time::timeout(self.config.wait_timeout, exchange).await {
+            Err(_) => return Err(io::Error::new(io::ErrorKind::TimedOut, "Timed out waiting for the timestamps from rnp_server.")),
+            Ok(Err(e)) => return Err(e),
+            Ok(Ok(None)) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Unexpected timestamp response, the target might not be rnp_server."))
+            }
+            Ok(Ok(Some(sample))) => sample,
+        };
+
+        let mut estimators = self.one_way_latency_estimators.lock().unwrap();
+        return Ok(estimators.entry(*target).or_default().add_sample(sample));
+    }
+
+    // Connect to the target and return the connection with the connect RTT.
+    // If the connect timed out, no connection is returned, since timeout is an expected result instead of an actual failure.
+    //
+    // The socket is created and bound with socket2, so we can control the source port, TTL and linger, but the connect itself
+    // is awaited in tokio, so waiting for the connect won't block the runtime threads when running many pings in parallel.
+    #[tracing::instrument(name = "Connecting to target for ping", level = "debug", skip(self))]
+    pub(crate) async fn connect_for_ping(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<(Option<TcpStream>, Duration)> {
+        let socket = self.prepare_socket_for_ping(source).map_err(|e| PingClientError::PreparationFailed(Box::new(e)))?;
+        let socket = TcpSocket::from_std_stream(socket.into());
+
+        let start_time = Instant::now();
+        let connect_result = tokio::time::timeout(self.config.wait_timeout, socket.connect(*target)).await;
+        let rtt = Instant::now().duration_since(start_time);
+        return match connect_result {
+            Err(_) => Ok((None, rtt)),
+            Ok(Err(e)) if e.kind() == io::ErrorKind::TimedOut => Ok((None, rtt)),
+            Ok(Err(e)) => Err(PingClientError::PingFailed(Box::new(e))),
+            Ok(Ok(connection)) => Ok((Some(connection), rtt)),
         };
     }
 
@@ -56,7 +110,7 @@ impl PingClientTcp {
         let socket_domain = if source.is_ipv4() { Domain::IPV4 } else { Domain::IPV6 };
         let socket = Socket::new(socket_domain, Type::STREAM, None)?;
 
-        socket.set_read_timeout(Some(self.config.wait_timeout))?;
+        socket.set_nonblocking(true)?;
         if !self.config.check_disconnect {
             socket.set_linger(Some(Duration::from_secs(0)))?;
         }
@@ -70,14 +124,12 @@ impl PingClientTcp {
     }
 
     #[tracing::instrument(name = "Shutdown connection after ping", level = "debug", skip(self))]
-    async fn shutdown_connection(&self, socket: Socket, target: &SocketAddr) -> io::Result<()> {
+    async fn shutdown_connection(&self, mut connection: TcpStream, target: &SocketAddr) -> io::Result<()> {
         if !self.config.wait_before_disconnect.is_zero() {
             tracing::debug!("Waiting {:?} before disconnect; target={}", self.config.wait_before_disconnect, target);
             tokio::time::sleep(self.config.wait_before_disconnect).await;
         }
 
-        // Convert into TcpStream in tokio, so it is easier to work with it.
-        let mut connection = TcpStream::from_std(socket.into())?;
         let mut read_buffer = vec![0 as u8; 128];
 
         // Before disconnect, we need to check if the connection is still alive or not.
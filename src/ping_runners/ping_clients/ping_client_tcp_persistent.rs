@@ -0,0 +1,196 @@
+use crate::ping_clients::ping_client_one_way_latency::*;
+use crate::ping_clients::ping_client_tcp::PingClientTcp;
+use crate::*;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+// Application level ping over a persistent connection to rnp_server. Instead of connecting in every ping, the connection is kept
+// open and every ping sends a timestamp request on it, and the RTT is the time to get the response back. This shows the latency of
+// the established connections, which is what most applications are seeing, and also the connection drops in the middle.
+//
+// Each worker has its own ping client, so each worker keeps its own connection to each target.
+//
+// Late responses are skipped on the next pings, but if no response is received in a few pings in a row, the connection is considered
+// stuck and dropped, e.g. the data is lost and being retransmitted for too long.
+const MAX_CONSECUTIVE_TIMEOUT_COUNT: u32 = 3;
+
+pub struct PingClientTcpPersistent {
+    config: PingClientConfig,
+    tcp_client: PingClientTcp,
+    next_sequence: AtomicU32,
+
+    // Connections are taken out while pinging, and put back only when they are still healthy after the ping.
+    connections: Mutex<HashMap<SocketAddr, PersistentConnection>>,
+
+    // Why the last connection to the target is dropped, which is reported in the result of the reconnect.
+    reconnect_reasons: Mutex<HashMap<SocketAddr, String>>,
+
+    one_way_latency_estimators: Mutex<HashMap<SocketAddr, OneWayLatencyEstimator>>,
+}
+
+struct PersistentConnection {
+    stream: TcpStream,
+
+    // Responses of the timed out pings can still arrive later, so the data read is kept across pings until a full response is received.
+    read_buf: Vec<u8>,
+    consecutive_timeout_count: u32,
+}
+
+enum ConnectResult {
+    Connected(PersistentConnection),
+    Reconnected(PingClientPingResultDetails),
+    TimedOut(Duration),
+}
+
+impl PingClientTcpPersistent {
+    pub fn new(config: &PingClientConfig) -> PingClientTcpPersistent {
+        return PingClientTcpPersistent {
+            config: config.clone(),
+            tcp_client: PingClientTcp::new(config),
+            next_sequence: AtomicU32::new(0),
+            connections: Mutex::new(HashMap::new()),
+            reconnect_reasons: Mutex::new(HashMap::new()),
+            one_way_latency_estimators: Mutex::new(HashMap::new()),
+        };
+    }
+
+    #[tracing::instrument(name = "Running persistent TCP ping in ping client", level = "debug", skip(self))]
+    async fn ping_target(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
+        let existing_connection = self.connections.lock().unwrap().remove(target);
+        let mut connection = match existing_connection {
+            Some(connection) => connection,
+            None => match self.connect(source, target).await? {
+                ConnectResult::Connected(connection) => connection,
+                ConnectResult::Reconnected(result) => return Ok(result),
+                ConnectResult::TimedOut(rtt) => return Ok(PingClientPingResultDetails::new(None, rtt, true, None)),
+            },
+        };
+        let local_addr = connection.stream.local_addr();
+
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        let start_time = Instant::now();
+        let deadline = start_time + self.config.wait_timeout;
+
+        let request = build_one_way_latency_request(sequence, now_in_us());
+        match tokio::time::timeout_at(deadline, connection.stream.write_all(&request)).await {
+            // The request might be partially written, so the connection can't be used anymore.
+            Err(_) => {
+                self.record_reconnect_reason(target, "Timed out sending ping request.".to_string());
+                return Ok(PingClientPingResultDetails::new(local_addr.ok(), self.config.wait_timeout, true, None));
+            }
+            Ok(Err(e)) => return Err(self.drop_connection_with_error(target, e)),
+            Ok(Ok(())) => (),
+        }
+
+        let sample = match tokio::time::timeout_at(deadline, Self::read_response(&mut connection, sequence)).await {
+            // Late responses will be skipped by sequence in the next ping, so the connection is kept, unless the connection seems to be
+            // stuck, e.g. the data is lost and being retransmitted for too long.
+            Err(_) => {
+                connection.consecutive_timeout_count += 1;
+                if connection.consecutive_timeout_count >= MAX_CONSECUTIVE_TIMEOUT_COUNT {
+                    self.record_reconnect_reason(target, format!("No response received in {} pings in a row.", connection.consecutive_timeout_count));
+                } else {
+                    self.connections.lock().unwrap().insert(*target, connection);
+                }
+                return Ok(PingClientPingResultDetails::new(local_addr.ok(), self.config.wait_timeout, true, None));
+            }
+            Ok(Err(e)) => return Err(self.drop_connection_with_error(target, e)),
+            Ok(Ok(sample)) => sample,
+        };
+        let rtt = Instant::now().duration_since(start_time);
+        connection.consecutive_timeout_count = 0;
+        self.connections.lock().unwrap().insert(*target, connection);
+
+        let mut one_way_latency: Option<PingOneWayLatency> = None;
+        if self.config.measure_one_way_latency {
+            let mut estimators = self.one_way_latency_estimators.lock().unwrap();
+            one_way_latency = Some(estimators.entry(*target).or_default().add_sample(sample));
+        }
+
+        return Ok(PingClientPingResultDetails::new(local_addr.ok(), rtt, false, None).with_one_way_latency(one_way_latency));
+    }
+
+    // When the previous connection is dropped, the reconnect is reported as a separate result, and the connect time is used as its RTT.
+    // The pings on the new connection are sent from the next round, so the reconnect won't be mixed up with the ping results, and result
+    // processors skip reconnects in statistics.
+    //
+    // Failed reconnects are returned as ping failures, and the reconnect will be reported once it succeeds.
+    async fn connect(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<ConnectResult> {
+        let (stream, rtt) = match self.tcp_client.connect_for_ping(source, target).await? {
+            (Some(stream), rtt) => (stream, rtt),
+            (None, rtt) => return Ok(ConnectResult::TimedOut(rtt)),
+        };
+        let connection = PersistentConnection { stream, read_buf: Vec::with_capacity(ONE_WAY_LATENCY_RESPONSE_SIZE), consecutive_timeout_count: 0 };
+
+        let reconnect_reason = self.reconnect_reasons.lock().unwrap().remove(target);
+        return match reconnect_reason {
+            None => Ok(ConnectResult::Connected(connection)),
+            Some(reason) => {
+                let local_addr = connection.stream.local_addr();
+                self.connections.lock().unwrap().insert(*target, connection);
+
+                let warning = PingClientWarning::Reconnected(Box::new(io::Error::new(io::ErrorKind::ConnectionReset, reason)));
+                Ok(ConnectResult::Reconnected(PingClientPingResultDetails::new(local_addr.ok(), rtt, false, Some(warning))))
+            }
+        };
+    }
+
+    // Read until the response of the current ping is received. Responses of the previous pings are skipped.
+    //
+    // Only read is awaited here, which is cancel safe, so the data is not lost when the ping is timed out.
+    async fn read_response(connection: &mut PersistentConnection, sequence: u32) -> io::Result<OneWayLatencySample> {
+        let mut buf = [0u8; ONE_WAY_LATENCY_RESPONSE_SIZE];
+        loop {
+            while connection.read_buf.len() >= ONE_WAY_LATENCY_RESPONSE_SIZE {
+                let response: Vec<u8> = connection.read_buf.drain(..ONE_WAY_LATENCY_RESPONSE_SIZE).collect();
+                if &response[..4] != ONE_WAY_LATENCY_PROBE_MAGIC {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "Unexpected ping response, the target might not be rnp_server."));
+                }
+
+                if let Some(sample) = parse_one_way_latency_response(&response, sequence, now_in_us()) {
+                    return Ok(sample);
+                }
+            }
+
+            let n = connection.stream.read(&mut buf).await?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection is closed by remote side."));
+            }
+            connection.read_buf.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    fn record_reconnect_reason(&self, target: &SocketAddr, reason: String) {
+        tracing::debug!("Persistent connection dropped, will reconnect in next ping; target={}, reason={}", target, reason);
+        self.reconnect_reasons.lock().unwrap().insert(*target, reason);
+    }
+
+    // The ping failure is reported when the connection is dropped, and the error kind is kept, so it is classified as usual.
+    fn drop_connection_with_error(&self, target: &SocketAddr, error: io::Error) -> PingClientError {
+        self.record_reconnect_reason(target, error.to_string());
+        return PingClientError::PingFailed(Box::new(io::Error::new(error.kind(), format!("Persistent connection dropped: {}", error))));
+    }
+}
+
+#[async_trait]
+impl PingClient for PingClientTcpPersistent {
+    fn protocol(&self) -> &'static str {
+        "TCP"
+    }
+
+    async fn prepare_ping(&mut self, _: &SocketAddr) -> Result<(), PingClientError> {
+        Ok(())
+    }
+
+    async fn ping(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
+        return self.ping_target(source, target).await;
+    }
+}
@@ -1,10 +1,19 @@
+use crate::ping_clients::ping_client::PingClientPingResultDetails;
+use crate::ping_clients::ping_client_one_way_latency::*;
 use crate::ping_clients::ping_client_test_common::*;
 use crate::stub_servers::stub_server_factory;
-use crate::{ping_clients::ping_client_factory, rnp_test_common, PingClientConfig, RnpStubServerConfig, RnpSupportedProtocol};
+use crate::{
+    ping_clients::ping_client_factory, rnp_test_common, PingClient, PingClientConfig, PingClientRegistry, PingClientWarning, RnpStubServerConfig,
+    RnpStubServerFaultAction, RnpStubServerFaultRule, RnpStubServerFaultTrigger, RnpSupportedProtocol,
+};
 use futures_intrusive::sync::ManualResetEvent;
+use socket2::{Domain, Socket, Type};
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::runtime::Runtime;
 
 #[test]
@@ -18,7 +27,7 @@ fn ping_client_tcp_should_work_when_pinging_good_host() {
 
     rt.block_on(async move {
         let config = create_ping_client_tcp_default_config();
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
         ping_client_should_work_when_pinging_good_host(&mut ping_client, &server_address).await;
     });
 }
@@ -34,7 +43,7 @@ fn ping_client_tcp_should_fail_when_binding_unavailable_source_port() {
 
     rt.block_on(async move {
         let config = create_ping_client_tcp_default_config();
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
 
         let expected_result = ExpectedTestCaseResult::Failed(
             "Only one usage of each socket address (protocol/network address/port) is normally permitted. (os error 10048)",
@@ -56,7 +65,7 @@ fn ping_client_tcp_should_work_when_pinging_good_host_with_check_disconnect() {
         let mut config = create_ping_client_tcp_default_config();
         config.check_disconnect = true;
 
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
         ping_client_should_work_when_pinging_good_host(&mut ping_client, &server_address).await;
     });
 }
@@ -76,7 +85,7 @@ fn ping_client_tcp_should_work_when_pinging_good_host_with_check_disconnect() {
 //         config.check_disconnect = true;
 //         config.wait_before_disconnect = Duration::from_millis(5000);
 //
-//         let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+//         let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
 //         let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
 //         let expected_result = ExpectedTestCaseResult::Warning("Connection is already half shutdown by remote side.");
 //         ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
@@ -98,13 +107,341 @@ fn ping_client_tcp_should_warn_when_disconnect_timed_out() {
         config.check_disconnect = true;
         config.disconnect_timeout = Duration::from_millis(100);
 
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
         let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
         let expected_result = ExpectedTestCaseResult::Warning("Disconnect timed out.");
         ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
     });
 }
 
+#[test]
+fn ping_client_tcp_should_warn_when_server_resets_connection() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11341".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_tcp_stub_server_default_config(&server_address);
+    server_config.fault_rules = vec![RnpStubServerFaultRule { action: RnpStubServerFaultAction::Reset, trigger: RnpStubServerFaultTrigger::Always }];
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.check_disconnect = true;
+
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("An existing connection was forcibly closed by the remote host. (os error 10054)");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_warn_when_server_half_closes_connection() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11342".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_tcp_stub_server_default_config(&server_address);
+    server_config.fault_rules =
+        vec![RnpStubServerFaultRule { action: RnpStubServerFaultAction::HalfClose, trigger: RnpStubServerFaultTrigger::Always }];
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.check_disconnect = true;
+        config.wait_before_disconnect = Duration::from_millis(200);
+
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("Connection is already half shutdown by remote side.");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_warn_when_server_hangs_connection() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11343".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_tcp_stub_server_default_config(&server_address);
+    server_config.fault_rules = vec![RnpStubServerFaultRule { action: RnpStubServerFaultAction::Hang, trigger: RnpStubServerFaultTrigger::Always }];
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.check_disconnect = true;
+        config.disconnect_timeout = Duration::from_millis(100);
+
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("Disconnect timed out.");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_warn_when_server_closes_connection_after_writing_data() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11344".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_tcp_stub_server_default_config(&server_address);
+    server_config.write_chunk_size = 16;
+    server_config.fault_rules = vec![
+        RnpStubServerFaultRule { action: RnpStubServerFaultAction::CloseAfterBytes(64), trigger: RnpStubServerFaultTrigger::NthConnection(1) },
+        RnpStubServerFaultRule { action: RnpStubServerFaultAction::Reset, trigger: RnpStubServerFaultTrigger::NthConnection(1) },
+    ];
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.check_disconnect = true;
+        config.wait_before_disconnect = Duration::from_millis(200);
+
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("Connection is already half shutdown by remote side.");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
+
+        // Only the first connection is closed after writing data, so the second ping should work.
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &ExpectedTestCaseResult::Ok)
+            .await;
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_measure_one_way_latency_when_pinging_stub_server() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11345".parse::<SocketAddr>().unwrap();
+    let server_config = create_tcp_stub_server_default_config(&server_address);
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.measure_one_way_latency = true;
+
+        let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        for _ in 0..3 {
+            let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+            tracing::info!("Ping result: {:?}", ping_result);
+            assert!(ping_result.warning.is_none());
+
+            // Both sides are using the same clock, so the estimated clock offset should be close to 0.
+            let one_way_latency = ping_result.one_way_latency.unwrap();
+            assert!(one_way_latency.clock_offset_in_us.abs() < 100_000);
+            assert!(one_way_latency.forward_delay < config.wait_timeout);
+            assert!(one_way_latency.reverse_delay < config.wait_timeout);
+        }
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_warn_when_measuring_one_way_latency_without_response() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11346".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_tcp_stub_server_default_config(&server_address);
+    server_config.fault_rules =
+        vec![RnpStubServerFaultRule { action: RnpStubServerFaultAction::HalfClose, trigger: RnpStubServerFaultTrigger::Always }];
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.measure_one_way_latency = true;
+
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let expected_result = ExpectedTestCaseResult::Warning("early eof");
+        ping_client_result_should_be_expected(&mut ping_client, &source, &server_address, Duration::from_millis(200), &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_reuse_connection_when_pinging_with_persistent_connection() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11351".parse::<SocketAddr>().unwrap();
+    let server_config = create_tcp_stub_server_default_config(&server_address);
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.use_persistent_connection = true;
+        config.measure_one_way_latency = true;
+
+        let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        let mut local_addr: Option<SocketAddr> = None;
+        for _ in 0..3 {
+            let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+            tracing::info!("Ping result: {:?}", ping_result);
+            assert!(!ping_result.is_timeout);
+            assert!(ping_result.warning.is_none());
+            assert!(ping_result.one_way_latency.is_some());
+
+            // All pings are sent on the same connection, so the local port should never change.
+            assert!(ping_result.actual_local_addr.is_some());
+            if local_addr.is_some() {
+                assert_eq!(local_addr, ping_result.actual_local_addr);
+            }
+            local_addr = ping_result.actual_local_addr;
+        }
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_report_reconnect_when_persistent_connection_is_closed() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    // Each ping reads 16 bytes and writes 32 bytes in the stub server, so the first connection is closed after 2 pings.
+    let server_address = "127.0.0.1:11352".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_tcp_stub_server_default_config(&server_address);
+    server_config.fault_rules =
+        vec![RnpStubServerFaultRule { action: RnpStubServerFaultAction::CloseAfterBytes(96), trigger: RnpStubServerFaultTrigger::NthConnection(1) }];
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.use_persistent_connection = true;
+
+        let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        for _ in 0..2 {
+            let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+            assert!(ping_result.warning.is_none());
+        }
+
+        let ping_error = ping_client.ping(&source, &server_address).await.err().unwrap();
+        tracing::info!("Ping error after connection closed: {}", ping_error);
+        assert!(ping_error.to_string().starts_with("Persistent connection dropped: "));
+
+        let reconnect_result = ping_client.ping(&source, &server_address).await.unwrap();
+        ping_result_should_be_reconnect(&reconnect_result, "Connection is closed by remote side.");
+
+        // Reconnect is only reported once, and the pings after it are sent on the new connection.
+        let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+        assert!(ping_result.warning.is_none());
+        assert_eq!(reconnect_result.actual_local_addr, ping_result.actual_local_addr);
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_report_reconnect_after_failed_reconnects() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    // The first server only answers 2 pings, then closes the connection and stops listening, so the reconnects will be refused.
+    let server_address = "127.0.0.1:11353".parse::<SocketAddr>().unwrap();
+    let listener = rt.block_on(TcpListener::bind(server_address)).unwrap();
+    let server_task = rt.spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = [0u8; ONE_WAY_LATENCY_REQUEST_SIZE];
+        for _ in 0..2 {
+            stream.read_exact(&mut request).await.unwrap();
+            stream.write_all(&build_one_way_latency_response(&request, now_in_us(), now_in_us()).unwrap()).await.unwrap();
+        }
+    });
+
+    let mut config = create_ping_client_tcp_default_config();
+    config.use_persistent_connection = true;
+    let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+    let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+
+    rt.block_on(async {
+        for _ in 0..2 {
+            let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+            assert!(ping_result.warning.is_none());
+        }
+        server_task.await.unwrap();
+
+        let ping_error = ping_client.ping(&source, &server_address).await.err().unwrap();
+        assert!(ping_error.to_string().starts_with("Persistent connection dropped: "), "{}", ping_error);
+
+        // Failed reconnects are reported as failed pings, and the reason of the drop is kept until the reconnect succeeds.
+        for _ in 0..2 {
+            let ping_error = ping_client.ping(&source, &server_address).await.err().unwrap();
+            tracing::info!("Ping error when reconnecting: {}", ping_error);
+            assert!(!ping_error.to_string().starts_with("Persistent connection dropped: "), "{}", ping_error);
+        }
+    });
+
+    start_run_tcp_stub_server(&rt, create_tcp_stub_server_default_config(&server_address));
+    rt.block_on(async {
+        let reconnect_result = ping_client.ping(&source, &server_address).await.unwrap();
+        ping_result_should_be_reconnect(&reconnect_result, "Connection is closed by remote side.");
+
+        let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+        assert!(ping_result.warning.is_none());
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_reconnect_when_persistent_connection_keeps_timing_out() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11354".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_tcp_stub_server_default_config(&server_address);
+    server_config.fault_rules =
+        vec![RnpStubServerFaultRule { action: RnpStubServerFaultAction::Hang, trigger: RnpStubServerFaultTrigger::NthConnection(1) }];
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let mut config = create_ping_client_tcp_default_config();
+        config.use_persistent_connection = true;
+
+        let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        for _ in 0..3 {
+            let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+            assert!(ping_result.is_timeout);
+        }
+
+        let reconnect_result = ping_client.ping(&source, &server_address).await.unwrap();
+        ping_result_should_be_reconnect(&reconnect_result, "No response received in 3 pings in a row.");
+
+        let ping_result = ping_client.ping(&source, &server_address).await.unwrap();
+        assert!(!ping_result.is_timeout);
+        assert!(ping_result.warning.is_none());
+    });
+}
+
+#[test]
+fn ping_client_tcp_should_answer_split_and_coalesced_requests_on_persistent_connection() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11355".parse::<SocketAddr>().unwrap();
+    start_run_tcp_stub_server(&rt, create_tcp_stub_server_default_config(&server_address));
+
+    rt.block_on(async move {
+        let mut stream = TcpStream::connect(server_address).await.unwrap();
+        let mut requests = Vec::new();
+        for sequence in 0..3 {
+            requests.extend(build_one_way_latency_request(sequence, now_in_us()));
+        }
+
+        // Split the first request in the middle of the magic, and send the rest of the requests together.
+        stream.write_all(&requests[..2]).await.unwrap();
+        stream.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stream.write_all(&requests[2..]).await.unwrap();
+
+        let mut response = [0u8; ONE_WAY_LATENCY_RESPONSE_SIZE];
+        for sequence in 0..3 {
+            stream.read_exact(&mut response).await.unwrap();
+            assert!(parse_one_way_latency_response(&response, sequence, now_in_us()).is_some());
+        }
+    });
+}
+
 #[test]
 fn ping_client_tcp_should_fail_when_pinging_non_existing_host() {
     rnp_test_common::initialize();
@@ -112,7 +449,7 @@ fn ping_client_tcp_should_fail_when_pinging_non_existing_host() {
 
     rt.block_on(async move {
         let config = create_ping_client_tcp_default_config();
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
         ping_client_should_fail_when_pinging_non_existing_host(&mut ping_client, &ExpectedTestCaseResult::Timeout).await;
     });
 }
@@ -124,7 +461,7 @@ fn ping_client_tcp_should_fail_when_pinging_non_existing_port() {
 
     rt.block_on(async move {
         let config = create_ping_client_tcp_default_config();
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
 
         let expected_result = if cfg!(windows) { ExpectedTestCaseResult::Timeout } else { ExpectedTestCaseResult::Failed("connection refused") };
         ping_client_should_fail_when_pinging_non_existing_port(&mut ping_client, &expected_result).await;
@@ -138,23 +475,74 @@ fn ping_client_tcp_should_fail_when_binding_invalid_source_ip() {
 
     rt.block_on(async move {
         let config = create_ping_client_tcp_default_config();
-        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, None);
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap();
 
         let expected_result = ExpectedTestCaseResult::Failed("The requested address is not valid in its context. (os error 10049)");
         ping_client_should_fail_when_binding_invalid_source_ip(&mut ping_client, &expected_result).await;
     });
 }
 
+#[test]
+fn ping_client_tcp_should_not_block_runtime_when_running_many_pings_in_parallel() {
+    rnp_test_common::initialize();
+
+    // Only 2 worker threads are used here. If connect blocks the runtime threads, all pings will be serialized
+    // and take (ping count / thread count * wait timeout) to finish, which is 40 seconds here.
+    let rt = tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build().unwrap();
+
+    // Create a listener which never accepts and fill up its backlog with 1 connection, so the SYNs of all the
+    // following connects will be dropped, which makes it a local black hole target.
+    let listener = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+    listener.bind(&"127.0.0.1:0".parse::<SocketAddr>().unwrap().into()).unwrap();
+    listener.listen(0).unwrap();
+    let server_address = listener.local_addr().unwrap().as_socket().unwrap();
+    let _backlog_connection = std::net::TcpStream::connect(server_address).unwrap();
+
+    let config = create_ping_client_tcp_default_config();
+    let ping_client: Arc<Box<dyn PingClient + Send + Sync>> =
+        Arc::new(ping_client_factory::new_ping_client(&RnpSupportedProtocol::TCP, &config, &PingClientRegistry::new()).unwrap());
+
+    let parallel_ping_count = 200;
+    let start_time = Instant::now();
+    let timed_out_ping_count = rt.block_on(async move {
+        let mut ping_tasks = Vec::new();
+        for _ in 0..parallel_ping_count {
+            let ping_client = ping_client.clone();
+            ping_tasks.push(tokio::spawn(async move {
+                let source = "127.0.0.1:0".parse::<SocketAddr>().unwrap();
+                return ping_client.ping(&source, &server_address).await;
+            }));
+        }
+
+        let mut timed_out_ping_count = 0;
+        for ping_task in ping_tasks {
+            if ping_task.await.unwrap().unwrap().is_timeout {
+                timed_out_ping_count += 1;
+            }
+        }
+        return timed_out_ping_count;
+    });
+    let elapsed = start_time.elapsed();
+
+    tracing::info!("{} parallel pings finished: TimedOut = {}, Elapsed = {:?}", parallel_ping_count, timed_out_ping_count, elapsed);
+    assert_eq!(parallel_ping_count, timed_out_ping_count);
+    assert!(elapsed < config.wait_timeout * 10);
+}
+
 fn create_tcp_stub_server_default_config(server_address: &SocketAddr) -> RnpStubServerConfig {
     return RnpStubServerConfig {
         protocol: RnpSupportedProtocol::TCP,
         server_address: server_address.clone(),
         close_on_accept: false,
+        close_on_handshake: false,
+        alpn_protocols: vec![],
+        reject_alpn: false,
         sleep_before_write: Duration::ZERO,
         write_chunk_size: 0,
         write_count_limit: 0,
         report_interval: Duration::from_secs(1),
         wait_before_disconnect: Duration::ZERO,
+        fault_rules: vec![],
     };
 }
 
@@ -162,7 +550,7 @@ fn start_run_tcp_stub_server(rt: &Runtime, stub_server_config: RnpStubServerConf
     let ready_event = Arc::new(ManualResetEvent::new(false));
     let ready_event_clone = ready_event.clone();
     rt.spawn(async move {
-        let _ = stub_server_factory::run(&stub_server_config, Arc::new(ManualResetEvent::new(false)), ready_event_clone).await;
+        let _ = stub_server_factory::run(&stub_server_config, Arc::new(ManualResetEvent::new(false)), ready_event_clone).unwrap().await;
     });
     rt.block_on(ready_event.wait());
 }
@@ -178,5 +566,22 @@ fn create_ping_client_tcp_default_config() -> PingClientConfig {
         log_tls_key: false,
         alpn_protocol: None,
         use_timer_rtt: false,
+        http_method: String::from("GET"),
+        http_path: String::from("/"),
+        http_headers: vec![],
+        http_expected_status: None,
+        http_body_regex: None,
+        measure_one_way_latency: false,
+        use_persistent_connection: false,
+        protocol_options: BTreeMap::new(),
     };
 }
+
+fn ping_result_should_be_reconnect(ping_result: &PingClientPingResultDetails, expected_reason: &str) {
+    tracing::info!("Ping result after reconnect: {:?}", ping_result);
+    assert!(!ping_result.is_timeout);
+    match &ping_result.warning {
+        Some(PingClientWarning::Reconnected(reason)) => assert_eq!(expected_reason, reason.to_string()),
+        warning => panic!("Reconnect is expected to be reported, but got: {:?}", warning),
+    }
+}
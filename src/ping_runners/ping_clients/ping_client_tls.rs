@@ -0,0 +1,115 @@
+use crate::ping_clients::ping_client_tcp::PingClientTcp;
+use crate::*;
+use async_trait::async_trait;
+use rustls::{ClientConfig, RootCertStore, ServerName};
+use std::convert::TryFrom;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+
+pub struct PingClientTls {
+    config: PingClientConfig,
+    tcp_client: PingClientTcp,
+    connector: TlsConnector,
+}
+
+impl PingClientTls {
+    pub fn new(config: &PingClientConfig) -> PingClientTls {
+        let connector = TlsConnector::from(Arc::new(PingClientTls::create_client_config(config)));
+        return PingClientTls { config: config.clone(), tcp_client: PingClientTcp::new(config), connector };
+    }
+
+    fn create_client_config(config: &PingClientConfig) -> ClientConfig {
+        // Unlike QUIC ping, we verify the server certificate with the system root store here,
+        // because certificate issues are one of the most common failures we want to catch with TLS ping.
+        let mut root_store = RootCertStore::empty();
+        match rustls_native_certs::load_native_certs() {
+            Ok(certs) => {
+                let certs: Vec<Vec<u8>> = certs.into_iter().map(|cert| cert.0).collect();
+                let (added, ignored) = root_store.add_parsable_certificates(&certs);
+                tracing::debug!("Native root certificates loaded: Added = {}, Ignored = {}", added, ignored);
+            }
+            Err(e) => tracing::warn!("Failed to load native root certificates: Error = {}", e),
+        }
+
+        let mut crypto = ClientConfig::builder().with_safe_defaults().with_root_certificates(root_store).with_no_client_auth();
+
+        // Setup ALPN protocol if specified.
+        if let Some(alpn_protocol) = &config.alpn_protocol {
+            crypto.alpn_protocols = vec![alpn_protocol.as_bytes().to_vec()];
+        }
+
+        // TLS key log is used for debugging purpose.
+        if config.log_tls_key {
+            crypto.key_log = Arc::new(rustls::KeyLogFile::new());
+        }
+
+        return crypto;
+    }
+
+    #[tracing::instrument(name = "Running TLS ping in ping client", level = "debug", skip(self))]
+    async fn ping_target(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
+        let (connection, rtt) = match self.tcp_client.connect_for_ping(source, target).await? {
+            (Some(connection), rtt) => (connection, rtt),
+            (None, rtt) => return Ok(PingClientPingResultDetails::new(None, rtt, true, None)),
+        };
+
+        // If getting local address failed, we ignore it, the same as TCP ping.
+        let local_addr = connection.local_addr().ok();
+
+        // Once TCP connection is established, the underlying network is reachable, so any failure in TLS handshake
+        // is reported as warning instead of failure, the same as QUIC ping.
+        let (handshake_result, handshake_time) = self.handshake(connection, target).await?;
+        let warning = handshake_result.err().map(|e| PingClientWarning::AppHandshakeFailed(Box::new(e)));
+
+        return Ok(PingClientPingResultDetails::new(local_addr, rtt, false, warning).with_handshake_time(handshake_time));
+    }
+
+    // Run TLS handshake on an established connection. Only the failures in preparing the handshake are returned as errors,
+    // and the handshake result is returned together with the time it takes.
+    #[tracing::instrument(name = "Running TLS handshake", level = "debug", skip(self, connection))]
+    pub(crate) async fn handshake(
+        &self,
+        connection: TcpStream,
+        target: &SocketAddr,
+    ) -> PingClientResult<(io::Result<TlsStream<TcpStream>>, Duration)> {
+        let server_name = self.server_name(target).map_err(|e| PingClientError::PreparationFailed(Box::new(e)))?;
+
+        let start_time = Instant::now();
+        let handshake_result = match tokio::time::timeout(self.config.wait_timeout, self.connector.connect(server_name, connection)).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out.")),
+        };
+        let handshake_time = Instant::now().duration_since(start_time);
+
+        return Ok((handshake_result, handshake_time));
+    }
+
+    // SNI comes from the server name in config. If it is not specified, we fallback to the target IP.
+    fn server_name(&self, target: &SocketAddr) -> io::Result<ServerName> {
+        return match &self.config.server_name {
+            Some(server_name) => ServerName::try_from(server_name.as_str()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)),
+            None => Ok(ServerName::IpAddress(target.ip())),
+        };
+    }
+}
+
+#[async_trait]
+impl PingClient for PingClientTls {
+    fn protocol(&self) -> &'static str {
+        "TLS"
+    }
+
+    async fn prepare_ping(&mut self, _: &SocketAddr) -> Result<(), PingClientError> {
+        Ok(())
+    }
+
+    async fn ping(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
+        return self.ping_target(source, target).await;
+    }
+}
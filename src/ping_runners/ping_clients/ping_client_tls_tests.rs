@@ -0,0 +1,110 @@
+use crate::ping_clients::ping_client_test_common::*;
+use crate::stub_servers::stub_server_factory;
+use crate::{ping_clients::ping_client_factory, rnp_test_common, PingClientConfig, PingClientRegistry, RnpStubServerConfig, RnpSupportedProtocol};
+use futures_intrusive::sync::ManualResetEvent;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+#[test]
+fn ping_client_tls_should_warn_when_handshake_failed() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    // TCP stub server doesn't speak TLS, so the connection succeeds but the handshake fails.
+    let server_address = "127.0.0.1:11537".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_tcp_stub_server_default_config(&server_address);
+    server_config.close_on_accept = true;
+    start_run_tcp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let config = create_ping_client_tls_default_config();
+        let ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TLS, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+
+        let ping_result = ping_client.ping(&source, &server_address).await;
+        tracing::info!("Ping result: {:?}", ping_result);
+        let ping_result = ping_result.unwrap();
+        assert!(!ping_result.is_timeout);
+        assert!(ping_result.handshake_time.is_some());
+        assert!(ping_result.warning.is_some());
+    });
+}
+
+#[test]
+fn ping_client_tls_should_fail_when_pinging_non_existing_port() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async move {
+        let config = create_ping_client_tls_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TLS, &config, &PingClientRegistry::new()).unwrap();
+
+        let expected_result = if cfg!(windows) { ExpectedTestCaseResult::Timeout } else { ExpectedTestCaseResult::Failed("connection refused") };
+        ping_client_should_fail_when_pinging_non_existing_port(&mut ping_client, &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_tls_should_fail_when_binding_invalid_source_ip() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async move {
+        let config = create_ping_client_tls_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::TLS, &config, &PingClientRegistry::new()).unwrap();
+
+        let expected_result = ExpectedTestCaseResult::Failed("The requested address is not valid in its context. (os error 10049)");
+        ping_client_should_fail_when_binding_invalid_source_ip(&mut ping_client, &expected_result).await;
+    });
+}
+
+fn create_tcp_stub_server_default_config(server_address: &SocketAddr) -> RnpStubServerConfig {
+    return RnpStubServerConfig {
+        protocol: RnpSupportedProtocol::TCP,
+        server_address: server_address.clone(),
+        close_on_accept: false,
+        close_on_handshake: false,
+        alpn_protocols: vec![],
+        reject_alpn: false,
+        sleep_before_write: Duration::ZERO,
+        write_chunk_size: 0,
+        write_count_limit: 0,
+        report_interval: Duration::from_secs(1),
+        wait_before_disconnect: Duration::ZERO,
+        fault_rules: vec![],
+    };
+}
+
+fn start_run_tcp_stub_server(rt: &Runtime, stub_server_config: RnpStubServerConfig) {
+    let ready_event = Arc::new(ManualResetEvent::new(false));
+    let ready_event_clone = ready_event.clone();
+    rt.spawn(async move {
+        let _ = stub_server_factory::run(&stub_server_config, Arc::new(ManualResetEvent::new(false)), ready_event_clone).unwrap().await;
+    });
+    rt.block_on(ready_event.wait());
+}
+
+fn create_ping_client_tls_default_config() -> PingClientConfig {
+    return PingClientConfig {
+        wait_timeout: Duration::from_millis(300),
+        time_to_live: None,
+        check_disconnect: false,
+        wait_before_disconnect: Duration::ZERO,
+        disconnect_timeout: Duration::ZERO,
+        server_name: Some("localhost".to_string()),
+        log_tls_key: false,
+        alpn_protocol: Some("h2".to_string()),
+        use_timer_rtt: false,
+        http_method: String::from("GET"),
+        http_path: String::from("/"),
+        http_headers: vec![],
+        http_expected_status: None,
+        http_body_regex: None,
+        measure_one_way_latency: false,
+        use_persistent_connection: false,
+        protocol_options: BTreeMap::new(),
+    };
+}
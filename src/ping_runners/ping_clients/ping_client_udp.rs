@@ -0,0 +1,133 @@
+use crate::*;
+use async_trait::async_trait;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::convert::TryInto;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+// The UDP ping payload is laid out as below (all integers are in big endian):
+// - 4 bytes: Magic, used to filter out unrelated datagrams.
+// - 4 bytes: Sequence number of the ping.
+// - 8 bytes: Send timestamp in microseconds since UNIX epoch.
+pub const UDP_PING_PAYLOAD_MAGIC: &[u8; 4] = b"RNPU";
+pub const UDP_PING_PAYLOAD_SIZE: usize = 16;
+
+pub struct PingClientUdp {
+    config: PingClientConfig,
+    next_sequence: AtomicU32,
+}
+
+impl PingClientUdp {
+    pub fn new(config: &PingClientConfig) -> PingClientUdp {
+        return PingClientUdp { config: config.clone(), next_sequence: AtomicU32::new(0) };
+    }
+
+    #[tracing::instrument(name = "Running UDP ping in ping client", level = "debug", skip(self))]
+    async fn ping_target(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
+        let socket = self.prepare_socket_for_ping(source).map_err(|e| PingClientError::PreparationFailed(Box::new(e)))?;
+        socket.connect(target).await.map_err(|e| PingClientError::PingFailed(Box::new(e)))?;
+
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        let payload = PingClientUdp::build_ping_payload(sequence);
+
+        let start_time = Instant::now();
+        socket.send(&payload).await.map_err(|e| PingClientError::PingFailed(Box::new(e)))?;
+
+        let wait_result = tokio::time::timeout(self.config.wait_timeout, PingClientUdp::wait_for_echo(&socket, sequence)).await;
+        let rtt = Instant::now().duration_since(start_time);
+        match wait_result {
+            // Timeout is an expected value instead of an actual failure, so here we should return Ok.
+            Err(_) => return Ok(PingClientPingResultDetails::new(None, rtt, true, None)),
+            Ok(Err(e)) => return Err(PingClientError::PingFailed(Box::new(e))),
+            Ok(Ok(())) => (),
+        }
+
+        // If getting local address failed, we ignore it, same as TCP ping.
+        return match socket.local_addr() {
+            Ok(addr) => Ok(PingClientPingResultDetails::new(Some(addr), rtt, false, None)),
+            Err(_) => Ok(PingClientPingResultDetails::new(None, rtt, false, None)),
+        };
+    }
+
+    #[tracing::instrument(name = "Creating UDP socket for ping", level = "debug", skip(self))]
+    fn prepare_socket_for_ping(&self, source: &SocketAddr) -> io::Result<UdpSocket> {
+        let socket_domain = if source.is_ipv4() { Domain::IPV4 } else { Domain::IPV6 };
+        let socket = Socket::new(socket_domain, Type::DGRAM, Some(Protocol::UDP))?;
+
+        if let Some(ttl) = self.config.time_to_live {
+            socket.set_ttl(ttl)?;
+        }
+
+        socket.bind(&SockAddr::from(*source))?;
+        socket.set_nonblocking(true)?;
+
+        return UdpSocket::from_std(socket.into());
+    }
+
+    fn build_ping_payload(sequence: u32) -> Vec<u8> {
+        let timestamp_in_us = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_micros() as u64);
+
+        let mut payload = Vec::with_capacity(UDP_PING_PAYLOAD_SIZE);
+        payload.extend_from_slice(UDP_PING_PAYLOAD_MAGIC);
+        payload.extend_from_slice(&sequence.to_be_bytes());
+        payload.extend_from_slice(&timestamp_in_us.to_be_bytes());
+        return payload;
+    }
+
+    fn parse_echo_sequence(payload: &[u8]) -> Option<u32> {
+        if payload.len() < UDP_PING_PAYLOAD_SIZE || &payload[0..4] != UDP_PING_PAYLOAD_MAGIC {
+            return None;
+        }
+
+        return Some(u32::from_be_bytes(payload[4..8].try_into().unwrap()));
+    }
+
+    async fn wait_for_echo(socket: &UdpSocket, sequence: u32) -> io::Result<()> {
+        let mut read_buffer = vec![0u8; 1500];
+
+        // The socket is connected, so only datagrams from the target will be received. However, the echo of a previous ping
+        // can still arrive late, so we keep reading until we see the one matching our sequence number.
+        loop {
+            let read_size = socket.recv(&mut read_buffer).await?;
+            match PingClientUdp::parse_echo_sequence(&read_buffer[..read_size]) {
+                Some(echo_sequence) if echo_sequence == sequence => return Ok(()),
+                echo_sequence => tracing::debug!("Unexpected datagram received, skipping; sequence={:?}, expected={}", echo_sequence, sequence),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PingClient for PingClientUdp {
+    fn protocol(&self) -> &'static str {
+        "UDP"
+    }
+
+    async fn prepare_ping(&mut self, _: &SocketAddr) -> Result<(), PingClientError> {
+        Ok(())
+    }
+
+    async fn ping(&self, source: &SocketAddr, target: &SocketAddr) -> PingClientResult<PingClientPingResultDetails> {
+        return self.ping_target(source, target).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn udp_ping_payload_should_round_trip() {
+        let payload = PingClientUdp::build_ping_payload(12345);
+        assert_eq!(UDP_PING_PAYLOAD_SIZE, payload.len());
+        assert_eq!(Some(12345), PingClientUdp::parse_echo_sequence(&payload));
+
+        assert_eq!(None, PingClientUdp::parse_echo_sequence(&payload[..8]));
+        assert_eq!(None, PingClientUdp::parse_echo_sequence(&[0u8; UDP_PING_PAYLOAD_SIZE]));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::ping_clients::ping_client_test_common::*;
+use crate::stub_servers::stub_server_factory;
+use crate::{ping_clients::ping_client_factory, rnp_test_common, PingClientConfig, PingClientRegistry, RnpStubServerConfig, RnpSupportedProtocol};
+use futures_intrusive::sync::ManualResetEvent;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::runtime::Runtime;
+use tokio::time::Instant;
+
+#[test]
+fn ping_client_udp_should_work_when_pinging_good_host() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11437".parse::<SocketAddr>().unwrap();
+    let server_config = create_udp_stub_server_default_config(&server_address);
+    start_run_udp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let config = create_ping_client_udp_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::UDP, &config, &PingClientRegistry::new()).unwrap();
+        ping_client_should_work_when_pinging_good_host(&mut ping_client, &server_address).await;
+    });
+}
+
+#[test]
+fn ping_client_udp_should_timeout_when_server_echo_is_too_slow() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11438".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_udp_stub_server_default_config(&server_address);
+    server_config.sleep_before_write = Duration::from_millis(1000);
+    start_run_udp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let config = create_ping_client_udp_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::UDP, &config, &PingClientRegistry::new()).unwrap();
+        let source = "0.0.0.0:0".parse::<SocketAddr>().unwrap();
+        ping_client_result_should_be_expected(
+            &mut ping_client,
+            &source,
+            &server_address,
+            Duration::from_millis(200),
+            &ExpectedTestCaseResult::Timeout,
+        )
+        .await;
+    });
+}
+
+#[test]
+fn udp_stub_server_should_not_delay_later_datagrams_when_echo_is_delayed() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    let server_address = "127.0.0.1:11439".parse::<SocketAddr>().unwrap();
+    let mut server_config = create_udp_stub_server_default_config(&server_address);
+    server_config.sleep_before_write = Duration::from_millis(500);
+    start_run_udp_stub_server(&rt, server_config);
+
+    rt.block_on(async move {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let start_time = Instant::now();
+        for index in 0..4u8 {
+            socket.send_to(&[index], &server_address).await.unwrap();
+        }
+
+        // Echoing one by one takes 2s, while all echoes should be back in about 500ms when they are delayed in parallel.
+        let mut echoes = Vec::new();
+        let mut read_buffer = [0u8; 16];
+        while echoes.len() < 4 {
+            let (read_size, _) = tokio::time::timeout(Duration::from_millis(1500), socket.recv_from(&mut read_buffer)).await.unwrap().unwrap();
+            echoes.extend_from_slice(&read_buffer[..read_size]);
+        }
+        echoes.sort_unstable();
+
+        assert_eq!(vec![0, 1, 2, 3], echoes);
+        assert!(start_time.elapsed() >= Duration::from_millis(500));
+        assert!(start_time.elapsed() < Duration::from_millis(1500));
+    });
+}
+
+#[test]
+fn ping_client_udp_should_fail_when_pinging_non_existing_host() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async move {
+        let config = create_ping_client_udp_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::UDP, &config, &PingClientRegistry::new()).unwrap();
+        ping_client_should_fail_when_pinging_non_existing_host(&mut ping_client, &ExpectedTestCaseResult::Timeout).await;
+    });
+}
+
+#[test]
+fn ping_client_udp_should_fail_when_pinging_non_existing_port() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async move {
+        let config = create_ping_client_udp_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::UDP, &config, &PingClientRegistry::new()).unwrap();
+
+        // Port unreachable from ICMP will be reported as connection refused on the next receive on Linux and macOS.
+        let expected_result = if cfg!(windows) { ExpectedTestCaseResult::Timeout } else { ExpectedTestCaseResult::Failed("connection refused") };
+        ping_client_should_fail_when_pinging_non_existing_port(&mut ping_client, &expected_result).await;
+    });
+}
+
+#[test]
+fn ping_client_udp_should_fail_when_binding_invalid_source_ip() {
+    rnp_test_common::initialize();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async move {
+        let config = create_ping_client_udp_default_config();
+        let mut ping_client = ping_client_factory::new_ping_client(&RnpSupportedProtocol::UDP, &config, &PingClientRegistry::new()).unwrap();
+
+        let expected_result = ExpectedTestCaseResult::Failed("The requested address is not valid in its context. (os error 10049)");
+        ping_client_should_fail_when_binding_invalid_source_ip(&mut ping_client, &expected_result).await;
+    });
+}
+
+fn create_udp_stub_server_default_config(server_address: &SocketAddr) -> RnpStubServerConfig {
+    return RnpStubServerConfig {
+        protocol: RnpSupportedProtocol::UDP,
+        server_address: *server_address,
+        close_on_accept: false,
+        close_on_handshake: false,
+        alpn_protocols: vec![],
+        reject_alpn: false,
+        sleep_before_write: Duration::ZERO,
+        write_chunk_size: 0,
+        write_count_limit: 0,
+        report_interval: Duration::from_secs(1),
+        wait_before_disconnect: Duration::ZERO,
+        fault_rules: vec![],
+    };
+}
+
+fn start_run_udp_stub_server(rt: &Runtime, stub_server_config: RnpStubServerConfig) {
+    let ready_event = Arc::new(ManualResetEvent::new(false));
+    let ready_event_clone = ready_event.clone();
+    rt.spawn(async move {
+        let _ = stub_server_factory::run(&stub_server_config, Arc::new(ManualResetEvent::new(false)), ready_event_clone).unwrap().await;
+    });
+    rt.block_on(ready_event.wait());
+}
+
+fn create_ping_client_udp_default_config() -> PingClientConfig {
+    return PingClientConfig {
+        wait_timeout: Duration::from_millis(300),
+        time_to_live: None,
+        check_disconnect: false,
+        wait_before_disconnect: Duration::ZERO,
+        disconnect_timeout: Duration::ZERO,
+        server_name: None,
+        log_tls_key: false,
+        alpn_protocol: None,
+        use_timer_rtt: false,
+        http_method: String::from("GET"),
+        http_path: String::from("/"),
+        http_headers: vec![],
+        http_expected_status: None,
+        http_body_regex: None,
+        measure_one_way_latency: false,
+        use_persistent_connection: false,
+        protocol_options: BTreeMap::new(),
+    };
+}
@@ -3,6 +3,9 @@ use contracts::requires;
 
 pub struct PingPortPicker {
     remaining_ping_count: Option<u32>,
+    picked_port_count: u64,
+    target_count: usize,
+    target_rotation_period: u64,
 
     port_ranges: PortRangeList,
     next_port: u16,
@@ -18,7 +21,15 @@ impl PingPortPicker {
 
         let next_port = *port_ranges.ranges[0].start();
 
-        let mut port_picker = PingPortPicker { remaining_ping_count: ping_count, port_ranges, next_port, next_port_range_index: 0 };
+        let mut port_picker = PingPortPicker {
+            remaining_ping_count: ping_count,
+            picked_port_count: 0,
+            target_count: 1,
+            target_rotation_period: 1,
+            port_ranges,
+            next_port,
+            next_port_range_index: 0,
+        };
 
         for _ in 0..skip_port_count {
             port_picker.next();
@@ -27,6 +38,26 @@ impl PingPortPicker {
         return port_picker;
     }
 
+    /// Spread the picked ports across multiple targets in turn. Ping count and skipped ports are counted across all targets.
+    #[requires(target_count > 0)]
+    pub fn with_target_count(mut self, target_count: usize) -> PingPortPicker {
+        self.target_count = target_count;
+
+        // Ports and targets are both picked in turn, so the same port goes back to the same target after every lcm(port count, target
+        // count) pings. If both counts share a common divisor, e.g. 4 ports and 2 targets, each target will only get a part of the ports.
+        // Hence, we shift the targets by 1 after each period, so every target gets every port in the end.
+        let port_count: u64 = self.port_ranges.ranges.iter().map(|r| (*r.end() - *r.start()) as u64 + 1).sum();
+        self.target_rotation_period = port_count / gcd(port_count, target_count as u64) * target_count as u64;
+        return self;
+    }
+
+    /// Pick the next source port, together with the index of the target that this ping should go to.
+    pub fn next_with_target_index(&mut self) -> Option<(u16, usize)> {
+        let target_shift = self.picked_port_count / self.target_rotation_period;
+        let target_index = ((self.picked_port_count + target_shift) % self.target_count as u64) as usize;
+        return self.fetch_next_available_port().map(|port| (port, target_index));
+    }
+
     fn fetch_next_available_port(&mut self) -> Option<u16> {
         match self.remaining_ping_count {
             Some(remaining_ping_count) if remaining_ping_count == 0 => return None,
@@ -34,6 +65,7 @@ impl PingPortPicker {
             None => (),
         }
 
+        self.picked_port_count += 1;
         return Some(self.fetch_next_available_port_from_port_ranges());
     }
 
@@ -54,6 +86,10 @@ impl PingPortPicker {
     }
 }
 
+fn gcd(a: u64, b: u64) -> u64 {
+    return if b == 0 { a } else { gcd(b, a % b) };
+}
+
 impl Iterator for PingPortPicker {
     type Item = u16;
 
@@ -110,6 +146,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ping_port_picker_should_work_with_multiple_targets() {
+        let mut port_picker = PingPortPicker::new(Some(6), PortRangeList { ranges: vec![(1024..=1027)] }, 2).with_target_count(2);
+        let mut picked_ports = vec![];
+        while let Some(picked_port) = port_picker.next_with_target_index() {
+            picked_ports.push(picked_port);
+        }
+
+        assert_eq!(vec![(1026, 0), (1027, 1), (1024, 1), (1025, 0)], picked_ports);
+    }
+
+    #[test]
+    fn ping_port_picker_should_give_every_port_to_every_target() {
+        for (port_range, target_count) in [((1024..=1027), 2), ((1024..=1027), 4), ((1024..=1029), 4), ((1024..=1028), 3), ((1024..=1024), 3)] {
+            let port_count = (*port_range.end() - *port_range.start() + 1) as u32;
+            let ping_count = port_count * target_count as u32;
+            let mut port_picker =
+                PingPortPicker::new(Some(ping_count), PortRangeList { ranges: vec![port_range.clone()] }, 0).with_target_count(target_count);
+
+            let mut picked_ports_per_target = vec![vec![]; target_count];
+            while let Some((port, target_index)) = port_picker.next_with_target_index() {
+                picked_ports_per_target[target_index].push(port);
+            }
+
+            for mut picked_ports in picked_ports_per_target {
+                picked_ports.sort_unstable();
+                assert_eq!(port_range.clone().collect::<Vec<u16>>(), picked_ports, "Port range = {:?}, Target count = {}", port_range, target_count);
+            }
+        }
+    }
+
     #[test]
     #[should_panic]
     fn ping_port_picker_should_panic_when_port_list_is_empty() {
@@ -0,0 +1,144 @@
+use crate::{PingArrivalDistribution, PingRate};
+use futures_intrusive::sync::ManualResetEvent;
+use rand::Rng;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+use tokio::{task, task::JoinHandle};
+
+// Issues pings at the target rate by releasing one permit per ping to the ping workers. The schedule is open loop: it never
+// waits for any ping to complete, so slow or timed out pings won't lower the rate. The pending permits are capped at the worker
+// count, so the slots missed while all workers are busy are dropped instead of being released as a burst later.
+pub struct PingRateScheduler {
+    rate: PingRate,
+    arrival_distribution: PingArrivalDistribution,
+    worker_count: u32,
+    ping_permits: Arc<Semaphore>,
+    stop_event: Arc<ManualResetEvent>,
+}
+
+impl PingRateScheduler {
+    #[tracing::instrument(name = "Starting ping rate scheduler", level = "debug", skip(ping_permits, stop_event))]
+    pub fn run(
+        rate: PingRate,
+        arrival_distribution: PingArrivalDistribution,
+        worker_count: u32,
+        ping_permits: Arc<Semaphore>,
+        stop_event: Arc<ManualResetEvent>,
+    ) -> JoinHandle<()> {
+        let join_handle = task::spawn(async move {
+            let scheduler = PingRateScheduler { rate, arrival_distribution, worker_count, ping_permits, stop_event };
+            scheduler.run_scheduler_loop().await;
+            tracing::debug!("Ping rate scheduler loop exited.");
+        });
+
+        return join_handle;
+    }
+
+    #[tracing::instrument(name = "Running ping rate scheduler loop", level = "debug", skip(self))]
+    async fn run_scheduler_loop(&self) {
+        let mut is_falling_behind_reported = false;
+
+        // Next ping time is always moved forward from the last scheduled time instead of the current time, so timer delays won't
+        // accumulate into rate drifts.
+        let mut next_ping_time = Instant::now();
+        loop {
+            if self.ping_permits.available_permits() < self.worker_count as usize {
+                self.ping_permits.add_permits(1);
+            } else if !is_falling_behind_reported {
+                tracing::warn!(
+                    "All ping workers are busy, so scheduled pings are skipped. Please increase the parallel ping count to keep up with rate {}.",
+                    self.rate
+                );
+                is_falling_behind_reported = true;
+            }
+
+            let inter_arrival_time = PingRateScheduler::next_inter_arrival_time(&self.rate, &self.arrival_distribution, &mut rand::thread_rng());
+            next_ping_time = match next_ping_time.checked_add(inter_arrival_time) {
+                Some(next_ping_time) => next_ping_time,
+                None => {
+                    tracing::warn!("Next ping time is too far away to schedule, so no more pings will be issued: Rate = {}", self.rate);
+                    self.stop_event.wait().await;
+                    return;
+                }
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(next_ping_time) => (),
+                _ = self.stop_event.wait() => {
+                    tracing::debug!("Stop event received, stopping ping rate scheduler.");
+                    return;
+                }
+            }
+        }
+    }
+
+    fn next_inter_arrival_time(rate: &PingRate, arrival_distribution: &PingArrivalDistribution, rng: &mut impl Rng) -> Duration {
+        let interval = rate.interval();
+        return match arrival_distribution {
+            PingArrivalDistribution::Constant => interval,
+
+            // Exponential distribution sampled by inverse transform. 1 - x is used to avoid ln(0), because x is in [0, 1).
+            PingArrivalDistribution::Poisson => interval.mul_f64(-(1.0 - rng.gen::<f64>()).ln()),
+
+            PingArrivalDistribution::Jitter => interval.mul_f64(rng.gen_range(0.5..1.5)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn constant_inter_arrival_time_should_match_rate() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let rate = PingRate { pings_per_second: 500.0 };
+        assert_eq!(Duration::from_millis(2), PingRateScheduler::next_inter_arrival_time(&rate, &PingArrivalDistribution::Constant, &mut rng));
+    }
+
+    #[test]
+    fn random_inter_arrival_time_should_average_to_rate() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let rate = PingRate { pings_per_second: 100.0 };
+
+        for arrival_distribution in [PingArrivalDistribution::Poisson, PingArrivalDistribution::Jitter] {
+            let inter_arrival_times: Vec<Duration> =
+                (0..10000).map(|_| PingRateScheduler::next_inter_arrival_time(&rate, &arrival_distribution, &mut rng)).collect();
+
+            let average_in_ms = inter_arrival_times.iter().sum::<Duration>().as_secs_f64() * 1000.0 / inter_arrival_times.len() as f64;
+            assert!((average_in_ms - 10.0).abs() < 0.5, "Average inter-arrival time is {}ms with {}", average_in_ms, arrival_distribution);
+
+            if arrival_distribution == PingArrivalDistribution::Jitter {
+                assert!(inter_arrival_times.iter().all(|t| *t >= Duration::from_millis(5) && *t <= Duration::from_millis(15)));
+            }
+        }
+    }
+
+    #[test]
+    fn pending_ping_permits_should_not_exceed_worker_count_when_all_workers_are_busy() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let ping_permits = Arc::new(Semaphore::new(0));
+            let stop_event = Arc::new(ManualResetEvent::new(false));
+            let scheduler_join_handle = PingRateScheduler::run(
+                PingRate { pings_per_second: 1000.0 },
+                PingArrivalDistribution::Constant,
+                2,
+                ping_permits.clone(),
+                stop_event.clone(),
+            );
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert_eq!(2, ping_permits.available_permits());
+
+            stop_event.set();
+            scheduler_join_handle.await.unwrap();
+        });
+    }
+}
@@ -1,9 +1,13 @@
 use crate::ping_clients::ping_client::PingClientError;
 use crate::ping_clients::ping_client::PingClientWarning;
+use crate::ping_clients::ping_client::PingFailureClass;
+use crate::ping_clients::ping_client::PingOneWayLatency;
 use crate::PingResultDto;
 use chrono::{offset::Utc, DateTime};
 use contracts::requires;
-use std::{net::SocketAddr, time::Duration};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::{io, net::SocketAddr, time::Duration};
 
 #[derive(Debug)]
 pub struct PingResult {
@@ -18,6 +22,14 @@ pub struct PingResult {
     is_timed_out: bool,
     warning: Option<PingClientWarning>,
     error: Option<PingClientError>,
+    dns_resolve_time: Option<Duration>,
+    handshake_time: Option<Duration>,
+    first_byte_time: Option<Duration>,
+    response_time: Option<Duration>,
+    one_way_latency: Option<PingOneWayLatency>,
+
+    // Only set when the result is loaded from logs, because the original errors are gone and can't be classified again.
+    logged_failure_class: Option<PingFailureClass>,
 }
 
 impl PingResult {
@@ -49,7 +61,103 @@ impl PingResult {
             is_timed_out,
             warning,
             error,
+            dns_resolve_time: None,
+            handshake_time: None,
+            first_byte_time: None,
+            response_time: None,
+            one_way_latency: None,
+            logged_failure_class: None,
+        }
+    }
+
+    /// Rebuild the ping result from the records loaded from the CSV or JSON logs, so they can be processed again. The errors are
+    /// only kept as their messages.
+    pub fn from_dto(dto: &PingResultDto) -> Result<PingResult, String> {
+        let create_error = |message: &str| -> Box<dyn std::error::Error + Send> { Box::new(io::Error::other(message.to_string())) };
+
+        let error = if !dto.preparation_error.is_empty() {
+            Some(PingClientError::PreparationFailed(create_error(&dto.preparation_error)))
+        } else if !dto.ping_error.is_empty() {
+            Some(PingClientError::PingFailed(create_error(&dto.ping_error)))
+        } else {
+            None
+        };
+
+        let warning = if !dto.handshake_error.is_empty() {
+            Some(PingClientWarning::AppHandshakeFailed(create_error(&dto.handshake_error)))
+        } else if !dto.disconnect_error.is_empty() {
+            Some(PingClientWarning::DisconnectFailed(create_error(&dto.disconnect_error)))
+        } else if !dto.reconnect_reason.is_empty() {
+            Some(PingClientWarning::Reconnected(create_error(&dto.reconnect_reason)))
+        } else {
+            None
+        };
+
+        // Same rules as the contracts in the constructor, but bad records are returned as errors instead of panicking.
+        let is_consistent =
+            if dto.is_succeeded { !dto.is_timed_out && error.is_none() } else { (dto.is_timed_out || error.is_some()) && warning.is_none() };
+        if !is_consistent {
+            return Err(format!(
+                "Inconsistent ping result from {}:{} to {}:{} at {:?}: Succeeded = {}, TimedOut = {}.",
+                dto.source_ip, dto.source_port, dto.target_ip, dto.target_port, dto.utc_time, dto.is_succeeded, dto.is_timed_out
+            ));
+        }
+
+        let to_duration = |time_in_ms: f64| -> Result<Option<Duration>, String> {
+            if time_in_ms == 0.0 {
+                return Ok(None);
+            }
+
+            return Duration::try_from_secs_f64(time_in_ms / 1000.0).map(Some).map_err(|_| format!("Invalid time in ping result: {}ms.", time_in_ms));
+        };
+
+        let mut ping_result = PingResult::new(
+            &dto.utc_time,
+            dto.worker_id,
+            intern_protocol_name(&dto.protocol),
+            SocketAddr::new(dto.target_ip, dto.target_port),
+            SocketAddr::new(dto.source_ip, dto.source_port),
+            dto.is_warmup,
+            dto.is_succeeded,
+            to_duration(dto.rtt_in_ms)?.unwrap_or_default(),
+            dto.is_timed_out,
+            warning,
+            error,
+        )
+        .with_dns_resolve_time(to_duration(dto.dns_resolve_time_in_ms)?)
+        .with_handshake_time(to_duration(dto.handshake_time_in_ms)?)
+        .with_response_time(to_duration(dto.first_byte_time_in_ms)?, to_duration(dto.response_time_in_ms)?);
+        if dto.forward_delay_in_ms != 0.0 || dto.reverse_delay_in_ms != 0.0 || dto.clock_offset_in_ms != 0.0 {
+            ping_result = ping_result.with_one_way_latency(Some(PingOneWayLatency {
+                forward_delay: to_duration(dto.forward_delay_in_ms)?.unwrap_or_default(),
+                reverse_delay: to_duration(dto.reverse_delay_in_ms)?.unwrap_or_default(),
+                clock_offset_in_us: (dto.clock_offset_in_ms * 1000.0).round() as i64,
+            }));
         }
+        ping_result.logged_failure_class = dto.failure_class;
+
+        return Ok(ping_result);
+    }
+
+    pub fn with_dns_resolve_time(mut self, dns_resolve_time: Option<Duration>) -> PingResult {
+        self.dns_resolve_time = dns_resolve_time;
+        return self;
+    }
+
+    pub fn with_handshake_time(mut self, handshake_time: Option<Duration>) -> PingResult {
+        self.handshake_time = handshake_time;
+        return self;
+    }
+
+    pub fn with_response_time(mut self, first_byte_time: Option<Duration>, response_time: Option<Duration>) -> PingResult {
+        self.first_byte_time = first_byte_time;
+        self.response_time = response_time;
+        return self;
+    }
+
+    pub fn with_one_way_latency(mut self, one_way_latency: Option<PingOneWayLatency>) -> PingResult {
+        self.one_way_latency = one_way_latency;
+        return self;
     }
 
     pub fn ping_time(&self) -> &DateTime<Utc> {
@@ -85,6 +193,21 @@ impl PingResult {
     pub fn error(&self) -> &Option<PingClientError> {
         &self.error
     }
+    pub fn dns_resolve_time(&self) -> Option<Duration> {
+        self.dns_resolve_time
+    }
+    pub fn handshake_time(&self) -> Option<Duration> {
+        self.handshake_time
+    }
+    pub fn first_byte_time(&self) -> Option<Duration> {
+        self.first_byte_time
+    }
+    pub fn response_time(&self) -> Option<Duration> {
+        self.response_time
+    }
+    pub fn one_way_latency(&self) -> Option<PingOneWayLatency> {
+        self.one_way_latency
+    }
     pub fn is_preparation_error(&self) -> bool {
         if let Some(PingClientError::PreparationFailed(_)) = self.error() {
             true
@@ -93,6 +216,38 @@ impl PingResult {
         }
     }
 
+    // Reconnects of persistent connections are reported as separate results to show the connection drops. Their RTT is only the
+    // connect time of the new connection, not a ping.
+    pub fn is_reconnect(&self) -> bool {
+        matches!(self.warning, Some(PingClientWarning::Reconnected(_)))
+    }
+
+    // Warmup pings are skipped in analysis, and so are preparation errors, since they are not remote issues, and reconnects, since they
+    // are not pings. All result processors that calculate statistics should follow the same rule, so their summaries can match each other.
+    pub fn is_counted_in_statistics(&self) -> bool {
+        !self.is_warmup() && !self.is_preparation_error() && !self.is_reconnect()
+    }
+
+    // Failed pings are always classified, and so are the app handshake failures, such as TLS failures. Disconnect failures are
+    // not classified, because the target is already reached.
+    pub fn failure_class(&self) -> Option<PingFailureClass> {
+        if self.logged_failure_class.is_some() {
+            return self.logged_failure_class;
+        }
+
+        if self.is_timed_out() {
+            return Some(PingFailureClass::Timeout);
+        }
+
+        return match (self.error(), self.warning()) {
+            (Some(PingClientError::PreparationFailed(e)), _) | (Some(PingClientError::PingFailed(e)), _) => {
+                Some(PingFailureClass::from_error(e.as_ref()))
+            }
+            (None, Some(PingClientWarning::AppHandshakeFailed(w))) => Some(PingFailureClass::from_error(w.as_ref())),
+            _ => None,
+        };
+    }
+
     pub fn create_dto(&self) -> PingResultDto {
         let preparation_error = self.error().as_ref().map_or(String::from(""), |e| {
             if let PingClientError::PreparationFailed(pe) = e {
@@ -130,6 +285,14 @@ impl PingResult {
             }
         });
 
+        let reconnect_reason = self.warning().as_ref().map_or(String::from(""), |w| {
+            if let PingClientWarning::Reconnected(rw) = w {
+                rw.to_string()
+            } else {
+                String::from("")
+            }
+        });
+
         return PingResultDto {
             utc_time: self.ping_time().clone(),
             worker_id: self.worker_id(),
@@ -146,6 +309,15 @@ impl PingResult {
             ping_error,
             handshake_error,
             disconnect_error,
+            dns_resolve_time_in_ms: self.dns_resolve_time().map_or(0.0, |t| t.as_micros() as f64 / 1000.0),
+            handshake_time_in_ms: self.handshake_time().map_or(0.0, |t| t.as_micros() as f64 / 1000.0),
+            first_byte_time_in_ms: self.first_byte_time().map_or(0.0, |t| t.as_micros() as f64 / 1000.0),
+            response_time_in_ms: self.response_time().map_or(0.0, |t| t.as_micros() as f64 / 1000.0),
+            failure_class: self.failure_class(),
+            forward_delay_in_ms: self.one_way_latency().map_or(0.0, |l| l.forward_delay.as_micros() as f64 / 1000.0),
+            reverse_delay_in_ms: self.one_way_latency().map_or(0.0, |l| l.reverse_delay.as_micros() as f64 / 1000.0),
+            clock_offset_in_ms: self.one_way_latency().map_or(0.0, |l| l.clock_offset_in_us as f64 / 1000.0),
+            reconnect_reason,
         };
     }
 
@@ -162,12 +334,29 @@ impl PingResult {
     }
 }
 
+// The protocol is kept as static string in ping results, because it always comes from the ping clients. The protocols loaded from logs
+// are leaked only once per name, so loading large logs won't keep leaking memory.
+fn intern_protocol_name(protocol: &str) -> &'static str {
+    static PROTOCOL_NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut protocol_names = PROTOCOL_NAMES.lock().unwrap();
+    if let Some(protocol_name) = protocol_names.get(protocol) {
+        return protocol_name;
+    }
+
+    let protocol_name: &'static str = Box::leak(protocol.to_string().into_boxed_str());
+    protocol_names.insert(protocol_name);
+    return protocol_name;
+}
+
 #[cfg(test)]
 mod tests {
     use crate::ping_result::PingResult;
     use crate::rnp_test_common;
-    use chrono::Utc;
+    use crate::{PingClientError, PingClientWarning, PingFailureClass, PingOneWayLatency, PingResultDto};
+    use chrono::{TimeZone, Utc};
     use pretty_assertions::assert_eq;
+    use std::io;
     use std::net::SocketAddr;
     use std::time::Duration;
 
@@ -219,12 +408,12 @@ mod tests {
         let results = rnp_test_common::generate_ping_result_test_samples();
         assert_eq!(
             vec![
-                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":true,\"IsSucceeded\":true,\"RttInMs\":10.00,\"IsTimedOut\":false,\"PreparationError\":\"\",\"PingError\":\"\",\"HandshakeError\":\"\",\"DisconnectError\":\"\"}",
-                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":false,\"RttInMs\":1000.00,\"IsTimedOut\":true,\"PreparationError\":\"\",\"PingError\":\"\",\"HandshakeError\":\"\",\"DisconnectError\":\"\"}",
-                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":true,\"RttInMs\":20.00,\"IsTimedOut\":false,\"PreparationError\":\"\",\"PingError\":\"\",\"HandshakeError\":\"connect aborted\",\"DisconnectError\":\"\"}",
-                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":true,\"RttInMs\":20.00,\"IsTimedOut\":false,\"PreparationError\":\"\",\"PingError\":\"\",\"HandshakeError\":\"\",\"DisconnectError\":\"disconnect timeout\"}",
-                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":false,\"RttInMs\":0.00,\"IsTimedOut\":false,\"PreparationError\":\"\",\"PingError\":\"connect failed\",\"HandshakeError\":\"\",\"DisconnectError\":\"\"}",
-                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":false,\"RttInMs\":0.00,\"IsTimedOut\":false,\"PreparationError\":\"address in use\",\"PingError\":\"\",\"HandshakeError\":\"\",\"DisconnectError\":\"\"}",
+                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":true,\"IsSucceeded\":true,\"RttInMs\":10.00,\"IsTimedOut\":false,\"PreparationError\":\"\",\"PingError\":\"\",\"HandshakeError\":\"\",\"DisconnectError\":\"\",\"DnsResolveTimeInMs\":5.00,\"HandshakeTimeInMs\":0.00,\"FirstByteTimeInMs\":0.00,\"ResponseTimeInMs\":0.00,\"FailureClass\":null,\"ForwardDelayInMs\":0.00,\"ReverseDelayInMs\":0.00,\"ClockOffsetInMs\":0.00,\"ReconnectReason\":\"\"}",
+                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":false,\"RttInMs\":1000.00,\"IsTimedOut\":true,\"PreparationError\":\"\",\"PingError\":\"\",\"HandshakeError\":\"\",\"DisconnectError\":\"\",\"DnsResolveTimeInMs\":0.00,\"HandshakeTimeInMs\":0.00,\"FirstByteTimeInMs\":0.00,\"ResponseTimeInMs\":0.00,\"FailureClass\":\"Timeout\",\"ForwardDelayInMs\":0.00,\"ReverseDelayInMs\":0.00,\"ClockOffsetInMs\":0.00,\"ReconnectReason\":\"\"}",
+                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":true,\"RttInMs\":20.00,\"IsTimedOut\":false,\"PreparationError\":\"\",\"PingError\":\"\",\"HandshakeError\":\"connect aborted\",\"DisconnectError\":\"\",\"DnsResolveTimeInMs\":0.00,\"HandshakeTimeInMs\":0.00,\"FirstByteTimeInMs\":0.00,\"ResponseTimeInMs\":0.00,\"FailureClass\":\"Other\",\"ForwardDelayInMs\":0.00,\"ReverseDelayInMs\":0.00,\"ClockOffsetInMs\":0.00,\"ReconnectReason\":\"\"}",
+                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":true,\"RttInMs\":20.00,\"IsTimedOut\":false,\"PreparationError\":\"\",\"PingError\":\"\",\"HandshakeError\":\"\",\"DisconnectError\":\"disconnect timeout\",\"DnsResolveTimeInMs\":0.00,\"HandshakeTimeInMs\":0.00,\"FirstByteTimeInMs\":0.00,\"ResponseTimeInMs\":0.00,\"FailureClass\":null,\"ForwardDelayInMs\":0.00,\"ReverseDelayInMs\":0.00,\"ClockOffsetInMs\":0.00,\"ReconnectReason\":\"\"}",
+                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":false,\"RttInMs\":0.00,\"IsTimedOut\":false,\"PreparationError\":\"\",\"PingError\":\"connect failed\",\"HandshakeError\":\"\",\"DisconnectError\":\"\",\"DnsResolveTimeInMs\":0.00,\"HandshakeTimeInMs\":0.00,\"FirstByteTimeInMs\":0.00,\"ResponseTimeInMs\":0.00,\"FailureClass\":\"Refused\",\"ForwardDelayInMs\":0.00,\"ReverseDelayInMs\":0.00,\"ClockOffsetInMs\":0.00,\"ReconnectReason\":\"\"}",
+                "{\"UtcTime\":\"2021-07-06T09:10:11.012Z\",\"WorkerId\":1,\"Protocol\":\"TCP\",\"TargetIp\":\"1.2.3.4\",\"TargetPort\":443,\"SourceIp\":\"5.6.7.8\",\"SourcePort\":8080,\"IsWarmup\":false,\"IsSucceeded\":false,\"RttInMs\":0.00,\"IsTimedOut\":false,\"PreparationError\":\"address in use\",\"PingError\":\"\",\"HandshakeError\":\"\",\"DisconnectError\":\"\",\"DnsResolveTimeInMs\":0.00,\"HandshakeTimeInMs\":0.00,\"FirstByteTimeInMs\":0.00,\"ResponseTimeInMs\":0.00,\"FailureClass\":\"LocalPortInUse\",\"ForwardDelayInMs\":0.00,\"ReverseDelayInMs\":0.00,\"ClockOffsetInMs\":0.00,\"ReconnectReason\":\"\"}",
             ],
             results.into_iter().map(|x| x.format_as_json_string()).collect::<Vec<String>>()
         );
@@ -235,14 +424,136 @@ mod tests {
         let results = rnp_test_common::generate_ping_result_test_samples();
         assert_eq!(
             vec![
-                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,true,true,10.00,false,\"\",\"\",\"\",\"\"",
-                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,false,1000.00,true,\"\",\"\",\"\",\"\"",
-                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,true,20.00,false,\"\",\"\",\"connect aborted\",\"\"",
-                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,true,20.00,false,\"\",\"\",\"\",\"disconnect timeout\"",
-                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,false,0.00,false,\"\",\"connect failed\",\"\",\"\"",
-                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,false,0.00,false,\"address in use\",\"\",\"\",\"\"",
+                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,true,true,10.00,false,\"\",\"\",\"\",\"\",5.00,0.00,0.00,0.00,,0.00,0.00,0.00,\"\"",
+                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,false,1000.00,true,\"\",\"\",\"\",\"\",0.00,0.00,0.00,0.00,Timeout,0.00,0.00,0.00,\"\"",
+                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,true,20.00,false,\"\",\"\",\"connect aborted\",\"\",0.00,0.00,0.00,0.00,Other,0.00,0.00,0.00,\"\"",
+                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,true,20.00,false,\"\",\"\",\"\",\"disconnect timeout\",0.00,0.00,0.00,0.00,,0.00,0.00,0.00,\"\"",
+                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,false,0.00,false,\"\",\"connect failed\",\"\",\"\",0.00,0.00,0.00,0.00,Refused,0.00,0.00,0.00,\"\"",
+                "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,false,false,0.00,false,\"address in use\",\"\",\"\",\"\",0.00,0.00,0.00,0.00,LocalPortInUse,0.00,0.00,0.00,\"\"",
             ],
             results.into_iter().map(|x| x.format_as_csv_string()).collect::<Vec<String>>()
         );
     }
+
+    #[test]
+    fn ping_result_should_round_trip_through_dto() {
+        let results = rnp_test_common::generate_ping_result_test_samples();
+        for result in &results {
+            let dto = result.create_dto();
+            let loaded_result = PingResult::from_dto(&dto).unwrap();
+            assert_eq!(dto, loaded_result.create_dto());
+            assert_eq!(result.is_counted_in_statistics(), loaded_result.is_counted_in_statistics());
+        }
+
+        // The failure class is taken from the logs, since the original errors are gone.
+        let mut dto = results[4].create_dto();
+        dto.failure_class = Some(PingFailureClass::HostUnreachable);
+        assert_eq!(Some(PingFailureClass::HostUnreachable), PingResult::from_dto(&dto).unwrap().failure_class());
+    }
+
+    #[test]
+    fn ping_result_with_special_characters_in_errors_should_round_trip_through_log() {
+        let message = "Error with \"quotes\", back\\slash,\r\nand new line.";
+        let create_error = || -> Box<dyn std::error::Error + Send> { Box::new(io::Error::other(message)) };
+        let create_result = |warning: Option<PingClientWarning>, error: Option<PingClientError>| {
+            return PingResult::new(
+                &(Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12)),
+                1,
+                "TCP",
+                "1.2.3.4:443".parse().unwrap(),
+                "5.6.7.8:8080".parse().unwrap(),
+                false,
+                error.is_none(),
+                Duration::from_millis(10),
+                false,
+                warning,
+                error,
+            );
+        };
+
+        let results = vec![
+            create_result(None, Some(PingClientError::PreparationFailed(create_error()))),
+            create_result(None, Some(PingClientError::PingFailed(create_error()))),
+            create_result(Some(PingClientWarning::AppHandshakeFailed(create_error())), None),
+            create_result(Some(PingClientWarning::DisconnectFailed(create_error())), None),
+            create_result(Some(PingClientWarning::Reconnected(create_error())), None),
+        ];
+
+        for result in &results {
+            let dto = result.create_dto();
+
+            let json_dto: PingResultDto = serde_json::from_str(&result.format_as_json_string()).unwrap();
+            assert_eq!(dto, PingResult::from_dto(&json_dto).unwrap().create_dto());
+
+            let csv_string = result.format_as_csv_string();
+            let mut csv_reader = csv::ReaderBuilder::new().has_headers(false).from_reader(csv_string.as_bytes());
+            let csv_dto: PingResultDto = csv_reader.deserialize().next().unwrap().unwrap();
+            assert_eq!(dto, PingResult::from_dto(&csv_dto).unwrap().create_dto());
+        }
+    }
+
+    #[test]
+    fn ping_result_with_one_way_latency_should_work() {
+        let one_way_latency =
+            PingOneWayLatency { forward_delay: Duration::from_micros(7500), reverse_delay: Duration::from_micros(2500), clock_offset_in_us: -1250 };
+        let result = rnp_test_common::generate_ping_result_test_samples().remove(0).with_one_way_latency(Some(one_way_latency));
+        assert_eq!(
+            "Reaching TCP 1.2.3.4:443 from 5.6.7.8:8080 (warmup) succeeded: RTT=10.00ms, Forward=7.50ms, Reverse=2.50ms",
+            result.format_as_console_log()
+        );
+        assert_eq!(
+            "2021-07-06T09:10:11.012Z,1,TCP,1.2.3.4,443,5.6.7.8,8080,true,true,10.00,false,\"\",\"\",\"\",\"\",5.00,0.00,0.00,0.00,,7.50,2.50,-1.25,\"\"",
+            result.format_as_csv_string()
+        );
+
+        let loaded_result = PingResult::from_dto(&result.create_dto()).unwrap();
+        assert_eq!(Some(one_way_latency), loaded_result.one_way_latency());
+    }
+
+    #[test]
+    fn ping_result_with_reconnect_should_work() {
+        let result = PingResult::new(
+            &(Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12)),
+            1,
+            "TCP",
+            "1.2.3.4:443".parse().unwrap(),
+            "5.6.7.8:8080".parse().unwrap(),
+            false,
+            true,
+            Duration::from_millis(10),
+            false,
+            Some(PingClientWarning::Reconnected(Box::new(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection is closed by remote side.")))),
+            None,
+        );
+        assert_eq!(
+            "Reaching TCP 1.2.3.4:443 from 5.6.7.8:8080 succeeded, but reconnected: RTT=10.00ms, Reason = Connection is closed by remote side.",
+            result.format_as_console_log()
+        );
+        assert!(result.format_as_csv_string().ends_with(",\"Connection is closed by remote side.\""));
+        assert!(result.is_reconnect());
+        assert!(!result.is_counted_in_statistics());
+
+        let loaded_result = PingResult::from_dto(&result.create_dto()).unwrap();
+        match loaded_result.warning() {
+            Some(PingClientWarning::Reconnected(e)) => assert_eq!("Connection is closed by remote side.", e.to_string()),
+            warning => panic!("Reconnect warning is expected, but got: {:?}", warning),
+        }
+    }
+
+    #[test]
+    fn loading_inconsistent_ping_result_from_dto_should_fail() {
+        let results = rnp_test_common::generate_ping_result_test_samples();
+
+        let mut dto = results[4].create_dto();
+        dto.ping_error = "".to_string();
+        assert!(PingResult::from_dto(&dto).is_err());
+
+        let mut dto = results[2].create_dto();
+        dto.is_timed_out = true;
+        assert!(PingResult::from_dto(&dto).is_err());
+
+        let mut dto = results[0].create_dto();
+        dto.rtt_in_ms = -1.0;
+        assert!(PingResult::from_dto(&dto).is_err());
+    }
 }
@@ -0,0 +1,78 @@
+use crate::{ping_result_processors::ping_result_processor_factory, PingResult, PingResultProcessor, PingResultProcessorConfig, RnpError};
+use futures_intrusive::sync::ManualResetEvent;
+use std::sync::Arc;
+
+// Runs the ping result processors over the results loaded from logs, in the same way as the ping result processing worker does in a
+// live run, so the summaries can be reproduced or calculated again with different settings without pinging again.
+pub struct PingResultAnalyzer {
+    processors: Vec<Box<dyn PingResultProcessor + Send + Sync>>,
+}
+
+impl PingResultAnalyzer {
+    pub fn new(
+        config: &PingResultProcessorConfig,
+        extra_ping_result_processors: Vec<Box<dyn PingResultProcessor + Send + Sync>>,
+    ) -> Result<PingResultAnalyzer, RnpError> {
+        config.validate()?;
+
+        // Nothing is pinging here, so the ping stop event set by exit on fail is simply ignored.
+        let processors = ping_result_processor_factory::new(config, extra_ping_result_processors, Arc::new(ManualResetEvent::new(false)))?;
+        return Ok(PingResultAnalyzer { processors });
+    }
+
+    #[tracing::instrument(name = "Analyzing ping results", level = "debug", skip(self, ping_results), fields(result_count = %ping_results.len()))]
+    pub fn run(mut self, ping_results: &[PingResult]) {
+        for processor in &mut self.processors {
+            processor.initialize();
+        }
+
+        for ping_result in ping_results {
+            for processor in &mut self.processors {
+                processor.process_ping_result(ping_result);
+            }
+        }
+
+        for processor in &mut self.processors {
+            processor.rundown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn analyzing_ping_results_loaded_from_logs_should_work() {
+        let expected_results: Vec<PingResultDto> = rnp_test_common::generate_ping_result_test_samples().iter().map(|r| r.create_dto()).collect();
+        let ping_results: Vec<PingResult> = expected_results.iter().map(|dto| PingResult::from_dto(dto).unwrap()).collect();
+
+        // Converting the results to another format goes through the same processors, so we can use it to check what was processed.
+        let test_log_file_path = PathBuf::from("tests_data/ping_result_analyzer_tests/test_log.json");
+        let config = PingResultProcessorConfig {
+            common_config: PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT },
+            json_log_path: Some(test_log_file_path.clone()),
+            show_result_scatter: true,
+            latency_buckets: Some(vec![0.1, 0.5, 1.0, 10.0]),
+            ..Default::default()
+        };
+        PingResultAnalyzer::new(&config, vec![]).unwrap().run(&ping_results);
+
+        assert_eq!(Ok(expected_results), load_ping_results_from_file(&test_log_file_path));
+    }
+
+    #[test]
+    fn creating_analyzer_with_invalid_config_should_fail() {
+        let config = PingResultProcessorConfig { latency_percentiles: Some(vec![50.0, 0.0]), ..Default::default() };
+        assert!(matches!(
+            PingResultAnalyzer::new(&config, vec![]),
+            Err(RnpError::InvalidConfig(RnpConfigError::InvalidLatencyPercentile(p))) if p == 0.0
+        ));
+
+        let config = PingResultProcessorConfig { exit_on_fail: true, ..Default::default() };
+        assert!(matches!(PingResultAnalyzer::new(&config, vec![]), Err(RnpError::InvalidConfig(RnpConfigError::NoExitFailureReason))));
+    }
+}
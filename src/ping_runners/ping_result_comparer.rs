@@ -0,0 +1,411 @@
+use crate::ping_result_processors::{LatencyHistogram, PingResultProcessorResultScatterLogger};
+use crate::{PingResult, PingResultComparisonConfig, RnpError, RNP_QUIET_LEVEL_NO_OUTPUT};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::SocketAddr;
+
+// Statistics of a single run on a single target. Only the pings counted in statistics are used, the same as the summaries, except
+// the source port results, which follow the result scatter map.
+struct PingTargetRunStatistics {
+    ping_count: u32,
+    failure_count: u32,
+    latency_histogram: LatencyHistogram,
+
+    // Hits of all latency buckets, followed by timed out and failed pings.
+    bucket_hit_counts: Vec<u32>,
+
+    port_results: BTreeMap<u16, BTreeSet<char>>,
+}
+
+impl PingTargetRunStatistics {
+    fn new(latency_bucket_count: usize) -> PingTargetRunStatistics {
+        return PingTargetRunStatistics {
+            ping_count: 0,
+            failure_count: 0,
+            latency_histogram: LatencyHistogram::new(),
+            bucket_hit_counts: vec![0; latency_bucket_count + 2],
+            port_results: BTreeMap::new(),
+        };
+    }
+
+    fn update(&mut self, ping_result: &PingResult, buckets_in_us: &[u128]) {
+        if ping_result.is_warmup() {
+            return;
+        }
+
+        let port_result = PingResultProcessorResultScatterLogger::result_symbol(ping_result);
+        self.port_results.entry(ping_result.source().port()).or_default().insert(port_result);
+
+        if !ping_result.is_counted_in_statistics() {
+            return;
+        }
+
+        self.ping_count += 1;
+        if !ping_result.is_succeeded() {
+            self.failure_count += 1;
+        }
+
+        let bucket_index = if ping_result.is_timed_out() {
+            buckets_in_us.len()
+        } else if ping_result.error().is_some() {
+            buckets_in_us.len() + 1
+        } else {
+            self.latency_histogram.record(&ping_result.round_trip_time());
+            buckets_in_us.iter().position(|upper_bound| ping_result.round_trip_time().as_micros() < *upper_bound).unwrap()
+        };
+        self.bucket_hit_counts[bucket_index] += 1;
+    }
+
+    fn failure_rate_in_percent(&self) -> f64 {
+        return self.hit_rate_in_percent(self.failure_count);
+    }
+
+    fn hit_rate_in_percent(&self, hit_count: u32) -> f64 {
+        if self.ping_count == 0 {
+            return 0.0;
+        }
+
+        return (hit_count as f64 * 100.0) / (self.ping_count as f64);
+    }
+
+    // Latency percentiles are only available when any ping reached the target.
+    fn latency_at_percentile_in_ms(&self, percentile: f64) -> Option<f64> {
+        if self.ping_count == self.failure_count {
+            return None;
+        }
+
+        return Some(self.latency_histogram.value_at_percentile(percentile) as f64 / 1000.0);
+    }
+
+    fn port_results_as_string(&self, port: u16) -> String {
+        return self.port_results.get(&port).map_or(String::new(), |results| results.iter().collect());
+    }
+}
+
+// Compares the results of 2 runs target by target, e.g. the runs before and after a network change, and reports the regressions
+// that exceed the thresholds in config.
+pub struct PingResultComparer {
+    config: PingResultComparisonConfig,
+    buckets_in_us: Vec<u128>,
+    protocol: Option<String>,
+    baseline_statistics: BTreeMap<SocketAddr, PingTargetRunStatistics>,
+    current_statistics: BTreeMap<SocketAddr, PingTargetRunStatistics>,
+}
+
+impl PingResultComparer {
+    #[tracing::instrument(name = "Comparing ping results", level = "debug", skip(baseline_results, current_results))]
+    pub fn new(
+        config: PingResultComparisonConfig,
+        baseline_results: &[PingResult],
+        current_results: &[PingResult],
+    ) -> Result<PingResultComparer, RnpError> {
+        config.validate()?;
+
+        // The same as latency bucket logger, buckets in config are the upper bounds, and the last bucket takes everything else.
+        let mut buckets_in_us: Vec<u128> = config.latency_buckets.iter().map(|x| (x * 1000.0) as u128).collect();
+        buckets_in_us.push(u128::MAX);
+
+        let protocol = baseline_results.iter().chain(current_results.iter()).next().map(|r| r.protocol().to_string());
+        let baseline_statistics = PingResultComparer::collect_statistics(baseline_results, &buckets_in_us);
+        let current_statistics = PingResultComparer::collect_statistics(current_results, &buckets_in_us);
+        return Ok(PingResultComparer { config, buckets_in_us, protocol, baseline_statistics, current_statistics });
+    }
+
+    fn collect_statistics(ping_results: &[PingResult], buckets_in_us: &[u128]) -> BTreeMap<SocketAddr, PingTargetRunStatistics> {
+        let mut statistics = BTreeMap::new();
+        for ping_result in ping_results {
+            statistics
+                .entry(ping_result.target())
+                .or_insert_with(|| PingTargetRunStatistics::new(buckets_in_us.len()))
+                .update(ping_result, buckets_in_us);
+        }
+
+        return statistics;
+    }
+
+    fn compared_targets(&self) -> impl Iterator<Item = (&SocketAddr, &PingTargetRunStatistics, &PingTargetRunStatistics)> {
+        return self
+            .baseline_statistics
+            .iter()
+            .filter_map(move |(target, baseline)| self.current_statistics.get(target).map(|current| (target, baseline, current)));
+    }
+
+    /// Return all regressions that exceed the thresholds. Targets that only show up in one of the runs are not compared.
+    pub fn regressions(&self) -> Vec<String> {
+        let mut regressions = Vec::new();
+
+        for (target, baseline, current) in self.compared_targets() {
+            if let Some(max_failure_rate_increase) = self.config.max_failure_rate_increase {
+                let failure_rate_increase = current.failure_rate_in_percent() - baseline.failure_rate_in_percent();
+                if failure_rate_increase > max_failure_rate_increase {
+                    regressions.push(format!(
+                        "{}: Failure rate increased from {:.2}% to {:.2}%, which is more than {:.2}%.",
+                        target,
+                        baseline.failure_rate_in_percent(),
+                        current.failure_rate_in_percent(),
+                        max_failure_rate_increase
+                    ));
+                }
+            }
+
+            if let Some(max_latency_increase_in_ms) = self.config.max_latency_increase_in_ms {
+                for percentile in &self.config.latency_percentiles {
+                    if let (Some(baseline_latency), Some(current_latency)) =
+                        (baseline.latency_at_percentile_in_ms(*percentile), current.latency_at_percentile_in_ms(*percentile))
+                    {
+                        if current_latency - baseline_latency > max_latency_increase_in_ms {
+                            regressions.push(format!(
+                                "{}: P{} latency increased from {:.2}ms to {:.2}ms, which is more than {:.2}ms.",
+                                target, percentile, baseline_latency, current_latency, max_latency_increase_in_ms
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        return regressions;
+    }
+
+    pub fn output_to_console(&self) {
+        if self.config.common_config.quiet_level >= RNP_QUIET_LEVEL_NO_OUTPUT {
+            return;
+        }
+
+        let protocol = self.protocol.as_deref().unwrap_or("");
+        for (target, baseline, current) in self.compared_targets() {
+            println!("\n=== Comparison for {} {:?} (baseline -> current) ===", protocol, target);
+            println!(
+                "- Connects: Sent = {} -> {}, Failed = {:.2}% -> {:.2}% ({:+.2}%).",
+                baseline.ping_count,
+                current.ping_count,
+                baseline.failure_rate_in_percent(),
+                current.failure_rate_in_percent(),
+                current.failure_rate_in_percent() - baseline.failure_rate_in_percent(),
+            );
+
+            let percentile_summaries: Vec<String> = self
+                .config
+                .latency_percentiles
+                .iter()
+                .map(|p| match (baseline.latency_at_percentile_in_ms(*p), current.latency_at_percentile_in_ms(*p)) {
+                    (Some(b), Some(c)) => format!("P{} = {:.2}ms -> {:.2}ms ({:+.2}ms)", p, b, c, c - b),
+                    (b, c) => format!("P{} = {} -> {}", p, PingResultComparer::format_latency(b), PingResultComparer::format_latency(c)),
+                })
+                .collect();
+            println!("- Percentiles: {}.", percentile_summaries.join(", "));
+
+            self.output_bucket_comparison_to_console(baseline, current);
+        }
+
+        self.output_changed_ports_to_console();
+
+        for target in self.baseline_statistics.keys().filter(|t| !self.current_statistics.contains_key(t)) {
+            println!("\nTarget {} is only found in baseline, hence skipped.", target);
+        }
+        for target in self.current_statistics.keys().filter(|t| !self.baseline_statistics.contains_key(t)) {
+            println!("\nTarget {} is only found in current run, hence skipped.", target);
+        }
+
+        if self.config.max_failure_rate_increase.is_some() || self.config.max_latency_increase_in_ms.is_some() {
+            let regressions = self.regressions();
+            println!("\n=== Regressions ===");
+            if regressions.is_empty() {
+                println!("No regression found.");
+            }
+            for regression in regressions {
+                println!("- {}", regression);
+            }
+        }
+    }
+
+    fn format_latency(latency_in_ms: Option<f64>) -> String {
+        return latency_in_ms.map_or(String::from("N/A"), |l| format!("{:.2}ms", l));
+    }
+
+    fn output_bucket_comparison_to_console(&self, baseline: &PingTargetRunStatistics, current: &PingTargetRunStatistics) {
+        println!("- Latency buckets (in % of pings):");
+        println!("{:>15} | {:>9} | {:>9} | {:>9}", "Latency Range", "Baseline", "Current", "Delta");
+        println!("{}+{}+{}+{}", "-".repeat(16), "-".repeat(11), "-".repeat(11), "-".repeat(11));
+
+        let bucket_count = self.buckets_in_us.len();
+        for bucket_index in 0..bucket_count + 2 {
+            let bucket_range = if bucket_index == bucket_count {
+                String::from("Timed Out")
+            } else if bucket_index == bucket_count + 1 {
+                String::from("Failed")
+            } else if bucket_index < bucket_count - 1 {
+                format!("< {:.2}ms", self.buckets_in_us[bucket_index] as f64 / 1000.0)
+            } else if bucket_count > 1 {
+                format!(">= {:.2}ms", self.buckets_in_us[bucket_index - 1] as f64 / 1000.0)
+            } else {
+                String::from("Succeeded")
+            };
+
+            let baseline_rate = baseline.hit_rate_in_percent(baseline.bucket_hit_counts[bucket_index]);
+            let current_rate = current.hit_rate_in_percent(current.bucket_hit_counts[bucket_index]);
+            println!("{:>15} | {:>8.2}% | {:>8.2}% | {:>+8.2}%", bucket_range, baseline_rate, current_rate, current_rate - baseline_rate);
+        }
+    }
+
+    // Source ports are compared with the same results as the result scatter map. A port is changed when the set of results seen on
+    // it is different between the 2 runs, e.g. "O" -> "OX" means some pings started to fail on this port.
+    fn output_changed_ports_to_console(&self) {
+        let mut changed_ports = Vec::new();
+        for (target, baseline, current) in self.compared_targets() {
+            for (port, baseline_results) in &baseline.port_results {
+                if let Some(current_results) = current.port_results.get(port) {
+                    if baseline_results != current_results {
+                        changed_ports.push((target, *port, baseline.port_results_as_string(*port), current.port_results_as_string(*port)));
+                    }
+                }
+            }
+        }
+
+        if changed_ports.is_empty() {
+            return;
+        }
+
+        println!("\n=== Source ports with changed results ===");
+        println!("(Results are shown in the same symbols as the ping result scatter map.)\n");
+        println!("{:<47} | {:>8} | {:>8} | Current", "Target", "Src Port", "Baseline");
+        println!("{}+{}+{}+{}", "-".repeat(48), "-".repeat(10), "-".repeat(10), "-".repeat(10));
+        for (target, port, baseline_results, current_results) in changed_ports {
+            println!("{:<47} | {:>8} | {:>8} | {}", target.to_string(), port, baseline_results, current_results);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rnp_test_common, PingClientError, PingResultProcessorCommonConfig, RnpConfigError, RNP_QUIET_LEVEL_NONE};
+    use chrono::Utc;
+    use pretty_assertions::assert_eq;
+    use std::io;
+    use std::time::Duration;
+
+    fn create_ping_result(source_port: u16, rtt_in_ms: Option<u64>) -> PingResult {
+        let source = SocketAddr::new("5.6.7.8".parse().unwrap(), source_port);
+        return match rtt_in_ms {
+            Some(rtt_in_ms) => PingResult::new(
+                &Utc::now(),
+                1,
+                "TCP",
+                "1.2.3.4:443".parse().unwrap(),
+                source,
+                false,
+                true,
+                Duration::from_millis(rtt_in_ms),
+                false,
+                None,
+                None,
+            ),
+            None => PingResult::new(
+                &Utc::now(),
+                1,
+                "TCP",
+                "1.2.3.4:443".parse().unwrap(),
+                source,
+                false,
+                false,
+                Duration::from_millis(0),
+                false,
+                None,
+                Some(PingClientError::PingFailed(Box::new(io::Error::new(io::ErrorKind::ConnectionRefused, "connect failed")))),
+            ),
+        };
+    }
+
+    fn create_comparison_config() -> PingResultComparisonConfig {
+        return PingResultComparisonConfig {
+            common_config: PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NONE },
+            latency_percentiles: vec![50.0, 99.0],
+            latency_buckets: vec![10.0, 50.0],
+            max_failure_rate_increase: None,
+            max_latency_increase_in_ms: None,
+        };
+    }
+
+    #[test]
+    fn comparing_same_results_should_not_find_regressions() {
+        let config =
+            PingResultComparisonConfig { max_failure_rate_increase: Some(0.0), max_latency_increase_in_ms: Some(0.0), ..create_comparison_config() };
+        let ping_results = rnp_test_common::generate_ping_result_test_samples();
+        let comparer = PingResultComparer::new(config, &ping_results, &ping_results).unwrap();
+        assert_eq!(Vec::<String>::new(), comparer.regressions());
+        comparer.output_to_console();
+    }
+
+    #[test]
+    fn comparing_results_should_find_regressions_over_thresholds() {
+        let baseline_results: Vec<PingResult> = (1..=4).map(|port| create_ping_result(port, Some(5))).collect();
+        let current_results =
+            vec![create_ping_result(1, Some(5)), create_ping_result(2, Some(30)), create_ping_result(3, None), create_ping_result(4, Some(5))];
+
+        let comparer = PingResultComparer::new(create_comparison_config(), &baseline_results, &current_results).unwrap();
+        assert_eq!(Vec::<String>::new(), comparer.regressions());
+
+        let target = "1.2.3.4:443".parse().unwrap();
+        let baseline_statistics = &comparer.baseline_statistics[&target];
+        let current_statistics = &comparer.current_statistics[&target];
+        assert_eq!(vec![4, 0, 0, 0, 0], baseline_statistics.bucket_hit_counts);
+        assert_eq!(vec![2, 1, 0, 0, 1], current_statistics.bucket_hit_counts);
+        assert_eq!("O", baseline_statistics.port_results_as_string(3));
+        assert_eq!("X", current_statistics.port_results_as_string(3));
+        comparer.output_to_console();
+
+        let config = PingResultComparisonConfig {
+            max_failure_rate_increase: Some(10.0),
+            max_latency_increase_in_ms: Some(20.0),
+            ..create_comparison_config()
+        };
+        let comparer = PingResultComparer::new(config, &baseline_results, &current_results).unwrap();
+        assert_eq!(
+            vec![
+                "1.2.3.4:443: Failure rate increased from 0.00% to 25.00%, which is more than 10.00%.".to_string(),
+                "1.2.3.4:443: P99 latency increased from 5.00ms to 30.00ms, which is more than 20.00ms.".to_string(),
+            ],
+            comparer.regressions()
+        );
+    }
+
+    #[test]
+    fn comparing_results_should_find_ports_changed_from_passed_to_timed_out() {
+        let baseline_results: Vec<PingResult> = (1..=2).map(|port| create_ping_result(port, Some(5))).collect();
+        let timed_out_result = PingResult::new(
+            &Utc::now(),
+            1,
+            "TCP",
+            "1.2.3.4:443".parse().unwrap(),
+            "5.6.7.8:2".parse().unwrap(),
+            false,
+            false,
+            Duration::from_millis(1000),
+            true,
+            None,
+            None,
+        );
+        let current_results = vec![create_ping_result(1, Some(5)), timed_out_result];
+
+        let config = PingResultComparisonConfig { max_failure_rate_increase: Some(10.0), ..create_comparison_config() };
+        let comparer = PingResultComparer::new(config, &baseline_results, &current_results).unwrap();
+        assert_eq!(vec!["1.2.3.4:443: Failure rate increased from 0.00% to 50.00%, which is more than 10.00%.".to_string()], comparer.regressions());
+
+        let target = "1.2.3.4:443".parse().unwrap();
+        assert_eq!(vec![2, 0, 0, 0, 0], comparer.baseline_statistics[&target].bucket_hit_counts);
+        assert_eq!(vec![1, 0, 0, 1, 0], comparer.current_statistics[&target].bucket_hit_counts);
+        assert_eq!("O", comparer.baseline_statistics[&target].port_results_as_string(2));
+        assert_eq!("X", comparer.current_statistics[&target].port_results_as_string(2));
+        comparer.output_to_console();
+    }
+
+    #[test]
+    fn creating_comparer_with_invalid_latency_percentile_should_fail() {
+        let config = PingResultComparisonConfig { latency_percentiles: vec![50.0, 100.5], ..create_comparison_config() };
+        let ping_results = rnp_test_common::generate_ping_result_test_samples();
+        assert!(matches!(
+            PingResultComparer::new(config, &ping_results, &ping_results),
+            Err(RnpError::InvalidConfig(RnpConfigError::InvalidLatencyPercentile(p))) if p == 100.5
+        ));
+    }
+}
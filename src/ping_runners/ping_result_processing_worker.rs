@@ -1,28 +1,33 @@
-use crate::{ping_result_processors::ping_result_processor_factory, PingResult, PingResultProcessor, PingResultProcessorConfig};
-use contracts::requires;
+use crate::{PingResult, PingResultProcessor, PingStopConditionChecker, PingStopReason};
 use futures_intrusive::sync::ManualResetEvent;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
 use tokio::{sync::mpsc, task, task::JoinHandle};
 
+const PROCESSOR_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct PingResultProcessingWorker {
     stop_event: Arc<ManualResetEvent>,
+    ping_stop_event: Arc<ManualResetEvent>,
+    stop_condition_checker: PingStopConditionChecker,
+    stop_reason: Arc<Mutex<Option<PingStopReason>>>,
 
     receiver: mpsc::UnboundedReceiver<PingResult>,
     processors: Vec<Box<dyn PingResultProcessor + Send + Sync>>,
 }
 
 impl PingResultProcessingWorker {
-    #[requires(config.exit_on_fail -> config.exit_failure_reason.is_some())]
     pub fn run(
-        config: Arc<PingResultProcessorConfig>,
-        extra_ping_result_processors: Vec<Box<dyn PingResultProcessor + Send + Sync>>,
+        processors: Vec<Box<dyn PingResultProcessor + Send + Sync>>,
+        stop_condition_checker: PingStopConditionChecker,
+        stop_reason: Arc<Mutex<Option<PingStopReason>>>,
         stop_event: Arc<ManualResetEvent>,
         ping_stop_event: Arc<ManualResetEvent>,
         receiver: mpsc::UnboundedReceiver<PingResult>,
     ) -> JoinHandle<()> {
         let join_handle = task::spawn(async move {
-            let processors = ping_result_processor_factory::new(&config, extra_ping_result_processors, ping_stop_event);
-            let mut worker = PingResultProcessingWorker { stop_event, receiver, processors };
+            let mut worker = PingResultProcessingWorker { stop_event, ping_stop_event, stop_condition_checker, stop_reason, receiver, processors };
             worker.run_worker().await;
         });
 
@@ -45,12 +50,21 @@ impl PingResultProcessingWorker {
 
     #[tracing::instrument(name = "Running ping result processing loop.", level = "debug", skip(self), fields(processor_count = %self.processors.len()))]
     async fn run_result_processing_loop(&mut self) {
+        let mut tick_interval = tokio::time::interval(PROCESSOR_TICK_INTERVAL);
+        tick_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         loop {
             tokio::select! {
                 Some(ping_result) = self.receiver.recv() => {
                     self.process_ping_result(&ping_result);
                 }
 
+                _ = tick_interval.tick() => {
+                    for processor in &mut self.processors {
+                        processor.on_tick();
+                    }
+                }
+
                 _ = self.stop_event.wait() => {
                     tracing::debug!("Stop event received, stopping receiver to avoid future message and drain till completed.");
                     self.receiver.close();
@@ -73,6 +87,12 @@ impl PingResultProcessingWorker {
 
     #[tracing::instrument(name = "Processing ping result", level = "debug", skip(self), fields(processor_count = %self.processors.len()))]
     fn process_ping_result(&mut self, ping_result: &PingResult) {
+        if let Some(stop_reason) = self.stop_condition_checker.check(ping_result) {
+            tracing::debug!("Stop condition met, signal all ping workers to exit: Reason = {}", stop_reason);
+            self.stop_reason.lock().unwrap().get_or_insert(stop_reason);
+            self.ping_stop_event.set();
+        }
+
         for processor in &mut self.processors {
             processor.process_ping_result(ping_result);
         }
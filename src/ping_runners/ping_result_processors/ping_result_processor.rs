@@ -10,5 +10,9 @@ pub trait PingResultProcessor {
 
     fn initialize(&mut self) {}
     fn process_ping_result(&mut self, ping_result: &PingResult);
+
+    // Called periodically while pinging, even when no result arrives, e.g. for refreshing the output while all pings are timing out.
+    fn on_tick(&mut self) {}
+
     fn rundown(&mut self) {}
 }
@@ -1,5 +1,6 @@
 use crate::*;
 use futures_intrusive::sync::ManualResetEvent;
+use std::collections::BTreeMap;
 use std::io::{stdout, Write};
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
@@ -8,6 +9,7 @@ use tracing;
 
 pub struct PingResultProcessorConsoleLogger {
     common_config: Arc<PingResultProcessorCommonConfig>,
+    show_live_output: bool,
     last_console_flush_time: Option<Instant>,
 
     ping_stop_event: Arc<ManualResetEvent>,
@@ -15,61 +17,40 @@ pub struct PingResultProcessorConsoleLogger {
     exit_failure_reason: Option<Arc<Mutex<Option<PingResultDto>>>>,
 
     protocol: Option<String>,
-    target: Option<SocketAddr>,
+    ping_count: u32,
+    target_statistics: BTreeMap<SocketAddr, PingTargetStatistics>,
+}
+
+struct PingTargetStatistics {
     ping_count: u32,
     success_count: u32,
     failure_count: u32,
     handshake_failed_count: u32,
     disconnect_failed_count: u32,
+    reconnected_count: u32,
+    failure_class_counts: BTreeMap<PingFailureClass, u32>,
     min_latency_in_us: u128,
     max_latency_in_us: u128,
     average_latency_in_us: f64,
 }
 
-impl PingResultProcessorConsoleLogger {
-    #[tracing::instrument(name = "Creating ping result console logger", level = "debug")]
-    pub fn new(
-        common_config: Arc<PingResultProcessorCommonConfig>,
-        ping_stop_event: Arc<ManualResetEvent>,
-        exit_on_fail: bool,
-        exit_failure_reason: Option<Arc<Mutex<Option<PingResultDto>>>>,
-    ) -> PingResultProcessorConsoleLogger {
-        return PingResultProcessorConsoleLogger {
-            common_config,
-            last_console_flush_time: None,
-            ping_stop_event,
-            exit_on_fail,
-            exit_failure_reason,
-            protocol: None,
-            target: None,
+impl PingTargetStatistics {
+    fn new() -> PingTargetStatistics {
+        return PingTargetStatistics {
             ping_count: 0,
             success_count: 0,
             failure_count: 0,
             handshake_failed_count: 0,
             disconnect_failed_count: 0,
+            reconnected_count: 0,
+            failure_class_counts: BTreeMap::new(),
             min_latency_in_us: u128::MAX,
             max_latency_in_us: u128::MIN,
             average_latency_in_us: 0.0,
         };
     }
 
-    fn update_statistics(&mut self, ping_result: &PingResult) {
-        // Skip warmup pings in analysis.
-        if ping_result.is_warmup() {
-            return;
-        }
-
-        // Skip preparation errors in analysis, since it is not a remote issue.
-        if ping_result.is_preparation_error() {
-            return;
-        }
-
-        // Save some info for outputting summary.
-        if self.target.is_none() {
-            self.protocol = Some(ping_result.protocol().to_string());
-            self.target = Some(ping_result.target());
-        }
-
+    fn update(&mut self, ping_result: &PingResult) {
         self.ping_count += 1;
         if ping_result.is_succeeded() {
             self.success_count += 1;
@@ -81,6 +62,7 @@ impl PingResultProcessorConsoleLogger {
             match warning {
                 PingClientWarning::AppHandshakeFailed(_) => self.handshake_failed_count += 1,
                 PingClientWarning::DisconnectFailed(_) => self.disconnect_failed_count += 1,
+                PingClientWarning::Reconnected(_) => (), // Reconnects are not counted as pings, so they are counted separately.
             }
         };
 
@@ -97,7 +79,96 @@ impl PingResultProcessorConsoleLogger {
         self.average_latency_in_us += (latency_in_us as f64 - self.average_latency_in_us) / (self.ping_count as f64);
     }
 
+    fn update_failure_class(&mut self, ping_result: &PingResult) {
+        if let Some(failure_class) = ping_result.failure_class() {
+            *self.failure_class_counts.entry(failure_class).or_insert(0) += 1;
+        }
+    }
+
+    fn failure_class_summary(&self) -> String {
+        return self
+            .failure_class_counts
+            .iter()
+            .map(|(failure_class, count)| format!("{} = {}", failure_class, count))
+            .collect::<Vec<String>>()
+            .join(", ");
+    }
+
+    fn warning_count(&self) -> u32 {
+        return self.handshake_failed_count + self.disconnect_failed_count + self.reconnected_count;
+    }
+
+    fn failure_rate_in_percent(&self) -> f64 {
+        if self.ping_count == 0 {
+            return 0.0;
+        }
+
+        return (self.failure_count as f64 * 100.0) / (self.ping_count as f64);
+    }
+
+    // If we haven't received any data, the min/max/average data won't be updated correctly, so we output 0 instead.
+    fn latency_summary_in_ms(&self) -> (f64, f64, f64) {
+        if self.min_latency_in_us == u128::MAX {
+            return (0.0, 0.0, 0.0);
+        }
+
+        return (self.min_latency_in_us as f64 / 1000.0, self.max_latency_in_us as f64 / 1000.0, self.average_latency_in_us / 1000.0);
+    }
+}
+
+impl PingResultProcessorConsoleLogger {
+    #[tracing::instrument(name = "Creating ping result console logger", level = "debug")]
+    pub fn new(
+        common_config: Arc<PingResultProcessorCommonConfig>,
+        ping_stop_event: Arc<ManualResetEvent>,
+        exit_on_fail: bool,
+        exit_failure_reason: Option<Arc<Mutex<Option<PingResultDto>>>>,
+        show_live_output: bool,
+    ) -> PingResultProcessorConsoleLogger {
+        return PingResultProcessorConsoleLogger {
+            common_config,
+            show_live_output,
+            last_console_flush_time: None,
+            ping_stop_event,
+            exit_on_fail,
+            exit_failure_reason,
+            protocol: None,
+            ping_count: 0,
+            target_statistics: BTreeMap::new(),
+        };
+    }
+
+    fn update_statistics(&mut self, ping_result: &PingResult) {
+        if ping_result.is_warmup() {
+            return;
+        }
+
+        // Save some info for outputting summary.
+        if self.protocol.is_none() {
+            self.protocol = Some(ping_result.protocol().to_string());
+        }
+
+        // Preparation errors and reconnects are not counted as pings, but they are still worth showing in the summary, e.g. source port
+        // in use or connection drops.
+        let statistics = self.target_statistics.entry(ping_result.target()).or_insert_with(PingTargetStatistics::new);
+        statistics.update_failure_class(ping_result);
+        if ping_result.is_reconnect() {
+            statistics.reconnected_count += 1;
+        }
+        if !ping_result.is_counted_in_statistics() {
+            return;
+        }
+
+        self.ping_count += 1;
+        statistics.update(ping_result);
+    }
+
     fn output_result_to_console(&mut self, ping_result: &PingResult) {
+        // When the dashboard is shown on terminal, each ping result and the ping count are already part of it.
+        if !self.show_live_output {
+            return;
+        }
+
         if self.config().quiet_level == RNP_QUIET_LEVEL_NO_PING_RESULT || self.config().quiet_level == RNP_QUIET_LEVEL_NO_PING_SUMMARY {
             self.output_ping_count_update_to_console(false);
             return;
@@ -129,6 +200,81 @@ impl PingResultProcessorConsoleLogger {
         let mut stdout = stdout();
         stdout.flush().unwrap();
     }
+    fn output_target_statistics_to_console(&self, target: &SocketAddr, statistics: &PingTargetStatistics) {
+        println!("\n=== Connect statistics for {} {:?} ===", self.protocol.as_ref().unwrap(), target);
+
+        let mut warning: String = String::from("");
+        if statistics.warning_count() > 0 {
+            let mut warning_messages = Vec::new();
+            if statistics.handshake_failed_count > 0 {
+                warning_messages.push(format!("App Handshake Failed = {}", statistics.handshake_failed_count));
+            }
+            if statistics.disconnect_failed_count > 0 {
+                warning_messages.push(format!("Disconnect Failed = {}", statistics.disconnect_failed_count));
+            }
+            if statistics.reconnected_count > 0 {
+                warning_messages.push(format!("Reconnected = {}", statistics.reconnected_count));
+            }
+            warning = format!(" ({})", warning_messages.join(", "));
+        }
+
+        println!(
+            "- Connects: Sent = {}, Succeeded = {}{}, Failed = {} ({:.2}%).",
+            statistics.ping_count,
+            statistics.success_count,
+            warning,
+            statistics.failure_count,
+            statistics.failure_rate_in_percent(),
+        );
+
+        if !statistics.failure_class_counts.is_empty() {
+            println!("- Failures by class: {}.", statistics.failure_class_summary());
+        }
+
+        if statistics.min_latency_in_us == u128::MAX {
+            println!("- Round trip time: Minimum = 0ms, Maximum = 0ms, Average = 0ms.");
+        } else {
+            let (min_latency_in_ms, max_latency_in_ms, average_latency_in_ms) = statistics.latency_summary_in_ms();
+            println!(
+                "- Round trip time: Minimum = {:.2}ms, Maximum = {:.2}ms, Average = {:.2}ms.",
+                min_latency_in_ms, max_latency_in_ms, average_latency_in_ms
+            );
+        }
+    }
+
+    fn output_target_summary_table_to_console(&self) {
+        println!("\n=== Connect statistics for {} on {} targets ===", self.protocol.as_ref().unwrap(), self.target_statistics.len());
+        println!(
+            "{:<47} | {:>8} | {:>9} | {:>8} | {:>8} | {:>9} | {:>9} | {:>9} | {:>9}",
+            "Target", "Sent", "Succeeded", "Failed", "Fail %", "Warnings", "Min (ms)", "Max (ms)", "Avg (ms)"
+        );
+        println!("{:-<48}+{:-<10}+{:-<11}+{:-<10}+{:-<10}+{:-<11}+{:-<11}+{:-<11}+{:-<10}", "", "", "", "", "", "", "", "", "");
+
+        for (target, statistics) in &self.target_statistics {
+            let (min_latency_in_ms, max_latency_in_ms, average_latency_in_ms) = statistics.latency_summary_in_ms();
+            println!(
+                "{:<47} | {:>8} | {:>9} | {:>8} | {:>7.2}% | {:>9} | {:>9.2} | {:>9.2} | {:>9.2}",
+                target.to_string(),
+                statistics.ping_count,
+                statistics.success_count,
+                statistics.failure_count,
+                statistics.failure_rate_in_percent(),
+                statistics.warning_count(),
+                min_latency_in_ms,
+                max_latency_in_ms,
+                average_latency_in_ms,
+            );
+        }
+
+        let targets_with_failures: Vec<(&SocketAddr, &PingTargetStatistics)> =
+            self.target_statistics.iter().filter(|(_, statistics)| !statistics.failure_class_counts.is_empty()).collect();
+        if !targets_with_failures.is_empty() {
+            println!("\n=== Failures by class ===");
+            for (target, statistics) in targets_with_failures {
+                println!("{:<47} | {}", target.to_string(), statistics.failure_class_summary());
+            }
+        }
+    }
 }
 
 impl PingResultProcessor for PingResultProcessorConsoleLogger {
@@ -160,7 +306,9 @@ impl PingResultProcessor for PingResultProcessorConsoleLogger {
     }
 
     fn rundown(&mut self) {
-        if self.config().quiet_level == RNP_QUIET_LEVEL_NO_PING_RESULT || self.config().quiet_level == RNP_QUIET_LEVEL_NO_PING_SUMMARY {
+        if self.show_live_output
+            && (self.config().quiet_level == RNP_QUIET_LEVEL_NO_PING_RESULT || self.config().quiet_level == RNP_QUIET_LEVEL_NO_PING_SUMMARY)
+        {
             self.output_ping_count_update_to_console(true);
             println!();
         }
@@ -178,44 +326,91 @@ impl PingResultProcessor for PingResultProcessorConsoleLogger {
         }
 
         // Didn't received any result, skip output statistics.
-        if self.target.is_none() {
+        if self.target_statistics.is_empty() {
             return;
         }
 
-        println!("\n=== Connect statistics for {} {:?} ===", self.protocol.as_ref().unwrap(), self.target.as_ref().unwrap(),);
-
-        let mut warning: String = String::from("");
-        if self.handshake_failed_count > 0 || self.disconnect_failed_count > 0 {
-            let mut warning_messages = Vec::new();
-            if self.handshake_failed_count > 0 {
-                warning_messages.push(format!("App Handshake Failed = {}", self.handshake_failed_count));
-            }
-            if self.disconnect_failed_count > 0 {
-                warning_messages.push(format!("Disconnect Failed = {}", self.disconnect_failed_count));
-            }
-            warning = format!(" ({})", warning_messages.join(", "));
+        if self.target_statistics.len() == 1 {
+            let (target, statistics) = self.target_statistics.iter().next().unwrap();
+            self.output_target_statistics_to_console(target, statistics);
+        } else {
+            self.output_target_summary_table_to_console();
         }
+    }
+}
 
-        println!(
-            "- Connects: Sent = {}, Succeeded = {}{}, Failed = {} ({:.2}%).",
-            self.ping_count,
-            self.success_count,
-            warning,
-            self.failure_count,
-            (self.failure_count as f64 * 100.0) / (self.ping_count as f64),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rnp_test_common;
+    use chrono::Utc;
+    use std::io;
+    use std::time::Duration;
+
+    #[test]
+    fn console_logger_should_count_failure_class_of_preparation_errors() {
+        let mut logger = PingResultProcessorConsoleLogger::new(
+            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_PING_RESULT }),
+            Arc::new(ManualResetEvent::new(false)),
+            false,
+            None,
+            true,
         );
 
-        // If we haven't received any data, the min/max/average data won't be updated correctly,
-        // os we output the data differently.
-        if self.min_latency_in_us == u128::MAX {
-            println!("- Round trip time: Minimum = 0ms, Maximum = 0ms, Average = 0ms.");
-        } else {
-            println!(
-                "- Round trip time: Minimum = {:.2}ms, Maximum = {:.2}ms, Average = {:.2}ms.",
-                self.min_latency_in_us as f64 / 1000.0,
-                self.max_latency_in_us as f64 / 1000.0,
-                self.average_latency_in_us / 1000.0
-            );
-        }
+        let preparation_error_result = rnp_test_common::generate_ping_result_test_samples()
+            .into_iter()
+            .find(|r| r.is_preparation_error())
+            .expect("Preparation error is expected in test samples.");
+        assert_eq!(Some(PingFailureClass::LocalPortInUse), preparation_error_result.failure_class());
+
+        logger.process_ping_result(&preparation_error_result);
+        logger.process_ping_result(&preparation_error_result);
+        logger.rundown();
+
+        // Preparation errors are not counted as pings, but they are still counted in the failure classes.
+        let statistics = &logger.target_statistics[&preparation_error_result.target()];
+        assert_eq!(0, statistics.ping_count);
+        assert_eq!(Some(&2), statistics.failure_class_counts.get(&PingFailureClass::LocalPortInUse));
+        assert_eq!("LocalPortInUse = 2", statistics.failure_class_summary());
+    }
+
+    #[test]
+    fn console_logger_should_count_reconnects_without_counting_their_rtt_as_latency() {
+        let mut logger = PingResultProcessorConsoleLogger::new(
+            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_PING_RESULT }),
+            Arc::new(ManualResetEvent::new(false)),
+            false,
+            None,
+            true,
+        );
+
+        let create_ping_result = |rtt_in_ms: u64, warning: Option<PingClientWarning>| {
+            PingResult::new(
+                &Utc::now(),
+                1,
+                "TCP",
+                "1.2.3.4:443".parse().unwrap(),
+                "5.6.7.8:8080".parse().unwrap(),
+                false,
+                true,
+                Duration::from_millis(rtt_in_ms),
+                false,
+                warning,
+                None,
+            )
+        };
+        let reconnect_warning =
+            PingClientWarning::Reconnected(Box::new(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection is closed by remote side.")));
+
+        logger.process_ping_result(&create_ping_result(10, None));
+        logger.process_ping_result(&create_ping_result(500, Some(reconnect_warning)));
+        logger.process_ping_result(&create_ping_result(20, None));
+        logger.rundown();
+
+        let statistics = &logger.target_statistics[&"1.2.3.4:443".parse().unwrap()];
+        assert_eq!(2, statistics.ping_count);
+        assert_eq!(2, statistics.success_count);
+        assert_eq!(1, statistics.reconnected_count);
+        assert_eq!(20000, statistics.max_latency_in_us);
     }
 }
@@ -7,20 +7,35 @@ pub struct PingResultProcessorCsvLogger {
     common_config: Arc<PingResultProcessorCommonConfig>,
     log_path: PathBuf,
     log_file: File,
+
+    // Failing to write logs shouldn't stop the pings, so only the first failure is reported to avoid flooding the output.
+    has_write_failed: bool,
 }
 
 impl PingResultProcessorCsvLogger {
     #[tracing::instrument(name = "Creating ping result csv logger", level = "debug")]
-    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, log_path_buf: &PathBuf) -> PingResultProcessorCsvLogger {
-        return PingResultProcessorCsvLogger { common_config, log_path: log_path_buf.clone(), log_file: rnp_utils::create_log_file(log_path_buf) };
+    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, log_path_buf: &PathBuf) -> Result<PingResultProcessorCsvLogger, RnpError> {
+        return Ok(PingResultProcessorCsvLogger {
+            common_config,
+            log_path: log_path_buf.clone(),
+            log_file: rnp_utils::create_log_file(log_path_buf)?,
+            has_write_failed: false,
+        });
     }
 
     fn log_result_as_csv(&mut self, ping_result: &PingResult) -> io::Result<()> {
         let log_content = ping_result.format_as_csv_string();
-        self.log_file.write(log_content.as_bytes())?;
-        self.log_file.write("\n".as_bytes())?;
+        self.log_file.write_all(log_content.as_bytes())?;
+        self.log_file.write_all("\n".as_bytes())?;
         return Ok(());
     }
+
+    fn report_write_failure(&mut self, e: io::Error) {
+        if !self.has_write_failed {
+            self.has_write_failed = true;
+            tracing::error!("Failed to write logs to csv file! Path = {}, Error = {}", self.log_path.display(), e);
+        }
+    }
 }
 
 impl PingResultProcessor for PingResultProcessorCsvLogger {
@@ -33,16 +48,17 @@ impl PingResultProcessor for PingResultProcessorCsvLogger {
 
     fn initialize(&mut self) {
         // Writer CSV header
-        self.log_file
-            .write("UtcTime,WorkerId,Protocol,TargetIp,TargetPort,SourceIp,SourcePort,IsWarmup,IsSucceeded,RttInMs,IsTimedOut,PreparationError,PingError,HandshakeError,DisconnectError\n".as_bytes())
-            .expect(&format!(
-                "Failed to write logs to csv file! Path = {}",
-                self.log_path.display()
-            ));
+        if let Err(e) = self.log_file
+            .write_all("UtcTime,WorkerId,Protocol,TargetIp,TargetPort,SourceIp,SourcePort,IsWarmup,IsSucceeded,RttInMs,IsTimedOut,PreparationError,PingError,HandshakeError,DisconnectError,DnsResolveTimeInMs,HandshakeTimeInMs,FirstByteTimeInMs,ResponseTimeInMs,FailureClass,ForwardDelayInMs,ReverseDelayInMs,ClockOffsetInMs,ReconnectReason\n".as_bytes())
+        {
+            self.report_write_failure(e);
+        }
     }
 
     fn process_ping_result(&mut self, ping_result: &PingResult) {
-        self.log_result_as_csv(ping_result).expect(&format!("Failed to write logs to csv file! Path = {}", self.log_path.display()));
+        if let Err(e) = self.log_result_as_csv(ping_result) {
+            self.report_write_failure(e);
+        }
     }
 }
 
@@ -50,6 +66,7 @@ impl PingResultProcessor for PingResultProcessorCsvLogger {
 mod tests {
     use super::*;
     use crate::ping_result_processors::ping_result_processor_test_common;
+    use crate::rnp_test_common;
     use crate::PingResultDto;
     use chrono::{TimeZone, Utc};
     use pretty_assertions::assert_eq;
@@ -57,10 +74,13 @@ mod tests {
     #[test]
     fn ping_result_process_csv_logger_should_work() {
         let test_log_file_path = "tests_data/ping_result_processor_csv_logger_tests/test_log.csv";
-        let mut processor: Box<dyn PingResultProcessor + Send + Sync> = Box::new(PingResultProcessorCsvLogger::new(
-            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
-            &PathBuf::from(test_log_file_path),
-        ));
+        let mut processor: Box<dyn PingResultProcessor + Send + Sync> = Box::new(
+            PingResultProcessorCsvLogger::new(
+                Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+                &PathBuf::from(test_log_file_path),
+            )
+            .unwrap(),
+        );
         ping_result_processor_test_common::run_ping_result_processor_with_test_samples(&mut processor);
 
         let mut actual_logged_records = Vec::new();
@@ -90,6 +110,15 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 5f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: None,
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -107,6 +136,15 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: Some(PingFailureClass::Timeout),
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -124,6 +162,15 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "connect aborted".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: Some(PingFailureClass::Other),
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -141,6 +188,15 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "disconnect timeout".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: None,
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -158,6 +214,15 @@ mod tests {
                     ping_error: "connect failed".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: Some(PingFailureClass::Refused),
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -175,9 +240,39 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: Some(PingFailureClass::LocalPortInUse),
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
             ],
             actual_logged_records,
         );
     }
+
+    #[test]
+    fn ping_result_process_csv_logger_should_not_panic_when_log_write_failed() {
+        let test_log_file_path = PathBuf::from("tests_data/ping_result_processor_csv_logger_tests/read_only_log.csv");
+        let mut processor = PingResultProcessorCsvLogger::new(
+            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+            &test_log_file_path,
+        )
+        .unwrap();
+
+        // Writing to a file opened as read only fails.
+        processor.log_file = File::open(&test_log_file_path).unwrap();
+        processor.initialize();
+        for ping_result in &rnp_test_common::generate_ping_result_test_samples() {
+            processor.process_ping_result(ping_result);
+        }
+        processor.rundown();
+
+        assert!(processor.has_write_failed);
+        assert_eq!("", std::fs::read_to_string(&test_log_file_path).unwrap());
+    }
 }
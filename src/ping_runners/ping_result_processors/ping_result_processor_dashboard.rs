@@ -0,0 +1,473 @@
+use crate::ping_result_processors::ping_result_processor_result_scatter_logger::{
+    PingResultProcessorResultScatterLogger, COUNT_PER_ROW, SCATTER_SYMBOL_NOT_TESTED_YET,
+};
+use crate::*;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{stdout, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing;
+
+const RECENT_PING_COUNT: usize = 100;
+const SPARKLINE_WIDTH: usize = 60;
+const SPARKLINE_SYMBOLS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SPARKLINE_SYMBOL_FAILED: char = 'X';
+const RECENT_ERROR_COUNT: usize = 5;
+const SCATTER_ROW_LIMIT: usize = 10;
+
+const TERMINAL_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+const FALLBACK_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+// The dashboard is redrawn in place with ANSI escape codes: move the cursor back to the top left, overwrite each line and clear
+// whatever is left from the last frame.
+const ANSI_CLEAR_SCREEN: &str = "\x1b[2J";
+const ANSI_CURSOR_HOME: &str = "\x1b[H";
+const ANSI_CLEAR_TO_LINE_END: &str = "\x1b[K";
+const ANSI_CLEAR_TO_SCREEN_END: &str = "\x1b[J";
+
+pub struct PingResultProcessorDashboard {
+    common_config: Arc<PingResultProcessorCommonConfig>,
+    latency_percentiles: Vec<f64>,
+    is_terminal: bool,
+
+    start_time: Instant,
+    last_refresh_time: Option<Instant>,
+
+    protocol: Option<String>,
+    ping_count: u32,
+    target_states: BTreeMap<SocketAddr, DashboardTargetState>,
+    recent_errors: VecDeque<String>,
+}
+
+struct DashboardTargetState {
+    ping_count: u32,
+    success_count: u32,
+
+    // Latest pings counted in statistics, as (is succeeded, round trip time). Round trip time is not set when the target is not reached.
+    recent_pings: VecDeque<(bool, Option<u128>)>,
+
+    // Latest result of each source port, in the same rows as the result scatter map.
+    latest_port_results: BTreeMap<u32, Vec<char>>,
+}
+
+impl DashboardTargetState {
+    fn new() -> DashboardTargetState {
+        return DashboardTargetState {
+            ping_count: 0,
+            success_count: 0,
+            recent_pings: VecDeque::with_capacity(RECENT_PING_COUNT + 1),
+            latest_port_results: BTreeMap::new(),
+        };
+    }
+
+    fn update(&mut self, ping_result: &PingResult) {
+        let (row, index) = PingResultProcessorResultScatterLogger::get_ping_history_position(ping_result.source().port() as u32);
+        let row_results = self.latest_port_results.entry(row).or_insert_with(|| vec![SCATTER_SYMBOL_NOT_TESTED_YET; COUNT_PER_ROW as usize]);
+        row_results[index] = PingResultProcessorResultScatterLogger::result_symbol(ping_result);
+
+        if !ping_result.is_counted_in_statistics() {
+            return;
+        }
+
+        self.ping_count += 1;
+        if ping_result.is_succeeded() {
+            self.success_count += 1;
+        }
+
+        let is_target_reached = !ping_result.is_timed_out() && ping_result.error().is_none();
+        let round_trip_time_in_us = if is_target_reached { Some(ping_result.round_trip_time().as_micros()) } else { None };
+        self.recent_pings.push_back((ping_result.is_succeeded(), round_trip_time_in_us));
+        if self.recent_pings.len() > RECENT_PING_COUNT {
+            self.recent_pings.pop_front();
+        }
+    }
+
+    fn success_rate_in_percent(&self) -> f64 {
+        if self.ping_count == 0 {
+            return 0.0;
+        }
+
+        return (self.success_count as f64 * 100.0) / (self.ping_count as f64);
+    }
+
+    fn recent_success_rate_in_percent(&self) -> f64 {
+        if self.recent_pings.is_empty() {
+            return 0.0;
+        }
+
+        let recent_success_count = self.recent_pings.iter().filter(|(is_succeeded, _)| *is_succeeded).count();
+        return (recent_success_count as f64 * 100.0) / (self.recent_pings.len() as f64);
+    }
+
+    // Percentiles are calculated with nearest rank on the recent pings only, so they show how the latency looks like now.
+    fn recent_latency_at_percentile_in_ms(&self, percentile: f64) -> Option<f64> {
+        let mut latencies: Vec<u128> = self.recent_pings.iter().filter_map(|(_, latency)| *latency).collect();
+        if latencies.is_empty() {
+            return None;
+        }
+
+        latencies.sort_unstable();
+        let rank = ((percentile / 100.0) * latencies.len() as f64).ceil() as usize;
+        let index = rank.clamp(1, latencies.len()) - 1;
+        return Some(latencies[index] as f64 / 1000.0);
+    }
+
+    fn latency_sparkline(&self) -> String {
+        let skipped_count = self.recent_pings.len().saturating_sub(SPARKLINE_WIDTH);
+        let shown_pings: Vec<Option<u128>> = self.recent_pings.iter().skip(skipped_count).map(|(_, latency)| *latency).collect();
+
+        let min_latency = shown_pings.iter().filter_map(|x| *x).min().unwrap_or(0);
+        let max_latency = shown_pings.iter().filter_map(|x| *x).max().unwrap_or(0);
+        let max_symbol_index = (SPARKLINE_SYMBOLS.len() - 1) as u128;
+
+        return shown_pings
+            .iter()
+            .map(|latency| match latency {
+                None => SPARKLINE_SYMBOL_FAILED,
+                Some(_) if max_latency == min_latency => SPARKLINE_SYMBOLS[0],
+                Some(latency) => SPARKLINE_SYMBOLS[((latency - min_latency) * max_symbol_index / (max_latency - min_latency)) as usize],
+            })
+            .collect();
+    }
+}
+
+impl PingResultProcessorDashboard {
+    #[tracing::instrument(name = "Creating ping result dashboard", level = "debug")]
+    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, latency_percentiles: &[f64], is_terminal: bool) -> PingResultProcessorDashboard {
+        return PingResultProcessorDashboard {
+            common_config,
+            latency_percentiles: latency_percentiles.to_vec(),
+            is_terminal,
+            start_time: Instant::now(),
+            last_refresh_time: None,
+            protocol: None,
+            ping_count: 0,
+            target_states: BTreeMap::new(),
+            recent_errors: VecDeque::with_capacity(RECENT_ERROR_COUNT + 1),
+        };
+    }
+
+    fn update(&mut self, ping_result: &PingResult) {
+        if self.protocol.is_none() {
+            self.protocol = Some(ping_result.protocol().to_string());
+        }
+
+        if ping_result.is_counted_in_statistics() {
+            self.ping_count += 1;
+        }
+
+        self.target_states.entry(ping_result.target()).or_insert_with(DashboardTargetState::new).update(ping_result);
+
+        if !ping_result.is_succeeded() || ping_result.warning().is_some() {
+            self.recent_errors.push_back(ping_result.format_as_console_log());
+            if self.recent_errors.len() > RECENT_ERROR_COUNT {
+                self.recent_errors.pop_front();
+            }
+        }
+    }
+
+    fn refresh(&mut self, force: bool) {
+        let now = Instant::now();
+        let refresh_interval = if self.is_terminal { TERMINAL_REFRESH_INTERVAL } else { FALLBACK_REFRESH_INTERVAL };
+        if let Some(last_refresh_time) = self.last_refresh_time {
+            if !force && now - last_refresh_time < refresh_interval {
+                return;
+            }
+        }
+        self.last_refresh_time = Some(now);
+
+        let mut output = String::new();
+        if self.is_terminal {
+            output.push_str(ANSI_CURSOR_HOME);
+            for line in self.render_dashboard() {
+                output.push_str(&line);
+                output.push_str(ANSI_CLEAR_TO_LINE_END);
+                output.push('\n');
+            }
+            output.push_str(ANSI_CLEAR_TO_SCREEN_END);
+        } else {
+            // Redrawing doesn't work when stdout is redirected, so we only write a few plain status lines, which also reads well in files.
+            for line in self.render_status_lines() {
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+
+        write_to_stdout(&output);
+    }
+
+    fn elapsed_time(&self) -> String {
+        return humantime::format_duration(Duration::from_secs(self.start_time.elapsed().as_secs())).to_string();
+    }
+
+    fn format_percentiles(&self, state: &DashboardTargetState) -> String {
+        return self
+            .latency_percentiles
+            .iter()
+            .map(|p| match state.recent_latency_at_percentile_in_ms(*p) {
+                Some(latency) => format!("P{} = {:.2}ms", p, latency),
+                None => format!("P{} = N/A", p),
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+    }
+
+    fn render_status_lines(&self) -> Vec<String> {
+        return self
+            .target_states
+            .iter()
+            .map(|(target, state)| {
+                format!(
+                    "[{}] {}: Sent = {}, Succeeded = {:.2}%, Recent succeeded = {:.2}%, Recent latency: {}.",
+                    self.elapsed_time(),
+                    target,
+                    state.ping_count,
+                    state.success_rate_in_percent(),
+                    state.recent_success_rate_in_percent(),
+                    self.format_percentiles(state)
+                )
+            })
+            .collect();
+    }
+
+    fn render_dashboard(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        lines.push(format!(
+            "=== Live dashboard for {} - {} pings in {} (Press Ctrl+C to stop) ===",
+            self.protocol.as_deref().unwrap_or(""),
+            self.ping_count,
+            self.elapsed_time()
+        ));
+
+        self.render_statistics_table(&mut lines);
+        self.render_sparklines(&mut lines);
+        self.render_result_scatter_map(&mut lines);
+
+        lines.push(String::new());
+        lines.push(String::from("=== Recent errors ==="));
+        if self.recent_errors.is_empty() {
+            lines.push(String::from("None."));
+        }
+        lines.extend(self.recent_errors.iter().cloned());
+
+        return lines;
+    }
+
+    fn render_statistics_table(&self, lines: &mut Vec<String>) {
+        let mut header = format!("{:<47} | {:>8} | {:>9} | {:>9}", "Target", "Sent", "Succeeded", "Recent");
+        let mut separator = format!("{:-<48}+{:-<10}+{:-<11}+{:-<11}", "", "", "", "");
+        for percentile in &self.latency_percentiles {
+            header.push_str(&format!(" | {:>11}", format!("P{} (ms)", percentile)));
+            separator.push_str(&format!("+{:-<13}", ""));
+        }
+
+        lines.push(String::new());
+        lines.push(header);
+        lines.push(separator);
+
+        for (target, state) in &self.target_states {
+            let mut line = format!(
+                "{:<47} | {:>8} | {:>8.2}% | {:>8.2}%",
+                target.to_string(),
+                state.ping_count,
+                state.success_rate_in_percent(),
+                state.recent_success_rate_in_percent()
+            );
+            for percentile in &self.latency_percentiles {
+                match state.recent_latency_at_percentile_in_ms(*percentile) {
+                    Some(latency) => line.push_str(&format!(" | {:>11.2}", latency)),
+                    None => line.push_str(&format!(" | {:>11}", "N/A")),
+                }
+            }
+            lines.push(line);
+        }
+    }
+
+    fn render_sparklines(&self, lines: &mut Vec<String>) {
+        lines.push(String::new());
+        lines.push(format!("=== Recent latency (last {} pings, \"{}\" = Not reached) ===", SPARKLINE_WIDTH, SPARKLINE_SYMBOL_FAILED));
+        for (target, state) in &self.target_states {
+            lines.push(format!("{:<47} | {}", target.to_string(), state.latency_sparkline()));
+        }
+    }
+
+    fn render_result_scatter_map(&self, lines: &mut Vec<String>) {
+        lines.push(String::new());
+        lines.push(String::from("=== Ping result scatter map (latest result of each source port) ==="));
+
+        let show_target = self.target_states.len() > 1;
+        for (target, state) in &self.target_states {
+            if show_target {
+                lines.push(format!("--- Target: {} ---", target));
+            }
+
+            lines.push(format!("{:>5} | {}", "Src", "Results"));
+            lines.push(format!("{:>5} | ", "Port"));
+            lines.push(format!("{:->7}-0---4-5---9-0---4-5---9-", "+"));
+
+            // Showing all source ports could easily take more than a screen, so only the latest rows are shown.
+            let skipped_row_count = state.latest_port_results.len().saturating_sub(SCATTER_ROW_LIMIT);
+            if skipped_row_count > 0 {
+                lines.push(format!("{:>5} | ({} more rows)", "...", skipped_row_count));
+            }
+            for (port_bucket, result_hits) in state.latest_port_results.iter().skip(skipped_row_count) {
+                lines.push(format!("{:>5} | {}", port_bucket, PingResultProcessorResultScatterLogger::convert_result_hits_to_string(result_hits)));
+            }
+        }
+    }
+}
+
+// Stdout can be closed while pinging, e.g. piped into head, which shouldn't stop pinging, so the write failures are only logged.
+fn write_to_stdout(output: &str) {
+    let mut stdout = stdout();
+    if let Err(e) = stdout.write_all(output.as_bytes()).and_then(|_| stdout.flush()) {
+        tracing::debug!("Failed to write dashboard to stdout: Error = {}", e);
+    }
+}
+
+impl PingResultProcessor for PingResultProcessorDashboard {
+    fn name(&self) -> &'static str {
+        "Dashboard"
+    }
+    fn config(&self) -> &PingResultProcessorCommonConfig {
+        self.common_config.as_ref()
+    }
+
+    fn initialize(&mut self) {
+        self.start_time = Instant::now();
+
+        if self.is_terminal && !self.has_quiet_level(RNP_QUIET_LEVEL_NO_OUTPUT) {
+            write_to_stdout(&format!("{}{}", ANSI_CLEAR_SCREEN, ANSI_CURSOR_HOME));
+        }
+    }
+
+    fn process_ping_result(&mut self, ping_result: &PingResult) {
+        if self.has_quiet_level(RNP_QUIET_LEVEL_NO_OUTPUT) {
+            return;
+        }
+
+        // Skip warmup pings in analysis.
+        if ping_result.is_warmup() {
+            return;
+        }
+
+        self.update(ping_result);
+        self.refresh(false);
+    }
+
+    // The elapsed time and the results keep being redrawn, when no result arrives for a while, e.g. all targets are timing out.
+    fn on_tick(&mut self) {
+        if self.has_quiet_level(RNP_QUIET_LEVEL_NO_OUTPUT) || self.protocol.is_none() {
+            return;
+        }
+
+        self.refresh(false);
+    }
+
+    fn rundown(&mut self) {
+        if self.has_quiet_level(RNP_QUIET_LEVEL_NO_OUTPUT) {
+            return;
+        }
+
+        // Draw the last frame, so the dashboard matches the summaries printed after it.
+        if self.is_terminal {
+            self.refresh(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ping_result_processors::ping_result_processor_test_common;
+    use pretty_assertions::assert_eq;
+
+    fn create_dashboard_with_test_samples() -> PingResultProcessorDashboard {
+        let mut dashboard =
+            PingResultProcessorDashboard::new(Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NONE }), &[50.0, 99.0], false);
+        for ping_result in &rnp_test_common::generate_ping_result_test_samples() {
+            if !ping_result.is_warmup() {
+                dashboard.update(ping_result);
+            }
+        }
+
+        return dashboard;
+    }
+
+    #[test]
+    fn ping_result_process_dashboard_should_work() {
+        let mut processor: Box<dyn PingResultProcessor + Send + Sync> = Box::new(PingResultProcessorDashboard::new(
+            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NONE }),
+            &[50.0, 90.0],
+            false,
+        ));
+        ping_result_processor_test_common::run_ping_result_processor_with_test_samples(&mut processor);
+    }
+
+    #[test]
+    fn updating_dashboard_should_work() {
+        let dashboard = create_dashboard_with_test_samples();
+        assert_eq!(5, dashboard.recent_errors.len());
+
+        let state = &dashboard.target_states[&"1.2.3.4:443".parse().unwrap()];
+        assert_eq!(4, state.ping_count);
+        assert_eq!(2, state.success_count);
+        assert_eq!(50.0, state.recent_success_rate_in_percent());
+        assert_eq!(Some(20.0), state.recent_latency_at_percentile_in_ms(50.0));
+        assert_eq!("X▁▁X", state.latency_sparkline());
+        assert_eq!(
+            vec![(8080, "-.... ..... ..... .....".to_string())],
+            state
+                .latest_port_results
+                .iter()
+                .map(|(row, hits)| (*row, PingResultProcessorResultScatterLogger::convert_result_hits_to_string(hits)))
+                .collect::<Vec<(u32, String)>>()
+        );
+    }
+
+    #[test]
+    fn dashboard_should_refresh_on_tick_after_first_result() {
+        let mut dashboard =
+            PingResultProcessorDashboard::new(Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NONE }), &[50.0], false);
+        dashboard.on_tick();
+        assert_eq!(None, dashboard.last_refresh_time);
+
+        let mut dashboard = create_dashboard_with_test_samples();
+        dashboard.on_tick();
+        assert!(dashboard.last_refresh_time.is_some());
+
+        let mut dashboard =
+            PingResultProcessorDashboard::new(Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }), &[50.0], false);
+        dashboard.process_ping_result(&rnp_test_common::generate_ping_result_test_samples()[0]);
+        dashboard.on_tick();
+        assert_eq!(None, dashboard.last_refresh_time);
+    }
+
+    #[test]
+    fn latency_sparkline_should_scale_between_min_and_max() {
+        let mut state = DashboardTargetState::new();
+        for latency in &[Some(1000), Some(8000), None, Some(4500)] {
+            state.recent_pings.push_back((latency.is_some(), *latency));
+        }
+
+        assert_eq!("▁█X▄", state.latency_sparkline());
+        assert_eq!(Some(4.5), state.recent_latency_at_percentile_in_ms(50.0));
+        assert_eq!(Some(8.0), state.recent_latency_at_percentile_in_ms(99.0));
+    }
+
+    #[test]
+    fn rendering_dashboard_should_work() {
+        let dashboard = create_dashboard_with_test_samples();
+
+        let lines = dashboard.render_dashboard();
+        assert!(lines[0].starts_with("=== Live dashboard for TCP - 4 pings in "));
+        assert!(lines.contains(&format!("{:<47} | {:>8} | {:>8.2}% | {:>8.2}% | {:>11.2} | {:>11.2}", "1.2.3.4:443", 4, 50.0, 50.0, 20.0, 20.0)));
+        assert!(lines.contains(&format!("{:<47} | X▁▁X", "1.2.3.4:443")));
+
+        let status_lines = dashboard.render_status_lines();
+        assert_eq!(1, status_lines.len());
+        assert!(status_lines[0]
+            .ends_with("1.2.3.4:443: Sent = 4, Succeeded = 50.00%, Recent succeeded = 50.00%, Recent latency: P50 = 20.00ms, P99 = 20.00ms."));
+    }
+}
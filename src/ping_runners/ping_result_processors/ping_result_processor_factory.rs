@@ -1,48 +1,77 @@
 use crate::ping_result_processors::ping_result_processor_console_logger::PingResultProcessorConsoleLogger;
 use crate::ping_result_processors::ping_result_processor_csv_logger::PingResultProcessorCsvLogger;
+use crate::ping_result_processors::ping_result_processor_dashboard::PingResultProcessorDashboard;
+use crate::ping_result_processors::ping_result_processor_html_report::PingResultProcessorHtmlReport;
 use crate::ping_result_processors::ping_result_processor_json_logger::PingResultProcessorJsonLogger;
 use crate::ping_result_processors::ping_result_processor_latency_bucket_logger::PingResultProcessorLatencyBucketLogger;
+use crate::ping_result_processors::ping_result_processor_latency_histogram_logger::PingResultProcessorLatencyHistogramLogger;
 use crate::ping_result_processors::ping_result_processor_latency_scatter_logger::PingResultProcessorLatencyScatterLogger;
+use crate::ping_result_processors::ping_result_processor_prometheus_exporter::PingResultProcessorPrometheusExporter;
 use crate::ping_result_processors::ping_result_processor_result_scatter_logger::PingResultProcessorResultScatterLogger;
 use crate::ping_result_processors::ping_result_processor_text_logger::PingResultProcessorTextLogger;
-use crate::{PingResultProcessor, PingResultProcessorConfig};
+use crate::{PingResultProcessor, PingResultProcessorConfig, RnpError};
 use futures_intrusive::sync::ManualResetEvent;
+use std::io::{stdout, IsTerminal};
 use std::sync::Arc;
 
 pub fn new(
     config: &PingResultProcessorConfig,
     mut extra_ping_result_processors: Vec<Box<dyn PingResultProcessor + Send + Sync>>,
     ping_stop_event: Arc<ManualResetEvent>,
-) -> Vec<Box<dyn PingResultProcessor + Send + Sync>> {
+) -> Result<Vec<Box<dyn PingResultProcessor + Send + Sync>>, RnpError> {
     let common_config = Arc::new(config.common_config.clone());
     let mut processors = Vec::new();
 
+    // The dashboard redraws the whole screen on terminals, so it takes over the live output from the console logger there. It is also
+    // created first, so its last frame is drawn before all summaries in rundown.
+    let is_dashboard_on_terminal = config.show_dashboard && stdout().is_terminal();
+    if config.show_dashboard {
+        let latency_percentiles = config.latency_percentiles.as_deref().unwrap_or(&[50.0, 90.0, 99.0]);
+        let dashboard: Box<dyn PingResultProcessor + Send + Sync> =
+            Box::new(PingResultProcessorDashboard::new(common_config.clone(), latency_percentiles, is_dashboard_on_terminal));
+        processors.push(dashboard);
+    }
+
     // We always create the console logger for keeping our user informed.
     let console_logger: Box<dyn PingResultProcessor + Send + Sync> = Box::new(PingResultProcessorConsoleLogger::new(
         common_config.clone(),
         ping_stop_event.clone(),
         config.exit_on_fail,
         config.exit_failure_reason.clone(),
+        !is_dashboard_on_terminal,
     ));
     processors.push(console_logger);
 
     if let Some(csv_log_path) = &config.csv_log_path {
-        let csv_logger: Box<dyn PingResultProcessor + Send + Sync> = Box::new(PingResultProcessorCsvLogger::new(common_config.clone(), csv_log_path));
+        let csv_logger: Box<dyn PingResultProcessor + Send + Sync> =
+            Box::new(PingResultProcessorCsvLogger::new(common_config.clone(), csv_log_path)?);
         processors.push(csv_logger);
     }
 
     if let Some(json_log_path) = &config.json_log_path {
         let json_logger: Box<dyn PingResultProcessor + Send + Sync> =
-            Box::new(PingResultProcessorJsonLogger::new(common_config.clone(), json_log_path));
+            Box::new(PingResultProcessorJsonLogger::new(common_config.clone(), json_log_path)?);
         processors.push(json_logger);
     }
 
     if let Some(text_log_path) = &config.text_log_path {
         let text_logger: Box<dyn PingResultProcessor + Send + Sync> =
-            Box::new(PingResultProcessorTextLogger::new(common_config.clone(), text_log_path));
+            Box::new(PingResultProcessorTextLogger::new(common_config.clone(), text_log_path)?);
         processors.push(text_logger);
     }
 
+    if let Some(html_report_path) = &config.html_report_path {
+        let html_report: Box<dyn PingResultProcessor + Send + Sync> =
+            Box::new(PingResultProcessorHtmlReport::new(common_config.clone(), html_report_path, &config.latency_buckets)?);
+        processors.push(html_report);
+    }
+
+    if let Some(metrics_address) = &config.metrics_address {
+        let prometheus_exporter: Box<dyn PingResultProcessor + Send + Sync> =
+            Box::new(PingResultProcessorPrometheusExporter::new(common_config.clone(), metrics_address)?);
+        processors.push(prometheus_exporter);
+    }
+
     if config.show_result_scatter {
         let result_scatter_logger: Box<dyn PingResultProcessor + Send + Sync> =
             Box::new(PingResultProcessorResultScatterLogger::new(common_config.clone()));
@@ -61,10 +90,16 @@ pub fn new(
         processors.push(latency_bucket_logger);
     }
 
+    if let Some(latency_percentiles) = &config.latency_percentiles {
+        let latency_histogram_logger: Box<dyn PingResultProcessor + Send + Sync> =
+            Box::new(PingResultProcessorLatencyHistogramLogger::new(common_config.clone(), latency_percentiles));
+        processors.push(latency_histogram_logger);
+    }
+
     // Move all extra ping result processors into the processors
     processors.append(&mut extra_ping_result_processors);
 
-    return processors;
+    return Ok(processors);
 }
 
 #[cfg(test)]
@@ -84,12 +119,16 @@ mod tests {
             csv_log_path: None,
             json_log_path: None,
             text_log_path: None,
+            html_report_path: None,
+            metrics_address: None,
             show_result_scatter: false,
             show_latency_scatter: false,
+            show_dashboard: false,
             latency_buckets: None,
+            latency_percentiles: None,
         };
 
-        let ping_clients = new(&config, vec![], Arc::new(ManualResetEvent::new(false)));
+        let ping_clients = new(&config, vec![], Arc::new(ManualResetEvent::new(false))).unwrap();
         assert_eq!(1, ping_clients.len());
     }
 
@@ -102,12 +141,16 @@ mod tests {
             csv_log_path: Some(PathBuf::from("tests_data/ping_result_factory_tests/log.csv")),
             json_log_path: Some(PathBuf::from("tests_data/ping_result_factory_tests/log.json")),
             text_log_path: Some(PathBuf::from("tests_data/ping_result_factory_tests/log.txt")),
+            html_report_path: Some(PathBuf::from("tests_data/ping_result_factory_tests/report.html")),
+            metrics_address: Some("127.0.0.1:0".parse().unwrap()),
             show_result_scatter: true,
             show_latency_scatter: true,
+            show_dashboard: true,
             latency_buckets: Some(vec![0.1, 0.5, 1.0, 10.0]),
+            latency_percentiles: Some(vec![50.0, 90.0, 99.0]),
         };
 
-        let ping_clients = new(&config, vec![], Arc::new(ManualResetEvent::new(false)));
-        assert_eq!(7, ping_clients.len());
+        let ping_clients = new(&config, vec![], Arc::new(ManualResetEvent::new(false))).unwrap();
+        assert_eq!(11, ping_clients.len());
     }
 }
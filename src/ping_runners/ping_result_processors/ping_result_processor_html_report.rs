@@ -0,0 +1,710 @@
+use crate::ping_result_processors::ping_result_processor_latency_bucket_logger::LatencyBuckets;
+use crate::ping_result_processors::ping_result_processor_result_scatter_logger::*;
+use crate::*;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::prelude::*;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing;
+
+const DEFAULT_LATENCY_BUCKETS: [f64; 10] = [0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 50.0, 100.0, 300.0, 500.0];
+
+const CHART_WIDTH: usize = 900;
+const CHART_HEIGHT: usize = 300;
+const CHART_MARGIN_LEFT: usize = 70;
+const CHART_MARGIN_RIGHT: usize = 20;
+const CHART_MARGIN_TOP: usize = 20;
+const CHART_MARGIN_BOTTOM: usize = 40;
+const HISTOGRAM_BIN_COUNT: usize = 40;
+const HISTOGRAM_FINE_BIN_COUNT: usize = HISTOGRAM_BIN_COUNT * 8;
+const RTT_TIME_BUCKET_COUNT_LIMIT: i64 = 2048;
+const SCATTER_CELL_SIZE: usize = 12;
+const SCATTER_ROW_LABEL_WIDTH: usize = 110;
+
+const TARGET_COLORS: [&str; 8] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#9467bd", "#8c564b", "#e377c2", "#17becf", "#bcbd22"];
+const FAILURE_COLOR: &str = "#d62728";
+
+// Writes all results into a single html file in rundown. Charts are drawn as inline svg, so the report can be opened anywhere
+// without any network access, e.g. when attached to a ticket.
+pub struct PingResultProcessorHtmlReport {
+    common_config: Arc<PingResultProcessorCommonConfig>,
+    report_path: PathBuf,
+    report_file: File,
+
+    protocol: Option<String>,
+    first_ping_time: Option<DateTime<Utc>>,
+    last_ping_time: Option<DateTime<Utc>>,
+    target_statistics: BTreeMap<SocketAddr, HtmlReportTargetStatistics>,
+    rtt_time_bucket_size_in_ms: i64,
+    rtt_histogram: RttHistogram,
+    latency_buckets: LatencyBuckets,
+    scatter_map: ResultScatterMap,
+}
+
+struct HtmlReportTargetStatistics {
+    ping_count: u32,
+    success_count: u32,
+    failure_class_counts: BTreeMap<PingFailureClass, u32>,
+
+    // Pings are aggregated into fixed time buckets as they arrive, keyed by the bucket index since the Unix epoch, so the memory
+    // doesn't grow with the ping count in long runs.
+    rtt_time_buckets: BTreeMap<i64, RttTimeBucket>,
+}
+
+impl HtmlReportTargetStatistics {
+    fn new() -> HtmlReportTargetStatistics {
+        return HtmlReportTargetStatistics {
+            ping_count: 0,
+            success_count: 0,
+            failure_class_counts: BTreeMap::new(),
+            rtt_time_buckets: BTreeMap::new(),
+        };
+    }
+
+    fn update(&mut self, ping_result: &PingResult, round_trip_time_in_us: Option<u128>, rtt_time_bucket_size_in_ms: i64) {
+        self.ping_count += 1;
+        if ping_result.is_succeeded() {
+            self.success_count += 1;
+        }
+
+        if let Some(failure_class) = ping_result.failure_class() {
+            *self.failure_class_counts.entry(failure_class).or_insert(0) += 1;
+        }
+
+        let bucket_index = ping_result.ping_time().timestamp_millis().div_euclid(rtt_time_bucket_size_in_ms);
+        self.rtt_time_buckets.entry(bucket_index).or_default().update(round_trip_time_in_us);
+    }
+
+    fn rtt_time_bucket_range(&self) -> i64 {
+        return match (self.rtt_time_buckets.keys().next(), self.rtt_time_buckets.keys().next_back()) {
+            (Some(first), Some(last)) => last - first,
+            _ => 0,
+        };
+    }
+
+    // Neighbouring buckets are merged when the bucket size is doubled.
+    fn merge_rtt_time_buckets(&mut self) {
+        let mut merged_buckets: BTreeMap<i64, RttTimeBucket> = BTreeMap::new();
+        for (bucket_index, bucket) in std::mem::take(&mut self.rtt_time_buckets) {
+            merged_buckets.entry(bucket_index.div_euclid(2)).or_default().merge(&bucket);
+        }
+        self.rtt_time_buckets = merged_buckets;
+    }
+
+    fn failure_rate_in_percent(&self) -> f64 {
+        if self.ping_count == 0 {
+            return 0.0;
+        }
+
+        return ((self.ping_count - self.success_count) as f64 * 100.0) / (self.ping_count as f64);
+    }
+}
+
+// Min and max latency of the pings in a time bucket, which is all the round trip time over time chart needs for drawing spikes.
+#[derive(Default)]
+struct RttTimeBucket {
+    min_latency_in_us: Option<u128>,
+    max_latency_in_us: Option<u128>,
+    has_failure: bool,
+}
+
+impl RttTimeBucket {
+    fn update(&mut self, round_trip_time_in_us: Option<u128>) {
+        match round_trip_time_in_us {
+            Some(latency) => {
+                self.min_latency_in_us = Some(self.min_latency_in_us.map_or(latency, |min| min.min(latency)));
+                self.max_latency_in_us = Some(self.max_latency_in_us.map_or(latency, |max| max.max(latency)));
+            }
+            None => self.has_failure = true,
+        }
+    }
+
+    fn merge(&mut self, other: &RttTimeBucket) {
+        if let Some(min_latency) = other.min_latency_in_us {
+            self.update(Some(min_latency));
+        }
+        if let Some(max_latency) = other.max_latency_in_us {
+            self.update(Some(max_latency));
+        }
+        self.has_failure |= other.has_failure;
+    }
+}
+
+// The histogram keeps more bins than the chart draws, and the bin size is doubled when a latency goes beyond the last bin, so the
+// bins can be regrouped to the max latency in rundown without keeping every latency.
+struct RttHistogram {
+    bin_size_in_us: u128,
+    bins: [u32; HISTOGRAM_FINE_BIN_COUNT],
+    max_latency_in_us: Option<u128>,
+}
+
+impl RttHistogram {
+    fn new() -> RttHistogram {
+        return RttHistogram { bin_size_in_us: 1, bins: [0; HISTOGRAM_FINE_BIN_COUNT], max_latency_in_us: None };
+    }
+
+    fn update(&mut self, latency_in_us: u128) {
+        while latency_in_us >= self.bin_size_in_us * HISTOGRAM_FINE_BIN_COUNT as u128 {
+            for bin_index in 0..HISTOGRAM_FINE_BIN_COUNT {
+                self.bins[bin_index] =
+                    if bin_index < HISTOGRAM_FINE_BIN_COUNT / 2 { self.bins[bin_index * 2] + self.bins[bin_index * 2 + 1] } else { 0 };
+            }
+            self.bin_size_in_us *= 2;
+        }
+
+        self.bins[(latency_in_us / self.bin_size_in_us) as usize] += 1;
+        self.max_latency_in_us = Some(self.max_latency_in_us.map_or(latency_in_us, |max| max.max(latency_in_us)));
+    }
+
+    // Each bin is put into the chart bin where it starts.
+    fn chart_bins(&self, max_latency_in_us: u128) -> [u32; HISTOGRAM_BIN_COUNT] {
+        let mut chart_bins = [0u32; HISTOGRAM_BIN_COUNT];
+        for (bin_index, bin_count) in self.bins.iter().enumerate().filter(|(_, count)| **count > 0) {
+            let bin_start_in_us = bin_index as u128 * self.bin_size_in_us;
+            let chart_bin_index = ((bin_start_in_us * HISTOGRAM_BIN_COUNT as u128) / (max_latency_in_us + 1)) as usize;
+            chart_bins[chart_bin_index.min(HISTOGRAM_BIN_COUNT - 1)] += bin_count;
+        }
+        return chart_bins;
+    }
+}
+
+impl PingResultProcessorHtmlReport {
+    #[tracing::instrument(name = "Creating ping result html report", level = "debug")]
+    pub fn new(
+        common_config: Arc<PingResultProcessorCommonConfig>,
+        report_path: &PathBuf,
+        latency_buckets: &Option<Vec<f64>>,
+    ) -> Result<PingResultProcessorHtmlReport, RnpError> {
+        // The file is created here instead of in rundown, so a bad path fails the run before any ping is sent.
+        return Ok(PingResultProcessorHtmlReport {
+            common_config,
+            report_path: report_path.clone(),
+            report_file: rnp_utils::create_log_file(report_path)?,
+            protocol: None,
+            first_ping_time: None,
+            last_ping_time: None,
+            target_statistics: BTreeMap::new(),
+            rtt_time_bucket_size_in_ms: 1,
+            rtt_histogram: RttHistogram::new(),
+            latency_buckets: LatencyBuckets::new(latency_buckets.as_deref().unwrap_or(&DEFAULT_LATENCY_BUCKETS)),
+            scatter_map: ResultScatterMap::new(),
+        });
+    }
+
+    fn update_statistics(&mut self, ping_result: &PingResult) {
+        self.scatter_map.update(ping_result);
+
+        if !ping_result.is_counted_in_statistics() {
+            return;
+        }
+
+        if self.protocol.is_none() {
+            self.protocol = Some(ping_result.protocol().to_string());
+        }
+
+        let ping_time = *ping_result.ping_time();
+        self.first_ping_time = Some(self.first_ping_time.map_or(ping_time, |first| first.min(ping_time)));
+        self.last_ping_time = Some(self.last_ping_time.map_or(ping_time, |last| last.max(ping_time)));
+
+        let is_target_reached = !ping_result.is_timed_out() && ping_result.error().is_none();
+        let round_trip_time_in_us = if is_target_reached { Some(ping_result.round_trip_time().as_micros()) } else { None };
+        if let Some(round_trip_time_in_us) = round_trip_time_in_us {
+            self.rtt_histogram.update(round_trip_time_in_us);
+        }
+
+        self.latency_buckets.update(ping_result);
+
+        let target_statistics = self.target_statistics.entry(ping_result.target()).or_insert_with(HtmlReportTargetStatistics::new);
+        target_statistics.update(ping_result, round_trip_time_in_us, self.rtt_time_bucket_size_in_ms);
+        if target_statistics.rtt_time_bucket_range() >= RTT_TIME_BUCKET_COUNT_LIMIT {
+            self.double_rtt_time_bucket_size();
+        }
+    }
+
+    // All targets share the same bucket size, so their buckets are drawn on the same time axis.
+    fn double_rtt_time_bucket_size(&mut self) {
+        while self.target_statistics.values().any(|statistics| statistics.rtt_time_bucket_range() >= RTT_TIME_BUCKET_COUNT_LIMIT) {
+            self.rtt_time_bucket_size_in_ms *= 2;
+            self.target_statistics.values_mut().for_each(|statistics| statistics.merge_rtt_time_buckets());
+        }
+    }
+
+    fn render_report(&self) -> String {
+        let protocol = escape_html(self.protocol.as_deref().unwrap_or(""));
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        writeln!(html, "<title>Rnp report - {}</title>", protocol).unwrap();
+        html.push_str(
+            "<style>\n\
+             body { font-family: Segoe UI, Helvetica, Arial, sans-serif; margin: 24px; color: #222; }\n\
+             table { border-collapse: collapse; margin: 8px 0 16px 0; }\n\
+             th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: right; }\n\
+             th:first-child, td:first-child { text-align: left; }\n\
+             svg text { font-size: 11px; fill: #444; }\n\
+             </style>\n</head>\n<body>\n",
+        );
+        writeln!(html, "<h1>Rnp report - {}</h1>", protocol).unwrap();
+
+        self.render_summary(&mut html);
+        self.render_rtt_over_time_chart(&mut html);
+        self.render_rtt_histogram_chart(&mut html);
+        self.render_latency_bucket_table(&mut html);
+        self.render_failure_breakdown(&mut html);
+        self.render_result_scatter_map(&mut html);
+
+        html.push_str("</body>\n</html>\n");
+        return html;
+    }
+
+    fn render_summary(&self, html: &mut String) {
+        html.push_str("<h2>Summary</h2>\n");
+
+        if let (Some(first_ping_time), Some(last_ping_time)) = (self.first_ping_time, self.last_ping_time) {
+            writeln!(
+                html,
+                "<p>Pings sent from {} to {} (UTC).</p>",
+                first_ping_time.format("%Y-%m-%d %H:%M:%S%.3f"),
+                last_ping_time.format("%Y-%m-%d %H:%M:%S%.3f")
+            )
+            .unwrap();
+        }
+
+        html.push_str("<table>\n<tr><th>Target</th><th>Sent</th><th>Succeeded</th><th>Failed</th><th>Fail %</th></tr>\n");
+        for (target, statistics) in &self.target_statistics {
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}%</td></tr>",
+                target,
+                statistics.ping_count,
+                statistics.success_count,
+                statistics.ping_count - statistics.success_count,
+                statistics.failure_rate_in_percent()
+            )
+            .unwrap();
+        }
+        html.push_str("</table>\n");
+    }
+
+    // Each pixel column of the chart only draws the min and max latency of the time buckets in it, so the svg keeps a bounded size in
+    // long runs, while spikes are still shown.
+    fn render_rtt_over_time_chart(&self, html: &mut String) {
+        html.push_str("<h2>Round trip time over time</h2>\n");
+
+        let (first_ping_time, last_ping_time) = match (self.first_ping_time, self.last_ping_time) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                html.push_str("<p>No ping result.</p>\n");
+                return;
+            }
+        };
+        let max_latency_in_us = self.rtt_histogram.max_latency_in_us.unwrap_or(0).max(1);
+
+        let plot_width = CHART_WIDTH - CHART_MARGIN_LEFT - CHART_MARGIN_RIGHT;
+        let plot_height = CHART_HEIGHT - CHART_MARGIN_TOP - CHART_MARGIN_BOTTOM;
+        let time_range_in_ms = (last_ping_time - first_ping_time).num_milliseconds().max(1) as f64;
+
+        let mut svg = String::new();
+        render_chart_axes(&mut svg, &format!("{:.2}ms", max_latency_in_us as f64 / 1000.0), "0ms");
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\">{}</text><text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            CHART_MARGIN_LEFT,
+            CHART_HEIGHT - CHART_MARGIN_BOTTOM + 16,
+            first_ping_time.format("%H:%M:%S"),
+            CHART_WIDTH - CHART_MARGIN_RIGHT,
+            CHART_HEIGHT - CHART_MARGIN_BOTTOM + 16,
+            last_ping_time.format("%H:%M:%S")
+        )
+        .unwrap();
+
+        for (target_index, (target, statistics)) in self.target_statistics.iter().enumerate() {
+            let color = TARGET_COLORS[target_index % TARGET_COLORS.len()];
+
+            // The bucket start is clamped into the time range, since the first bucket can start before the first ping.
+            let mut columns: BTreeMap<usize, RttTimeBucket> = BTreeMap::new();
+            for (bucket_index, bucket) in &statistics.rtt_time_buckets {
+                let bucket_start_time_in_ms = (bucket_index * self.rtt_time_bucket_size_in_ms).max(first_ping_time.timestamp_millis());
+                let time_offset_in_ms = (bucket_start_time_in_ms - first_ping_time.timestamp_millis()) as f64;
+                let column = ((time_offset_in_ms / time_range_in_ms).min(1.0) * (plot_width - 1) as f64) as usize;
+                columns.entry(column).or_default().merge(bucket);
+            }
+
+            writeln!(svg, "<g stroke=\"{}\" fill=\"{}\"><title>{}</title>", color, color, target).unwrap();
+            for (column, bucket) in &columns {
+                if let (Some(min_latency), Some(max_latency)) = (bucket.min_latency_in_us, bucket.max_latency_in_us) {
+                    let x = CHART_MARGIN_LEFT + column;
+                    let y_of = |latency: u128| CHART_MARGIN_TOP as f64 + plot_height as f64 * (1.0 - latency as f64 / max_latency_in_us as f64);
+                    writeln!(
+                        svg,
+                        "<line x1=\"{}\" y1=\"{:.1}\" x2=\"{}\" y2=\"{:.1}\" stroke-width=\"2\"/>",
+                        x,
+                        y_of(max_latency) - 1.0,
+                        x,
+                        y_of(min_latency) + 1.0
+                    )
+                    .unwrap();
+                }
+            }
+            svg.push_str("</g>\n");
+
+            // Pings that never reached the target are marked on top of the chart.
+            writeln!(svg, "<g fill=\"{}\"><title>{} not reached</title>", FAILURE_COLOR, target).unwrap();
+            for (column, _) in columns.iter().filter(|(_, bucket)| bucket.has_failure) {
+                writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"2\" height=\"6\"/>", CHART_MARGIN_LEFT + column - 1, CHART_MARGIN_TOP - 8).unwrap();
+            }
+            svg.push_str("</g>\n");
+        }
+
+        html.push_str(&wrap_svg(&svg, CHART_WIDTH, CHART_HEIGHT));
+        self.render_target_legend(html);
+    }
+
+    fn render_target_legend(&self, html: &mut String) {
+        html.push_str("<p>");
+        for (target_index, target) in self.target_statistics.keys().enumerate() {
+            let color = TARGET_COLORS[target_index % TARGET_COLORS.len()];
+            write!(html, "<span style=\"color: {}\">&#9632;</span> {} &nbsp; ", color, target).unwrap();
+        }
+        writeln!(html, "<span style=\"color: {}\">&#9632;</span> Not reached</p>", FAILURE_COLOR).unwrap();
+    }
+
+    fn render_rtt_histogram_chart(&self, html: &mut String) {
+        html.push_str("<h2>Round trip time histogram</h2>\n");
+
+        let max_latency_in_us = match self.rtt_histogram.max_latency_in_us {
+            Some(max_latency_in_us) => max_latency_in_us.max(1),
+            None => {
+                html.push_str("<p>No ping reached the target.</p>\n");
+                return;
+            }
+        };
+
+        let bins = self.rtt_histogram.chart_bins(max_latency_in_us);
+        let max_bin_count = *bins.iter().max().unwrap();
+
+        let plot_width = CHART_WIDTH - CHART_MARGIN_LEFT - CHART_MARGIN_RIGHT;
+        let plot_height = CHART_HEIGHT - CHART_MARGIN_TOP - CHART_MARGIN_BOTTOM;
+        let bar_width = plot_width as f64 / HISTOGRAM_BIN_COUNT as f64;
+
+        let mut svg = String::new();
+        render_chart_axes(&mut svg, &max_bin_count.to_string(), "0");
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\">0ms</text><text x=\"{}\" y=\"{}\" text-anchor=\"end\">{:.2}ms</text>",
+            CHART_MARGIN_LEFT,
+            CHART_HEIGHT - CHART_MARGIN_BOTTOM + 16,
+            CHART_WIDTH - CHART_MARGIN_RIGHT,
+            CHART_HEIGHT - CHART_MARGIN_BOTTOM + 16,
+            max_latency_in_us as f64 / 1000.0
+        )
+        .unwrap();
+
+        writeln!(svg, "<g fill=\"{}\">", TARGET_COLORS[0]).unwrap();
+        for (bin_index, bin_count) in bins.iter().enumerate().filter(|(_, count)| **count > 0) {
+            let bar_height = plot_height as f64 * *bin_count as f64 / max_bin_count as f64;
+            let bin_start_in_ms = (max_latency_in_us + 1) as f64 * bin_index as f64 / HISTOGRAM_BIN_COUNT as f64 / 1000.0;
+            writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"><title>&gt;= {:.2}ms: {}</title></rect>",
+                CHART_MARGIN_LEFT as f64 + bar_width * bin_index as f64 + 1.0,
+                (CHART_HEIGHT - CHART_MARGIN_BOTTOM) as f64 - bar_height,
+                bar_width - 2.0,
+                bar_height,
+                bin_start_in_ms,
+                bin_count
+            )
+            .unwrap();
+        }
+        svg.push_str("</g>\n");
+
+        html.push_str(&wrap_svg(&svg, CHART_WIDTH, CHART_HEIGHT));
+    }
+
+    fn render_latency_bucket_table(&self, html: &mut String) {
+        html.push_str("<h2>Latency buckets</h2>\n");
+        html.push_str("<table>\n<tr><th>Latency Range</th><th>Count</th><th>%</th><th></th></tr>\n");
+
+        let total_hit_count = self.latency_buckets.total_hit_count().max(1);
+        for (bucket_range, hit_count) in self.latency_buckets.bucket_hits() {
+            let bar_width = 200 * hit_count / total_hit_count;
+            let color = if bucket_range == "Timed Out" || bucket_range == "Failed" { FAILURE_COLOR } else { TARGET_COLORS[0] };
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{:.2}%</td><td style=\"text-align: left\">{}</td></tr>",
+                escape_html(&bucket_range),
+                hit_count,
+                hit_count as f64 * 100.0 / total_hit_count as f64,
+                wrap_svg(&format!("<rect width=\"{}\" height=\"10\" fill=\"{}\"/>", bar_width, color), 200, 10).trim_end()
+            )
+            .unwrap();
+        }
+
+        writeln!(html, "<tr><th>Total</th><th>{}</th><th></th><th></th></tr>\n</table>", self.latency_buckets.total_hit_count()).unwrap();
+    }
+
+    fn render_failure_breakdown(&self, html: &mut String) {
+        html.push_str("<h2>Failures by class</h2>\n");
+
+        let failure_classes: Vec<PingFailureClass> = self
+            .target_statistics
+            .values()
+            .flat_map(|statistics| statistics.failure_class_counts.keys().cloned())
+            .collect::<std::collections::BTreeSet<PingFailureClass>>()
+            .into_iter()
+            .collect();
+        if failure_classes.is_empty() {
+            html.push_str("<p>No failure.</p>\n");
+            return;
+        }
+
+        html.push_str("<table>\n<tr><th>Target</th>");
+        for failure_class in &failure_classes {
+            write!(html, "<th>{}</th>", failure_class).unwrap();
+        }
+        html.push_str("</tr>\n");
+
+        for (target, statistics) in &self.target_statistics {
+            write!(html, "<tr><td>{}</td>", target).unwrap();
+            for failure_class in &failure_classes {
+                write!(html, "<td>{}</td>", statistics.failure_class_counts.get(failure_class).unwrap_or(&0)).unwrap();
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn render_result_scatter_map(&self, html: &mut String) {
+        html.push_str("<h2>Ping result scatter map</h2>\n<p>");
+        for (symbol, name) in &[
+            (SCATTER_SYMBOL_PASSED, "Ok"),
+            (SCATTER_SYMBOL_FAILED, "Fail"),
+            (SCATTER_SYMBOL_NOT_TESTED_YET, "Not tested yet"),
+            (SCATTER_SYMBOL_PREPARE_FAILED, "Preparation failed"),
+            (SCATTER_SYMBOL_HANDSHAKE_FAILED, "App handshake failed"),
+            (SCATTER_SYMBOL_DISCONNECT_FAILED, "Disconnect failed"),
+            (SCATTER_SYMBOL_RECONNECTED, "Reconnected"),
+        ] {
+            write!(html, "<span style=\"color: {}\">&#9632;</span> {} &nbsp; ", scatter_symbol_color(*symbol), name).unwrap();
+        }
+        html.push_str("</p>\n");
+
+        for (target, target_ping_history) in self.scatter_map.iter() {
+            writeln!(html, "<h3>{}</h3>", target).unwrap();
+
+            let rows: Vec<(usize, &u32, &Vec<char>)> = target_ping_history
+                .iter()
+                .enumerate()
+                .flat_map(|(iteration_index, iteration)| iteration.iter().map(move |(port_bucket, hits)| (iteration_index, port_bucket, hits)))
+                .collect();
+
+            // Every 5 ports are grouped with a small gap, the same as the scatter map in console.
+            let cell_x = |index: usize| SCATTER_ROW_LABEL_WIDTH + index * SCATTER_CELL_SIZE + (index / 5) * (SCATTER_CELL_SIZE / 2);
+            let width = cell_x(COUNT_PER_ROW as usize);
+            let height = SCATTER_CELL_SIZE * (rows.len() + 1);
+
+            let mut svg = String::new();
+            writeln!(svg, "<text x=\"0\" y=\"{}\">Iter / Src Port</text>", SCATTER_CELL_SIZE - 2).unwrap();
+            for index in (0..COUNT_PER_ROW as usize).step_by(5) {
+                writeln!(svg, "<text x=\"{}\" y=\"{}\">{}</text>", cell_x(index), SCATTER_CELL_SIZE - 2, index).unwrap();
+            }
+
+            for (row_index, (iteration_index, port_bucket, hits)) in rows.iter().enumerate() {
+                let y = SCATTER_CELL_SIZE * (row_index + 1);
+                writeln!(svg, "<text x=\"0\" y=\"{}\">{} / {}</text>", y + SCATTER_CELL_SIZE - 2, iteration_index, port_bucket).unwrap();
+                for (index, symbol) in hits.iter().enumerate() {
+                    writeln!(
+                        svg,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}</title></rect>",
+                        cell_x(index),
+                        y + 1,
+                        SCATTER_CELL_SIZE - 2,
+                        SCATTER_CELL_SIZE - 2,
+                        scatter_symbol_color(*symbol),
+                        **port_bucket as usize + index
+                    )
+                    .unwrap();
+                }
+            }
+
+            html.push_str(&wrap_svg(&svg, width, height));
+        }
+    }
+}
+
+fn render_chart_axes(svg: &mut String, y_max_label: &str, y_min_label: &str) {
+    let x_axis_y = CHART_HEIGHT - CHART_MARGIN_BOTTOM;
+    writeln!(
+        svg,
+        "<line x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"#888\"/><line x1=\"{0}\" y1=\"{2}\" x2=\"{3}\" y2=\"{2}\" stroke=\"#888\"/>",
+        CHART_MARGIN_LEFT,
+        CHART_MARGIN_TOP,
+        x_axis_y,
+        CHART_WIDTH - CHART_MARGIN_RIGHT
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<text x=\"{0}\" y=\"{1}\" text-anchor=\"end\">{2}</text><text x=\"{0}\" y=\"{3}\" text-anchor=\"end\">{4}</text>",
+        CHART_MARGIN_LEFT - 6,
+        CHART_MARGIN_TOP + 4,
+        escape_html(y_max_label),
+        x_axis_y,
+        escape_html(y_min_label)
+    )
+    .unwrap();
+}
+
+fn wrap_svg(content: &str, width: usize, height: usize) -> String {
+    return format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n{2}</svg>\n",
+        width, height, content
+    );
+}
+
+fn scatter_symbol_color(symbol: char) -> &'static str {
+    return match symbol {
+        SCATTER_SYMBOL_PASSED => "#2ca02c",
+        SCATTER_SYMBOL_FAILED => FAILURE_COLOR,
+        SCATTER_SYMBOL_PREPARE_FAILED => "#7f7f7f",
+        SCATTER_SYMBOL_HANDSHAKE_FAILED => "#ff7f0e",
+        SCATTER_SYMBOL_DISCONNECT_FAILED => "#bcbd22",
+        SCATTER_SYMBOL_RECONNECTED => "#17becf",
+        _ => "#eeeeee",
+    };
+}
+
+fn escape_html(s: &str) -> String {
+    return s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+}
+
+impl PingResultProcessor for PingResultProcessorHtmlReport {
+    fn name(&self) -> &'static str {
+        "HtmlReport"
+    }
+    fn config(&self) -> &PingResultProcessorCommonConfig {
+        self.common_config.as_ref()
+    }
+
+    fn process_ping_result(&mut self, ping_result: &PingResult) {
+        self.update_statistics(ping_result);
+    }
+
+    fn rundown(&mut self) {
+        // Failing to write the report shouldn't take down the rundown of the other processors, so the error is only reported.
+        let report = self.render_report();
+        if let Err(e) = self.report_file.write_all(report.as_bytes()) {
+            tracing::error!("Failed to write html report! Path = {}, Error = {}", self.report_path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ping_result_processors::ping_result_processor_test_common;
+    use crate::rnp_test_common;
+    use chrono::TimeZone;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn ping_result_process_html_report_should_work() {
+        let test_report_path = PathBuf::from("tests_data/ping_result_html_report_tests/report.html");
+        let mut processor: Box<dyn PingResultProcessor + Send + Sync> = Box::new(
+            PingResultProcessorHtmlReport::new(
+                Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+                &test_report_path,
+                &Some(vec![0.1, 0.5, 1.0, 10.0]),
+            )
+            .unwrap(),
+        );
+        ping_result_processor_test_common::run_ping_result_processor_with_test_samples(&mut processor);
+
+        let report = fs::read_to_string(&test_report_path).unwrap();
+        assert!(report.starts_with("<!DOCTYPE html>"));
+        assert!(report.ends_with("</html>\n"));
+        assert_eq!(6, report.matches("<h2>").count());
+
+        // Summary, latency buckets and failure breakdown.
+        assert!(report.contains("<tr><td>1.2.3.4:443</td><td>4</td><td>2</td><td>2</td><td>50.00%</td></tr>"));
+        assert!(report.contains("<tr><td>&gt;= 10.00ms</td><td>2</td><td>50.00%</td>"));
+        assert!(report.contains("<tr><td>Timed Out</td><td>1</td><td>25.00%</td>"));
+        assert!(report.contains("<tr><th>Target</th><th>Refused</th><th>Timeout</th><th>Other</th></tr>"));
+
+        // Charts: round trip time over time, histogram, 7 bars in latency bucket table and 1 scatter map.
+        assert_eq!(10, report.matches("<svg ").count());
+        assert!(!report.contains("<script") && !report.contains("<link") && !report.contains("src="));
+    }
+
+    #[test]
+    fn ping_result_process_html_report_should_keep_bounded_statistics_in_long_runs() {
+        let test_report_path = PathBuf::from("tests_data/ping_result_html_report_tests/long_run_report.html");
+        let mut processor = PingResultProcessorHtmlReport::new(
+            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+            &test_report_path,
+            &None,
+        )
+        .unwrap();
+
+        // A day of pings every second, with the latency growing over time.
+        let start_time = Utc.with_ymd_and_hms(2021, 7, 6, 0, 0, 0).unwrap();
+        for ping_index in 0..86400u64 {
+            let ping_result = PingResult::new(
+                &(start_time + chrono::Duration::seconds(ping_index as i64)),
+                1,
+                "TCP",
+                "1.2.3.4:443".parse().unwrap(),
+                "5.6.7.8:8080".parse().unwrap(),
+                false,
+                true,
+                Duration::from_micros(100 + ping_index * 10),
+                false,
+                None,
+                None,
+            );
+            processor.process_ping_result(&ping_result);
+        }
+
+        let statistics = processor.target_statistics.values().next().unwrap();
+        assert_eq!(86400, statistics.ping_count);
+        assert!(statistics.rtt_time_buckets.len() <= RTT_TIME_BUCKET_COUNT_LIMIT as usize);
+        assert_eq!(Some(100 + 86399 * 10), processor.rtt_histogram.max_latency_in_us);
+        assert_eq!(86400, processor.rtt_histogram.chart_bins(100 + 86399 * 10).iter().sum::<u32>());
+        assert_eq!(Some(start_time + chrono::Duration::seconds(86399)), processor.last_ping_time);
+
+        processor.rundown();
+        let report = fs::read_to_string(&test_report_path).unwrap();
+        assert!(report.contains("<p>Pings sent from 2021-07-06 00:00:00.000 to 2021-07-06 23:59:59.000 (UTC).</p>"));
+        assert!(report.contains("<text x=\"70\" y=\"276\">00:00:00</text>"));
+    }
+
+    #[test]
+    fn ping_result_process_html_report_should_not_panic_when_report_write_failed() {
+        let test_report_path = PathBuf::from("tests_data/ping_result_html_report_tests/read_only_report.html");
+        let mut processor = PingResultProcessorHtmlReport::new(
+            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+            &test_report_path,
+            &None,
+        )
+        .unwrap();
+
+        // Writing to a file opened as read only fails.
+        processor.report_file = File::open(&test_report_path).unwrap();
+        for ping_result in &rnp_test_common::generate_ping_result_test_samples() {
+            processor.process_ping_result(ping_result);
+        }
+        processor.rundown();
+
+        assert_eq!("", fs::read_to_string(&test_report_path).unwrap());
+    }
+
+    #[test]
+    fn escaping_html_should_work() {
+        assert_eq!("&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;", escape_html("<a href=\"x\">&</a>"));
+    }
+}
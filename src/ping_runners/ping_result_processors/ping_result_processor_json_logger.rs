@@ -8,32 +8,43 @@ pub struct PingResultProcessorJsonLogger {
     log_path: PathBuf,
     log_file: File,
     is_first_element: bool,
+
+    // Failing to write logs shouldn't stop the pings, so only the first failure is reported to avoid flooding the output.
+    has_write_failed: bool,
 }
 
 impl PingResultProcessorJsonLogger {
     #[tracing::instrument(name = "Creating ping result json logger", level = "debug")]
-    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, log_path_buf: &PathBuf) -> PingResultProcessorJsonLogger {
-        return PingResultProcessorJsonLogger {
+    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, log_path_buf: &PathBuf) -> Result<PingResultProcessorJsonLogger, RnpError> {
+        return Ok(PingResultProcessorJsonLogger {
             common_config,
             log_path: log_path_buf.clone(),
-            log_file: rnp_utils::create_log_file(log_path_buf),
+            log_file: rnp_utils::create_log_file(log_path_buf)?,
             is_first_element: true,
-        };
+            has_write_failed: false,
+        });
     }
 
     fn log_result_as_json(&mut self, ping_result: &PingResult) -> io::Result<()> {
         if self.is_first_element {
             self.is_first_element = false;
-            self.log_file.write("\n  ".as_bytes())?;
+            self.log_file.write_all("\n  ".as_bytes())?;
         } else {
-            self.log_file.write(",\n  ".as_bytes())?;
+            self.log_file.write_all(",\n  ".as_bytes())?;
         }
 
         let log_content = ping_result.format_as_json_string();
-        self.log_file.write(log_content.as_bytes())?;
+        self.log_file.write_all(log_content.as_bytes())?;
 
         return Ok(());
     }
+
+    fn report_write_failure(&mut self, e: io::Error) {
+        if !self.has_write_failed {
+            self.has_write_failed = true;
+            tracing::error!("Failed to write logs to json file! Path = {}, Error = {}", self.log_path.display(), e);
+        }
+    }
 }
 
 impl PingResultProcessor for PingResultProcessorJsonLogger {
@@ -46,16 +57,22 @@ impl PingResultProcessor for PingResultProcessorJsonLogger {
 
     fn initialize(&mut self) {
         // Writer json start
-        self.log_file.write("[".as_bytes()).expect(&format!("Failed to write logs to json file! Path = {}", self.log_path.display()));
+        if let Err(e) = self.log_file.write_all("[".as_bytes()) {
+            self.report_write_failure(e);
+        }
     }
 
     fn process_ping_result(&mut self, ping_result: &PingResult) {
-        self.log_result_as_json(ping_result).expect(&format!("Failed to write logs to json file! Path = {}", self.log_path.display()));
+        if let Err(e) = self.log_result_as_json(ping_result) {
+            self.report_write_failure(e);
+        }
     }
 
     fn rundown(&mut self) {
         // Writer json end
-        self.log_file.write("\n]\n".as_bytes()).expect(&format!("Failed to write logs to json file! Path = {}", self.log_path.display()));
+        if let Err(e) = self.log_file.write_all("\n]\n".as_bytes()) {
+            self.report_write_failure(e);
+        }
     }
 }
 
@@ -63,6 +80,7 @@ impl PingResultProcessor for PingResultProcessorJsonLogger {
 mod tests {
     use super::*;
     use crate::ping_result_processors::ping_result_processor_test_common;
+    use crate::rnp_test_common;
     use crate::PingResultDto;
     use chrono::{TimeZone, Utc};
     use pretty_assertions::assert_eq;
@@ -71,10 +89,13 @@ mod tests {
     #[test]
     fn ping_result_process_json_logger_should_work() {
         let test_log_file_path = "tests_data/ping_result_processor_json_logger_tests/test_log.json";
-        let mut processor: Box<dyn PingResultProcessor + Send + Sync> = Box::new(PingResultProcessorJsonLogger::new(
-            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
-            &PathBuf::from(test_log_file_path),
-        ));
+        let mut processor: Box<dyn PingResultProcessor + Send + Sync> = Box::new(
+            PingResultProcessorJsonLogger::new(
+                Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+                &PathBuf::from(test_log_file_path),
+            )
+            .unwrap(),
+        );
         ping_result_processor_test_common::run_ping_result_processor_with_test_samples(&mut processor);
 
         let actual_logged_records: Vec<PingResultDto>;
@@ -102,6 +123,15 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 5f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: None,
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -119,6 +149,15 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: Some(PingFailureClass::Timeout),
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -136,6 +175,15 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "connect aborted".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: Some(PingFailureClass::Other),
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -153,6 +201,15 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "disconnect timeout".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: None,
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -170,6 +227,15 @@ mod tests {
                     ping_error: "connect failed".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: Some(PingFailureClass::Refused),
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
                 PingResultDto {
                     utc_time: Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12),
@@ -187,9 +253,39 @@ mod tests {
                     ping_error: "".to_string(),
                     handshake_error: "".to_string(),
                     disconnect_error: "".to_string(),
+                    dns_resolve_time_in_ms: 0f64,
+                    handshake_time_in_ms: 0f64,
+                    first_byte_time_in_ms: 0f64,
+                    response_time_in_ms: 0f64,
+                    failure_class: Some(PingFailureClass::LocalPortInUse),
+                    forward_delay_in_ms: 0f64,
+                    reverse_delay_in_ms: 0f64,
+                    clock_offset_in_ms: 0f64,
+                    reconnect_reason: "".to_string(),
                 },
             ],
             actual_logged_records,
         );
     }
+
+    #[test]
+    fn ping_result_process_json_logger_should_not_panic_when_log_write_failed() {
+        let test_log_file_path = PathBuf::from("tests_data/ping_result_processor_json_logger_tests/read_only_log.json");
+        let mut processor = PingResultProcessorJsonLogger::new(
+            Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+            &test_log_file_path,
+        )
+        .unwrap();
+
+        // Writing to a file opened as read only fails.
+        processor.log_file = File::open(&test_log_file_path).unwrap();
+        processor.initialize();
+        for ping_result in &rnp_test_common::generate_ping_result_test_samples() {
+            processor.process_ping_result(ping_result);
+        }
+        processor.rundown();
+
+        assert!(processor.has_write_failed);
+        assert_eq!("", std::fs::read_to_string(&test_log_file_path).unwrap());
+    }
 }
@@ -6,6 +6,17 @@ use tracing;
 
 pub struct PingResultProcessorLatencyBucketLogger {
     common_config: Arc<PingResultProcessorCommonConfig>,
+    latency_buckets: LatencyBuckets,
+}
+
+impl PingResultProcessorLatencyBucketLogger {
+    #[tracing::instrument(name = "Creating ping result latency bucket logger", level = "debug")]
+    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, buckets: &Vec<f64>) -> PingResultProcessorLatencyBucketLogger {
+        return PingResultProcessorLatencyBucketLogger { common_config, latency_buckets: LatencyBuckets::new(buckets) };
+    }
+}
+
+pub(crate) struct LatencyBuckets {
     buckets_in_us: Vec<u128>,
 
     total_hit_count: u32,
@@ -14,11 +25,10 @@ pub struct PingResultProcessorLatencyBucketLogger {
     failed_hit_count: u32,
 }
 
-impl PingResultProcessorLatencyBucketLogger {
-    #[tracing::instrument(name = "Creating ping result latency bucket logger", level = "debug")]
+impl LatencyBuckets {
     #[allow(unreachable_code)]
     #[requires(buckets.len() >= 1)]
-    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, buckets: &Vec<f64>) -> PingResultProcessorLatencyBucketLogger {
+    pub(crate) fn new(buckets: &[f64]) -> LatencyBuckets {
         // The buckets from settings are treated as separators, so the real buckets are:
         // - 0->The first bucket defined in settings
         // - whatever defined in settings
@@ -29,12 +39,11 @@ impl PingResultProcessorLatencyBucketLogger {
         // - Timed out
         // - Failed
         let mut normalized_buckets = vec![];
-        buckets.into_iter().for_each(|x| normalized_buckets.push((x * 1000.0) as u128));
+        buckets.iter().for_each(|x| normalized_buckets.push((x * 1000.0) as u128));
         normalized_buckets.push(u128::MAX);
 
         let normalized_bucket_count = normalized_buckets.len();
-        return PingResultProcessorLatencyBucketLogger {
-            common_config,
+        return LatencyBuckets {
             buckets_in_us: normalized_buckets,
             total_hit_count: 0,
             bucket_hit_counts: vec![0; normalized_bucket_count],
@@ -43,14 +52,8 @@ impl PingResultProcessorLatencyBucketLogger {
         };
     }
 
-    fn update_statistics(&mut self, ping_result: &PingResult) {
-        // Skip warmup pings in analysis.
-        if ping_result.is_warmup() {
-            return;
-        }
-
-        // Skip preparation errors in analysis, since it is not a remote issue.
-        if ping_result.is_preparation_error() {
+    pub(crate) fn update(&mut self, ping_result: &PingResult) {
+        if !ping_result.is_counted_in_statistics() {
             return;
         }
 
@@ -76,6 +79,28 @@ impl PingResultProcessorLatencyBucketLogger {
 
         unreachable!();
     }
+
+    pub(crate) fn total_hit_count(&self) -> u32 {
+        return self.total_hit_count;
+    }
+
+    // All buckets as (latency range, hit count), followed by the timed out and failed pings.
+    pub(crate) fn bucket_hits(&self) -> Vec<(String, u32)> {
+        let mut bucket_hits = Vec::new();
+        for (bucket_index, bucket_time_upper_bound_in_us) in self.buckets_in_us.iter().enumerate() {
+            let bucket_range = if bucket_index < self.buckets_in_us.len() - 1 {
+                format!("< {:.2}ms", *bucket_time_upper_bound_in_us as f64 / 1000.0)
+            } else {
+                format!(">= {:.2}ms", self.buckets_in_us[bucket_index - 1] as f64 / 1000.0)
+            };
+
+            bucket_hits.push((bucket_range, self.bucket_hit_counts[bucket_index]));
+        }
+
+        bucket_hits.push((String::from("Timed Out"), self.timed_out_hit_count));
+        bucket_hits.push((String::from("Failed"), self.failed_hit_count));
+        return bucket_hits;
+    }
 }
 
 impl PingResultProcessor for PingResultProcessorLatencyBucketLogger {
@@ -91,7 +116,7 @@ impl PingResultProcessor for PingResultProcessorLatencyBucketLogger {
             return;
         }
 
-        self.update_statistics(ping_result);
+        self.latency_buckets.update(ping_result);
     }
 
     fn rundown(&mut self) {
@@ -103,20 +128,12 @@ impl PingResultProcessor for PingResultProcessorLatencyBucketLogger {
         println!("{:>15} | {}", "Latency Range", "Count");
         println!("{:->17}------------ ", "+");
 
-        for (bucket_index, bucket_time_upper_bound_in_us) in self.buckets_in_us.iter().enumerate() {
-            let bucket_range = if bucket_index < self.buckets_in_us.len() - 1 {
-                format!("< {:.2}ms", *bucket_time_upper_bound_in_us as f64 / 1000.0)
-            } else {
-                format!(">= {:.2}ms", self.buckets_in_us[bucket_index - 1] as f64 / 1000.0)
-            };
-
-            println!("{:>15} | {}", bucket_range, self.bucket_hit_counts[bucket_index]);
+        for (bucket_range, hit_count) in self.latency_buckets.bucket_hits() {
+            println!("{:>15} | {}", bucket_range, hit_count);
         }
 
-        println!("{:>15} | {}", "Timed Out", self.timed_out_hit_count);
-        println!("{:>15} | {}", "Failed", self.failed_hit_count);
         println!("{:->17}------------ ", "+");
-        println!("{:>15} | {}", "Total", self.total_hit_count);
+        println!("{:>15} | {}", "Total", self.latency_buckets.total_hit_count());
     }
 }
 
@@ -133,10 +150,10 @@ mod tests {
             Arc::new(PingResultProcessorCommonConfig { quiet_level: 0 }),
             &vec![0.1, 0.5, 1.0, 10.0, 50.0, 100.0],
         );
-        ping_results.iter().for_each(|x| logger.update_statistics(x));
+        ping_results.iter().for_each(|x| logger.latency_buckets.update(x));
 
-        assert_eq!(4, logger.total_hit_count);
-        assert_eq!(1, logger.timed_out_hit_count);
-        assert_eq!(1, logger.failed_hit_count);
+        assert_eq!(4, logger.latency_buckets.total_hit_count);
+        assert_eq!(1, logger.latency_buckets.timed_out_hit_count);
+        assert_eq!(1, logger.latency_buckets.failed_hit_count);
     }
 }
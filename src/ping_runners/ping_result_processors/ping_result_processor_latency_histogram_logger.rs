@@ -0,0 +1,269 @@
+use crate::*;
+use contracts::requires;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing;
+
+pub struct PingResultProcessorLatencyHistogramLogger {
+    common_config: Arc<PingResultProcessorCommonConfig>,
+    percentiles: Vec<f64>,
+    target_histograms: BTreeMap<SocketAddr, LatencyHistogram>,
+}
+
+impl PingResultProcessorLatencyHistogramLogger {
+    #[tracing::instrument(name = "Creating ping result latency histogram logger", level = "debug")]
+    #[requires(percentiles.iter().all(|p| *p > 0.0 && *p <= 100.0))]
+    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, percentiles: &Vec<f64>) -> PingResultProcessorLatencyHistogramLogger {
+        return PingResultProcessorLatencyHistogramLogger { common_config, percentiles: percentiles.clone(), target_histograms: BTreeMap::new() };
+    }
+
+    fn update_statistics(&mut self, ping_result: &PingResult) {
+        if !ping_result.is_counted_in_statistics() {
+            return;
+        }
+
+        // Only the pings that reached the remote side have valid latency, which also includes the ones with warnings.
+        if ping_result.is_timed_out() || ping_result.error().is_some() {
+            return;
+        }
+
+        self.target_histograms.entry(ping_result.target()).or_insert_with(LatencyHistogram::new).record(&ping_result.round_trip_time());
+    }
+
+    fn output_histogram_summary_to_console(&self, histogram: &LatencyHistogram) {
+        let percentile_summaries: Vec<String> =
+            self.percentiles.iter().map(|p| format!("P{} = {:.2}ms", p, histogram.value_at_percentile(*p) as f64 / 1000.0)).collect();
+        println!("- Percentiles: {}.", percentile_summaries.join(", "));
+        println!(
+            "- Standard deviation = {:.2}ms, Jitter = {:.2}ms, Samples = {}.",
+            histogram.standard_deviation_in_us() / 1000.0,
+            histogram.jitter_in_us() / 1000.0,
+            histogram.total_count,
+        );
+    }
+}
+
+impl PingResultProcessor for PingResultProcessorLatencyHistogramLogger {
+    fn name(&self) -> &'static str {
+        "LatencyHistogramLogger"
+    }
+    fn config(&self) -> &PingResultProcessorCommonConfig {
+        self.common_config.as_ref()
+    }
+
+    fn process_ping_result(&mut self, ping_result: &PingResult) {
+        if self.has_quiet_level(RNP_QUIET_LEVEL_NO_PING_SUMMARY) {
+            return;
+        }
+
+        self.update_statistics(ping_result);
+    }
+
+    fn rundown(&mut self) {
+        if self.has_quiet_level(RNP_QUIET_LEVEL_NO_PING_SUMMARY) {
+            return;
+        }
+
+        // Didn't receive any latency data, skip output statistics.
+        if self.target_histograms.is_empty() {
+            return;
+        }
+
+        println!("\n=== Latency percentiles (in milliseconds) ===");
+        if self.target_histograms.len() == 1 {
+            self.output_histogram_summary_to_console(self.target_histograms.values().next().unwrap());
+            return;
+        }
+
+        for (target, histogram) in &self.target_histograms {
+            println!("--- Target: {} ---", target);
+            self.output_histogram_summary_to_console(histogram);
+        }
+    }
+}
+
+// Each power of 2 range is split into 64 linear sub buckets, which keeps the relative error of any recorded value below 1/64,
+// while values below 128us are recorded exactly.
+const LATENCY_HISTOGRAM_SUB_BUCKET_BITS: u32 = 6;
+
+// Log-linear histogram for latencies in microseconds, which uses constant memory regardless of how many pings we run.
+pub(crate) struct LatencyHistogram {
+    bucket_counts: Vec<u64>,
+    total_count: u64,
+    min_in_us: u64,
+    max_in_us: u64,
+
+    // For calculating standard deviation.
+    sum_in_us: f64,
+    sum_of_squares_in_us: f64,
+
+    // Jitter is the mean absolute difference between 2 consecutive latencies, similar to the jitter defined in RFC 3550,
+    // but without smoothing, so it won't depend on the order of pings too much.
+    last_latency_in_us: Option<u64>,
+    sum_of_latency_differences_in_us: f64,
+}
+
+impl LatencyHistogram {
+    pub(crate) fn new() -> LatencyHistogram {
+        return LatencyHistogram {
+            bucket_counts: vec![],
+            total_count: 0,
+            min_in_us: u64::MAX,
+            max_in_us: 0,
+            sum_in_us: 0.0,
+            sum_of_squares_in_us: 0.0,
+            last_latency_in_us: None,
+            sum_of_latency_differences_in_us: 0.0,
+        };
+    }
+
+    pub(crate) fn record(&mut self, latency: &Duration) {
+        let latency_in_us = latency.as_micros() as u64;
+
+        let bucket_index = LatencyHistogram::bucket_index(latency_in_us);
+        if bucket_index >= self.bucket_counts.len() {
+            self.bucket_counts.resize(bucket_index + 1, 0);
+        }
+        self.bucket_counts[bucket_index] += 1;
+
+        self.total_count += 1;
+        self.min_in_us = std::cmp::min(self.min_in_us, latency_in_us);
+        self.max_in_us = std::cmp::max(self.max_in_us, latency_in_us);
+        self.sum_in_us += latency_in_us as f64;
+        self.sum_of_squares_in_us += (latency_in_us as f64) * (latency_in_us as f64);
+
+        if let Some(last_latency_in_us) = self.last_latency_in_us {
+            self.sum_of_latency_differences_in_us += (latency_in_us as f64 - last_latency_in_us as f64).abs();
+        }
+        self.last_latency_in_us = Some(latency_in_us);
+    }
+
+    // Returns the highest value that is equivalent to the value at the given percentile, which never underestimates the tail
+    // latency, and it is capped by the max value we have seen.
+    pub(crate) fn value_at_percentile(&self, percentile: f64) -> u64 {
+        if self.total_count == 0 {
+            return 0;
+        }
+
+        let target_count = std::cmp::max(1, ((percentile / 100.0) * self.total_count as f64).ceil() as u64);
+        let mut accumulated_count = 0;
+        for (bucket_index, bucket_count) in self.bucket_counts.iter().enumerate() {
+            accumulated_count += bucket_count;
+            if accumulated_count >= target_count {
+                let bucket_upper_bound = LatencyHistogram::bucket_upper_bound(bucket_index);
+                return std::cmp::max(self.min_in_us, std::cmp::min(bucket_upper_bound, self.max_in_us));
+            }
+        }
+
+        return self.max_in_us;
+    }
+
+    fn standard_deviation_in_us(&self) -> f64 {
+        if self.total_count == 0 {
+            return 0.0;
+        }
+
+        let mean = self.sum_in_us / self.total_count as f64;
+        let variance = self.sum_of_squares_in_us / self.total_count as f64 - mean * mean;
+        return variance.max(0.0).sqrt();
+    }
+
+    fn jitter_in_us(&self) -> f64 {
+        if self.total_count < 2 {
+            return 0.0;
+        }
+
+        return self.sum_of_latency_differences_in_us / (self.total_count - 1) as f64;
+    }
+
+    fn bucket_index(value: u64) -> usize {
+        let half_sub_bucket_count = 1u64 << LATENCY_HISTOGRAM_SUB_BUCKET_BITS;
+        if value < half_sub_bucket_count * 2 {
+            return value as usize;
+        }
+
+        let shift = (63 - value.leading_zeros()) - LATENCY_HISTOGRAM_SUB_BUCKET_BITS;
+        return ((shift as u64) * half_sub_bucket_count + (value >> shift)) as usize;
+    }
+
+    fn bucket_lower_bound(bucket_index: usize) -> u64 {
+        let half_sub_bucket_count = 1usize << LATENCY_HISTOGRAM_SUB_BUCKET_BITS;
+        if bucket_index < half_sub_bucket_count * 2 {
+            return bucket_index as u64;
+        }
+
+        let shift = bucket_index / half_sub_bucket_count - 1;
+        let sub_bucket_index = bucket_index % half_sub_bucket_count + half_sub_bucket_count;
+        return (sub_bucket_index as u64) << shift;
+    }
+
+    fn bucket_upper_bound(bucket_index: usize) -> u64 {
+        let half_sub_bucket_count = 1usize << LATENCY_HISTOGRAM_SUB_BUCKET_BITS;
+        if bucket_index < half_sub_bucket_count * 2 {
+            return bucket_index as u64;
+        }
+
+        let shift = bucket_index / half_sub_bucket_count - 1;
+        return LatencyHistogram::bucket_lower_bound(bucket_index) + ((1u64 << shift) - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rnp_test_common;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn latency_histogram_logger_should_work() {
+        let ping_results = rnp_test_common::generate_ping_result_test_samples();
+
+        let mut logger =
+            PingResultProcessorLatencyHistogramLogger::new(Arc::new(PingResultProcessorCommonConfig { quiet_level: 0 }), &vec![50.0, 90.0, 99.9]);
+        ping_results.iter().for_each(|x| logger.update_statistics(x));
+
+        // Warmup, timed out, failed and preparation error results are not counted.
+        assert_eq!(1, logger.target_histograms.len());
+        let histogram = logger.target_histograms.values().next().unwrap();
+        assert_eq!(2, histogram.total_count);
+        assert_eq!(20000, histogram.value_at_percentile(50.0));
+        assert_eq!(20000, histogram.value_at_percentile(99.9));
+        assert_eq!(0.0, histogram.standard_deviation_in_us());
+        assert_eq!(0.0, histogram.jitter_in_us());
+    }
+
+    #[test]
+    fn latency_histogram_bucket_should_cover_values_continuously() {
+        let mut last_bucket_index = 0;
+        for value in 0..100000u64 {
+            let bucket_index = LatencyHistogram::bucket_index(value);
+            assert!(bucket_index == last_bucket_index || bucket_index == last_bucket_index + 1);
+            assert!(LatencyHistogram::bucket_lower_bound(bucket_index) <= value);
+            assert!(LatencyHistogram::bucket_upper_bound(bucket_index) >= value);
+            last_bucket_index = bucket_index;
+        }
+
+        assert_eq!(u64::MAX, LatencyHistogram::bucket_upper_bound(LatencyHistogram::bucket_index(u64::MAX)));
+    }
+
+    #[test]
+    fn latency_histogram_should_calculate_percentiles_and_deviations() {
+        let mut histogram = LatencyHistogram::new();
+        for latency_in_ms in 1..=1000u64 {
+            histogram.record(&Duration::from_millis(latency_in_ms));
+        }
+
+        // Relative error of the percentile values should be below 1/64.
+        for (percentile, expected_value_in_us) in [(50.0, 500000.0), (90.0, 900000.0), (99.0, 990000.0), (99.9, 999000.0), (100.0, 1000000.0)] {
+            let value_in_us = histogram.value_at_percentile(percentile) as f64;
+            assert!(value_in_us >= expected_value_in_us && value_in_us <= expected_value_in_us * (1.0 + 1.0 / 64.0));
+        }
+        assert_eq!(1000000, histogram.value_at_percentile(100.0));
+
+        // Standard deviation of 1..=1000 is sqrt((1000^2 - 1) / 12) ~= 288.67.
+        assert!((histogram.standard_deviation_in_us() / 1000.0 - 288.67).abs() < 0.01);
+        assert_eq!(1000.0, histogram.jitter_in_us());
+    }
+}
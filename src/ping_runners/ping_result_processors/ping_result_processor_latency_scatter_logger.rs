@@ -1,5 +1,6 @@
 use crate::*;
 use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tracing;
 
@@ -14,13 +15,13 @@ struct LatencyHits {
 
 pub struct PingResultProcessorLatencyScatterLogger {
     common_config: Arc<PingResultProcessorCommonConfig>,
-    ping_history: Vec<BTreeMap<usize, LatencyHits>>,
+    ping_history: BTreeMap<SocketAddr, Vec<BTreeMap<usize, LatencyHits>>>,
 }
 
 impl PingResultProcessorLatencyScatterLogger {
     #[tracing::instrument(name = "Creating ping result latency scatter logger", level = "debug")]
     pub fn new(common_config: Arc<PingResultProcessorCommonConfig>) -> PingResultProcessorLatencyScatterLogger {
-        return PingResultProcessorLatencyScatterLogger { common_config, ping_history: vec![BTreeMap::new()] };
+        return PingResultProcessorLatencyScatterLogger { common_config, ping_history: BTreeMap::new() };
     }
 
     fn get_ping_history_item_pos(&self, port: u32) -> (usize, usize) {
@@ -76,19 +77,25 @@ impl PingResultProcessor for PingResultProcessorLatencyScatterLogger {
             return;
         }
 
+        // Skip reconnects, since their RTT is only the connect time of the new connection.
+        if ping_result.is_reconnect() {
+            return;
+        }
+
         let (row, col) = self.get_ping_history_item_pos(ping_result.source().port() as u32);
         let bit_mask_bit = 1 << col;
 
-        // Find the last iteration and update the result.
+        // Find the last iteration of the target and update the result.
+        let target_ping_history = self.ping_history.entry(ping_result.target()).or_insert_with(|| vec![BTreeMap::new()]);
         loop {
-            let last_iteration = self.ping_history.last_mut().expect("Ping history should always be non-empty.");
+            let last_iteration = target_ping_history.last_mut().expect("Ping history should always be non-empty.");
 
             let last_iteration_results = last_iteration.entry(row).or_insert(LatencyHits { bitmask: 0, results: vec![f64::NAN; COUNT_PER_ROW] });
 
             // If the source port is already tested in the last iteration, it means a new iteration is started,
             // hence create a new iteration and update there.
             if last_iteration_results.bitmask & bit_mask_bit != 0 {
-                self.ping_history.push(BTreeMap::new());
+                target_ping_history.push(BTreeMap::new());
                 continue;
             }
 
@@ -109,25 +116,32 @@ impl PingResultProcessor for PingResultProcessorLatencyScatterLogger {
 
         println!("\n=== Latency scatter map (in milliseconds) ===\n");
 
-        println!(
-            "{:>7} | {:>8} | {} (\"{}\" = Fail, \"{}\" = Not Tested)",
-            "Iter #",
-            "Src Port",
-            "Results",
-            SCATTER_SYMBOL_FAILED.trim(),
-            SCATTER_SYMBOL_NOT_TESTED.trim()
-        );
-        println!(
-            "{:->9}{:->11}-{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}",
-            "+", "+", 0, 1, 2, 3, 4, 5, 6, 7, 8, 9
-        );
-
-        for (iteration_index, iteration) in self.ping_history.iter().enumerate() {
-            for (port_bucket, latency_hits) in iteration {
-                print!("{:>7} | {:>8} | ", iteration_index, port_bucket);
+        let show_target = self.ping_history.len() > 1;
+        for (target, target_ping_history) in &self.ping_history {
+            if show_target {
+                println!("\n--- Target: {} ---\n", target);
+            }
 
-                let result = PingResultProcessorLatencyScatterLogger::convert_latency_hits_to_string(latency_hits);
-                println!("{}", result);
+            println!(
+                "{:>7} | {:>8} | {} (\"{}\" = Fail, \"{}\" = Not Tested)",
+                "Iter #",
+                "Src Port",
+                "Results",
+                SCATTER_SYMBOL_FAILED.trim(),
+                SCATTER_SYMBOL_NOT_TESTED.trim()
+            );
+            println!(
+                "{:->9}{:->11}-{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}{:-^9.2}",
+                "+", "+", 0, 1, 2, 3, 4, 5, 6, 7, 8, 9
+            );
+
+            for (iteration_index, iteration) in target_ping_history.iter().enumerate() {
+                for (port_bucket, latency_hits) in iteration {
+                    print!("{:>7} | {:>8} | ", iteration_index, port_bucket);
+
+                    let result = PingResultProcessorLatencyScatterLogger::convert_latency_hits_to_string(latency_hits);
+                    println!("{}", result);
+                }
             }
         }
     }
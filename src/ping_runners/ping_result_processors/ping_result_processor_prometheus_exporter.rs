@@ -0,0 +1,386 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing;
+
+// Upper bounds of the RTT histogram buckets in seconds, which covers the latencies from same rack to cross continent.
+const PROMETHEUS_RTT_BUCKETS_IN_SECS: [f64; 14] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// Scrape requests are tiny, so anything larger or slower than this is not a Prometheus scraper.
+const PROMETHEUS_MAX_REQUEST_HEAD_SIZE: usize = 8192;
+const PROMETHEUS_REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct PingResultProcessorPrometheusExporter {
+    common_config: Arc<PingResultProcessorCommonConfig>,
+    listen_address: SocketAddr,
+    listener: Option<std::net::TcpListener>,
+    metrics: Arc<Mutex<PrometheusMetrics>>,
+    server_task: Option<JoinHandle<()>>,
+}
+
+impl PingResultProcessorPrometheusExporter {
+    #[tracing::instrument(name = "Creating ping result prometheus exporter", level = "debug")]
+    pub fn new(
+        common_config: Arc<PingResultProcessorCommonConfig>,
+        listen_address: &SocketAddr,
+    ) -> Result<PingResultProcessorPrometheusExporter, RnpError> {
+        // Bind the address here instead of inside the server task, so a bad address fails the run right away, the same as a bad log path.
+        let listener = std::net::TcpListener::bind(listen_address)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|source| RnpError::MetricsServerStartFailed { address: *listen_address, source })?;
+
+        return Ok(PingResultProcessorPrometheusExporter {
+            common_config,
+            listen_address: *listen_address,
+            listener: Some(listener),
+            metrics: Arc::new(Mutex::new(PrometheusMetrics::new())),
+            server_task: None,
+        });
+    }
+
+    #[tracing::instrument(name = "Running prometheus metrics server", level = "debug", skip(listener, metrics))]
+    async fn run_metrics_server(listener: TcpListener, metrics: Arc<Mutex<PrometheusMetrics>>) {
+        loop {
+            let (stream, peer_address) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("Failed to accept prometheus scrape connection: Error = {}", e);
+                    continue;
+                }
+            };
+
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                if let Err(e) = PingResultProcessorPrometheusExporter::serve_scrape_request(stream, metrics).await {
+                    tracing::debug!("Failed to serve prometheus scrape request: Peer = {}, Error = {}", peer_address, e);
+                }
+            });
+        }
+    }
+
+    async fn serve_scrape_request(mut stream: TcpStream, metrics: Arc<Mutex<PrometheusMetrics>>) -> std::io::Result<()> {
+        let request_head = match tokio::time::timeout(
+            PROMETHEUS_REQUEST_READ_TIMEOUT,
+            PingResultProcessorPrometheusExporter::read_request_head(&mut stream),
+        )
+        .await
+        {
+            Ok(result) => result?,
+            Err(_) => return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "Reading scrape request timed out.")),
+        };
+
+        // Only the request line matters to us, e.g. "GET /metrics HTTP/1.1".
+        let request_line = request_head.lines().next().unwrap_or_default();
+        let mut request_line_parts = request_line.split(' ');
+        let method = request_line_parts.next().unwrap_or_default();
+        let path = request_line_parts.next().unwrap_or_default().split('?').next().unwrap_or_default();
+
+        let response = if method == "GET" && path == "/metrics" {
+            let body = metrics.lock().unwrap().format_as_prometheus_text();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        } else {
+            String::from("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+        };
+
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+        return Ok(());
+    }
+
+    async fn read_request_head(stream: &mut TcpStream) -> std::io::Result<String> {
+        let mut request_head = Vec::new();
+        let mut read_buffer = vec![0u8; 1024];
+        while !request_head.windows(4).any(|w| w == b"\r\n\r\n") {
+            let read_size = stream.read(&mut read_buffer).await?;
+            if read_size == 0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Connection closed before request head is received."));
+            }
+
+            request_head.extend_from_slice(&read_buffer[..read_size]);
+            if request_head.len() > PROMETHEUS_MAX_REQUEST_HEAD_SIZE {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Request head is too large."));
+            }
+        }
+
+        return Ok(String::from_utf8_lossy(&request_head).to_string());
+    }
+}
+
+impl PingResultProcessor for PingResultProcessorPrometheusExporter {
+    fn name(&self) -> &'static str {
+        "PrometheusExporter"
+    }
+    fn config(&self) -> &PingResultProcessorCommonConfig {
+        self.common_config.as_ref()
+    }
+
+    fn initialize(&mut self) {
+        let listener = match self.listener.take().map(TcpListener::from_std) {
+            Some(Ok(listener)) => listener,
+            Some(Err(e)) => {
+                tracing::error!("Failed to start prometheus metrics server! Address = {}, Error = {}", self.listen_address, e);
+                return;
+            }
+            None => return,
+        };
+
+        tracing::debug!("Prometheus metrics server started: Address = {}", self.listen_address);
+        self.server_task = Some(tokio::spawn(PingResultProcessorPrometheusExporter::run_metrics_server(listener, self.metrics.clone())));
+    }
+
+    fn process_ping_result(&mut self, ping_result: &PingResult) {
+        if !ping_result.is_counted_in_statistics() {
+            return;
+        }
+
+        self.metrics.lock().unwrap().update(ping_result);
+    }
+
+    fn rundown(&mut self) {
+        if let Some(server_task) = self.server_task.take() {
+            server_task.abort();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum PrometheusResultClass {
+    Success,
+    Timeout,
+    Failure,
+}
+
+impl PrometheusResultClass {
+    fn from_ping_result(ping_result: &PingResult) -> PrometheusResultClass {
+        if ping_result.is_timed_out() {
+            return PrometheusResultClass::Timeout;
+        }
+
+        // Reachable targets with warnings, such as app handshake failures, are still counted as succeeded, the same as the console summary.
+        if ping_result.is_succeeded() {
+            return PrometheusResultClass::Success;
+        }
+
+        return PrometheusResultClass::Failure;
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            PrometheusResultClass::Success => "success",
+            PrometheusResultClass::Timeout => "timeout",
+            PrometheusResultClass::Failure => "failure",
+        }
+    }
+}
+
+struct PrometheusSeriesMetrics {
+    ping_count: u64,
+    rtt_bucket_counts: Vec<u64>,
+    rtt_count: u64,
+    rtt_sum_in_secs: f64,
+}
+
+impl PrometheusSeriesMetrics {
+    fn new() -> PrometheusSeriesMetrics {
+        return PrometheusSeriesMetrics {
+            ping_count: 0,
+            rtt_bucket_counts: vec![0; PROMETHEUS_RTT_BUCKETS_IN_SECS.len()],
+            rtt_count: 0,
+            rtt_sum_in_secs: 0.0,
+        };
+    }
+}
+
+struct PrometheusMetrics {
+    series: BTreeMap<(&'static str, SocketAddr, PrometheusResultClass), PrometheusSeriesMetrics>,
+}
+
+impl PrometheusMetrics {
+    fn new() -> PrometheusMetrics {
+        return PrometheusMetrics { series: BTreeMap::new() };
+    }
+
+    fn update(&mut self, ping_result: &PingResult) {
+        let result_class = PrometheusResultClass::from_ping_result(ping_result);
+        let series_key = (ping_result.protocol(), ping_result.target(), result_class);
+        let series_metrics = self.series.entry(series_key).or_insert_with(PrometheusSeriesMetrics::new);
+        series_metrics.ping_count += 1;
+
+        // The RTT of timed out pings is only the wait timeout, and the RTT of failed pings might not be set at all.
+        let rtt_in_secs = ping_result.round_trip_time().as_secs_f64();
+        if result_class == PrometheusResultClass::Timeout || rtt_in_secs == 0.0 {
+            return;
+        }
+
+        if let Some(bucket_index) = PROMETHEUS_RTT_BUCKETS_IN_SECS.iter().position(|upper_bound| rtt_in_secs <= *upper_bound) {
+            series_metrics.rtt_bucket_counts[bucket_index] += 1;
+        }
+        series_metrics.rtt_count += 1;
+        series_metrics.rtt_sum_in_secs += rtt_in_secs;
+    }
+
+    fn format_as_prometheus_text(&self) -> String {
+        let mut text = String::new();
+
+        // Total ping count is aggregated from all result classes, so it can be used as the denominator of success rate.
+        let mut ping_counts: BTreeMap<(&str, SocketAddr), u64> = BTreeMap::new();
+        for ((protocol, target, _), series_metrics) in &self.series {
+            *ping_counts.entry((*protocol, *target)).or_insert(0) += series_metrics.ping_count;
+        }
+
+        text.push_str("# HELP rnp_pings_total Total number of pings sent, excluding warmup pings.\n");
+        text.push_str("# TYPE rnp_pings_total counter\n");
+        for ((protocol, target), ping_count) in &ping_counts {
+            let _ = writeln!(text, "rnp_pings_total{{protocol=\"{}\",target=\"{}\"}} {}", escape_label_value(protocol), target, ping_count);
+        }
+
+        text.push_str("# HELP rnp_ping_results_total Total number of ping results by result class.\n");
+        text.push_str("# TYPE rnp_ping_results_total counter\n");
+        for ((protocol, target, result_class), series_metrics) in &self.series {
+            let _ = writeln!(text, "rnp_ping_results_total{{{}}} {}", format_labels(protocol, target, result_class), series_metrics.ping_count);
+        }
+
+        text.push_str("# HELP rnp_ping_rtt_seconds Round trip time of the pings that are not timed out.\n");
+        text.push_str("# TYPE rnp_ping_rtt_seconds histogram\n");
+        for ((protocol, target, result_class), series_metrics) in &self.series {
+            // Timed out pings never carry an RTT, and neither do most failed pings, so these series would only be zeros. Since the
+            // counts never go down, a series shows up on its first RTT and stays from then on.
+            if series_metrics.rtt_count == 0 {
+                continue;
+            }
+
+            let labels = format_labels(protocol, target, result_class);
+
+            let mut cumulative_count = 0;
+            for (upper_bound, bucket_count) in PROMETHEUS_RTT_BUCKETS_IN_SECS.iter().zip(series_metrics.rtt_bucket_counts.iter()) {
+                cumulative_count += bucket_count;
+                let _ = writeln!(text, "rnp_ping_rtt_seconds_bucket{{{},le=\"{}\"}} {}", labels, upper_bound, cumulative_count);
+            }
+            let _ = writeln!(text, "rnp_ping_rtt_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, series_metrics.rtt_count);
+            let _ = writeln!(text, "rnp_ping_rtt_seconds_sum{{{}}} {}", labels, series_metrics.rtt_sum_in_secs);
+            let _ = writeln!(text, "rnp_ping_rtt_seconds_count{{{}}} {}", labels, series_metrics.rtt_count);
+        }
+
+        return text;
+    }
+}
+
+fn format_labels(protocol: &str, target: &SocketAddr, result_class: &PrometheusResultClass) -> String {
+    return format!("protocol=\"{}\",target=\"{}\",result=\"{}\"", escape_label_value(protocol), target, result_class.label());
+}
+
+// External protocols can have any name, so we escape the label values as the prometheus text format requires.
+fn escape_label_value(value: &str) -> String {
+    return value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ping_result_processors::ping_result_processor_test_common;
+    use crate::rnp_test_common;
+    use pretty_assertions::assert_eq;
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn prometheus_metrics_should_be_formatted_in_prometheus_text_format() {
+        let mut metrics = PrometheusMetrics::new();
+        rnp_test_common::generate_ping_result_test_samples().iter().filter(|x| x.is_counted_in_statistics()).for_each(|x| metrics.update(x));
+
+        let expected_text = vec![
+            "# HELP rnp_pings_total Total number of pings sent, excluding warmup pings.",
+            "# TYPE rnp_pings_total counter",
+            "rnp_pings_total{protocol=\"TCP\",target=\"1.2.3.4:443\"} 4",
+            "# HELP rnp_ping_results_total Total number of ping results by result class.",
+            "# TYPE rnp_ping_results_total counter",
+            "rnp_ping_results_total{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\"} 2",
+            "rnp_ping_results_total{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"timeout\"} 1",
+            "rnp_ping_results_total{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"failure\"} 1",
+            "# HELP rnp_ping_rtt_seconds Round trip time of the pings that are not timed out.",
+            "# TYPE rnp_ping_rtt_seconds histogram",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.0005\"} 0",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.001\"} 0",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.0025\"} 0",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.005\"} 0",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.01\"} 0",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.025\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.05\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.1\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.25\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"0.5\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"1\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"2.5\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"5\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"10\"} 2",
+            "rnp_ping_rtt_seconds_bucket{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\",le=\"+Inf\"} 2",
+            "rnp_ping_rtt_seconds_sum{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\"} 0.04",
+            "rnp_ping_rtt_seconds_count{protocol=\"TCP\",target=\"1.2.3.4:443\",result=\"success\"} 2",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(expected_text, metrics.format_as_prometheus_text());
+    }
+
+    #[test]
+    fn prometheus_exporter_should_serve_metrics_endpoint() {
+        rnp_test_common::initialize();
+        let rt = Runtime::new().unwrap();
+        let _runtime_guard = rt.enter();
+
+        let listen_address = "127.0.0.1:11737".parse::<SocketAddr>().unwrap();
+        let mut processor: Box<dyn PingResultProcessor + Send + Sync> = Box::new(
+            PingResultProcessorPrometheusExporter::new(
+                Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+                &listen_address,
+            )
+            .unwrap(),
+        );
+        processor.initialize();
+        rnp_test_common::generate_ping_result_test_samples().iter().for_each(|x| processor.process_ping_result(x));
+
+        rt.block_on(async move {
+            let metrics_response = send_scrape_request(&listen_address, "/metrics").await;
+            assert!(metrics_response.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(metrics_response.contains("rnp_pings_total{protocol=\"TCP\",target=\"1.2.3.4:443\"} 4\n"));
+
+            let not_found_response = send_scrape_request(&listen_address, "/").await;
+            assert!(not_found_response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        });
+
+        processor.rundown();
+    }
+
+    #[test]
+    fn prometheus_exporter_should_work_with_test_samples() {
+        let rt = Runtime::new().unwrap();
+        let _runtime_guard = rt.enter();
+
+        let mut processor: Box<dyn PingResultProcessor + Send + Sync> = Box::new(
+            PingResultProcessorPrometheusExporter::new(
+                Arc::new(PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NO_OUTPUT }),
+                &"127.0.0.1:0".parse().unwrap(),
+            )
+            .unwrap(),
+        );
+        ping_result_processor_test_common::run_ping_result_processor_with_test_samples(&mut processor);
+    }
+
+    async fn send_scrape_request(listen_address: &SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(listen_address).await.unwrap();
+        stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        return response;
+    }
+}
@@ -1,34 +1,105 @@
 use crate::*;
 use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tracing;
 
-const COUNT_PER_ROW: u32 = 20;
-const SCATTER_SYMBOL_NOT_TESTED_YET: char = '.';
-const SCATTER_SYMBOL_PASSED: char = 'O';
-const SCATTER_SYMBOL_FAILED: char = 'X';
-const SCATTER_SYMBOL_PREPARE_FAILED: char = '-';
-const SCATTER_SYMBOL_HANDSHAKE_FAILED: char = 'H';
-const SCATTER_SYMBOL_DISCONNECT_FAILED: char = 'D';
+pub(crate) const COUNT_PER_ROW: u32 = 20;
+pub(crate) const SCATTER_SYMBOL_NOT_TESTED_YET: char = '.';
+pub(crate) const SCATTER_SYMBOL_PASSED: char = 'O';
+pub(crate) const SCATTER_SYMBOL_FAILED: char = 'X';
+pub(crate) const SCATTER_SYMBOL_PREPARE_FAILED: char = '-';
+pub(crate) const SCATTER_SYMBOL_HANDSHAKE_FAILED: char = 'H';
+pub(crate) const SCATTER_SYMBOL_DISCONNECT_FAILED: char = 'D';
+pub(crate) const SCATTER_SYMBOL_RECONNECTED: char = 'R';
 
 pub struct PingResultProcessorResultScatterLogger {
     common_config: Arc<PingResultProcessorCommonConfig>,
-    ping_history: Vec<BTreeMap<u32, Vec<char>>>,
+    scatter_map: ResultScatterMap,
+}
+
+// Results of each target, split into iterations. A new iteration starts whenever a source port is used again, and each iteration is
+// stored as rows of COUNT_PER_ROW source ports.
+pub(crate) struct ResultScatterMap {
+    ping_history: BTreeMap<SocketAddr, Vec<BTreeMap<u32, Vec<char>>>>,
+}
+
+impl ResultScatterMap {
+    pub(crate) fn new() -> ResultScatterMap {
+        return ResultScatterMap { ping_history: BTreeMap::new() };
+    }
+
+    pub(crate) fn update(&mut self, ping_result: &PingResult) {
+        // Skip warmup pings in analysis.
+        if ping_result.is_warmup() {
+            return;
+        }
+
+        let (row, index) = PingResultProcessorResultScatterLogger::get_ping_history_position(ping_result.source().port() as u32);
+        let result = PingResultProcessorResultScatterLogger::result_symbol(ping_result);
+
+        // Find the last iteration of the target and update the result.
+        let target_ping_history = self.ping_history.entry(ping_result.target()).or_insert_with(|| vec![BTreeMap::new()]);
+        loop {
+            let last_iteration = target_ping_history.last_mut().expect("Ping history should always be non-empty.");
+
+            let last_iteration_results = last_iteration.entry(row).or_insert(vec![SCATTER_SYMBOL_NOT_TESTED_YET; COUNT_PER_ROW as usize]);
+
+            // If the source port is already tested in the last iteration, it means a new iteration is started,
+            // hence create a new iteration and update there.
+            if last_iteration_results[index] != SCATTER_SYMBOL_NOT_TESTED_YET {
+                target_ping_history.push(BTreeMap::new());
+                continue;
+            }
+
+            last_iteration_results[index] = result;
+
+            break;
+        }
+    }
+
+    pub(crate) fn target_count(&self) -> usize {
+        return self.ping_history.len();
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&SocketAddr, &Vec<BTreeMap<u32, Vec<char>>>)> {
+        return self.ping_history.iter();
+    }
 }
 
 impl PingResultProcessorResultScatterLogger {
     #[tracing::instrument(name = "Creating ping result result scatter logger", level = "debug")]
     pub fn new(common_config: Arc<PingResultProcessorCommonConfig>) -> PingResultProcessorResultScatterLogger {
-        return PingResultProcessorResultScatterLogger { common_config, ping_history: vec![BTreeMap::new()] };
+        return PingResultProcessorResultScatterLogger { common_config, scatter_map: ResultScatterMap::new() };
     }
 
-    fn get_ping_history_position(&self, port: u32) -> (u32, usize) {
+    pub(crate) fn get_ping_history_position(port: u32) -> (u32, usize) {
         let row: u32 = (port / COUNT_PER_ROW) * COUNT_PER_ROW;
         let index = port % COUNT_PER_ROW;
         return (row, index as usize);
     }
 
-    fn convert_result_hits_to_string(hits: &Vec<char>) -> String {
+    // Timed out pings don't have any error, so they are shown as failed as well.
+    pub(crate) fn result_symbol(ping_result: &PingResult) -> char {
+        return if let Some(e) = ping_result.error() {
+            match e {
+                PingClientError::PreparationFailed(_) => SCATTER_SYMBOL_PREPARE_FAILED,
+                PingClientError::PingFailed(_) => SCATTER_SYMBOL_FAILED,
+            }
+        } else if let Some(e) = ping_result.warning() {
+            match e {
+                PingClientWarning::AppHandshakeFailed(_) => SCATTER_SYMBOL_HANDSHAKE_FAILED,
+                PingClientWarning::DisconnectFailed(_) => SCATTER_SYMBOL_DISCONNECT_FAILED,
+                PingClientWarning::Reconnected(_) => SCATTER_SYMBOL_RECONNECTED,
+            }
+        } else if ping_result.is_timed_out() {
+            SCATTER_SYMBOL_FAILED
+        } else {
+            SCATTER_SYMBOL_PASSED
+        };
+    }
+
+    pub(crate) fn convert_result_hits_to_string(hits: &Vec<char>) -> String {
         let mut s: String = String::new();
 
         for index in 0..COUNT_PER_ROW {
@@ -56,43 +127,7 @@ impl PingResultProcessor for PingResultProcessorResultScatterLogger {
             return;
         }
 
-        // Skip warmup pings in analysis.
-        if ping_result.is_warmup() {
-            return;
-        }
-
-        let (row, index) = self.get_ping_history_position(ping_result.source().port() as u32);
-        let result = if let Some(e) = ping_result.error() {
-            match e {
-                PingClientError::PreparationFailed(_) => SCATTER_SYMBOL_PREPARE_FAILED,
-                PingClientError::PingFailed(_) => SCATTER_SYMBOL_FAILED,
-            }
-        } else if let Some(e) = ping_result.warning() {
-            match e {
-                PingClientWarning::AppHandshakeFailed(_) => SCATTER_SYMBOL_HANDSHAKE_FAILED,
-                PingClientWarning::DisconnectFailed(_) => SCATTER_SYMBOL_DISCONNECT_FAILED,
-            }
-        } else {
-            SCATTER_SYMBOL_PASSED
-        };
-
-        // Find the last iteration and update the result.
-        loop {
-            let last_iteration = self.ping_history.last_mut().expect("Ping history should always be non-empty.");
-
-            let last_iteration_results = last_iteration.entry(row).or_insert(vec![SCATTER_SYMBOL_NOT_TESTED_YET; COUNT_PER_ROW as usize]);
-
-            // If the source port is already tested in the last iteration, it means a new iteration is started,
-            // hence create a new iteration and update there.
-            if last_iteration_results[index] != SCATTER_SYMBOL_NOT_TESTED_YET {
-                self.ping_history.push(BTreeMap::new());
-                continue;
-            }
-
-            last_iteration_results[index] = result;
-
-            break;
-        }
+        self.scatter_map.update(ping_result);
     }
 
     fn rundown(&mut self) {
@@ -102,20 +137,34 @@ impl PingResultProcessor for PingResultProcessorResultScatterLogger {
 
         println!("\n=== Ping result scatter map ===");
         println!(
-            "(\"{}\" = Ok, \"{}\" = Fail, \"{}\" = Not tested yet, \"{}\" = Preparation failed, \"{}\" = App handshake failed, \"{}\" = Disconnect failed)",
-            SCATTER_SYMBOL_PASSED, SCATTER_SYMBOL_FAILED, SCATTER_SYMBOL_NOT_TESTED_YET, SCATTER_SYMBOL_PREPARE_FAILED, SCATTER_SYMBOL_HANDSHAKE_FAILED, SCATTER_SYMBOL_DISCONNECT_FAILED
+            "(\"{}\" = Ok, \"{}\" = Fail, \"{}\" = Not tested yet, \"{}\" = Preparation failed, \"{}\" = App handshake failed, \"{}\" = Disconnect failed, \"{}\" = Reconnected)",
+            SCATTER_SYMBOL_PASSED,
+            SCATTER_SYMBOL_FAILED,
+            SCATTER_SYMBOL_NOT_TESTED_YET,
+            SCATTER_SYMBOL_PREPARE_FAILED,
+            SCATTER_SYMBOL_HANDSHAKE_FAILED,
+            SCATTER_SYMBOL_DISCONNECT_FAILED,
+            SCATTER_SYMBOL_RECONNECTED
         );
 
-        println!("\n{:>5} | {:>5} | {}", "Iter", "Src", "Results");
-        println!("{:>5} | {:>5} | ", "#", "Port");
-        println!("{:->6}|{:->8}-0---4-5---9-0---4-5---9-", "", "+");
+        // With multiple targets, each target gets its own map, since the same source port is used for different targets.
+        let show_target = self.scatter_map.target_count() > 1;
+        for (target, target_ping_history) in self.scatter_map.iter() {
+            if show_target {
+                println!("\n--- Target: {} ---", target);
+            }
+
+            println!("\n{:>5} | {:>5} | {}", "Iter", "Src", "Results");
+            println!("{:>5} | {:>5} | ", "#", "Port");
+            println!("{:->6}|{:->8}-0---4-5---9-0---4-5---9-", "", "+");
 
-        for (iteration_index, iteration) in self.ping_history.iter().enumerate() {
-            for (port_bucket, result_hits) in iteration {
-                print!("{:>5} | {:>5} | ", iteration_index, port_bucket);
+            for (iteration_index, iteration) in target_ping_history.iter().enumerate() {
+                for (port_bucket, result_hits) in iteration {
+                    print!("{:>5} | {:>5} | ", iteration_index, port_bucket);
 
-                let result = PingResultProcessorResultScatterLogger::convert_result_hits_to_string(result_hits);
-                println!("{}", result);
+                    let result = PingResultProcessorResultScatterLogger::convert_result_hits_to_string(result_hits);
+                    println!("{}", result);
+                }
             }
         }
     }
@@ -1,5 +1,6 @@
 use crate::*;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -9,12 +10,27 @@ pub struct PingResultProcessorTextLogger {
     common_config: Arc<PingResultProcessorCommonConfig>,
     log_path: PathBuf,
     log_file: File,
+
+    // Failing to write logs shouldn't stop the pings, so only the first failure is reported to avoid flooding the output.
+    has_write_failed: bool,
 }
 
 impl PingResultProcessorTextLogger {
     #[tracing::instrument(name = "Creating ping result text logger", level = "debug")]
-    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, log_path_buf: &PathBuf) -> PingResultProcessorTextLogger {
-        return PingResultProcessorTextLogger { common_config, log_path: log_path_buf.clone(), log_file: rnp_utils::create_log_file(log_path_buf) };
+    pub fn new(common_config: Arc<PingResultProcessorCommonConfig>, log_path_buf: &PathBuf) -> Result<PingResultProcessorTextLogger, RnpError> {
+        return Ok(PingResultProcessorTextLogger {
+            common_config,
+            log_path: log_path_buf.clone(),
+            log_file: rnp_utils::create_log_file(log_path_buf)?,
+            has_write_failed: false,
+        });
+    }
+
+    fn log_result_as_text(&mut self, ping_result: &PingResult) -> io::Result<()> {
+        let log_content: String = ping_result.format_as_console_log();
+        self.log_file.write_all(log_content.as_bytes())?;
+        self.log_file.write_all("\n".as_bytes())?;
+        return Ok(());
     }
 }
 
@@ -27,8 +43,11 @@ impl PingResultProcessor for PingResultProcessorTextLogger {
     }
 
     fn process_ping_result(&mut self, ping_result: &PingResult) {
-        let log_content: String = ping_result.format_as_console_log();
-        self.log_file.write(log_content.as_bytes()).expect(&format!("Failed to write logs to text file! Path = {}", self.log_path.display()));
-        self.log_file.write("\n".as_bytes()).expect(&format!("Failed to write logs to text file! Path = {}", self.log_path.display()));
+        if let Err(e) = self.log_result_as_text(ping_result) {
+            if !self.has_write_failed {
+                self.has_write_failed = true;
+                tracing::error!("Failed to write logs to text file! Path = {}, Error = {}", self.log_path.display(), e);
+            }
+        }
     }
 }
@@ -1,12 +1,18 @@
+use crate::ping_result_processors::ping_result_processor_factory;
 use crate::*;
 use futures_intrusive::sync::ManualResetEvent;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use tokio::{sync::mpsc, task::JoinHandle};
 
 pub struct PingRunnerCore {
     config: RnpPingRunnerConfig,
 
     stop_event: Arc<ManualResetEvent>,
+    stop_reason: Arc<Mutex<Option<PingStopReason>>>,
+    duration_timer_join_handle: Option<JoinHandle<()>>,
+    rate_scheduler_join_handle: Option<JoinHandle<()>>,
     worker_join_handles: Vec<JoinHandle<()>>,
     ping_result_processor_stop_event: Arc<ManualResetEvent>,
     ping_result_processor_join_handle: Option<JoinHandle<()>>,
@@ -21,12 +27,13 @@ impl PingRunnerCore {
     /// * `config`: The configuration of Rnp.
     /// * `stop_event`: The event to signal Rnp to stop.
     ///
-    /// returns: RnpCore
+    /// returns: RnpCore, or the error if the config is invalid or any result processor cannot be created, e.g. a bad log path.
     ///
     /// # Examples
     ///
     /// ```
     /// use rnp::*;
+    /// use std::collections::BTreeMap;
     /// use std::ops::Range;
     /// use std::time::Duration;
     /// use std::sync::Arc;
@@ -36,8 +43,9 @@ impl PingRunnerCore {
     /// let config = RnpPingRunnerConfig {
     ///     worker_config: PingWorkerConfig {
     ///         protocol: RnpSupportedProtocol::TCP,
-    ///         target: "10.0.0.1:443".parse().unwrap(),
+    ///         targets: vec!["10.0.0.1:443".parse().unwrap(), "www.example.com:443".parse().unwrap()],
     ///         source_ip: "10.0.0.2".parse().unwrap(),
+    ///         resolve_on_every_ping: false,
     ///         ping_interval: Duration::from_millis(1500),
     ///         ping_client_config: PingClientConfig {
     ///             wait_timeout: Duration::from_millis(1000),
@@ -49,6 +57,14 @@ impl PingRunnerCore {
     ///             log_tls_key: false,
     ///             alpn_protocol: None,
     ///             use_timer_rtt: false,
+    ///             http_method: String::from("GET"),
+    ///             http_path: String::from("/"),
+    ///             http_headers: vec![],
+    ///             http_expected_status: None,
+    ///             http_body_regex: None,
+    ///             measure_one_way_latency: false,
+    ///             use_persistent_connection: false,
+    ///             protocol_options: BTreeMap::new(),
     ///         },
     ///     },
     ///     worker_scheduler_config: PingWorkerSchedulerConfig {
@@ -56,8 +72,14 @@ impl PingRunnerCore {
     ///             ranges: vec![(1024..=2048), (3096..=3096), (3097..=3097)]
     ///         },
     ///         ping_count: Some(4),
+    ///         ping_until_stopped: false,
     ///         warmup_count: 1,
     ///         parallel_ping_count: 1,
+    ///         duration: None,
+    ///         stop_on_consecutive_failures: None,
+    ///         stop_on_recovery: None,
+    ///         rate: None,
+    ///         arrival_distribution: PingArrivalDistribution::Constant,
     ///     },
     ///     result_processor_config: PingResultProcessorConfig {
     ///         common_config: PingResultProcessorCommonConfig {
@@ -68,41 +90,58 @@ impl PingRunnerCore {
     ///         csv_log_path: None,
     ///         json_log_path: None,
     ///         text_log_path: None,
+    ///         html_report_path: None,
+    ///         metrics_address: None,
     ///         show_result_scatter: false,
     ///         show_latency_scatter: false,
+    ///         show_dashboard: false,
     ///         latency_buckets: None,
+    ///         latency_percentiles: None,
     ///     },
-    ///     external_ping_client_factory: None,
+    ///     ping_client_registry: PingClientRegistry::new(),
+    ///     external_ping_target_resolver: None,
     ///     extra_ping_result_processors: vec![],
     /// };
     ///
     /// let rt = Runtime::new().unwrap();
     /// rt.block_on(async {
     ///     let stop_event = Arc::new(ManualResetEvent::new(false));
-    ///     let core = PingRunnerCore::new(config, stop_event);
+    ///     let core = PingRunnerCore::new(config, stop_event).unwrap();
     /// });
     ///
     /// ```
     #[tracing::instrument(name = "Start running Rnp core", level = "debug", skip(stop_event))]
-    pub fn new(mut config: RnpPingRunnerConfig, stop_event: Arc<ManualResetEvent>) -> PingRunnerCore {
+    pub fn new(mut config: RnpPingRunnerConfig, stop_event: Arc<ManualResetEvent>) -> Result<PingRunnerCore, RnpError> {
+        config.validate()?;
+
+        // Ping clients are created in each ping worker, so we create one here first, which fails the run right away if the protocol is
+        // not supported, instead of failing every ping worker later.
+        ping_client_factory::new_ping_client(&config.worker_config.protocol, &config.worker_config.ping_client_config, &config.ping_client_registry)?;
+
         // Move all extra ping result processors into another Vec for initializing result processing worker.
         // Otherwise RnpCoreConfig will be partially moved and results in compile error.
         let mut extra_ping_result_processors = Vec::new();
         extra_ping_result_processors.append(&mut config.extra_ping_result_processors);
 
         let ping_result_processor_stop_event = Arc::new(ManualResetEvent::new(false));
+        let stop_reason = Arc::new(Mutex::new(None));
 
         let (result_sender, ping_result_processor_join_handle) = PingRunnerCore::create_ping_result_processing_worker(
             config.result_processor_config.clone(),
             extra_ping_result_processors,
             config.worker_scheduler_config.parallel_ping_count,
+            PingStopConditionChecker::new(&config.worker_scheduler_config),
+            stop_reason.clone(),
             ping_result_processor_stop_event.clone(),
             stop_event.clone(),
-        );
+        )?;
 
         let rnp_core = PingRunnerCore {
             config,
             stop_event,
+            stop_reason,
+            duration_timer_join_handle: None,
+            rate_scheduler_join_handle: None,
             worker_join_handles: Vec::new(),
             ping_result_processor_stop_event,
             ping_result_processor_join_handle: Some(ping_result_processor_join_handle),
@@ -111,27 +150,31 @@ impl PingRunnerCore {
 
         rnp_core.log_header_to_console();
 
-        return rnp_core;
+        return Ok(rnp_core);
     }
 
-    #[tracing::instrument(name = "Creating ping result processing worker", level = "debug", skip(extra_ping_result_processors))]
+    #[tracing::instrument(
+        name = "Creating ping result processing worker",
+        level = "debug",
+        skip(extra_ping_result_processors, stop_condition_checker, stop_reason)
+    )]
     fn create_ping_result_processing_worker(
         result_processor_config: PingResultProcessorConfig,
         extra_ping_result_processors: Vec<Box<dyn PingResultProcessor + Send + Sync>>,
         parallel_ping_count: u32,
+        stop_condition_checker: PingStopConditionChecker,
+        stop_reason: Arc<Mutex<Option<PingStopReason>>>,
         stop_event: Arc<ManualResetEvent>,
         ping_stop_event: Arc<ManualResetEvent>,
-    ) -> (mpsc::UnboundedSender<PingResult>, JoinHandle<()>) {
+    ) -> Result<(mpsc::UnboundedSender<PingResult>, JoinHandle<()>), RnpError> {
+        // Result processors are created here instead of in the processing worker, so errors like a bad log path can be returned.
+        let processors = ping_result_processor_factory::new(&result_processor_config, extra_ping_result_processors, ping_stop_event.clone())?;
+
         let (ping_result_sender, ping_result_receiver) = mpsc::unbounded_channel();
-        let ping_result_processor_join_handle = PingResultProcessingWorker::run(
-            Arc::new(result_processor_config),
-            extra_ping_result_processors,
-            stop_event,
-            ping_stop_event,
-            ping_result_receiver,
-        );
+        let ping_result_processor_join_handle =
+            PingResultProcessingWorker::run(processors, stop_condition_checker, stop_reason, stop_event, ping_stop_event, ping_result_receiver);
 
-        return (ping_result_sender, ping_result_processor_join_handle);
+        return Ok((ping_result_sender, ping_result_processor_join_handle));
     }
 
     fn log_header_to_console(&self) {
@@ -144,7 +187,13 @@ impl PingRunnerCore {
             None => "".to_string(),
         };
 
-        println!("Start testing {} {:?}{}:", self.config.worker_config.protocol, self.config.worker_config.target, ttl_message);
+        let targets = &self.config.worker_config.targets;
+        let target_message = match targets.len() {
+            1 => targets[0].to_string(),
+            _ => format!("{} targets", targets.len()),
+        };
+
+        println!("Start testing {} {}{}:", self.config.worker_config.protocol, target_message, ttl_message);
     }
 
     /// Run all warm up pings one by one and wait until they are all completed.
@@ -157,16 +206,17 @@ impl PingRunnerCore {
         }
 
         tracing::debug!("Creating warmup worker.");
-        let source_port_picker = Arc::new(Mutex::new(PingPortPicker::new(
-            Some(self.config.worker_scheduler_config.warmup_count),
-            self.config.worker_scheduler_config.source_ports.clone(),
-            0,
-        )));
+        let target_count = self.config.worker_config.targets.len();
+        let source_port_picker = Arc::new(Mutex::new(
+            PingPortPicker::new(Some(warmup_count * target_count as u32), self.config.worker_scheduler_config.source_ports.clone(), 0)
+                .with_target_count(target_count),
+        ));
 
         let mut worker_join_handles = self.create_ping_workers_with_options(
             1, // Warmup always use only 1 worker.
             source_port_picker,
             true,
+            None, // Warmup pings always run one by one, so they are never rate scheduled.
         );
 
         tracing::debug!("Waiting for warmup worker to stop.");
@@ -188,17 +238,57 @@ impl PingRunnerCore {
         // When doing normal pings, we need to skip the ports we have used for warmups, because we
         // need to give them time for OS to recycle the ports. If we use them again immediately,
         // we might see TCP connect retry causing 1 extra second delay on the TTL.
-        let warmup_count = self.config.worker_scheduler_config.warmup_count;
-        let adjusted_ping_count = match self.config.worker_scheduler_config.ping_count {
+        // Ping count and warmup count are both per target, and pings are spread across all targets in turn.
+        let target_count = self.config.worker_config.targets.len() as u32;
+        let warmup_count = self.config.worker_scheduler_config.warmup_count * target_count;
+        let scheduler_config = &self.config.worker_scheduler_config;
+        let ping_count = if scheduler_config.ping_until_stopped { None } else { Some(scheduler_config.ping_count.unwrap_or(RNP_DEFAULT_PING_COUNT)) };
+        let adjusted_ping_count = match ping_count {
             None => None, // None means pings forever (infinite), hence infinite + warmup count = infinite.
-            Some(ping_count) => Some(ping_count + warmup_count),
+            Some(ping_count) => Some(ping_count * target_count + warmup_count),
         };
 
-        let source_port_picker =
-            Arc::new(Mutex::new(PingPortPicker::new(adjusted_ping_count, self.config.worker_scheduler_config.source_ports.clone(), warmup_count)));
+        let source_port_picker = Arc::new(Mutex::new(
+            PingPortPicker::new(adjusted_ping_count, self.config.worker_scheduler_config.source_ports.clone(), warmup_count)
+                .with_target_count(target_count as usize),
+        ));
 
         let worker_count = self.config.worker_scheduler_config.parallel_ping_count;
-        self.worker_join_handles = self.create_ping_workers_with_options(worker_count, source_port_picker, false);
+        let ping_permits = self.start_rate_scheduler(worker_count);
+        self.worker_join_handles = self.create_ping_workers_with_options(worker_count, source_port_picker, false, ping_permits);
+
+        if let Some(duration) = self.config.worker_scheduler_config.duration {
+            self.duration_timer_join_handle = Some(self.start_duration_timer(duration));
+        }
+    }
+
+    // In rate mode, the ping workers wait for the permits released by the rate scheduler instead of sleeping between pings.
+    fn start_rate_scheduler(&mut self, worker_count: u32) -> Option<Arc<Semaphore>> {
+        let rate = self.config.worker_scheduler_config.rate?;
+
+        let ping_permits = Arc::new(Semaphore::new(0));
+        self.rate_scheduler_join_handle = Some(PingRateScheduler::run(
+            rate,
+            self.config.worker_scheduler_config.arrival_distribution,
+            worker_count,
+            ping_permits.clone(),
+            self.stop_event.clone(),
+        ));
+
+        return Some(ping_permits);
+    }
+
+    // Duration only covers normal pings, so a slow warmup won't eat into the time we want to measure.
+    fn start_duration_timer(&self, duration: Duration) -> JoinHandle<()> {
+        let stop_event = self.stop_event.clone();
+        let stop_reason = self.stop_reason.clone();
+        return tokio::spawn(async move {
+            if tokio::time::timeout(duration, stop_event.wait()).await.is_err() {
+                tracing::debug!("Ping duration elapsed, signal all ping workers to exit: Duration = {:?}", duration);
+                stop_reason.lock().unwrap().get_or_insert(PingStopReason::DurationElapsed(duration));
+                stop_event.set();
+            }
+        });
     }
 
     fn create_ping_workers_with_options(
@@ -206,20 +296,27 @@ impl PingRunnerCore {
         worker_count: u32,
         source_port_picker: Arc<Mutex<PingPortPicker>>,
         is_warmup_worker: bool,
+        ping_permits: Option<Arc<Semaphore>>,
     ) -> Vec<JoinHandle<()>> {
         let mut worker_join_handles = Vec::new();
 
-        let shared_worker_config = Arc::new(self.config.worker_config.clone());
+        let ping_target_resolver: Arc<dyn PingTargetResolver + Send + Sync> = match &self.config.external_ping_target_resolver {
+            Some(resolver) => resolver.clone(),
+            None => Arc::new(PingTargetResolverSystem::new()),
+        };
+        let worker_context = PingWorkerContext {
+            config: Arc::new(self.config.worker_config.clone()),
+            ping_client_registry: self.config.ping_client_registry.clone(),
+            ping_target_resolver,
+            port_picker: source_port_picker,
+            stop_event: self.stop_event.clone(),
+            result_sender: self.result_sender.clone(),
+            is_warmup_worker,
+            ping_permits,
+        };
+
         for worker_id in 0..worker_count {
-            let worker_join_handle = PingWorker::run(
-                worker_id,
-                shared_worker_config.clone(),
-                self.config.external_ping_client_factory.clone(),
-                source_port_picker.clone(),
-                self.stop_event.clone(),
-                self.result_sender.clone(),
-                is_warmup_worker,
-            );
+            let worker_join_handle = PingWorker::run(worker_id, worker_context.clone());
             worker_join_handles.push(worker_join_handle);
         }
 
@@ -235,6 +332,15 @@ impl PingRunnerCore {
         }
         self.worker_join_handles.clear();
 
+        if let Some(duration_timer_join_handle) = self.duration_timer_join_handle.take() {
+            duration_timer_join_handle.abort();
+        }
+
+        // All workers are stopped, so no more pings will be issued by the rate scheduler.
+        if let Some(rate_scheduler_join_handle) = self.rate_scheduler_join_handle.take() {
+            rate_scheduler_join_handle.abort();
+        }
+
         // If all the ping jobs are finished, the workers will stop automatically.
         // In this case, the stop events won't be set, and we set it here to be safe.
         if !self.stop_event.is_set() {
@@ -250,4 +356,9 @@ impl PingRunnerCore {
         self.ping_result_processor_join_handle.take().unwrap().await.unwrap();
         tracing::debug!("Result processor stopped.");
     }
+
+    /// The stop condition that ends the pings early, if any. It is None when pings stop because ping count is reached or stop event is set.
+    pub fn stop_reason(&self) -> Option<PingStopReason> {
+        return self.stop_reason.lock().unwrap().clone();
+    }
 }
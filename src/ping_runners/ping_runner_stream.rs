@@ -0,0 +1,147 @@
+use crate::*;
+use futures_core::Stream;
+use futures_intrusive::sync::ManualResetEvent;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// The stream of ping results produced by a ping run, which ends after the run is completed and all results are processed.
+///
+/// Warmup pings are included as well, and they can be told apart with the `is_warmup` flag.
+pub struct PingResultStream<T> {
+    receiver: mpsc::UnboundedReceiver<T>,
+}
+
+impl PingResultStream<PingResultDto> {
+    /// Start running the pings in the background and return the results as a stream of `PingResultDto`, together with the
+    /// handle to stop or wait for the run. It must be called within a tokio runtime, and it fails if the ping runner cannot be created.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use rnp::*;
+    ///
+    /// async fn run_pings(config: RnpPingRunnerConfig) -> Result<Option<PingStopReason>, RnpError> {
+    ///     let (mut results, handle) = PingResultStream::start_with_dtos(config)?;
+    ///     while let Some(result) = results.next().await {
+    ///         println!("{}: {}ms", result.target_ip, result.rtt_in_ms);
+    ///     }
+    ///
+    ///     return handle.join().await;
+    /// }
+    /// ```
+    pub fn start_with_dtos(config: RnpPingRunnerConfig) -> Result<(PingResultStream<PingResultDto>, PingRunHandle), RnpError> {
+        return PingResultStream::start(config, |ping_result| ping_result.create_dto());
+    }
+}
+
+impl PingResultStream<Result<PingResult, RnpError>> {
+    /// Same as `start_with_dtos`, but returns the results as `PingResult`. Since the results are shared with all other result
+    /// processors, what we get here is a copy of each result, and the errors are only kept as their messages. Results that fail to be
+    /// copied are returned as errors in the stream, and the run goes on.
+    pub fn start_with_ping_results(config: RnpPingRunnerConfig) -> Result<(PingResultStream<Result<PingResult, RnpError>>, PingRunHandle), RnpError> {
+        return PingResultStream::start(config, |ping_result| {
+            PingResult::from_dto(&ping_result.create_dto()).map_err(RnpError::PingResultCopyFailed)
+        });
+    }
+}
+
+impl<T: Send + 'static> PingResultStream<T> {
+    #[tracing::instrument(name = "Start running pings as stream", level = "debug", skip(config, converter))]
+    fn start(mut config: RnpPingRunnerConfig, converter: fn(&PingResult) -> T) -> Result<(PingResultStream<T>, PingRunHandle), RnpError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        config.extra_ping_result_processors.push(Box::new(PingResultForwarder::new(
+            config.result_processor_config.common_config.clone(),
+            converter,
+            sender,
+        )));
+
+        let stop_event = Arc::new(ManualResetEvent::new(false));
+        let mut core = PingRunnerCore::new(config, stop_event.clone())?;
+        let join_handle = tokio::spawn(async move {
+            core.run_warmup_pings().await;
+            core.start_running_normal_pings();
+            core.join().await;
+            return core.stop_reason();
+        });
+
+        return Ok((PingResultStream { receiver }, PingRunHandle { stop_event, join_handle }));
+    }
+
+    /// Wait for the next ping result. None is returned once the run is completed and all results are received.
+    pub async fn next(&mut self) -> Option<T> {
+        return self.receiver.recv().await;
+    }
+}
+
+impl<T> Stream for PingResultStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        return self.receiver.poll_recv(cx);
+    }
+}
+
+/// The handle of a ping run started as stream, which can be used to stop the run or wait for it to complete.
+pub struct PingRunHandle {
+    stop_event: Arc<ManualResetEvent>,
+    join_handle: JoinHandle<Option<PingStopReason>>,
+}
+
+impl PingRunHandle {
+    /// Signal all pings to stop. The result stream still returns all results that are already received before it ends.
+    pub fn stop(&self) {
+        self.stop_event.set();
+    }
+
+    /// Check if the run is completed without waiting, e.g. when polling for it together with other work.
+    pub fn is_finished(&self) -> bool {
+        return self.join_handle.is_finished();
+    }
+
+    /// Wait for the run to complete and return the stop condition that ends the pings early, if any. It fails only when the run is
+    /// aborted, e.g. a result processor panicked.
+    pub async fn join(self) -> Result<Option<PingStopReason>, RnpError> {
+        return self.join_handle.await.map_err(RnpError::PingRunAborted);
+    }
+}
+
+// Result processors are synchronous callbacks, so we forward the results into a channel to get them back into async code.
+struct PingResultForwarder<T> {
+    common_config: PingResultProcessorCommonConfig,
+    converter: fn(&PingResult) -> T,
+    sender: Option<mpsc::UnboundedSender<T>>,
+}
+
+impl<T> PingResultForwarder<T> {
+    fn new(
+        common_config: PingResultProcessorCommonConfig,
+        converter: fn(&PingResult) -> T,
+        sender: mpsc::UnboundedSender<T>,
+    ) -> PingResultForwarder<T> {
+        return PingResultForwarder { common_config, converter, sender: Some(sender) };
+    }
+}
+
+impl<T> PingResultProcessor for PingResultForwarder<T> {
+    fn name(&self) -> &'static str {
+        "PingResultForwarder"
+    }
+
+    fn config(&self) -> &PingResultProcessorCommonConfig {
+        &self.common_config
+    }
+
+    fn process_ping_result(&mut self, ping_result: &PingResult) {
+        if let Some(sender) = &self.sender {
+            // The stream might be dropped by the caller, who is no longer interested in the results, which is fine.
+            let _ = sender.send((self.converter)(ping_result));
+        }
+    }
+
+    fn rundown(&mut self) {
+        // Close the channel, so the stream ends right after the last result.
+        self.sender.take();
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{PingResult, PingWorkerSchedulerConfig};
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PingStopReason {
+    DurationElapsed(Duration),
+    ConsecutiveFailures(u32),
+    Recovered(u32),
+}
+
+impl PingStopReason {
+    /// Stopping on failures means the target is still broken, which should be reported the same way as exit on fail.
+    pub fn is_failure(&self) -> bool {
+        matches!(self, PingStopReason::ConsecutiveFailures(_))
+    }
+}
+
+impl fmt::Display for PingStopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PingStopReason::DurationElapsed(duration) => write!(f, "Ping duration {} elapsed", humantime::format_duration(*duration)),
+            PingStopReason::ConsecutiveFailures(count) => write!(f, "{} consecutive pings failed", count),
+            PingStopReason::Recovered(count) => write!(f, "{} consecutive pings succeeded after failure", count),
+        }
+    }
+}
+
+// Checks the stop conditions that depend on ping results. Results from all targets are counted together in the order they come back.
+pub struct PingStopConditionChecker {
+    stop_on_consecutive_failures: Option<u32>,
+    stop_on_recovery: Option<u32>,
+
+    consecutive_failure_count: u32,
+    consecutive_success_count: u32,
+    has_failure: bool,
+    is_stopped: bool,
+}
+
+impl PingStopConditionChecker {
+    pub fn new(config: &PingWorkerSchedulerConfig) -> PingStopConditionChecker {
+        return PingStopConditionChecker {
+            stop_on_consecutive_failures: config.stop_on_consecutive_failures,
+            stop_on_recovery: config.stop_on_recovery,
+            consecutive_failure_count: 0,
+            consecutive_success_count: 0,
+            has_failure: false,
+            is_stopped: false,
+        };
+    }
+
+    /// Update the counters with the ping result, and return the stop reason when any condition is met for the first time.
+    pub fn check(&mut self, ping_result: &PingResult) -> Option<PingStopReason> {
+        // Warmup pings and preparation errors are not counted, the same as in the ping statistics.
+        if self.is_stopped || !ping_result.is_counted_in_statistics() {
+            return None;
+        }
+
+        if ping_result.is_succeeded() {
+            self.consecutive_failure_count = 0;
+            self.consecutive_success_count += 1;
+        } else {
+            self.consecutive_failure_count += 1;
+            self.consecutive_success_count = 0;
+            self.has_failure = true;
+        }
+
+        let stop_reason = match (self.stop_on_consecutive_failures, self.stop_on_recovery) {
+            (Some(failure_count), _) if self.consecutive_failure_count >= failure_count => Some(PingStopReason::ConsecutiveFailures(failure_count)),
+            (_, Some(success_count)) if self.has_failure && self.consecutive_success_count >= success_count => {
+                Some(PingStopReason::Recovered(success_count))
+            }
+            _ => None,
+        };
+
+        self.is_stopped = stop_reason.is_some();
+        return stop_reason;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rnp_test_common;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn stop_condition_checker_should_do_nothing_without_conditions() {
+        let mut checker = PingStopConditionChecker::new(&PingWorkerSchedulerConfig::default());
+        let stop_reasons: Vec<Option<PingStopReason>> =
+            rnp_test_common::generate_ping_result_test_samples().iter().map(|x| checker.check(x)).collect();
+        assert_eq!(vec![None; 6], stop_reasons);
+    }
+
+    #[test]
+    fn stop_condition_checker_should_stop_on_consecutive_failures() {
+        let config = PingWorkerSchedulerConfig { stop_on_consecutive_failures: Some(1), ..Default::default() };
+        let mut checker = PingStopConditionChecker::new(&config);
+        let stop_reasons: Vec<Option<PingStopReason>> =
+            rnp_test_common::generate_ping_result_test_samples().iter().map(|x| checker.check(x)).collect();
+
+        // Warmup ping is skipped, and the condition is only reported once.
+        assert_eq!(vec![None, Some(PingStopReason::ConsecutiveFailures(1)), None, None, None, None], stop_reasons);
+    }
+
+    #[test]
+    fn stop_condition_checker_should_stop_on_recovery() {
+        let config = PingWorkerSchedulerConfig { stop_on_consecutive_failures: Some(3), stop_on_recovery: Some(2), ..Default::default() };
+        let mut checker = PingStopConditionChecker::new(&config);
+        let stop_reasons: Vec<Option<PingStopReason>> =
+            rnp_test_common::generate_ping_result_test_samples().iter().map(|x| checker.check(x)).collect();
+
+        // Pings with warnings are still counted as succeeded, because the target is reachable.
+        assert_eq!(vec![None, None, None, Some(PingStopReason::Recovered(2)), None, None], stop_reasons);
+    }
+
+    #[test]
+    fn stop_condition_checker_should_not_stop_on_successes_without_failure() {
+        let config = PingWorkerSchedulerConfig { stop_on_recovery: Some(1), ..Default::default() };
+        let mut checker = PingStopConditionChecker::new(&config);
+        let ping_results = rnp_test_common::generate_ping_result_test_samples();
+        assert_eq!(None, checker.check(&ping_results[2]));
+        assert_eq!(None, checker.check(&ping_results[1]));
+        assert_eq!(Some(PingStopReason::Recovered(1)), checker.check(&ping_results[2]));
+    }
+}
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use std::io;
+use std::net::SocketAddr;
+
+#[async_trait]
+pub trait PingTargetResolver {
+    async fn resolve(&self, host_name: &str, port: u16) -> io::Result<Vec<SocketAddr>>;
+}
+
+/// Resolve ping targets with the resolver provided by the OS, i.e. `getaddrinfo`.
+#[derive(Default)]
+pub struct PingTargetResolverSystem {}
+
+impl PingTargetResolverSystem {
+    pub fn new() -> PingTargetResolverSystem {
+        return PingTargetResolverSystem {};
+    }
+}
+
+#[async_trait]
+impl PingTargetResolver for PingTargetResolverSystem {
+    #[tracing::instrument(name = "Resolving ping target with system resolver", level = "debug", skip(self))]
+    async fn resolve(&self, host_name: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        return Ok(tokio::net::lookup_host((host_name, port)).await?.collect());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn resolving_localhost_with_system_resolver_should_work() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let resolver = PingTargetResolverSystem::new();
+            let addresses = resolver.resolve("localhost", 443).await.unwrap();
+            assert!(!addresses.is_empty());
+            assert!(addresses.iter().all(|a| a.ip().is_loopback() && a.port() == 443));
+        });
+    }
+}
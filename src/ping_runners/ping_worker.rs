@@ -1,9 +1,13 @@
 use crate::ping_clients::ping_client::{PingClientError, PingClientPingResultDetails};
-use crate::{ping_client_factory, PingClient, PingClientFactory, PingPortPicker, PingResult, PingWorkerConfig};
+use crate::{ping_client_factory, PingClient, PingClientRegistry, PingPortPicker, PingResult, PingTarget, PingTargetResolver, PingWorkerConfig};
 use chrono::{offset::Utc, DateTime};
 use futures_intrusive::sync::ManualResetEvent;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::time::Duration;
 use std::{net::SocketAddr, sync::Arc, sync::Mutex};
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
 use tokio::{sync::mpsc, task, task::JoinHandle};
 
 pub struct PingWorker {
@@ -12,29 +16,72 @@ pub struct PingWorker {
     stop_event: Arc<ManualResetEvent>,
     port_picker: Arc<Mutex<PingPortPicker>>,
     ping_client: Box<dyn PingClient + Send + Sync>,
+    ping_target_resolver: Arc<dyn PingTargetResolver + Send + Sync>,
     result_sender: mpsc::UnboundedSender<PingResult>,
     is_warmup_worker: bool,
+
+    // Set when pings are issued by the rate scheduler, in which case each ping waits for a permit instead of sleeping for ping interval.
+    ping_permits: Option<Arc<Semaphore>>,
+
+    // Resolved address of each target and the source IP to use with it. For IP address targets, these are set once at creation and never change.
+    resolved_targets: Vec<Option<(SocketAddr, IpAddr)>>,
+}
+
+// Everything a worker needs from the ping runner, which is shared by all workers started together.
+#[derive(Clone)]
+pub struct PingWorkerContext {
+    pub config: Arc<PingWorkerConfig>,
+    pub ping_client_registry: PingClientRegistry,
+    pub ping_target_resolver: Arc<dyn PingTargetResolver + Send + Sync>,
+    pub port_picker: Arc<Mutex<PingPortPicker>>,
+    pub stop_event: Arc<ManualResetEvent>,
+    pub result_sender: mpsc::UnboundedSender<PingResult>,
+    pub is_warmup_worker: bool,
+    pub ping_permits: Option<Arc<Semaphore>>,
 }
 
 impl PingWorker {
-    #[tracing::instrument(
-        name = "Starting worker",
-        level = "debug",
-        skip(config, external_ping_client_factory, port_picker, stop_event, result_sender)
-    )]
-    pub fn run(
-        worker_id: u32,
-        config: Arc<PingWorkerConfig>,
-        external_ping_client_factory: Option<PingClientFactory>,
-        port_picker: Arc<Mutex<PingPortPicker>>,
-        stop_event: Arc<ManualResetEvent>,
-        result_sender: mpsc::UnboundedSender<PingResult>,
-        is_warmup_worker: bool,
-    ) -> JoinHandle<()> {
+    #[tracing::instrument(name = "Starting worker", level = "debug", skip(context))]
+    pub fn run(worker_id: u32, context: PingWorkerContext) -> JoinHandle<()> {
+        let PingWorkerContext {
+            config,
+            ping_client_registry,
+            ping_target_resolver,
+            port_picker,
+            stop_event,
+            result_sender,
+            is_warmup_worker,
+            ping_permits,
+        } = context;
+
         let join_handle = task::spawn(async move {
-            let ping_client = ping_client_factory::new_ping_client(&config.protocol, &config.ping_client_config, external_ping_client_factory);
+            // The ping client is already checked when creating the ping runner, so this only fails if a registered factory changes its mind.
+            let ping_client = match ping_client_factory::new_ping_client(&config.protocol, &config.ping_client_config, &ping_client_registry) {
+                Ok(ping_client) => ping_client,
+                Err(e) => {
+                    tracing::error!("Failed to create ping client, stopping ping worker: worker_id={}, Error = {}", worker_id, e);
+                    return;
+                }
+            };
 
-            let mut worker = PingWorker { id: worker_id, config, stop_event, port_picker, ping_client, result_sender, is_warmup_worker };
+            let resolved_targets = config
+                .targets
+                .iter()
+                .map(|target| target.address().and_then(|address| PingWorker::pick_resolved_target(&[address], &config.source_ip)))
+                .collect();
+
+            let mut worker = PingWorker {
+                id: worker_id,
+                config,
+                stop_event,
+                port_picker,
+                ping_client,
+                ping_target_resolver,
+                result_sender,
+                is_warmup_worker,
+                ping_permits,
+                resolved_targets,
+            };
             worker.run_worker_loop().await;
 
             tracing::debug!("Ping worker loop exited; worker_id={}", worker.id);
@@ -46,85 +93,192 @@ impl PingWorker {
     #[tracing::instrument(name = "Running worker loop", level = "debug", skip(self), fields(worker_id = %self.id))]
     async fn run_worker_loop(&mut self) {
         loop {
-            let source_port = self.port_picker.lock().expect("Failed getting port picker lock").next();
-            match source_port {
-                Some(source_port) => self.run_single_ping(source_port).await,
+            if self.ping_permits.is_some() && !self.wait_for_ping_permit().await {
+                break;
+            }
+
+            let next_ping = self.port_picker.lock().expect("Failed getting port picker lock").next_with_target_index();
+            match next_ping {
+                Some((source_port, target_index)) => self.run_single_ping(source_port, target_index).await,
                 None => {
                     tracing::debug!("Ping finished, stopping worker; worker_id={}", self.id);
                     return;
                 }
             }
 
-            if !self.wait_for_next_schedule().await {
+            if self.ping_permits.is_none() && !self.wait_for_next_schedule().await {
                 break;
             }
         }
     }
 
     #[tracing::instrument(name = "Running single ping", level = "debug", skip(self), fields(worker_id = %self.id))]
-    async fn run_single_ping(&mut self, source_port: u16) {
-        let source = SocketAddr::new(self.config.source_ip, source_port);
-        let target = self.config.target;
-
+    async fn run_single_ping(&mut self, source_port: u16, target_index: usize) {
         let ping_time = Utc::now();
+
+        let dns_resolve_time = match self.resolve_target(target_index).await {
+            Ok(dns_resolve_time) => dns_resolve_time,
+            Err(e) => {
+                let (target, source_ip) =
+                    self.resolved_targets[target_index].unwrap_or((self.unresolved_target(target_index), self.config.source_ip));
+                let error = PingClientError::PingFailed(Box::new(e));
+                self.process_ping_client_error(&ping_time, &target, &SocketAddr::new(source_ip, source_port), error, None).await;
+                return;
+            }
+        };
+
+        let (target, source_ip) = self.resolved_targets[target_index].unwrap();
+        let source = SocketAddr::new(source_ip, source_port);
         match self.ping_client.prepare_ping(&source).await {
-            Err(PingClientError::PreparationFailed(e)) => {
-                self.process_ping_client_error(&ping_time, source_port, PingClientError::PreparationFailed(e)).await
+            // Ping clients should only return preparation failures here, but whatever they return, it fails in preparation.
+            Err(PingClientError::PreparationFailed(e)) | Err(PingClientError::PingFailed(e)) => {
+                let error = PingClientError::PreparationFailed(e);
+                self.process_ping_client_error(&ping_time, &target, &source, error, dns_resolve_time).await
             }
-            Err(_) => panic!("Unexpected failure from prepare_ping! The error type should always be PingClientError::PreparationFailed."),
             Ok(()) => (),
         }
 
         match self.ping_client.ping(&source, &target).await {
-            Ok(result) => self.process_ping_client_result(&ping_time, source_port, result).await,
-            Err(error) => self.process_ping_client_error(&ping_time, source_port, error).await,
+            Ok(result) => self.process_ping_client_result(&ping_time, &target, &source, result, dns_resolve_time).await,
+            Err(error) => self.process_ping_client_error(&ping_time, &target, &source, error, dns_resolve_time).await,
         }
     }
 
-    #[tracing::instrument(name = "Processing ping client single ping result", level = "debug", skip(self), fields(worker_id = %self.id))]
-    async fn process_ping_client_result(&self, ping_time: &DateTime<Utc>, src_port: u16, ping_result: PingClientPingResultDetails) {
-        let mut source: Option<SocketAddr> = ping_result.actual_local_addr;
+    // Resolve the target if needed. When name resolution really happens in this ping, the time it takes will be returned.
+    #[tracing::instrument(name = "Resolving ping target", level = "debug", skip(self), fields(worker_id = %self.id))]
+    async fn resolve_target(&mut self, target_index: usize) -> io::Result<Option<Duration>> {
+        let target = &self.config.targets[target_index];
+        let (host_name, port) = match target {
+            PingTarget::Address(address) => {
+                // IP address targets never need resolving, but they can still be unreachable if the source IP is in another address family.
+                if self.resolved_targets[target_index].is_none() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Target {} cannot be reached from source IP {}", address, self.config.source_ip),
+                    ));
+                }
+
+                return Ok(None);
+            }
+            PingTarget::HostName(host_name, port) => (host_name, *port),
+        };
+
+        if self.resolved_targets[target_index].is_some() && !self.config.resolve_on_every_ping {
+            return Ok(None);
+        }
+
+        let start_time = Instant::now();
+        let addresses = self.ping_target_resolver.resolve(host_name, port).await?;
+        let dns_resolve_time = Instant::now().duration_since(start_time);
+
+        let resolved_target = PingWorker::pick_resolved_target(&addresses, &self.config.source_ip).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No address of {} can be reached from source IP {}. Resolved addresses: {:?}", host_name, self.config.source_ip, addresses),
+            )
+        })?;
+
+        if self.resolved_targets[target_index].map(|(address, _)| address) != Some(resolved_target.0) {
+            tracing::debug!("Ping target resolved; worker_id={}, target={}, address={}", self.id, target, resolved_target.0);
+        }
+
+        self.resolved_targets[target_index] = Some(resolved_target);
+        return Ok(Some(dns_resolve_time));
+    }
 
-        if source.is_none() {
-            source = Some(SocketAddr::new(self.config.source_ip, src_port));
+    // Pick the first address that can be reached from the source IP. If source IP is unspecified, we prefer the addresses
+    // in the same family, and then fall back to the other family with unspecified source IP, e.g. IPv6 only hosts.
+    fn pick_resolved_target(addresses: &[SocketAddr], source_ip: &IpAddr) -> Option<(SocketAddr, IpAddr)> {
+        if let Some(address) = addresses.iter().find(|a| a.is_ipv4() == source_ip.is_ipv4()) {
+            return Some((*address, *source_ip));
         }
 
+        if !source_ip.is_unspecified() {
+            return None;
+        }
+
+        return addresses.first().map(|address| {
+            let source_ip = if address.is_ipv4() { IpAddr::V4(Ipv4Addr::UNSPECIFIED) } else { IpAddr::V6(Ipv6Addr::UNSPECIFIED) };
+            (*address, source_ip)
+        });
+    }
+
+    // Used as the target in results when we never get a usable address for the target, which keeps the target port visible.
+    fn unresolved_target(&self, target_index: usize) -> SocketAddr {
+        let target = &self.config.targets[target_index];
+        if let Some(address) = target.address() {
+            return address;
+        }
+
+        let ip = if self.config.source_ip.is_ipv4() { IpAddr::V4(Ipv4Addr::UNSPECIFIED) } else { IpAddr::V6(Ipv6Addr::UNSPECIFIED) };
+        return SocketAddr::new(ip, target.port());
+    }
+
+    #[tracing::instrument(name = "Processing ping client single ping result", level = "debug", skip(self), fields(worker_id = %self.id))]
+    async fn process_ping_client_result(
+        &self,
+        ping_time: &DateTime<Utc>,
+        target: &SocketAddr,
+        source: &SocketAddr,
+        ping_result: PingClientPingResultDetails,
+        dns_resolve_time: Option<Duration>,
+    ) {
+        let source = ping_result.actual_local_addr.unwrap_or(*source);
+
         let result = PingResult::new(
             ping_time,
             self.id,
             self.ping_client.protocol(),
-            self.config.target,
-            source.unwrap(),
+            *target,
+            source,
             self.is_warmup_worker,
             !ping_result.is_timeout,
             ping_result.round_trip_time,
             ping_result.is_timeout,
             ping_result.warning,
             None,
-        );
+        )
+        .with_dns_resolve_time(dns_resolve_time)
+        .with_handshake_time(ping_result.handshake_time)
+        .with_response_time(ping_result.first_byte_time, ping_result.response_time)
+        .with_one_way_latency(ping_result.one_way_latency);
 
-        self.result_sender.send(result).unwrap();
+        self.send_ping_result(result);
     }
 
     #[tracing::instrument(name = "Processing ping client single ping error", level = "debug", skip(self), fields(worker_id = %self.id))]
-    async fn process_ping_client_error(&self, ping_time: &DateTime<Utc>, src_port: u16, error: PingClientError) {
-        let source = SocketAddr::new(self.config.source_ip, src_port);
-
+    async fn process_ping_client_error(
+        &self,
+        ping_time: &DateTime<Utc>,
+        target: &SocketAddr,
+        source: &SocketAddr,
+        error: PingClientError,
+        dns_resolve_time: Option<Duration>,
+    ) {
         let result = PingResult::new(
             ping_time,
             self.id,
             self.ping_client.protocol(),
-            self.config.target,
-            source,
+            *target,
+            *source,
             self.is_warmup_worker,
             false,
             Duration::from_millis(0),
             false,
             None,
             Some(error),
-        );
+        )
+        .with_dns_resolve_time(dns_resolve_time);
 
-        self.result_sender.send(result).unwrap();
+        self.send_ping_result(result);
+    }
+
+    fn send_ping_result(&self, result: PingResult) {
+        // The result processing worker only stops after all ping workers are stopped, unless it is gone because of a panic in any result
+        // processor. In this case, we keep running the pings, since the panic will be reported when joining the runner.
+        if self.result_sender.send(result).is_err() {
+            tracing::warn!("Ping result processing worker is stopped, dropping ping result: worker_id={}", self.id);
+        }
     }
 
     #[tracing::instrument(name = "Waiting for next schedule", level = "debug", skip(self), fields(worker_id = %self.id))]
@@ -141,4 +295,26 @@ impl PingWorker {
         // If not, continue to run.
         return true;
     }
+
+    #[tracing::instrument(name = "Waiting for ping permit", level = "debug", skip(self), fields(worker_id = %self.id))]
+    async fn wait_for_ping_permit(&self) -> bool {
+        // Stop event is checked first, otherwise we might run one more ping with the permits that are already released.
+        if self.stop_event.is_set() {
+            tracing::debug!("Stop event received, stopping worker; worker_id={}", self.id);
+            return false;
+        }
+
+        let ping_permits = self.ping_permits.as_ref().unwrap();
+        tokio::select! {
+            permit = ping_permits.acquire() => {
+                permit.expect("Ping permits should never be closed").forget();
+                return true;
+            }
+
+            _ = self.stop_event.wait() => {
+                tracing::debug!("Stop event received, stopping worker; worker_id={}", self.id);
+                return false;
+            }
+        }
+    }
 }
@@ -0,0 +1,623 @@
+use crate::*;
+use futures_intrusive::sync::ManualResetEvent;
+use std::convert::TryInto;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpSocket, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+// The request sent by rnp at the start of every throughput test stream, so rnp_server knows whether it should send data back:
+// - 4 bytes: Magic "RNPX".
+// - 1 byte: Direction. 0 = Upload, 1 = Download, 2 = Bidirectional.
+// - 3 bytes: Reserved.
+//
+// After the request, the data is simply streamed in the test direction until either side closes the connection.
+//
+// When uploading, rnp shuts down its writes to stop the test, and rnp_server confirms the bytes it received before closing the connection:
+// - 8 bytes: Uploaded bytes in big endian, sent as the last bytes of the connection, right after the download data if any.
+pub const THROUGHPUT_REQUEST_MAGIC: &[u8; 4] = b"RNPX";
+pub const THROUGHPUT_REQUEST_SIZE: usize = 8;
+pub const THROUGHPUT_UPLOAD_CONFIRMATION_SIZE: usize = 8;
+pub const THROUGHPUT_CHUNK_SIZE: usize = 64 * 1024;
+
+// The data buffered in the socket needs to be delivered before the upload is confirmed, which takes a while on slow networks.
+const THROUGHPUT_UPLOAD_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn build_throughput_request(direction: ThroughputDirection) -> Vec<u8> {
+    let mut request = Vec::with_capacity(THROUGHPUT_REQUEST_SIZE);
+    request.extend_from_slice(THROUGHPUT_REQUEST_MAGIC);
+    request.push(match direction {
+        ThroughputDirection::Upload => 0,
+        ThroughputDirection::Download => 1,
+        ThroughputDirection::Bidirectional => 2,
+    });
+    request.extend_from_slice(&[0u8; 3]);
+    return request;
+}
+
+// The upload data follows the request right away, so only the prefix of the data is checked. The stub server buffers the data at the
+// beginning of the connection until the request is fully received, since it can be split across reads.
+pub fn parse_throughput_request(data: &[u8]) -> Option<ThroughputDirection> {
+    if data.len() < THROUGHPUT_REQUEST_SIZE || &data[..4] != THROUGHPUT_REQUEST_MAGIC {
+        return None;
+    }
+
+    return match data[4] {
+        0 => Some(ThroughputDirection::Upload),
+        1 => Some(ThroughputDirection::Download),
+        2 => Some(ThroughputDirection::Bidirectional),
+        _ => None,
+    };
+}
+
+pub fn build_throughput_upload_confirmation(uploaded_bytes: u64) -> [u8; THROUGHPUT_UPLOAD_CONFIRMATION_SIZE] {
+    return uploaded_bytes.to_be_bytes();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThroughputSummary {
+    pub target: SocketAddr,
+    pub direction: ThroughputDirection,
+    pub parallel_stream_count: u32,
+    pub failed_stream_count: u32,
+    pub duration: Duration,
+
+    // The upload lasts until all data is confirmed by the server, which can be longer than the test duration, because the data
+    // buffered in the socket when the test is stopped still needs to be delivered.
+    pub upload_duration: Duration,
+    pub uploaded_bytes: u64,
+    pub downloaded_bytes: u64,
+}
+
+impl ThroughputSummary {
+    pub fn upload_bps(&self) -> f64 {
+        return calculate_bps(self.uploaded_bytes, self.upload_duration);
+    }
+
+    pub fn download_bps(&self) -> f64 {
+        return calculate_bps(self.downloaded_bytes, self.duration);
+    }
+}
+
+struct ThroughputUploadConfirmation {
+    uploaded_bytes: u64,
+    confirm_time: Instant,
+}
+
+pub struct ThroughputRunner {
+    config: RnpThroughputRunnerConfig,
+    stop_event: Arc<ManualResetEvent>,
+
+    // Bytes are counted across all streams, since we only report the throughput of the whole test. The uploaded bytes here are the
+    // bytes written into the sockets, which are only used in the interval reports, and the summary uses the bytes confirmed by the server.
+    uploaded_bytes: Arc<AtomicU64>,
+    downloaded_bytes: Arc<AtomicU64>,
+}
+
+impl ThroughputRunner {
+    pub fn new(config: RnpThroughputRunnerConfig, stop_event: Arc<ManualResetEvent>) -> ThroughputRunner {
+        return ThroughputRunner { config, stop_event, uploaded_bytes: Arc::new(AtomicU64::new(0)), downloaded_bytes: Arc::new(AtomicU64::new(0)) };
+    }
+
+    #[tracing::instrument(name = "Running throughput test", level = "debug", skip(self))]
+    pub async fn run(&self) -> Result<ThroughputSummary, RnpError> {
+        let target = self.resolve_target().await?;
+
+        // All streams are connected before the test starts, so the connect time is not counted in the throughput.
+        let mut streams = Vec::new();
+        for _ in 0..self.config.parallel_stream_count {
+            let stream = self.connect_stream(&target).await.map_err(|e| RnpError::ThroughputConnectFailed { target, source: e })?;
+            streams.push(stream);
+        }
+
+        if self.config.quiet_level < RNP_QUIET_LEVEL_NO_PING_RESULT {
+            println!(
+                "Start {} throughput test to {} with {} streams for {:?}.",
+                self.config.direction, target, self.config.parallel_stream_count, self.config.duration
+            );
+        }
+
+        let start_time = Instant::now();
+        let stop_time = start_time + self.config.duration;
+        let mut stream_tasks = Vec::new();
+        for stream in streams {
+            stream_tasks.push(self.start_stream_task(stream, stop_time));
+        }
+
+        self.report_until_stopped(start_time, stop_time).await;
+
+        let duration = start_time.elapsed().min(self.config.duration);
+        let mut failed_stream_count = 0;
+        let mut upload_duration = duration;
+        let mut uploaded_bytes = 0;
+        for stream_task in stream_tasks {
+            match stream_task.await {
+                Ok(Ok(Some(confirmation))) => {
+                    uploaded_bytes += confirmation.uploaded_bytes;
+                    upload_duration = upload_duration.max(confirmation.confirm_time - start_time);
+                }
+                Ok(Ok(None)) => (),
+                Ok(Err(e)) => {
+                    tracing::debug!("Throughput test stream failed: Error = {}", e);
+                    failed_stream_count += 1;
+                }
+                Err(_) => failed_stream_count += 1,
+            }
+        }
+
+        let summary = ThroughputSummary {
+            target,
+            direction: self.config.direction,
+            parallel_stream_count: self.config.parallel_stream_count,
+            failed_stream_count,
+            duration,
+            upload_duration,
+            uploaded_bytes,
+            downloaded_bytes: self.downloaded_bytes.load(Ordering::Relaxed),
+        };
+
+        if self.config.quiet_level < RNP_QUIET_LEVEL_NO_PING_SUMMARY {
+            self.output_summary(&summary);
+        }
+
+        return Ok(summary);
+    }
+
+    async fn resolve_target(&self) -> Result<SocketAddr, RnpError> {
+        let (host_name, port) = match &self.config.target {
+            PingTarget::Address(address) => return Ok(*address),
+            PingTarget::HostName(host_name, port) => (host_name, *port),
+        };
+
+        let addresses = PingTargetResolverSystem::new()
+            .resolve(host_name, port)
+            .await
+            .map_err(|e| RnpError::ThroughputTargetResolveFailed { target: self.config.target.to_string(), source: e })?;
+
+        return addresses.into_iter().next().ok_or_else(|| RnpError::ThroughputTargetResolveFailed {
+            target: self.config.target.to_string(),
+            source: io::Error::new(io::ErrorKind::NotFound, "No address found"),
+        });
+    }
+
+    #[tracing::instrument(name = "Connecting throughput test stream", level = "debug", skip(self))]
+    async fn connect_stream(&self, target: &SocketAddr) -> io::Result<TcpStream> {
+        // The source IP is only set when the target is an IP address, so we pick the unspecified address in the same family for host names.
+        let source_ip = match (self.config.source_ip.is_unspecified(), target) {
+            (true, SocketAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (true, SocketAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            (false, _) => self.config.source_ip,
+        };
+
+        let socket = if target.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        socket.bind(SocketAddr::new(source_ip, 0))?;
+
+        let mut stream = match tokio::time::timeout(self.config.connect_timeout, socket.connect(*target)).await {
+            Ok(connect_result) => connect_result?,
+            Err(_) => return Err(io::Error::new(io::ErrorKind::TimedOut, "Connect timed out")),
+        };
+
+        stream.write_all(&build_throughput_request(self.config.direction)).await?;
+        return Ok(stream);
+    }
+
+    fn start_stream_task(&self, stream: TcpStream, stop_time: Instant) -> JoinHandle<io::Result<Option<ThroughputUploadConfirmation>>> {
+        let direction = self.config.direction;
+        let uploaded_bytes = self.uploaded_bytes.clone();
+        let downloaded_bytes = self.downloaded_bytes.clone();
+        let stop_event = self.stop_event.clone();
+
+        return tokio::spawn(async move {
+            // Dropping the write half shuts down the writes, which makes rnp_server close the stream, so we hold it until the test is
+            // stopped, even in download only tests.
+            let (mut read_half, mut write_half) = stream.into_split();
+
+            {
+                let send = async {
+                    match direction.is_upload() {
+                        true => ThroughputRunner::send_until_failed(&mut write_half, uploaded_bytes).await,
+                        false => std::future::pending().await,
+                    }
+                };
+                let receive = async {
+                    match direction.is_download() {
+                        true => ThroughputRunner::receive_until_failed(&mut read_half, downloaded_bytes).await,
+                        false => std::future::pending().await,
+                    }
+                };
+
+                tokio::select! {
+                    result = async { tokio::try_join!(send, receive) } => result?,
+                    _ = tokio::time::sleep_until(stop_time) => ((), ()),
+                    _ = stop_event.wait() => ((), ()),
+                };
+            }
+
+            if !direction.is_upload() {
+                return Ok(None);
+            }
+
+            return match tokio::time::timeout(THROUGHPUT_UPLOAD_CONFIRM_TIMEOUT, ThroughputRunner::wait_upload_confirmation(read_half, write_half))
+                .await
+            {
+                Ok(result) => result.map(Some),
+                Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "Timed out waiting for the upload to be confirmed")),
+            };
+        });
+    }
+
+    async fn send_until_failed(write_half: &mut OwnedWriteHalf, uploaded_bytes: Arc<AtomicU64>) -> io::Result<()> {
+        let write_buf = vec![0u8; THROUGHPUT_CHUNK_SIZE];
+        loop {
+            let n = write_half.write(&write_buf).await?;
+            uploaded_bytes.fetch_add(n as u64, Ordering::Relaxed);
+        }
+    }
+
+    async fn receive_until_failed(read_half: &mut OwnedReadHalf, downloaded_bytes: Arc<AtomicU64>) -> io::Result<()> {
+        let mut read_buf = vec![0u8; THROUGHPUT_CHUNK_SIZE];
+        loop {
+            let n = read_half.read(&mut read_buf).await?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed by remote side"));
+            }
+            downloaded_bytes.fetch_add(n as u64, Ordering::Relaxed);
+        }
+    }
+
+    // The writes are shut down to tell rnp_server the upload is completed. The download data still in flight is drained without being
+    // counted, and the confirmation is the last bytes before the connection is closed.
+    async fn wait_upload_confirmation(mut read_half: OwnedReadHalf, mut write_half: OwnedWriteHalf) -> io::Result<ThroughputUploadConfirmation> {
+        write_half.shutdown().await?;
+
+        let mut read_buf = vec![0u8; THROUGHPUT_CHUNK_SIZE];
+        let mut last_bytes = Vec::with_capacity(THROUGHPUT_UPLOAD_CONFIRMATION_SIZE * 2);
+        loop {
+            let n = read_half.read(&mut read_buf).await?;
+            if n == 0 {
+                break;
+            }
+
+            last_bytes.extend_from_slice(&read_buf[n.saturating_sub(THROUGHPUT_UPLOAD_CONFIRMATION_SIZE)..n]);
+            if last_bytes.len() > THROUGHPUT_UPLOAD_CONFIRMATION_SIZE {
+                last_bytes.drain(..last_bytes.len() - THROUGHPUT_UPLOAD_CONFIRMATION_SIZE);
+            }
+        }
+        let confirm_time = Instant::now();
+
+        let confirmation: [u8; THROUGHPUT_UPLOAD_CONFIRMATION_SIZE] =
+            last_bytes.try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Connection closed without confirming the upload"))?;
+
+        return Ok(ThroughputUploadConfirmation { uploaded_bytes: u64::from_be_bytes(confirmation), confirm_time });
+    }
+
+    async fn report_until_stopped(&self, start_time: Instant, stop_time: Instant) {
+        let mut last_report_time = start_time;
+        let mut last_uploaded_bytes = 0;
+        let mut last_downloaded_bytes = 0;
+
+        loop {
+            let next_report_time = (last_report_time + self.config.report_interval).min(stop_time);
+            let is_stopped = tokio::select! {
+                _ = tokio::time::sleep_until(next_report_time) => next_report_time >= stop_time,
+                _ = self.stop_event.wait() => true,
+            };
+
+            // The last interval can be shorter than the report interval, when the test is stopped in the middle of it.
+            let report_time = Instant::now().min(stop_time);
+            let uploaded_bytes = self.uploaded_bytes.load(Ordering::Relaxed);
+            let downloaded_bytes = self.downloaded_bytes.load(Ordering::Relaxed);
+            if self.config.quiet_level < RNP_QUIET_LEVEL_NO_PING_RESULT && report_time > last_report_time {
+                println!(
+                    "[{:>7.2}s - {:>7.2}s] {}",
+                    (last_report_time - start_time).as_secs_f64(),
+                    (report_time - start_time).as_secs_f64(),
+                    self.format_transfer(
+                        uploaded_bytes - last_uploaded_bytes,
+                        downloaded_bytes - last_downloaded_bytes,
+                        report_time - last_report_time
+                    )
+                );
+            }
+
+            if is_stopped {
+                return;
+            }
+
+            last_report_time = report_time;
+            last_uploaded_bytes = uploaded_bytes;
+            last_downloaded_bytes = downloaded_bytes;
+        }
+    }
+
+    fn format_transfer(&self, uploaded_bytes: u64, downloaded_bytes: u64, duration: Duration) -> String {
+        let mut transfers = Vec::new();
+        if self.config.direction.is_upload() {
+            transfers.push(format!("Upload = {} ({})", format_bps(calculate_bps(uploaded_bytes, duration)), format_bytes(uploaded_bytes)));
+        }
+        if self.config.direction.is_download() {
+            transfers.push(format!("Download = {} ({})", format_bps(calculate_bps(downloaded_bytes, duration)), format_bytes(downloaded_bytes)));
+        }
+        return transfers.join(", ");
+    }
+
+    fn output_summary(&self, summary: &ThroughputSummary) {
+        println!("\n=== Throughput statistics for TCP {} ===", summary.target);
+        println!(
+            "- Streams: Direction = {}, Parallel = {}, Failed = {}, Duration = {:.2}s.",
+            summary.direction,
+            summary.parallel_stream_count,
+            summary.failed_stream_count,
+            summary.duration.as_secs_f64()
+        );
+
+        if summary.direction.is_upload() {
+            println!(
+                "- Upload: Transferred = {}, Duration = {:.2}s, Throughput = {}.",
+                format_bytes(summary.uploaded_bytes),
+                summary.upload_duration.as_secs_f64(),
+                format_bps(summary.upload_bps())
+            );
+        }
+
+        if summary.direction.is_download() {
+            println!("- Download: Transferred = {}, Throughput = {}.", format_bytes(summary.downloaded_bytes), format_bps(summary.download_bps()));
+        }
+    }
+}
+
+fn calculate_bps(bytes: u64, duration: Duration) -> f64 {
+    if duration.is_zero() {
+        return 0.0;
+    }
+
+    return bytes as f64 * 8.0 / duration.as_secs_f64();
+}
+
+// Same as most network tools, the units are in powers of 1000 instead of 1024.
+fn format_bytes(bytes: u64) -> String {
+    return format_with_unit(bytes as f64, &["B", "KB", "MB", "GB", "TB"]);
+}
+
+fn format_bps(bps: f64) -> String {
+    return format_with_unit(bps, &["bps", "Kbps", "Mbps", "Gbps", "Tbps"]);
+}
+
+fn format_with_unit(mut value: f64, units: &[&str]) -> String {
+    let mut unit_index = 0;
+    while value >= 1000.0 && unit_index < units.len() - 1 {
+        value /= 1000.0;
+        unit_index += 1;
+    }
+
+    return format!("{:.2} {}", value, units[unit_index]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rnp_test_common;
+    use crate::stub_servers::stub_server_factory;
+    use pretty_assertions::assert_eq;
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn throughput_request_should_round_trip() {
+        for direction in [ThroughputDirection::Upload, ThroughputDirection::Download, ThroughputDirection::Bidirectional] {
+            let mut request = build_throughput_request(direction);
+            assert_eq!(THROUGHPUT_REQUEST_SIZE, request.len());
+
+            // Upload data follows the request in the same read.
+            request.extend_from_slice(&[0u8; 100]);
+            assert_eq!(Some(direction), parse_throughput_request(&request));
+        }
+
+        assert_eq!(None, parse_throughput_request(&build_throughput_request(ThroughputDirection::Upload)[..4]));
+        assert_eq!(None, parse_throughput_request(&[0u8; THROUGHPUT_REQUEST_SIZE]));
+        assert_eq!(None, parse_throughput_request(b"RNPX\x03\x00\x00\x00"));
+    }
+
+    #[test]
+    fn formatting_throughput_should_work() {
+        assert_eq!("0.00 bps", format_bps(calculate_bps(1000, Duration::ZERO)));
+        assert_eq!("8.00 Kbps", format_bps(calculate_bps(1000, Duration::from_secs(1))));
+        assert_eq!("400.00 Mbps", format_bps(calculate_bps(100_000_000, Duration::from_secs(2))));
+        assert_eq!("999.00 B", format_bytes(999));
+        assert_eq!("1.50 MB", format_bytes(1_500_000));
+        assert_eq!("2000.00 TB", format_bytes(2_000_000_000_000_000));
+    }
+
+    #[test]
+    fn throughput_runner_should_work_with_stub_server() {
+        rnp_test_common::initialize();
+        let rt = Runtime::new().unwrap();
+
+        let directions = [
+            ("127.0.0.1:11347", ThroughputDirection::Upload),
+            ("127.0.0.1:11348", ThroughputDirection::Download),
+            ("127.0.0.1:11349", ThroughputDirection::Bidirectional),
+        ];
+        for (server_address, direction) in directions {
+            let server_address = server_address.parse::<SocketAddr>().unwrap();
+            start_run_tcp_stub_server(&rt, &server_address);
+
+            let summary = rt.block_on(async move {
+                let runner =
+                    ThroughputRunner::new(create_throughput_runner_config(&server_address, direction), Arc::new(ManualResetEvent::new(false)));
+                return runner.run().await.unwrap();
+            });
+
+            assert_eq!(server_address, summary.target);
+            assert_eq!(direction, summary.direction);
+            assert_eq!(2, summary.parallel_stream_count);
+            assert_eq!(0, summary.failed_stream_count);
+            assert_eq!(Duration::from_millis(500), summary.duration);
+            assert!(summary.upload_duration >= summary.duration);
+            assert_eq!(direction.is_upload(), summary.uploaded_bytes > 0);
+            assert_eq!(direction.is_download(), summary.downloaded_bytes > 0);
+        }
+    }
+
+    #[test]
+    fn stub_server_should_start_throughput_test_when_request_is_split_across_reads() {
+        rnp_test_common::initialize();
+        let rt = Runtime::new().unwrap();
+
+        let server_address = "127.0.0.1:11358".parse::<SocketAddr>().unwrap();
+        start_run_tcp_stub_server(&rt, &server_address);
+
+        let downloaded_bytes = rt.block_on(async move {
+            let mut stream = TcpStream::connect(server_address).await.unwrap();
+            stream.set_nodelay(true).unwrap();
+
+            let request = build_throughput_request(ThroughputDirection::Download);
+            stream.write_all(&request[..3]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stream.write_all(&request[3..]).await.unwrap();
+
+            let mut read_buf = vec![0u8; THROUGHPUT_CHUNK_SIZE];
+            return tokio::time::timeout(Duration::from_secs(5), stream.read(&mut read_buf)).await.unwrap().unwrap();
+        });
+
+        assert!(downloaded_bytes > 0);
+    }
+
+    #[test]
+    fn throughput_runner_should_stop_when_stop_event_is_set() {
+        rnp_test_common::initialize();
+        let rt = Runtime::new().unwrap();
+
+        let server_address = "127.0.0.1:11350".parse::<SocketAddr>().unwrap();
+        start_run_tcp_stub_server(&rt, &server_address);
+
+        let summary = rt.block_on(async move {
+            let mut config = create_throughput_runner_config(&server_address, ThroughputDirection::Upload);
+            config.duration = Duration::from_secs(60);
+
+            let stop_event = Arc::new(ManualResetEvent::new(false));
+            let stop_event_clone = stop_event.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                stop_event_clone.set();
+            });
+
+            return ThroughputRunner::new(config, stop_event).run().await.unwrap();
+        });
+
+        assert!(summary.duration < Duration::from_secs(5));
+        assert!(summary.uploaded_bytes > 0);
+    }
+
+    #[test]
+    fn throughput_runner_should_report_uploaded_bytes_confirmed_by_server() {
+        rnp_test_common::initialize();
+        let rt = Runtime::new().unwrap();
+
+        // The server confirms less data than rnp has written, like the data still buffered in the socket when the test is stopped.
+        let server_address = "127.0.0.1:11356".parse::<SocketAddr>().unwrap();
+        start_run_upload_confirming_server(&rt, &server_address, Some(1000));
+
+        let summary = rt.block_on(async move {
+            let mut config = create_throughput_runner_config(&server_address, ThroughputDirection::Upload);
+            config.parallel_stream_count = 1;
+            return ThroughputRunner::new(config, Arc::new(ManualResetEvent::new(false))).run().await.unwrap();
+        });
+
+        assert_eq!(0, summary.failed_stream_count);
+        assert_eq!(1000, summary.uploaded_bytes);
+        assert!(summary.upload_duration >= summary.duration);
+    }
+
+    #[test]
+    fn throughput_runner_should_fail_stream_when_upload_is_not_confirmed() {
+        rnp_test_common::initialize();
+        let rt = Runtime::new().unwrap();
+
+        let server_address = "127.0.0.1:11357".parse::<SocketAddr>().unwrap();
+        start_run_upload_confirming_server(&rt, &server_address, None);
+
+        let summary = rt.block_on(async move {
+            let mut config = create_throughput_runner_config(&server_address, ThroughputDirection::Upload);
+            config.parallel_stream_count = 1;
+            return ThroughputRunner::new(config, Arc::new(ManualResetEvent::new(false))).run().await.unwrap();
+        });
+
+        assert_eq!(1, summary.failed_stream_count);
+        assert_eq!(0, summary.uploaded_bytes);
+    }
+
+    #[test]
+    fn throughput_runner_should_fail_when_connecting_to_non_existing_server() {
+        rnp_test_common::initialize();
+        let rt = Runtime::new().unwrap();
+
+        let server_address = "127.0.0.1:56789".parse::<SocketAddr>().unwrap();
+        let result = rt.block_on(async move {
+            let runner = ThroughputRunner::new(
+                create_throughput_runner_config(&server_address, ThroughputDirection::Upload),
+                Arc::new(ManualResetEvent::new(false)),
+            );
+            return runner.run().await;
+        });
+
+        assert!(matches!(result, Err(RnpError::ThroughputConnectFailed { target, .. }) if target == server_address));
+    }
+
+    fn create_throughput_runner_config(server_address: &SocketAddr, direction: ThroughputDirection) -> RnpThroughputRunnerConfig {
+        return RnpThroughputRunnerConfig {
+            target: PingTarget::Address(*server_address),
+            source_ip: "0.0.0.0".parse().unwrap(),
+            direction,
+            duration: Duration::from_millis(500),
+            parallel_stream_count: 2,
+            report_interval: Duration::from_millis(200),
+            connect_timeout: Duration::from_millis(500),
+            quiet_level: RNP_QUIET_LEVEL_NONE,
+        };
+    }
+
+    // Reads all the upload data, then confirms the given bytes or closes the connection without confirming.
+    fn start_run_upload_confirming_server(rt: &Runtime, server_address: &SocketAddr, confirmed_bytes: Option<u64>) {
+        let listener = rt.block_on(tokio::net::TcpListener::bind(server_address)).unwrap();
+        rt.spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut read_buf = vec![0u8; THROUGHPUT_CHUNK_SIZE];
+            while stream.read(&mut read_buf).await.unwrap() > 0 {}
+
+            if let Some(confirmed_bytes) = confirmed_bytes {
+                stream.write_all(&build_throughput_upload_confirmation(confirmed_bytes)).await.unwrap();
+            }
+            stream.shutdown().await.unwrap();
+        });
+    }
+
+    fn start_run_tcp_stub_server(rt: &Runtime, server_address: &SocketAddr) {
+        let stub_server_config = RnpStubServerConfig {
+            protocol: RnpSupportedProtocol::TCP,
+            server_address: *server_address,
+            close_on_accept: false,
+            close_on_handshake: false,
+            alpn_protocols: vec![],
+            reject_alpn: false,
+            sleep_before_write: Duration::ZERO,
+            write_chunk_size: 0,
+            write_count_limit: 0,
+            report_interval: Duration::from_secs(1),
+            wait_before_disconnect: Duration::ZERO,
+            fault_rules: vec![],
+        };
+
+        let ready_event = Arc::new(ManualResetEvent::new(false));
+        let ready_event_clone = ready_event.clone();
+        rt.spawn(async move {
+            let _ = stub_server_factory::run(&stub_server_config, Arc::new(ManualResetEvent::new(false)), ready_event_clone).unwrap().await;
+        });
+        rt.block_on(ready_event.wait());
+    }
+}
@@ -1,8 +1,12 @@
+use crate::rnp_utils;
 use num::One;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 use std::iter::Sum;
+use std::net::SocketAddr;
 use std::ops::{Add, RangeInclusive, Sub};
 use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct RangeListInclusive<Idx> {
@@ -69,8 +73,142 @@ impl<Idx: fmt::Display + PartialEq> fmt::Display for RangeListInclusive<Idx> {
     }
 }
 
+// Range lists are written in the same format as the command line in config files, such as "1024,10000-11000".
+impl<Idx: fmt::Display + PartialEq> Serialize for RangeListInclusive<Idx> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, Idx: Copy + FromStr> Deserialize<'de> for RangeListInclusive<Idx> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let range_list = String::deserialize(deserializer)?;
+        range_list.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 pub type PortRangeList = RangeListInclusive<u16>;
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PingTarget {
+    Address(SocketAddr),
+    HostName(String, u16),
+}
+
+impl PingTarget {
+    pub fn port(&self) -> u16 {
+        match self {
+            PingTarget::Address(address) => address.port(),
+            PingTarget::HostName(_, port) => *port,
+        }
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        match self {
+            PingTarget::Address(address) => Some(*address),
+            PingTarget::HostName(_, _) => None,
+        }
+    }
+}
+
+impl FromStr for PingTarget {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<PingTarget, Self::Err> {
+        return rnp_utils::parse_ping_target_with_host_name(input);
+    }
+}
+
+impl fmt::Display for PingTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PingTarget::Address(address) => write!(f, "{}", address),
+            PingTarget::HostName(host_name, port) => write!(f, "{}:{}", host_name, port),
+        }
+    }
+}
+
+impl Serialize for PingTarget {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PingTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let target = String::deserialize(deserializer)?;
+        target.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Target rate of issuing pings, such as "500/s", "30/m" or "120/h". A bare number is counted in pings per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PingRate {
+    pub pings_per_second: f64,
+}
+
+impl PingRate {
+    // The rate can still be set in code without being parsed, so the interval is capped instead of panicking on overflow.
+    pub fn interval(&self) -> Duration {
+        return Duration::try_from_secs_f64(1.0 / self.pings_per_second).unwrap_or(Duration::MAX);
+    }
+
+    // Same check as parsing, so rates set in code can be rejected by config validation instead of reaching the scheduler.
+    pub fn is_valid(&self) -> bool {
+        return self.pings_per_second.is_finite() && self.pings_per_second > 0.0 && Duration::try_from_secs_f64(1.0 / self.pings_per_second).is_ok();
+    }
+}
+
+impl FromStr for PingRate {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<PingRate, Self::Err> {
+        let (count, unit) = match input.split_once('/') {
+            Some((count, unit)) => (count.trim(), unit.trim()),
+            None => (input.trim(), "s"),
+        };
+
+        let unit_in_seconds = match unit {
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return Err(format!("Invalid rate unit \"{}\". It should be s, m or h. Examples: 500/s, 30/m", unit)),
+        };
+
+        let count = f64::from_str(count).map_err(|_| format!("Parse rate \"{}\" failed.", count))?;
+        if !count.is_finite() || count <= 0.0 {
+            return Err(format!("Invalid rate \"{}\". It should be larger than 0.", input));
+        }
+
+        // Tiny rates, such as 1e-300/s, make the interval too large to fit in a duration.
+        let pings_per_second = count / unit_in_seconds;
+        if Duration::try_from_secs_f64(1.0 / pings_per_second).is_err() {
+            return Err(format!("Invalid rate \"{}\". It is too small.", input));
+        }
+
+        return Ok(PingRate { pings_per_second });
+    }
+}
+
+impl fmt::Display for PingRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/s", self.pings_per_second)
+    }
+}
+
+impl Serialize for PingRate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PingRate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rate = String::deserialize(deserializer)?;
+        rate.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,4 +246,29 @@ mod tests {
         assert_eq!("1,2,5-6", RangeListInclusive { ranges: vec![(1..=1), (2..=2), (5..=6)] }.to_string());
         assert_eq!("1,2,5-6,100-200", RangeListInclusive { ranges: vec![(1..=1), (2..=2), (5..=6), (100..=200)] }.to_string());
     }
+
+    #[test]
+    fn ping_target_to_string_should_work() {
+        assert_eq!("10.0.0.1:443", PingTarget::Address("10.0.0.1:443".parse().unwrap()).to_string());
+        assert_eq!("[::1]:443", PingTarget::Address("[::1]:443".parse().unwrap()).to_string());
+        assert_eq!("www.example.com:443", PingTarget::HostName("www.example.com".to_string(), 443).to_string());
+    }
+
+    #[test]
+    fn parsing_ping_rate_should_work() {
+        assert_eq!(PingRate { pings_per_second: 500.0 }, "500/s".parse::<PingRate>().unwrap());
+        assert_eq!(PingRate { pings_per_second: 500.0 }, "500".parse::<PingRate>().unwrap());
+        assert_eq!(PingRate { pings_per_second: 0.5 }, "30/m".parse::<PingRate>().unwrap());
+        assert_eq!(PingRate { pings_per_second: 0.1 }, "360/h".parse::<PingRate>().unwrap());
+        assert_eq!(Duration::from_millis(2), PingRate { pings_per_second: 500.0 }.interval());
+        assert_eq!("0.5/s", "30/m".parse::<PingRate>().unwrap().to_string());
+
+        assert!("0/s".parse::<PingRate>().is_err());
+        assert!("-1/s".parse::<PingRate>().is_err());
+        assert!("500/d".parse::<PingRate>().is_err());
+        assert!("fast".parse::<PingRate>().is_err());
+        assert!("1e-300/s".parse::<PingRate>().is_err());
+        assert!("1e-300/h".parse::<PingRate>().is_err());
+        assert_eq!(Duration::MAX, PingRate { pings_per_second: 1e-300 }.interval());
+    }
 }
@@ -1,20 +1,34 @@
-use crate::{PingClientFactory, PingResultDto, PingResultProcessor, PortRangeList};
+use crate::{
+    parse_http_header, parse_http_path, PingClientRegistry, PingRate, PingResultDto, PingResultProcessor, PingTarget, PingTargetResolver,
+    PortRangeList, RnpPingRunnerConfigBuilder,
+};
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Debug;
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::{path::PathBuf, time::Duration};
 
 pub const RNP_NAME: &str = "Rnp";
 pub const RNP_SERVER_NAME: &str = "Rnp Server";
+pub const RNP_ANALYZE_NAME: &str = "Rnp Analyze";
 pub const RNP_AUTHOR: &str = "r12f (r12f.com, github.com/r12f)";
 pub const RNP_ABOUT: &str = "A simple layer 4 ping tool for cloud.";
 
+pub const RNP_DEFAULT_PING_COUNT: u32 = 4;
+pub const RNP_DEFAULT_LATENCY_BUCKETS: [f64; 10] = [0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 50.0, 100.0, 300.0, 500.0];
+
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum RnpSupportedProtocol {
     TCP,
+    UDP,
     QUIC,
+    TLS,
+    HTTP,
+    HTTPS,
     External(String),
 }
 
@@ -24,17 +38,33 @@ impl FromStr for RnpSupportedProtocol {
     fn from_str(input: &str) -> Result<RnpSupportedProtocol, Self::Err> {
         match input.to_uppercase().as_str() {
             "TCP" => Ok(RnpSupportedProtocol::TCP),
+            "UDP" => Ok(RnpSupportedProtocol::UDP),
             "QUIC" => Ok(RnpSupportedProtocol::QUIC),
+            "TLS" => Ok(RnpSupportedProtocol::TLS),
+            "HTTP" => Ok(RnpSupportedProtocol::HTTP),
+            "HTTPS" => Ok(RnpSupportedProtocol::HTTPS),
+            // Other names are resolved with the ping client factories registered by library users, so we only check the name here.
+            _ if RnpSupportedProtocol::is_valid_external_protocol_name(input) => Ok(RnpSupportedProtocol::External(input.to_string())),
             _ => Err(String::from("Invalid protocol")),
         }
     }
 }
 
+impl RnpSupportedProtocol {
+    fn is_valid_external_protocol_name(name: &str) -> bool {
+        return name.starts_with(|c: char| c.is_ascii_alphabetic()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    }
+}
+
 impl fmt::Display for RnpSupportedProtocol {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let protocol = match self {
             RnpSupportedProtocol::TCP => "TCP",
+            RnpSupportedProtocol::UDP => "UDP",
             RnpSupportedProtocol::QUIC => "QUIC",
+            RnpSupportedProtocol::TLS => "TLS",
+            RnpSupportedProtocol::HTTP => "HTTP",
+            RnpSupportedProtocol::HTTPS => "HTTPS",
             RnpSupportedProtocol::External(p) => &p,
         };
 
@@ -42,11 +72,82 @@ impl fmt::Display for RnpSupportedProtocol {
     }
 }
 
+impl Serialize for RnpSupportedProtocol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RnpSupportedProtocol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let protocol = String::deserialize(deserializer)?;
+        protocol.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// How the time between 2 pings is picked when pings are issued at a target rate. Poisson arrivals are exponentially distributed
+// around the rate interval, and jittered arrivals are picked evenly between 50% and 150% of it, which avoids syncing with periodic
+// events on the target side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingArrivalDistribution {
+    Constant,
+    Poisson,
+    Jitter,
+}
+
+impl FromStr for PingArrivalDistribution {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<PingArrivalDistribution, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "constant" => Ok(PingArrivalDistribution::Constant),
+            "poisson" => Ok(PingArrivalDistribution::Poisson),
+            "jitter" => Ok(PingArrivalDistribution::Jitter),
+            _ => Err(String::from("Invalid arrival distribution")),
+        }
+    }
+}
+
+impl fmt::Display for PingArrivalDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let distribution = match self {
+            PingArrivalDistribution::Constant => "constant",
+            PingArrivalDistribution::Poisson => "poisson",
+            PingArrivalDistribution::Jitter => "jitter",
+        };
+
+        write!(f, "{}", distribution)
+    }
+}
+
+impl Serialize for PingArrivalDistribution {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PingArrivalDistribution {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let distribution = String::deserialize(deserializer)?;
+        distribution.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// The runtime extensions, such as external ping client factories, can only be set in code, so they are skipped in config files.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
 pub struct RnpPingRunnerConfig {
     pub worker_config: PingWorkerConfig,
     pub worker_scheduler_config: PingWorkerSchedulerConfig,
     pub result_processor_config: PingResultProcessorConfig,
-    pub external_ping_client_factory: Option<PingClientFactory>,
+
+    #[serde(skip)]
+    pub ping_client_registry: PingClientRegistry,
+
+    #[serde(skip)]
+    pub external_ping_target_resolver: Option<Arc<dyn PingTargetResolver + Send + Sync>>,
+
+    #[serde(skip)]
     pub extra_ping_result_processors: Vec<Box<dyn PingResultProcessor + Send + Sync>>,
 }
 
@@ -56,9 +157,10 @@ impl Debug for RnpPingRunnerConfig {
             .field("worker_config", &self.worker_config)
             .field("worker_scheduler_config", &self.worker_scheduler_config)
             .field("result_processor_config", &self.result_processor_config)
+            .field("ping_client_registry", &self.ping_client_registry)
             .field(
-                "external_ping_client_factory",
-                &if self.external_ping_client_factory.is_some() { "Some(PingClientFactory)".to_string() } else { "None".to_string() },
+                "external_ping_target_resolver",
+                &if self.external_ping_target_resolver.is_some() { "Some(PingTargetResolver)".to_string() } else { "None".to_string() },
             )
             .field("extra_ping_result_processors", &self.extra_ping_result_processors.iter().map(|p| p.name()).collect::<Vec<&'static str>>())
             .finish()
@@ -76,7 +178,10 @@ impl PartialEq for RnpPingRunnerConfig {
         if self.result_processor_config != other.result_processor_config {
             return false;
         }
-        if self.external_ping_client_factory.is_some() != other.external_ping_client_factory.is_some() {
+        if self.ping_client_registry != other.ping_client_registry {
+            return false;
+        }
+        if self.external_ping_target_resolver.is_some() != other.external_ping_target_resolver.is_some() {
             return false;
         }
         let matching_processor_count =
@@ -86,37 +191,345 @@ impl PartialEq for RnpPingRunnerConfig {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum RnpConfigError {
+    #[error("No target is specified.")]
+    NoTarget,
+
+    #[error("Source IP {source_ip} and target IP {target} are not both IPv4 or IPv6.")]
+    SourceIpFamilyMismatch { source_ip: IpAddr, target: SocketAddr },
+
+    #[error("No source port is specified.")]
+    NoSourcePort,
+
+    #[error("Invalid source port range: {0}-{1}. Ports should be larger than 0, and start should not be larger than end.")]
+    InvalidSourcePortRange(u16, u16),
+
+    #[error("Invalid ping count: 0. It should be at least 1.")]
+    InvalidPingCount,
+
+    #[error("Invalid parallel ping count: {parallel_ping_count}. It should be at least 1 and no larger than available source port count ({source_port_count}).")]
+    InvalidParallelPingCount { parallel_ping_count: u32, source_port_count: u32 },
+
+    #[error("Invalid rate: {0}/s. It should be larger than 0 and not too small for its interval to fit in a duration.")]
+    InvalidRate(f64),
+
+    #[error("Invalid duration: 0s. It should be larger than 0.")]
+    InvalidDuration,
+
+    #[error("Invalid stop condition: ping count to stop on should be at least 1.")]
+    InvalidStopCondition,
+
+    #[error("Invalid HTTP body regex: {0}")]
+    InvalidHttpBodyRegex(String),
+
+    #[error("Invalid HTTP request: {0}")]
+    InvalidHttpRequest(String),
+
+    #[error("Invalid latency percentile: {0}. It should be in (0, 100].")]
+    InvalidLatencyPercentile(f64),
+
+    #[error("Latency buckets should not be empty.")]
+    NoLatencyBucket,
+
+    #[error("Exit failure reason should be set when exit on fail is enabled.")]
+    NoExitFailureReason,
+
+    #[error("Protocol {0} is not supported. No ping client factory is registered with this name.")]
+    UnsupportedProtocol(String),
+
+    #[error("Invalid report interval: 0s. It should be larger than 0.")]
+    InvalidReportInterval,
+
+    #[error("Invalid fault rule probability: {0}. It should be in [0, 1].")]
+    InvalidFaultProbability(f64),
+
+    #[error("{option} is only supported by TCP, but protocol {protocol} is used.")]
+    TcpOnlyOption { option: &'static str, protocol: String },
+}
+
+impl RnpPingRunnerConfig {
+    pub fn builder() -> RnpPingRunnerConfigBuilder {
+        return RnpPingRunnerConfigBuilder::new();
+    }
+
+    /// Fix up the options that have an obvious replacement, such as picking a random source port range when none is specified or
+    /// reducing the parallel ping count to the available source port count. The command line options are normalized in the same way.
+    pub fn normalize(&mut self) {
+        // Only switch the source IP when all the IP address targets are in the other address family. For host name targets, the
+        // address family is only known after resolving, so as for mixed targets, the unspecified source IP is adjusted by ping workers.
+        let source_ip = self.worker_config.source_ip;
+        let target_addresses: Vec<SocketAddr> = self.worker_config.targets.iter().filter_map(|target| target.address()).collect();
+        if !target_addresses.is_empty() && target_addresses.iter().all(|address| address.is_ipv4() != source_ip.is_ipv4()) {
+            match source_ip {
+                IpAddr::V4(source_ip_v4) if source_ip_v4 == Ipv4Addr::UNSPECIFIED => self.worker_config.source_ip = IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+                IpAddr::V6(source_ip_v6) if source_ip_v6 == Ipv6Addr::UNSPECIFIED => self.worker_config.source_ip = IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                _ => (), // Specified source IP is never changed, and it will be reported by validate().
+            }
+        }
+
+        let scheduler_config = &mut self.worker_scheduler_config;
+        if scheduler_config.source_ports.ranges.is_empty() {
+            let range_start = rand::thread_rng().gen_range(10000..30000);
+            let range_end = range_start + 2000;
+            scheduler_config.source_ports = PortRangeList { ranges: vec![(range_start..=range_end)] };
+        }
+
+        // Stopping after a duration means pinging until then, so the default ping count doesn't stop it early. Only a ping count that
+        // is specified together with the duration still limits the pings.
+        if scheduler_config.ping_count.is_none() && !scheduler_config.ping_until_stopped {
+            match scheduler_config.duration {
+                Some(_) => scheduler_config.ping_until_stopped = true,
+                None => scheduler_config.ping_count = Some(RNP_DEFAULT_PING_COUNT),
+            }
+        }
+
+        if scheduler_config.source_ports.ranges.iter().all(|r| *r.start() > 0 && r.start() <= r.end()) {
+            let available_source_port_count = scheduler_config.source_ports.calculate_total_port_count();
+            if scheduler_config.parallel_ping_count > available_source_port_count as u32 {
+                tracing::warn!(
+                    "Parallel ping count ({}) is larger than available source port count ({}), to avoid port conflict reducing parallel ping count down to the same as available source port count.",
+                    scheduler_config.parallel_ping_count,
+                    available_source_port_count);
+
+                scheduler_config.parallel_ping_count = available_source_port_count as u32;
+            }
+        }
+
+        if scheduler_config.parallel_ping_count < 1 {
+            tracing::warn!("Parallel ping count cannot be 0. Setting to 1 as minimum.");
+            scheduler_config.parallel_ping_count = 1;
+        }
+
+        let result_processor_config = &mut self.result_processor_config;
+        if result_processor_config.exit_on_fail && result_processor_config.exit_failure_reason.is_none() {
+            result_processor_config.exit_failure_reason = Some(Arc::new(Mutex::new(None)));
+        }
+
+        if let Some(latency_buckets) = &mut result_processor_config.latency_buckets {
+            if latency_buckets.is_empty() || (latency_buckets.len() == 1 && latency_buckets[0] == 0.0) {
+                tracing::debug!("Latency bucket set to 0. Use default one.");
+                *latency_buckets = RNP_DEFAULT_LATENCY_BUCKETS.to_vec();
+            }
+        }
+    }
+
+    /// Check all the options, so invalid configs are rejected with an error before we start running any ping.
+    pub fn validate(&self) -> Result<(), RnpConfigError> {
+        let worker_config = &self.worker_config;
+        if worker_config.targets.is_empty() {
+            return Err(RnpConfigError::NoTarget);
+        }
+
+        if let RnpSupportedProtocol::External(protocol) = &worker_config.protocol {
+            if !self.ping_client_registry.contains(&worker_config.protocol) {
+                return Err(RnpConfigError::UnsupportedProtocol(protocol.clone()));
+            }
+        }
+
+        // Same as normalize(), mixed targets are checked by ping workers on each ping, since they can only be partially reached.
+        let source_ip = worker_config.source_ip;
+        let target_addresses: Vec<SocketAddr> = worker_config.targets.iter().filter_map(|target| target.address()).collect();
+        if !source_ip.is_unspecified()
+            && !target_addresses.is_empty()
+            && target_addresses.iter().all(|address| address.is_ipv4() != source_ip.is_ipv4())
+        {
+            return Err(RnpConfigError::SourceIpFamilyMismatch { source_ip, target: target_addresses[0] });
+        }
+
+        // Options from config files are not parsed by the command line parser, so the HTTP path and headers are checked here again.
+        let ping_client_config = &worker_config.ping_client_config;
+        parse_http_path(&ping_client_config.http_path).map_err(RnpConfigError::InvalidHttpRequest)?;
+        for (name, value) in &ping_client_config.http_headers {
+            parse_http_header(&format!("{}: {}", name, value)).map_err(RnpConfigError::InvalidHttpRequest)?;
+        }
+
+        if let Some(http_body_regex) = &worker_config.ping_client_config.http_body_regex {
+            regex::Regex::new(http_body_regex).map_err(|e| RnpConfigError::InvalidHttpBodyRegex(e.to_string()))?;
+        }
+
+        // Built-in ping clients other than TCP ignore these options, while the ping client factories registered by the caller receive
+        // them and decide by themselves.
+        if worker_config.protocol != RnpSupportedProtocol::TCP && !self.ping_client_registry.contains(&worker_config.protocol) {
+            let tcp_only_error = |option| RnpConfigError::TcpOnlyOption { option, protocol: worker_config.protocol.to_string() };
+            if ping_client_config.measure_one_way_latency {
+                return Err(tcp_only_error("One-way latency measurement"));
+            }
+
+            if ping_client_config.use_persistent_connection {
+                return Err(tcp_only_error("Persistent connection"));
+            }
+        }
+
+        let scheduler_config = &self.worker_scheduler_config;
+        if scheduler_config.source_ports.ranges.is_empty() {
+            return Err(RnpConfigError::NoSourcePort);
+        }
+
+        if let Some(r) = scheduler_config.source_ports.ranges.iter().find(|r| *r.start() == 0 || r.start() > r.end()) {
+            return Err(RnpConfigError::InvalidSourcePortRange(*r.start(), *r.end()));
+        }
+
+        if scheduler_config.ping_count == Some(0) {
+            return Err(RnpConfigError::InvalidPingCount);
+        }
+
+        let source_port_count = scheduler_config.source_ports.calculate_total_port_count() as u32;
+        if scheduler_config.parallel_ping_count < 1 || scheduler_config.parallel_ping_count > source_port_count {
+            return Err(RnpConfigError::InvalidParallelPingCount { parallel_ping_count: scheduler_config.parallel_ping_count, source_port_count });
+        }
+
+        if let Some(rate) = scheduler_config.rate.filter(|rate| !rate.is_valid()) {
+            return Err(RnpConfigError::InvalidRate(rate.pings_per_second));
+        }
+
+        if scheduler_config.duration == Some(Duration::ZERO) {
+            return Err(RnpConfigError::InvalidDuration);
+        }
+
+        if scheduler_config.stop_on_consecutive_failures == Some(0) || scheduler_config.stop_on_recovery == Some(0) {
+            return Err(RnpConfigError::InvalidStopCondition);
+        }
+
+        return self.result_processor_config.validate();
+    }
+}
+
+// Durations in config files are written in human readable format, such as "1s" or "500ms".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PingWorkerConfig {
     pub protocol: RnpSupportedProtocol,
-    pub target: SocketAddr,
+    pub targets: Vec<PingTarget>,
     pub source_ip: IpAddr,
+    pub resolve_on_every_ping: bool,
+
+    #[serde(with = "humantime_serde")]
     pub ping_interval: Duration,
+
     pub ping_client_config: PingClientConfig,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl Default for PingWorkerConfig {
+    fn default() -> Self {
+        return PingWorkerConfig {
+            protocol: RnpSupportedProtocol::TCP,
+            targets: vec![],
+            source_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            resolve_on_every_ping: false,
+            ping_interval: Duration::from_secs(1),
+            ping_client_config: PingClientConfig::default(),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PingClientConfig {
+    #[serde(with = "humantime_serde")]
     pub wait_timeout: Duration,
+
     pub time_to_live: Option<u32>,
     pub check_disconnect: bool,
+
+    #[serde(with = "humantime_serde")]
     pub wait_before_disconnect: Duration,
+
+    #[serde(with = "humantime_serde")]
     pub disconnect_timeout: Duration,
+
     pub server_name: Option<String>,
     pub log_tls_key: bool,
     pub alpn_protocol: Option<String>,
     pub use_timer_rtt: bool,
+    pub http_method: String,
+    pub http_path: String,
+    pub http_headers: Vec<(String, String)>,
+    pub http_expected_status: Option<u16>,
+    pub http_body_regex: Option<String>,
+
+    // Only supported in TCP pings to rnp_server, which returns its receive and send timestamps for measuring one-way latency.
+    pub measure_one_way_latency: bool,
+
+    // Only supported in TCP pings to rnp_server. Each worker keeps its connection open and sends a small timestamped frame per ping, and
+    // reconnects are reported as separate ping results.
+    pub use_persistent_connection: bool,
+
+    // Options for external ping clients, such as SNMP community, which are passed as is to the ping client factory.
+    pub protocol_options: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl Default for PingClientConfig {
+    fn default() -> Self {
+        return PingClientConfig {
+            wait_timeout: Duration::from_secs(2),
+            time_to_live: None,
+            check_disconnect: false,
+            wait_before_disconnect: Duration::ZERO,
+            disconnect_timeout: Duration::from_secs(2),
+            server_name: None,
+            log_tls_key: false,
+            alpn_protocol: None,
+            use_timer_rtt: false,
+            http_method: String::from("GET"),
+            http_path: String::from("/"),
+            http_headers: vec![],
+            http_expected_status: None,
+            http_body_regex: None,
+            measure_one_way_latency: false,
+            use_persistent_connection: false,
+            protocol_options: BTreeMap::new(),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PingWorkerSchedulerConfig {
     pub source_ports: PortRangeList,
+
+    // Ping count is ignored when pinging until stopped. When neither is specified, normalize() pings until the duration if it is
+    // set, or uses the default ping count.
     pub ping_count: Option<u32>,
+    pub ping_until_stopped: bool,
+
     pub warmup_count: u32,
     pub parallel_ping_count: u32,
+
+    // Stop conditions that are checked together with ping count. Whichever is met first stops all pings.
+    #[serde(with = "humantime_serde")]
+    pub duration: Option<Duration>,
+
+    pub stop_on_consecutive_failures: Option<u32>,
+
+    // Count of consecutive successes after at least one failure, which is how we know the target has recovered.
+    pub stop_on_recovery: Option<u32>,
+
+    // When rate is set, pings are issued by a central scheduler at this rate regardless of how long each ping takes, and ping interval
+    // is ignored. Parallel ping count limits how many pings can be in flight at the same time.
+    pub rate: Option<PingRate>,
+    pub arrival_distribution: PingArrivalDistribution,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl Default for PingWorkerSchedulerConfig {
+    fn default() -> Self {
+        return PingWorkerSchedulerConfig {
+            source_ports: PortRangeList { ranges: vec![] },
+            ping_count: None,
+            ping_until_stopped: false,
+            warmup_count: 0,
+            parallel_ping_count: 1,
+            duration: None,
+            stop_on_consecutive_failures: None,
+            stop_on_recovery: None,
+            rate: None,
+            arrival_distribution: PingArrivalDistribution::Constant,
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PingResultProcessorCommonConfig {
     pub quiet_level: i32,
 }
@@ -126,17 +539,47 @@ pub const RNP_QUIET_LEVEL_NO_PING_RESULT: i32 = 1;
 pub const RNP_QUIET_LEVEL_NO_PING_SUMMARY: i32 = 2;
 pub const RNP_QUIET_LEVEL_NO_OUTPUT: i32 = 3;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PingResultProcessorConfig {
     pub common_config: PingResultProcessorCommonConfig,
     pub exit_on_fail: bool,
+
+    #[serde(skip)]
     pub exit_failure_reason: Option<Arc<Mutex<Option<PingResultDto>>>>,
+
     pub csv_log_path: Option<PathBuf>,
     pub json_log_path: Option<PathBuf>,
     pub text_log_path: Option<PathBuf>,
+    pub html_report_path: Option<PathBuf>,
+    pub metrics_address: Option<SocketAddr>,
     pub show_result_scatter: bool,
     pub show_latency_scatter: bool,
+    pub show_dashboard: bool,
     pub latency_buckets: Option<Vec<f64>>,
+    pub latency_percentiles: Option<Vec<f64>>,
+}
+
+impl PingResultProcessorConfig {
+    /// Check the options that can't be used as they are. Ping runner config checks it as well, and results loaded from logs are
+    /// checked before being analyzed.
+    pub fn validate(&self) -> Result<(), RnpConfigError> {
+        if let Some(latency_percentiles) = &self.latency_percentiles {
+            if let Some(invalid_percentile) = latency_percentiles.iter().find(|p| !(**p > 0.0 && **p <= 100.0)) {
+                return Err(RnpConfigError::InvalidLatencyPercentile(*invalid_percentile));
+            }
+        }
+
+        if self.latency_buckets.as_ref().is_some_and(|buckets| buckets.is_empty()) {
+            return Err(RnpConfigError::NoLatencyBucket);
+        }
+
+        if self.exit_on_fail && self.exit_failure_reason.is_none() {
+            return Err(RnpConfigError::NoExitFailureReason);
+        }
+
+        return Ok(());
+    }
 }
 
 impl PartialEq for PingResultProcessorConfig {
@@ -159,27 +602,244 @@ impl PartialEq for PingResultProcessorConfig {
         if self.text_log_path != other.text_log_path {
             return false;
         }
+        if self.html_report_path != other.html_report_path {
+            return false;
+        }
+        if self.metrics_address != other.metrics_address {
+            return false;
+        }
         if self.show_result_scatter != other.show_result_scatter {
             return false;
         }
         if self.show_latency_scatter != other.show_latency_scatter {
             return false;
         }
+        if self.show_dashboard != other.show_dashboard {
+            return false;
+        }
         if self.latency_buckets != other.latency_buckets {
             return false;
         }
+        if self.latency_percentiles != other.latency_percentiles {
+            return false;
+        }
         return true;
     }
 }
 
+// Regression thresholds are checked per target. The failure rate increase is in percentage points, e.g. from 1% to 3% is 2.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PingResultComparisonConfig {
+    pub common_config: PingResultProcessorCommonConfig,
+    pub latency_percentiles: Vec<f64>,
+    pub latency_buckets: Vec<f64>,
+    pub max_failure_rate_increase: Option<f64>,
+    pub max_latency_increase_in_ms: Option<f64>,
+}
+
+impl PingResultComparisonConfig {
+    pub fn validate(&self) -> Result<(), RnpConfigError> {
+        if let Some(invalid_percentile) = self.latency_percentiles.iter().find(|p| !(**p > 0.0 && **p <= 100.0)) {
+            return Err(RnpConfigError::InvalidLatencyPercentile(*invalid_percentile));
+        }
+
+        return Ok(());
+    }
+}
+
+// Direction of the data in throughput test. Upload means the data is sent from rnp to rnp_server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThroughputDirection {
+    Upload,
+    Download,
+    Bidirectional,
+}
+
+impl ThroughputDirection {
+    pub fn is_upload(&self) -> bool {
+        return *self != ThroughputDirection::Download;
+    }
+
+    pub fn is_download(&self) -> bool {
+        return *self != ThroughputDirection::Upload;
+    }
+}
+
+impl FromStr for ThroughputDirection {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<ThroughputDirection, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "upload" | "up" => Ok(ThroughputDirection::Upload),
+            "download" | "down" => Ok(ThroughputDirection::Download),
+            "bidirectional" | "bidir" => Ok(ThroughputDirection::Bidirectional),
+            _ => Err(String::from("Invalid throughput direction. Supported directions are upload, download and bidirectional")),
+        }
+    }
+}
+
+impl fmt::Display for ThroughputDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = match self {
+            ThroughputDirection::Upload => "upload",
+            ThroughputDirection::Download => "download",
+            ThroughputDirection::Bidirectional => "bidirectional",
+        };
+
+        write!(f, "{}", direction)
+    }
+}
+
+// Throughput test always runs on TCP, and the target needs to be rnp_server, because it needs to know when to send data back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RnpThroughputRunnerConfig {
+    pub target: PingTarget,
+    pub source_ip: IpAddr,
+    pub direction: ThroughputDirection,
+    pub duration: Duration,
+    pub parallel_stream_count: u32,
+    pub report_interval: Duration,
+    pub connect_timeout: Duration,
+    pub quiet_level: i32,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct RnpStubServerConfig {
     pub protocol: RnpSupportedProtocol,
     pub server_address: SocketAddr,
     pub report_interval: Duration,
     pub close_on_accept: bool,
+    pub close_on_handshake: bool,
+    pub alpn_protocols: Vec<String>,
+    pub reject_alpn: bool,
     pub write_chunk_size: usize,
     pub write_count_limit: u32,
     pub sleep_before_write: Duration,
     pub wait_before_disconnect: Duration,
+    pub fault_rules: Vec<RnpStubServerFaultRule>,
+}
+
+// Fault rules are checked in order for every accepted connection, and the first rule triggered decides how the connection is
+// treated. They are written as `<action>[=<value>][@<trigger>]`, such as `rst@30%`, `close-after=1024@every=3` or `hang@nth=1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RnpStubServerFaultRule {
+    pub action: RnpStubServerFaultAction,
+    pub trigger: RnpStubServerFaultTrigger,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RnpStubServerFaultAction {
+    // Close the connection with RST instead of FIN, whenever it is closed.
+    Reset,
+
+    // Stop accepting new connections for a while after this one, so the following connections will stay in the backlog.
+    DelayAccept(Duration),
+
+    // Keep the connection open without reading or writing anything.
+    Hang,
+
+    // Shutdown the writes only, and keep reading until the remote side closes the connection.
+    HalfClose,
+
+    // Close the connection after the specified number of bytes are read or written.
+    CloseAfterBytes(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RnpStubServerFaultTrigger {
+    Always,
+    Probability(f64),
+    EveryNthConnection(u32),
+    NthConnection(u32),
+}
+
+impl RnpStubServerFaultTrigger {
+    pub fn validate(&self) -> Result<(), RnpConfigError> {
+        return match self {
+            RnpStubServerFaultTrigger::Probability(probability) if !(0.0..=1.0).contains(probability) => {
+                Err(RnpConfigError::InvalidFaultProbability(*probability))
+            }
+            _ => Ok(()),
+        };
+    }
+
+    // The connection index starts from 1, which is the first connection accepted by the server.
+    pub fn is_triggered(&self, connection_index: u32) -> bool {
+        return match self {
+            RnpStubServerFaultTrigger::Always => true,
+            RnpStubServerFaultTrigger::Probability(probability) => rand::thread_rng().gen_bool(*probability),
+            RnpStubServerFaultTrigger::EveryNthConnection(n) => connection_index.checked_rem(*n) == Some(0),
+            RnpStubServerFaultTrigger::NthConnection(n) => connection_index == *n,
+        };
+    }
+}
+
+impl FromStr for RnpStubServerFaultRule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<RnpStubServerFaultRule, Self::Err> {
+        let (action, trigger) = match input.find('@') {
+            Some(at_sign_index) => (&input[..at_sign_index], Some(&input[(at_sign_index + 1)..])),
+            None => (input, None),
+        };
+
+        let action = RnpStubServerFaultRule::parse_action(action.trim())
+            .map_err(|e| format!("Invalid fault rule \"{}\": {}. The format should be \"<action>[=<value>][@<trigger>]\"", input, e))?;
+        let trigger = match trigger {
+            Some(trigger) => RnpStubServerFaultRule::parse_trigger(trigger.trim())
+                .map_err(|e| format!("Invalid fault rule \"{}\": {}. The format should be \"<action>[=<value>][@<trigger>]\"", input, e))?,
+            None => RnpStubServerFaultTrigger::Always,
+        };
+
+        return Ok(RnpStubServerFaultRule { action, trigger });
+    }
+}
+
+impl RnpStubServerFaultRule {
+    fn parse_action(input: &str) -> Result<RnpStubServerFaultAction, String> {
+        let (name, value) = RnpStubServerFaultRule::split_name_value(input);
+        return match (name.to_lowercase().as_str(), value) {
+            ("rst", None) => Ok(RnpStubServerFaultAction::Reset),
+            ("hang", None) => Ok(RnpStubServerFaultAction::Hang),
+            ("half-close", None) => Ok(RnpStubServerFaultAction::HalfClose),
+            ("delay-accept", Some(value)) => value
+                .parse::<u64>()
+                .map(|delay_in_ms| RnpStubServerFaultAction::DelayAccept(Duration::from_millis(delay_in_ms)))
+                .map_err(|_| format!("Invalid accept delay in milliseconds \"{}\"", value)),
+            ("close-after", Some(value)) => match value.parse::<usize>() {
+                Ok(bytes) if bytes > 0 => Ok(RnpStubServerFaultAction::CloseAfterBytes(bytes)),
+                _ => Err(format!("Invalid byte count \"{}\", it should be a positive integer", value)),
+            },
+            _ => Err(String::from("Unknown action. Supported actions are rst, hang, half-close, delay-accept=<ms> and close-after=<bytes>")),
+        };
+    }
+
+    fn parse_trigger(input: &str) -> Result<RnpStubServerFaultTrigger, String> {
+        if let Some(percentage) = input.strip_suffix('%') {
+            return match percentage.parse::<f64>() {
+                Ok(percentage) if (0.0..=100.0).contains(&percentage) => Ok(RnpStubServerFaultTrigger::Probability(percentage / 100.0)),
+                _ => Err(format!("Invalid probability \"{}\", it should be a percentage between 0% and 100%", input)),
+            };
+        }
+
+        let (name, value) = RnpStubServerFaultRule::split_name_value(input);
+        let connection_index = match value.map(|value| value.parse::<u32>()) {
+            Some(Ok(connection_index)) if connection_index > 0 => Some(connection_index),
+            _ => None,
+        };
+
+        return match (name.to_lowercase().as_str(), connection_index) {
+            ("always", _) if value.is_none() => Ok(RnpStubServerFaultTrigger::Always),
+            ("every", Some(n)) => Ok(RnpStubServerFaultTrigger::EveryNthConnection(n)),
+            ("nth", Some(n)) => Ok(RnpStubServerFaultTrigger::NthConnection(n)),
+            _ => Err(String::from("Unknown trigger. Supported triggers are always, <percentage>%, every=<n> and nth=<n>")),
+        };
+    }
+
+    fn split_name_value(input: &str) -> (&str, Option<&str>) {
+        return match input.find('=') {
+            Some(equal_sign_index) => (input[..equal_sign_index].trim(), Some(input[(equal_sign_index + 1)..].trim())),
+            None => (input, None),
+        };
+    }
 }
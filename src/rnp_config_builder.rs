@@ -0,0 +1,308 @@
+use crate::*;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builder of `RnpPingRunnerConfig` for library users, which starts from the same defaults as the command line, so only the options
+/// we care about need to be specified.
+///
+/// # Examples
+///
+/// ```
+/// use rnp::*;
+/// use std::time::Duration;
+///
+/// let config = RnpPingRunnerConfig::builder()
+///     .with_protocol(RnpSupportedProtocol::TCP)
+///     .with_target("10.0.0.1:443".parse().unwrap())
+///     .with_ping_count(Some(10))
+///     .with_ping_interval(Duration::from_millis(500))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(1, config.worker_config.targets.len());
+/// assert!(!config.worker_scheduler_config.source_ports.ranges.is_empty());
+/// ```
+pub struct RnpPingRunnerConfigBuilder {
+    config: RnpPingRunnerConfig,
+}
+
+impl RnpPingRunnerConfigBuilder {
+    pub fn new() -> RnpPingRunnerConfigBuilder {
+        let mut config = RnpPingRunnerConfig::default();
+        config.result_processor_config.latency_percentiles = Some(vec![50.0, 90.0, 99.0, 99.9]);
+        return RnpPingRunnerConfigBuilder { config };
+    }
+
+    pub fn with_protocol(mut self, protocol: RnpSupportedProtocol) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_config.protocol = protocol;
+        return self;
+    }
+
+    pub fn with_target(mut self, target: PingTarget) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_config.targets.push(target);
+        return self;
+    }
+
+    pub fn with_targets(mut self, targets: Vec<PingTarget>) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_config.targets.extend(targets);
+        return self;
+    }
+
+    pub fn with_source_ip(mut self, source_ip: IpAddr) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_config.source_ip = source_ip;
+        return self;
+    }
+
+    pub fn with_source_ports(mut self, source_ports: PortRangeList) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_scheduler_config.source_ports = source_ports;
+        return self;
+    }
+
+    pub fn with_ping_interval(mut self, ping_interval: Duration) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_config.ping_interval = ping_interval;
+        return self;
+    }
+
+    pub fn with_ping_client_config(mut self, ping_client_config: PingClientConfig) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_config.ping_client_config = ping_client_config;
+        return self;
+    }
+
+    pub fn with_wait_timeout(mut self, wait_timeout: Duration) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_config.ping_client_config.wait_timeout = wait_timeout;
+        return self;
+    }
+
+    /// Ping count is per target. None means ping until stopped.
+    pub fn with_ping_count(mut self, ping_count: Option<u32>) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_scheduler_config.ping_count = ping_count;
+        self.config.worker_scheduler_config.ping_until_stopped = ping_count.is_none();
+        return self;
+    }
+
+    pub fn with_warmup_count(mut self, warmup_count: u32) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_scheduler_config.warmup_count = warmup_count;
+        return self;
+    }
+
+    pub fn with_parallel_ping_count(mut self, parallel_ping_count: u32) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_scheduler_config.parallel_ping_count = parallel_ping_count;
+        return self;
+    }
+
+    /// Pings until the duration is reached, unless a ping count is also specified.
+    pub fn with_duration(mut self, duration: Duration) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_scheduler_config.duration = Some(duration);
+        return self;
+    }
+
+    pub fn with_stop_on_consecutive_failures(mut self, failure_count: u32) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_scheduler_config.stop_on_consecutive_failures = Some(failure_count);
+        return self;
+    }
+
+    pub fn with_stop_on_recovery(mut self, success_count: u32) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_scheduler_config.stop_on_recovery = Some(success_count);
+        return self;
+    }
+
+    pub fn with_rate(mut self, rate: PingRate, arrival_distribution: PingArrivalDistribution) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_scheduler_config.rate = Some(rate);
+        self.config.worker_scheduler_config.arrival_distribution = arrival_distribution;
+        return self;
+    }
+
+    pub fn with_result_processor_config(mut self, result_processor_config: PingResultProcessorConfig) -> RnpPingRunnerConfigBuilder {
+        self.config.result_processor_config = result_processor_config;
+        return self;
+    }
+
+    pub fn with_quiet_level(mut self, quiet_level: i32) -> RnpPingRunnerConfigBuilder {
+        self.config.result_processor_config.common_config.quiet_level = quiet_level;
+        return self;
+    }
+
+    pub fn with_protocol_option(mut self, name: &str, value: &str) -> RnpPingRunnerConfigBuilder {
+        self.config.worker_config.ping_client_config.protocol_options.insert(name.to_string(), value.to_string());
+        return self;
+    }
+
+    /// Register the ping client factory for the protocol with this name, which can then be used with `with_protocol`.
+    pub fn with_ping_client_factory<F>(mut self, protocol_name: &str, ping_client_factory: F) -> RnpPingRunnerConfigBuilder
+    where
+        F: Fn(&RnpSupportedProtocol, &PingClientConfig) -> Option<Box<dyn PingClient + Send + Sync>> + Send + Sync + 'static,
+    {
+        self.config.ping_client_registry.register(protocol_name, ping_client_factory);
+        return self;
+    }
+
+    pub fn with_ping_client_registry(mut self, ping_client_registry: PingClientRegistry) -> RnpPingRunnerConfigBuilder {
+        self.config.ping_client_registry = ping_client_registry;
+        return self;
+    }
+
+    pub fn with_ping_target_resolver(mut self, ping_target_resolver: Arc<dyn PingTargetResolver + Send + Sync>) -> RnpPingRunnerConfigBuilder {
+        self.config.external_ping_target_resolver = Some(ping_target_resolver);
+        return self;
+    }
+
+    pub fn with_ping_result_processor(mut self, ping_result_processor: Box<dyn PingResultProcessor + Send + Sync>) -> RnpPingRunnerConfigBuilder {
+        self.config.extra_ping_result_processors.push(ping_result_processor);
+        return self;
+    }
+
+    /// Normalize the options in the same way as the command line and return the config if it is valid.
+    pub fn build(mut self) -> Result<RnpPingRunnerConfig, RnpConfigError> {
+        self.config.normalize();
+        self.config.validate()?;
+        return Ok(self.config);
+    }
+}
+
+impl Default for RnpPingRunnerConfigBuilder {
+    fn default() -> Self {
+        return RnpPingRunnerConfigBuilder::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use pretty_assertions::assert_eq;
+    use std::net::{IpAddr, Ipv6Addr};
+    use std::ops::RangeInclusive;
+    use std::time::Duration;
+
+    #[test]
+    fn building_config_with_defaults_should_work() {
+        let config = RnpPingRunnerConfig::builder().with_target("10.0.0.1:443".parse().unwrap()).build().unwrap();
+
+        assert_eq!(RnpSupportedProtocol::TCP, config.worker_config.protocol);
+        assert_eq!(Some(4), config.worker_scheduler_config.ping_count);
+        assert_eq!(1, config.worker_scheduler_config.parallel_ping_count);
+        assert_eq!(Duration::from_secs(2), config.worker_config.ping_client_config.wait_timeout);
+        assert_eq!(1, config.worker_scheduler_config.source_ports.ranges.len());
+        assert_eq!(2001, config.worker_scheduler_config.source_ports.calculate_total_port_count());
+    }
+
+    #[test]
+    fn building_config_should_normalize_options() {
+        let config = RnpPingRunnerConfig::builder()
+            .with_target("[2607:f8b0:400a:80a::200e]:443".parse().unwrap())
+            .with_source_ports(PortRangeList { ranges: vec![(1024..=1024), (1025..=1026)] })
+            .with_parallel_ping_count(100)
+            .with_result_processor_config(PingResultProcessorConfig { exit_on_fail: true, latency_buckets: Some(vec![0.0]), ..Default::default() })
+            .build()
+            .unwrap();
+
+        assert_eq!(IpAddr::V6(Ipv6Addr::UNSPECIFIED), config.worker_config.source_ip);
+        assert_eq!(3, config.worker_scheduler_config.parallel_ping_count);
+        assert!(config.result_processor_config.exit_failure_reason.is_some());
+        assert_eq!(Some(RNP_DEFAULT_LATENCY_BUCKETS.to_vec()), config.result_processor_config.latency_buckets);
+    }
+
+    #[test]
+    fn building_config_with_duration_should_ping_until_stopped_unless_ping_count_is_specified() {
+        let builder = || RnpPingRunnerConfig::builder().with_target("10.0.0.1:443".parse().unwrap()).with_duration(Duration::from_secs(60));
+
+        let config = builder().build().unwrap();
+        assert!(config.worker_scheduler_config.ping_until_stopped);
+
+        let config = builder().with_ping_count(Some(10)).build().unwrap();
+        assert!(!config.worker_scheduler_config.ping_until_stopped);
+        assert_eq!(Some(10), config.worker_scheduler_config.ping_count);
+    }
+
+    #[test]
+    fn building_invalid_config_should_fail_with_typed_error() {
+        assert_eq!(Some(RnpConfigError::NoTarget), RnpPingRunnerConfig::builder().build().err());
+
+        let builder = || RnpPingRunnerConfig::builder().with_target("10.0.0.1:443".parse().unwrap());
+        assert_eq!(
+            Some(RnpConfigError::SourceIpFamilyMismatch { source_ip: "::1".parse().unwrap(), target: "10.0.0.1:443".parse().unwrap() }),
+            builder().with_source_ip("::1".parse().unwrap()).build().err()
+        );
+        assert_eq!(
+            Some(RnpConfigError::InvalidSourcePortRange(2048, 1024)),
+            builder().with_source_ports(PortRangeList { ranges: vec![RangeInclusive::new(2048, 1024)] }).build().err()
+        );
+        assert_eq!(Some(RnpConfigError::InvalidPingCount), builder().with_ping_count(Some(0)).build().err());
+        assert_eq!(Some(RnpConfigError::InvalidDuration), builder().with_duration(Duration::ZERO).build().err());
+        assert_eq!(Some(RnpConfigError::InvalidStopCondition), builder().with_stop_on_recovery(0).build().err());
+        for pings_per_second in [0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300] {
+            let rate = PingRate { pings_per_second };
+            assert!(
+                matches!(builder().with_rate(rate, PingArrivalDistribution::Constant).build(), Err(RnpConfigError::InvalidRate(_))),
+                "Rate {} should be rejected",
+                rate
+            );
+        }
+        assert_eq!(
+            Some(RnpConfigError::UnsupportedProtocol(String::from("ICMP"))),
+            builder().with_protocol(RnpSupportedProtocol::External(String::from("ICMP"))).build().err()
+        );
+        assert_eq!(
+            Some(RnpConfigError::UnsupportedProtocol(String::from("ICMP"))),
+            builder().with_protocol(RnpSupportedProtocol::External(String::from("ICMP"))).with_ping_client_factory("SNMP", |_, _| None).build().err()
+        );
+
+        let ping_client_config = PingClientConfig { measure_one_way_latency: true, ..Default::default() };
+        assert_eq!(
+            Some(RnpConfigError::TcpOnlyOption { option: "One-way latency measurement", protocol: String::from("UDP") }),
+            builder().with_protocol(RnpSupportedProtocol::UDP).with_ping_client_config(ping_client_config.clone()).build().err()
+        );
+        assert!(builder().with_ping_client_config(ping_client_config).build().is_ok());
+
+        let ping_client_config = PingClientConfig { use_persistent_connection: true, ..Default::default() };
+        assert_eq!(
+            Some(RnpConfigError::TcpOnlyOption { option: "Persistent connection", protocol: String::from("QUIC") }),
+            builder().with_protocol(RnpSupportedProtocol::QUIC).with_ping_client_config(ping_client_config.clone()).build().err()
+        );
+        assert!(builder()
+            .with_protocol(RnpSupportedProtocol::QUIC)
+            .with_ping_client_config(ping_client_config)
+            .with_ping_client_factory("QUIC", |_, _| None)
+            .build()
+            .is_ok());
+
+        let ping_client_config = PingClientConfig { http_body_regex: Some(String::from("(")), ..Default::default() };
+        assert!(matches!(builder().with_ping_client_config(ping_client_config).build(), Err(RnpConfigError::InvalidHttpBodyRegex(_))));
+
+        let ping_client_config = PingClientConfig { http_path: String::from("/\r\nHost: evil.com"), ..Default::default() };
+        assert!(matches!(builder().with_ping_client_config(ping_client_config).build(), Err(RnpConfigError::InvalidHttpRequest(_))));
+
+        let ping_client_config =
+            PingClientConfig { http_headers: vec![(String::from("X-Test"), String::from("1\r\nHost: evil.com"))], ..Default::default() };
+        assert!(matches!(builder().with_ping_client_config(ping_client_config).build(), Err(RnpConfigError::InvalidHttpRequest(_))));
+    }
+
+    #[test]
+    fn building_config_with_external_protocol_should_work() {
+        let config = RnpPingRunnerConfig::builder()
+            .with_target("10.0.0.1:161".parse().unwrap())
+            .with_protocol("snmp".parse().unwrap())
+            .with_protocol_option("community", "public")
+            .with_ping_client_factory("SNMP", |_, _| None)
+            .build()
+            .unwrap();
+
+        assert_eq!(RnpSupportedProtocol::External(String::from("snmp")), config.worker_config.protocol);
+        assert_eq!(Some(&String::from("public")), config.worker_config.ping_client_config.protocol_options.get("community"));
+        assert_eq!(vec!["SNMP"], config.ping_client_registry.protocol_names());
+    }
+
+    #[test]
+    fn validating_config_without_normalizing_should_fail() {
+        let mut config = RnpPingRunnerConfig::default();
+        config.worker_config.targets.push("10.0.0.1:443".parse().unwrap());
+        assert_eq!(Err(RnpConfigError::NoSourcePort), config.validate());
+
+        config.worker_scheduler_config.source_ports = PortRangeList { ranges: vec![(1024..=1025)] };
+        config.worker_scheduler_config.parallel_ping_count = 3;
+        assert_eq!(Err(RnpConfigError::InvalidParallelPingCount { parallel_ping_count: 3, source_port_count: 2 }), config.validate());
+
+        config.normalize();
+        assert_eq!(Ok(()), config.validate());
+    }
+}
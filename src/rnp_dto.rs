@@ -1,3 +1,4 @@
+use crate::PingFailureClass;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
@@ -20,6 +21,33 @@ pub struct PingResultDto {
     pub ping_error: String,
     pub handshake_error: String,
     pub disconnect_error: String,
+
+    #[serde(default)]
+    pub dns_resolve_time_in_ms: f64,
+
+    #[serde(default)]
+    pub handshake_time_in_ms: f64,
+
+    #[serde(default)]
+    pub first_byte_time_in_ms: f64,
+
+    #[serde(default)]
+    pub response_time_in_ms: f64,
+
+    #[serde(default)]
+    pub failure_class: Option<PingFailureClass>,
+
+    #[serde(default)]
+    pub forward_delay_in_ms: f64,
+
+    #[serde(default)]
+    pub reverse_delay_in_ms: f64,
+
+    #[serde(default)]
+    pub clock_offset_in_ms: f64,
+
+    #[serde(default)]
+    pub reconnect_reason: String,
 }
 
 impl PingResultDto {
@@ -61,15 +89,37 @@ impl PingResultDto {
             );
         }
 
+        if !self.reconnect_reason.is_empty() {
+            return format!(
+                "Reaching {} {}:{} from {}:{}{} succeeded, but reconnected: RTT={:.2}ms, Reason = {}",
+                self.protocol, self.target_ip, self.target_port, self.source_ip, self.source_port, warmup_sign, self.rtt_in_ms, self.reconnect_reason,
+            );
+        }
+
+        // App level timings are only available in certain protocols, such as TLS and HTTP.
+        let mut app_timings = String::new();
+        if self.handshake_time_in_ms > 0.0 {
+            app_timings.push_str(&format!(", Handshake={:.2}ms", self.handshake_time_in_ms));
+        }
+        if self.first_byte_time_in_ms > 0.0 {
+            app_timings.push_str(&format!(", FirstByte={:.2}ms", self.first_byte_time_in_ms));
+        }
+        if self.response_time_in_ms > 0.0 {
+            app_timings.push_str(&format!(", Response={:.2}ms", self.response_time_in_ms));
+        }
+        if self.forward_delay_in_ms > 0.0 || self.reverse_delay_in_ms > 0.0 {
+            app_timings.push_str(&format!(", Forward={:.2}ms, Reverse={:.2}ms", self.forward_delay_in_ms, self.reverse_delay_in_ms));
+        }
+
         return format!(
-            "Reaching {} {}:{} from {}:{}{} succeeded: RTT={:.2}ms",
-            self.protocol, self.target_ip, self.target_port, self.source_ip, self.source_port, warmup_sign, self.rtt_in_ms,
+            "Reaching {} {}:{} from {}:{}{} succeeded: RTT={:.2}ms{}",
+            self.protocol, self.target_ip, self.target_port, self.source_ip, self.source_port, warmup_sign, self.rtt_in_ms, app_timings,
         );
     }
 
     pub fn to_json_lite(&self) -> String {
         format!(
-            "{{\"UtcTime\":\"{:?}\",\"WorkerId\":{},\"Protocol\":\"{}\",\"TargetIp\":\"{}\",\"TargetPort\":{},\"SourceIp\":\"{}\",\"SourcePort\":{},\"IsWarmup\":{},\"IsSucceeded\":{},\"RttInMs\":{:.2},\"IsTimedOut\":{},\"PreparationError\":\"{}\",\"PingError\":\"{}\",\"HandshakeError\":\"{}\",\"DisconnectError\":\"{}\"}}",
+            "{{\"UtcTime\":\"{:?}\",\"WorkerId\":{},\"Protocol\":\"{}\",\"TargetIp\":\"{}\",\"TargetPort\":{},\"SourceIp\":\"{}\",\"SourcePort\":{},\"IsWarmup\":{},\"IsSucceeded\":{},\"RttInMs\":{:.2},\"IsTimedOut\":{},\"PreparationError\":{},\"PingError\":{},\"HandshakeError\":{},\"DisconnectError\":{},\"DnsResolveTimeInMs\":{:.2},\"HandshakeTimeInMs\":{:.2},\"FirstByteTimeInMs\":{:.2},\"ResponseTimeInMs\":{:.2},\"FailureClass\":{},\"ForwardDelayInMs\":{:.2},\"ReverseDelayInMs\":{:.2},\"ClockOffsetInMs\":{:.2},\"ReconnectReason\":{}}}",
             self.utc_time,
             self.worker_id,
             self.protocol,
@@ -81,16 +131,25 @@ impl PingResultDto {
             self.is_succeeded,
             self.rtt_in_ms,
             self.is_timed_out,
-            self.preparation_error,
-            self.ping_error,
-            self.handshake_error,
-            self.disconnect_error,
+            to_json_string(&self.preparation_error),
+            to_json_string(&self.ping_error),
+            to_json_string(&self.handshake_error),
+            to_json_string(&self.disconnect_error),
+            self.dns_resolve_time_in_ms,
+            self.handshake_time_in_ms,
+            self.first_byte_time_in_ms,
+            self.response_time_in_ms,
+            self.failure_class.map_or(String::from("null"), |c| format!("\"{}\"", c)),
+            self.forward_delay_in_ms,
+            self.reverse_delay_in_ms,
+            self.clock_offset_in_ms,
+            to_json_string(&self.reconnect_reason),
         )
     }
 
     pub fn to_csv_lite(&self) -> String {
         format!(
-            "{:?},{},{},{},{},{},{},{},{},{:.2},{},\"{}\",\"{}\",\"{}\",\"{}\"",
+            "{:?},{},{},{},{},{},{},{},{},{:.2},{},{},{},{},{},{:.2},{:.2},{:.2},{:.2},{},{:.2},{:.2},{:.2},{}",
             self.utc_time,
             self.worker_id,
             self.protocol,
@@ -102,10 +161,29 @@ impl PingResultDto {
             self.is_succeeded,
             self.rtt_in_ms,
             self.is_timed_out,
-            self.preparation_error,
-            self.ping_error,
-            self.handshake_error,
-            self.disconnect_error,
+            to_csv_string(&self.preparation_error),
+            to_csv_string(&self.ping_error),
+            to_csv_string(&self.handshake_error),
+            to_csv_string(&self.disconnect_error),
+            self.dns_resolve_time_in_ms,
+            self.handshake_time_in_ms,
+            self.first_byte_time_in_ms,
+            self.response_time_in_ms,
+            self.failure_class.map_or(String::from(""), |c| c.to_string()),
+            self.forward_delay_in_ms,
+            self.reverse_delay_in_ms,
+            self.clock_offset_in_ms,
+            to_csv_string(&self.reconnect_reason),
         )
     }
 }
+
+// Error messages can contain any character, such as quotes in the HTTP body regex, so they need to be escaped, otherwise the log
+// files cannot be loaded back.
+fn to_json_string(value: &str) -> String {
+    return serde_json::to_string(value).unwrap_or_default();
+}
+
+fn to_csv_string(value: &str) -> String {
+    return format!("\"{}\"", value.replace('"', "\"\""));
+}
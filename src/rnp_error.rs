@@ -0,0 +1,33 @@
+use crate::RnpConfigError;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+// Errors that fail the whole run, which are returned to the caller instead of taking down the process. Failures of a single ping are not
+// errors here, because they are reported in the ping results.
+#[derive(thiserror::Error, Debug)]
+pub enum RnpError {
+    #[error("{0}")]
+    InvalidConfig(#[from] RnpConfigError),
+
+    #[error("Protocol {0} is not supported!")]
+    UnsupportedProtocol(String),
+
+    #[error("Failed to create log file: {}: {source}", path.display())]
+    LogFileCreationFailed { path: PathBuf, source: io::Error },
+
+    #[error("Failed to start prometheus metrics server! Address = {address}, Error = {source}")]
+    MetricsServerStartFailed { address: SocketAddr, source: io::Error },
+
+    #[error("Failed to resolve throughput test target {target}! Error = {source}")]
+    ThroughputTargetResolveFailed { target: String, source: io::Error },
+
+    #[error("Failed to connect to {target} for throughput test! Error = {source}")]
+    ThroughputConnectFailed { target: SocketAddr, source: io::Error },
+
+    #[error("Failed to copy ping result! Error = {0}")]
+    PingResultCopyFailed(String),
+
+    #[error("Ping run is aborted! Error = {0}")]
+    PingRunAborted(#[source] tokio::task::JoinError),
+}
@@ -27,7 +27,8 @@ pub fn generate_ping_result_test_samples() -> Vec<PingResult> {
             false,
             None,
             None,
-        ),
+        )
+        .with_dns_resolve_time(Some(Duration::from_millis(5))),
         // Timeout
         PingResult::new(
             &(Utc.with_ymd_and_hms(2021, 7, 6, 9, 10, 11).unwrap() + chrono::Duration::milliseconds(12)),
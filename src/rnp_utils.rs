@@ -1,21 +1,18 @@
+use crate::{PingResultDto, PingTarget, RnpError, RnpPingRunnerConfig};
 use std::fs::{self, File};
 use std::net::{IpAddr, SocketAddr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
-pub fn create_log_file(log_path_buf: &PathBuf) -> File {
+pub fn create_log_file(log_path_buf: &PathBuf) -> Result<File, RnpError> {
     let log_path = log_path_buf.as_path();
-    match log_path.parent() {
-        Some(log_folder) => fs::create_dir_all(log_folder).expect(&format!("Failed to create log folder: {}", log_folder.display())),
-        None => (), // current folder.
+    let to_error = |source| RnpError::LogFileCreationFailed { path: log_path_buf.clone(), source };
+    if let Some(log_folder) = log_path.parent() {
+        fs::create_dir_all(log_folder).map_err(to_error)?;
     }
 
-    let log_file = match File::create(&log_path) {
-        Err(e) => panic!("Failed to create log file: {}: {}", log_path.display(), e),
-        Ok(file) => file,
-    };
-
-    return log_file;
+    return File::create(log_path).map_err(to_error);
 }
 
 pub fn parse_ping_target(input: &str) -> Result<SocketAddr, String> {
@@ -71,14 +68,8 @@ pub fn parse_ping_target(input: &str) -> Result<SocketAddr, String> {
         for c in ip_str.chars() {
             if !c.is_numeric() && c != '.' {
                 return Err(format!(
-                    "Invalid IP \"{}\" found in ping target \"{}\"\n\n\
-                        NOTICE: \"{}\" looks like a domain name and pinging a domain name is explicitly banned. \
-                        This is because DNS could return different IP address for the same domain name, \
-                        which misleads people when collaborating on network issues. If it is a domain, \
-                        please run the following command and and choose a IP to ping, otherwise please \
-                        fix the ip and try again:\
-                        \n\n    nslookup {}\n",
-                    ip_str, input, ip_str, ip_str
+                    "Invalid IP \"{}\" found in ping target \"{}\". \"{}\" looks like a host name, which is not supported here. Please use IP address instead.",
+                    ip_str, input, ip_str
                 ));
             }
         }
@@ -93,10 +84,194 @@ pub fn parse_ping_target(input: &str) -> Result<SocketAddr, String> {
     return Ok(SocketAddr::new(ip, port));
 }
 
+/// Parse ping target which can be either a socket address (same as `parse_ping_target`) or a host name with an optional port,
+/// such as `www.example.com:443`. Host names will be resolved later by the ping target resolver.
+pub fn parse_ping_target_with_host_name(input: &str) -> Result<PingTarget, String> {
+    let ip_parse_error = match parse_ping_target(input) {
+        Ok(address) => return Ok(PingTarget::Address(address)),
+        Err(e) => e,
+    };
+
+    // Host name never contains brackets, so if we see them, it should be an invalid IPv6 address.
+    if input.contains('[') || input.contains(']') {
+        return Err(ip_parse_error);
+    }
+
+    let (host_name, port_str) = match input.rfind(':') {
+        Some(last_colon_index) => (&input[..last_colon_index], &input[(last_colon_index + 1)..]),
+        None => (input, ""),
+    };
+
+    // A host name with only digits and dots is an invalid IPv4 address, such as "10.0.0", instead of a host name.
+    if host_name.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return Err(ip_parse_error);
+    }
+
+    if !is_valid_host_name(host_name) {
+        return Err(format!("Invalid IP or host name \"{}\" found in ping target \"{}\"", host_name, input));
+    }
+
+    let port = if port_str.is_empty() {
+        80
+    } else {
+        u16::from_str(port_str).map_err(|_| format!("Invalid port \"{}\" found in ping target \"{}\"", port_str, input))?
+    };
+
+    return Ok(PingTarget::HostName(host_name.to_string(), port));
+}
+
+/// Load ping targets from a file with one target per line. Empty lines and lines starting with '#' are skipped.
+pub fn load_ping_targets_from_file(path: &Path) -> Result<Vec<PingTarget>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read targets file: {}: {}", path.display(), e))?;
+
+    let mut targets = Vec::new();
+    for (line_index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let target = parse_ping_target_with_host_name(line).map_err(|e| format!("{}:{}: {}", path.display(), line_index + 1, e))?;
+        targets.push(target);
+    }
+
+    return Ok(targets);
+}
+
+/// Load ping runner config from a TOML file, or a YAML file when the extension is `.yaml` or `.yml`. The file has the same fields as
+/// `RnpPingRunnerConfig`, and all fields are optional. When a profile is specified, the fields under `profiles.<profile>` are merged
+/// on top of the base config, e.g. `[profiles.lb-drain.worker_scheduler_config]`.
+pub fn load_ping_runner_config_from_file(path: &Path, profile: Option<&str>) -> Result<RnpPingRunnerConfig, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read config file: {}: {}", path.display(), e))?;
+
+    // Both formats are loaded into the same value tree first, so profiles can be merged in the same way.
+    let is_yaml = matches!(path.extension().and_then(|e| e.to_str()), Some("yaml") | Some("yml"));
+    let parse_result =
+        if is_yaml { serde_yaml::from_str(&content).map_err(|e| e.to_string()) } else { toml::from_str(&content).map_err(|e| e.to_string()) };
+    let mut config_value: serde_json::Value = parse_result.map_err(|e| format!("Failed to parse config file: {}: {}", path.display(), e))?;
+
+    let profiles = match config_value.as_object_mut() {
+        Some(config_table) => config_table.remove("profiles"),
+        None => return Err(format!("Invalid config file: {}: The root of the config should be a table.", path.display())),
+    };
+
+    if let Some(profile) = profile {
+        let profile_value = profiles
+            .and_then(|mut profiles| profiles.get_mut(profile).map(|p| p.take()))
+            .ok_or_else(|| format!("Profile \"{}\" is not found in config file: {}", profile, path.display()))?;
+        merge_config_value(&mut config_value, profile_value);
+    }
+
+    let mut config: RnpPingRunnerConfig =
+        serde_json::from_value(config_value).map_err(|e| format!("Invalid config file: {}: {}", path.display(), e))?;
+
+    // The exit failure reason is not a setting but the slot for reporting the failed ping, so we create it along with exit on fail.
+    if config.result_processor_config.exit_on_fail {
+        config.result_processor_config.exit_failure_reason = Some(Arc::new(Mutex::new(None)));
+    }
+
+    return Ok(config);
+}
+
+// Tables are merged recursively, while all other values, including arrays, are replaced by the overlay.
+fn merge_config_value(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base_table), serde_json::Value::Object(overlay_table)) => {
+            for (key, overlay_value) in overlay_table {
+                match base_table.get_mut(&key) {
+                    Some(base_value) => merge_config_value(base_value, overlay_value),
+                    None => {
+                        base_table.insert(key, overlay_value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Load ping results from the logs written by `--log-csv` or `--log-json`. The file is parsed as JSON when the extension is `.json`,
+/// otherwise as CSV.
+pub fn load_ping_results_from_file(path: &Path) -> Result<Vec<PingResultDto>, String> {
+    let is_json = matches!(path.extension().and_then(|e| e.to_str()), Some("json"));
+    if is_json {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read ping result file: {}: {}", path.display(), e))?;
+        return serde_json::from_str(&content).map_err(|e| format!("Failed to parse ping result file: {}: {}", path.display(), e));
+    }
+
+    let mut csv_reader = csv::Reader::from_path(path).map_err(|e| format!("Failed to read ping result file: {}: {}", path.display(), e))?;
+    let mut ping_results = Vec::new();
+    for record in csv_reader.deserialize() {
+        let ping_result: PingResultDto = record.map_err(|e| format!("Failed to parse ping result file: {}: {}", path.display(), e))?;
+        ping_results.push(ping_result);
+    }
+
+    return Ok(ping_results);
+}
+
+/// Parse HTTP request path, such as `/health`. Line breaks are rejected, since the path is written into the request as is.
+pub fn parse_http_path(input: &str) -> Result<String, String> {
+    if input.contains(['\r', '\n']) {
+        return Err(format!("Invalid HTTP path {:?}, line breaks are not allowed", input));
+    }
+
+    return Ok(input.to_string());
+}
+
+/// Parse HTTP header in the format of `Name: Value`, such as `Accept: text/html`.
+pub fn parse_http_header(input: &str) -> Result<(String, String), String> {
+    // Same as the path, the header is written into the request as is, so line breaks would inject extra headers.
+    if input.contains(['\r', '\n']) {
+        return Err(format!("Invalid HTTP header {:?}, line breaks are not allowed", input));
+    }
+
+    let (name, value) = match input.find(':') {
+        Some(colon_index) => (input[..colon_index].trim(), input[(colon_index + 1)..].trim()),
+        None => return Err(format!("Invalid HTTP header \"{}\", the format should be \"Name: Value\"", input)),
+    };
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(format!("Invalid HTTP header name \"{}\" found in HTTP header \"{}\"", name, input));
+    }
+
+    return Ok((name.to_string(), value.to_string()));
+}
+
+/// Parse the option of external ping clients in the format of `name=value`, such as `community=public`.
+pub fn parse_protocol_option(input: &str) -> Result<(String, String), String> {
+    let (name, value) = match input.find('=') {
+        Some(equal_sign_index) => (input[..equal_sign_index].trim(), input[(equal_sign_index + 1)..].trim()),
+        None => return Err(format!("Invalid protocol option \"{}\", the format should be \"name=value\"", input)),
+    };
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(format!("Invalid protocol option name \"{}\" found in protocol option \"{}\"", name, input));
+    }
+
+    return Ok((name.to_string(), value.to_string()));
+}
+
+fn is_valid_host_name(host_name: &str) -> bool {
+    if host_name.is_empty() || host_name.len() > 253 {
+        return false;
+    }
+
+    let host_name = host_name.strip_suffix('.').unwrap_or(host_name);
+    return host_name.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{rnp_test_common, PingClientConfig, PortRangeList, RnpSupportedProtocol};
     use pretty_assertions::assert_eq;
+    use std::time::Duration;
 
     #[test]
     fn parsing_ping_target_should_work() {
@@ -120,4 +295,246 @@ mod tests {
         assert!(parse_ping_target("www.google.com").is_err());
         assert!(parse_ping_target("www.google.com:443").is_err());
     }
+
+    #[test]
+    fn parsing_ping_target_with_host_name_should_work() {
+        assert_eq!(Ok(PingTarget::Address("10.0.0.1:443".parse().unwrap())), parse_ping_target_with_host_name("10.0.0.1:443"));
+        assert_eq!(Ok(PingTarget::Address("[::1]:80".parse().unwrap())), parse_ping_target_with_host_name("[::1]"));
+        assert_eq!(Ok(PingTarget::HostName("localhost".to_string(), 80)), parse_ping_target_with_host_name("localhost"));
+        assert_eq!(Ok(PingTarget::HostName("localhost".to_string(), 80)), parse_ping_target_with_host_name("localhost:"));
+        assert_eq!(Ok(PingTarget::HostName("www.google.com".to_string(), 443)), parse_ping_target_with_host_name("www.google.com:443"));
+        assert_eq!(Ok(PingTarget::HostName("my-lb.contoso.com.".to_string(), 8080)), parse_ping_target_with_host_name("my-lb.contoso.com.:8080"));
+
+        assert!(parse_ping_target_with_host_name(":").is_err());
+        assert!(parse_ping_target_with_host_name(":443").is_err());
+        assert!(parse_ping_target_with_host_name("[").is_err());
+        assert!(parse_ping_target_with_host_name("[]").is_err());
+        assert!(parse_ping_target_with_host_name("10.0.0").is_err());
+        assert!(parse_ping_target_with_host_name("www..google.com").is_err());
+        assert!(parse_ping_target_with_host_name("-www.google.com").is_err());
+        assert!(parse_ping_target_with_host_name("www.google.com:abc").is_err());
+        assert!(parse_ping_target_with_host_name("www.google.com:65536").is_err());
+    }
+
+    #[test]
+    fn loading_ping_targets_from_file_should_work() {
+        let test_targets_file_path = PathBuf::from("tests_data/rnp_utils_tests/targets.txt");
+        fs::create_dir_all(test_targets_file_path.parent().unwrap()).unwrap();
+        fs::write(&test_targets_file_path, "# Backends\n10.0.0.1:443\n\n  [::1]:8080  \nwww.example.com:443\n").unwrap();
+
+        assert_eq!(
+            Ok(vec![
+                PingTarget::Address("10.0.0.1:443".parse().unwrap()),
+                PingTarget::Address("[::1]:8080".parse().unwrap()),
+                PingTarget::HostName("www.example.com".to_string(), 443),
+            ]),
+            load_ping_targets_from_file(&test_targets_file_path)
+        );
+
+        fs::write(&test_targets_file_path, "10.0.0.1:443\n10.0.0:443\n").unwrap();
+        let error = load_ping_targets_from_file(&test_targets_file_path).unwrap_err();
+        assert!(error.starts_with("tests_data/rnp_utils_tests/targets.txt:2: "), "{}", error);
+
+        assert!(load_ping_targets_from_file(&PathBuf::from("tests_data/rnp_utils_tests/not_exist.txt")).is_err());
+    }
+
+    #[test]
+    fn parsing_http_header_should_work() {
+        assert_eq!(Ok(("Accept".to_string(), "text/html".to_string())), parse_http_header("Accept: text/html"));
+        assert_eq!(Ok(("X-Empty".to_string(), "".to_string())), parse_http_header("X-Empty:"));
+        assert_eq!(Ok(("Cookie".to_string(), "a=1; b=2".to_string())), parse_http_header(" Cookie :a=1; b=2 "));
+        assert_eq!(Ok(("X-Url".to_string(), "http://localhost:8080".to_string())), parse_http_header("X-Url: http://localhost:8080"));
+
+        assert!(parse_http_header("Accept").is_err());
+        assert!(parse_http_header(": text/html").is_err());
+        assert!(parse_http_header("Bad Name: value").is_err());
+        assert!(parse_http_header("X-Test: 1\r\nHost: evil.com").is_err());
+        assert!(parse_http_header("X-Test: 1\n").is_err());
+    }
+
+    #[test]
+    fn parsing_http_path_should_work() {
+        assert_eq!(Ok("/health".to_string()), parse_http_path("/health"));
+        assert_eq!(Ok("/search?q=a%20b".to_string()), parse_http_path("/search?q=a%20b"));
+
+        assert!(parse_http_path("/health HTTP/1.1\r\nHost: evil.com\r\n\r\n").is_err());
+        assert!(parse_http_path("/health\n").is_err());
+    }
+
+    #[test]
+    fn parsing_protocol_option_should_work() {
+        assert_eq!(Ok(("community".to_string(), "public".to_string())), parse_protocol_option("community=public"));
+        assert_eq!(Ok(("empty".to_string(), "".to_string())), parse_protocol_option("empty="));
+        assert_eq!(Ok(("query".to_string(), "a=1&b=2".to_string())), parse_protocol_option(" query = a=1&b=2 "));
+
+        assert!(parse_protocol_option("community").is_err());
+        assert!(parse_protocol_option("=public").is_err());
+        assert!(parse_protocol_option("bad name=value").is_err());
+    }
+
+    #[test]
+    fn loading_ping_results_from_file_should_work() {
+        let ping_results = rnp_test_common::generate_ping_result_test_samples();
+        let expected_results: Vec<PingResultDto> = ping_results.iter().map(|r| r.create_dto()).collect();
+
+        let test_csv_file_path = PathBuf::from("tests_data/rnp_utils_tests/results.csv");
+        fs::create_dir_all(test_csv_file_path.parent().unwrap()).unwrap();
+        let csv_content: Vec<String> = ping_results.iter().map(|r| r.format_as_csv_string()).collect();
+        fs::write(&test_csv_file_path, format!("UtcTime,WorkerId,Protocol,TargetIp,TargetPort,SourceIp,SourcePort,IsWarmup,IsSucceeded,RttInMs,IsTimedOut,PreparationError,PingError,HandshakeError,DisconnectError,DnsResolveTimeInMs,HandshakeTimeInMs,FirstByteTimeInMs,ResponseTimeInMs,FailureClass,ForwardDelayInMs,ReverseDelayInMs,ClockOffsetInMs,ReconnectReason\n{}\n", csv_content.join("\n"))).unwrap();
+        assert_eq!(Ok(expected_results), load_ping_results_from_file(&test_csv_file_path));
+
+        // Logs from older versions don't have the newer columns.
+        let test_json_file_path = PathBuf::from("tests_data/rnp_utils_tests/results.json");
+        fs::write(&test_json_file_path, r#"[{"UtcTime":"2021-07-06T09:10:11.012Z","WorkerId":1,"Protocol":"TCP","TargetIp":"1.2.3.4","TargetPort":443,"SourceIp":"5.6.7.8","SourcePort":8080,"IsWarmup":false,"IsSucceeded":false,"RttInMs":1000.00,"IsTimedOut":true,"PreparationError":"","PingError":"","HandshakeError":"","DisconnectError":""}]"#).unwrap();
+        let loaded_results = load_ping_results_from_file(&test_json_file_path).unwrap();
+        assert_eq!(1, loaded_results.len());
+        assert!(loaded_results[0].is_timed_out);
+        assert_eq!(None, loaded_results[0].failure_class);
+
+        fs::write(&test_json_file_path, "[{\"UtcTime\":\"bad time\"}]").unwrap();
+        assert!(load_ping_results_from_file(&test_json_file_path).is_err());
+        assert!(load_ping_results_from_file(&PathBuf::from("tests_data/rnp_utils_tests/not_exist.csv")).is_err());
+    }
+
+    #[test]
+    fn loading_ping_runner_config_from_file_should_work() {
+        let test_config_file_path = PathBuf::from("tests_data/rnp_utils_tests/config.toml");
+        fs::create_dir_all(test_config_file_path.parent().unwrap()).unwrap();
+        fs::write(
+            &test_config_file_path,
+            r#"
+[worker_config]
+protocol = "tls"
+targets = ["10.0.0.1:443", "www.example.com:443"]
+ping_interval = "500ms"
+
+[worker_config.ping_client_config]
+wait_timeout = "1s"
+server_name = "www.example.com"
+
+[worker_scheduler_config]
+source_ports = "1024-2048,3096"
+ping_count = 10
+
+[result_processor_config]
+latency_buckets = [0.1, 1, 10]
+
+[profiles.lb-drain.worker_scheduler_config]
+parallel_ping_count = 10
+
+[profiles.lb-drain.result_processor_config]
+exit_on_fail = true
+"#,
+        )
+        .unwrap();
+
+        let config = load_ping_runner_config_from_file(&test_config_file_path, None).unwrap();
+        assert_eq!(RnpSupportedProtocol::TLS, config.worker_config.protocol);
+        assert_eq!(
+            vec![PingTarget::Address("10.0.0.1:443".parse().unwrap()), PingTarget::HostName("www.example.com".to_string(), 443)],
+            config.worker_config.targets
+        );
+        assert_eq!(Duration::from_millis(500), config.worker_config.ping_interval);
+        assert_eq!(Duration::from_secs(1), config.worker_config.ping_client_config.wait_timeout);
+        assert_eq!(Some("www.example.com".to_string()), config.worker_config.ping_client_config.server_name);
+        assert_eq!(PortRangeList { ranges: vec![(1024..=2048), (3096..=3096)] }, config.worker_scheduler_config.source_ports);
+        assert_eq!(Some(10), config.worker_scheduler_config.ping_count);
+        assert_eq!(1, config.worker_scheduler_config.parallel_ping_count);
+        assert_eq!(Some(vec![0.1, 1.0, 10.0]), config.result_processor_config.latency_buckets);
+        assert!(!config.result_processor_config.exit_on_fail);
+
+        // Fields not specified in the file fall back to the defaults.
+        assert_eq!(PingClientConfig::default().disconnect_timeout, config.worker_config.ping_client_config.disconnect_timeout);
+
+        let config = load_ping_runner_config_from_file(&test_config_file_path, Some("lb-drain")).unwrap();
+        assert_eq!(RnpSupportedProtocol::TLS, config.worker_config.protocol);
+        assert_eq!(Some(10), config.worker_scheduler_config.ping_count);
+        assert_eq!(10, config.worker_scheduler_config.parallel_ping_count);
+        assert!(config.result_processor_config.exit_on_fail);
+        assert!(config.result_processor_config.exit_failure_reason.is_some());
+
+        assert!(load_ping_runner_config_from_file(&test_config_file_path, Some("not-exist")).is_err());
+        assert!(load_ping_runner_config_from_file(&PathBuf::from("tests_data/rnp_utils_tests/not_exist.toml"), None).is_err());
+    }
+
+    #[test]
+    fn loading_ping_runner_config_from_yaml_file_should_work() {
+        let test_config_file_path = PathBuf::from("tests_data/rnp_utils_tests/config.yaml");
+        fs::create_dir_all(test_config_file_path.parent().unwrap()).unwrap();
+        fs::write(
+            &test_config_file_path,
+            "worker_config:\n  targets: [\"10.0.0.1:443\"]\nprofiles:\n  forever:\n    worker_scheduler_config:\n      ping_until_stopped: true\n",
+        )
+        .unwrap();
+
+        let config = load_ping_runner_config_from_file(&test_config_file_path, None).unwrap();
+        assert_eq!(vec![PingTarget::Address("10.0.0.1:443".parse().unwrap())], config.worker_config.targets);
+        assert_eq!(None, config.worker_scheduler_config.ping_count);
+
+        let config = load_ping_runner_config_from_file(&test_config_file_path, Some("forever")).unwrap();
+        assert!(config.worker_scheduler_config.ping_until_stopped);
+    }
+
+    #[test]
+    fn loading_ping_runner_config_with_invalid_fields_should_fail() {
+        let test_config_file_path = PathBuf::from("tests_data/rnp_utils_tests/invalid_config.toml");
+        fs::create_dir_all(test_config_file_path.parent().unwrap()).unwrap();
+
+        fs::write(&test_config_file_path, "[worker_config]\nping_intervals = \"1s\"\n").unwrap();
+        assert!(load_ping_runner_config_from_file(&test_config_file_path, None).is_err());
+
+        fs::write(&test_config_file_path, "[worker_scheduler_config]\nsource_ports = \"1024-\"\n").unwrap();
+        assert!(load_ping_runner_config_from_file(&test_config_file_path, None).is_err());
+    }
+
+    #[test]
+    fn loading_ping_runner_config_with_external_protocol_should_work() {
+        let test_config_file_path = PathBuf::from("tests_data/rnp_utils_tests/external_protocol_config.toml");
+        fs::create_dir_all(test_config_file_path.parent().unwrap()).unwrap();
+        fs::write(
+            &test_config_file_path,
+            "[worker_config]\nprotocol = \"snmp\"\n\n[worker_config.ping_client_config.protocol_options]\ncommunity = \"public\"\nversion = \"2c\"\n",
+        )
+        .unwrap();
+
+        let config = load_ping_runner_config_from_file(&test_config_file_path, None).unwrap();
+        assert_eq!(RnpSupportedProtocol::External("snmp".to_string()), config.worker_config.protocol);
+        assert_eq!(
+            vec![("community".to_string(), "public".to_string()), ("version".to_string(), "2c".to_string())],
+            config.worker_config.ping_client_config.protocol_options.into_iter().collect::<Vec<(String, String)>>()
+        );
+
+        fs::write(&test_config_file_path, "[worker_config]\nprotocol = \"snmp v2\"\n").unwrap();
+        assert!(load_ping_runner_config_from_file(&test_config_file_path, None).is_err());
+    }
+
+    #[test]
+    fn ping_runner_config_should_round_trip_through_config_file() {
+        let mut config = RnpPingRunnerConfig::default();
+        config.worker_config.protocol = RnpSupportedProtocol::HTTPS;
+        config.worker_config.targets =
+            vec![PingTarget::Address("[::1]:443".parse().unwrap()), PingTarget::HostName("www.example.com".to_string(), 8443)];
+        config.worker_config.source_ip = "::".parse().unwrap();
+        config.worker_config.ping_client_config.time_to_live = Some(64);
+        config.worker_config.ping_client_config.wait_before_disconnect = Duration::from_millis(1500);
+        config.worker_config.ping_client_config.http_headers = vec![("Accept".to_string(), "text/html".to_string())];
+        config.worker_config.ping_client_config.http_expected_status = Some(204);
+        config.worker_scheduler_config.source_ports = PortRangeList { ranges: vec![(1024..=1024), (10000..=11000)] };
+        config.worker_scheduler_config.ping_until_stopped = true;
+        config.result_processor_config.exit_on_fail = true;
+        config.result_processor_config.exit_failure_reason = Some(Arc::new(Mutex::new(None)));
+        config.result_processor_config.csv_log_path = Some(PathBuf::from("log.csv"));
+        config.result_processor_config.metrics_address = Some("127.0.0.1:9100".parse().unwrap());
+        config.result_processor_config.latency_buckets = Some(vec![0.1, 0.5, 1.0]);
+        config.result_processor_config.latency_percentiles = Some(vec![50.0, 99.9]);
+
+        let test_config_file_path = PathBuf::from("tests_data/rnp_utils_tests/round_trip_config.toml");
+        fs::create_dir_all(test_config_file_path.parent().unwrap()).unwrap();
+        fs::write(&test_config_file_path, toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config, load_ping_runner_config_from_file(&test_config_file_path, None).unwrap());
+
+        let test_config_file_path = PathBuf::from("tests_data/rnp_utils_tests/round_trip_config.yaml");
+        fs::write(&test_config_file_path, serde_yaml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config, load_ping_runner_config_from_file(&test_config_file_path, None).unwrap());
+    }
 }
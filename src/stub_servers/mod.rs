@@ -1,2 +1,7 @@
 pub mod stub_server_factory;
 mod stub_server_tcp;
+mod stub_server_udp;
+
+// QUIC stub server is built on quinn, which has the same platform limitation as QUIC ping client.
+#[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+mod stub_server_quic;
@@ -1,22 +1,76 @@
 use crate::stub_servers::stub_server_tcp::StubServerTcp;
+use crate::stub_servers::stub_server_udp::StubServerUdp;
+
+#[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+use crate::stub_servers::stub_server_quic::StubServerQuic;
 use crate::*;
-use contracts::requires;
 use futures_intrusive::sync::ManualResetEvent;
 use std::error::Error;
 use std::sync::Arc;
 use tokio::task::JoinHandle;
 
-#[requires(config.report_interval.as_millis() > 0)]
+type StubServerJoinHandle = JoinHandle<Result<(), Box<dyn Error + Send + Sync>>>;
+type StubServerRunner = fn(RnpStubServerConfig, Arc<ManualResetEvent>, Arc<ManualResetEvent>) -> StubServerJoinHandle;
+
 #[tracing::instrument(name = "Start running stub server", level = "debug", skip(stop_event))]
 pub fn run(
     config: &RnpStubServerConfig,
     stop_event: Arc<ManualResetEvent>,
     server_started_event: Arc<ManualResetEvent>,
-) -> JoinHandle<Result<(), Box<dyn Error + Send + Sync>>> {
+) -> Result<StubServerJoinHandle, RnpError> {
+    if config.report_interval.is_zero() {
+        return Err(RnpError::InvalidConfig(RnpConfigError::InvalidReportInterval));
+    }
+
+    // Fault rules from command line are checked when parsed, but the ones built in code can still have any probability.
+    for fault_rule in &config.fault_rules {
+        fault_rule.trigger.validate()?;
+    }
+
+    let run_new_server: StubServerRunner = match config.protocol {
+        RnpSupportedProtocol::TCP => StubServerTcp::run_new,
+        RnpSupportedProtocol::UDP => StubServerUdp::run_new,
+        #[cfg(any(not(target_os = "windows"), not(target_arch = "aarch64")))]
+        RnpSupportedProtocol::QUIC => StubServerQuic::run_new,
+        _ => return Err(RnpError::UnsupportedProtocol(config.protocol.to_string())),
+    };
+
     println!("Starting rnp {} server at {} ...", config.protocol, config.server_address);
+    return Ok(run_new_server(config.clone(), stop_event, server_started_event));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn running_stub_server_with_invalid_fault_probability_should_fail() {
+        for probability in [-0.1, 1.5, f64::NAN] {
+            let config = RnpStubServerConfig {
+                protocol: RnpSupportedProtocol::TCP,
+                server_address: "127.0.0.1:11359".parse().unwrap(),
+                report_interval: Duration::from_secs(1),
+                close_on_accept: false,
+                close_on_handshake: false,
+                alpn_protocols: vec![],
+                reject_alpn: false,
+                write_chunk_size: 0,
+                write_count_limit: 0,
+                sleep_before_write: Duration::ZERO,
+                wait_before_disconnect: Duration::ZERO,
+                fault_rules: vec![RnpStubServerFaultRule {
+                    action: RnpStubServerFaultAction::Reset,
+                    trigger: RnpStubServerFaultTrigger::Probability(probability),
+                }],
+            };
 
-    match config.protocol {
-        RnpSupportedProtocol::TCP => return StubServerTcp::run_new(config.clone(), stop_event, server_started_event),
-        _ => panic!("Protocol {} is not supported!", config.protocol),
+            let result = run(&config, Arc::new(ManualResetEvent::new(false)), Arc::new(ManualResetEvent::new(false)));
+            assert!(
+                matches!(result, Err(RnpError::InvalidConfig(RnpConfigError::InvalidFaultProbability(_)))),
+                "Probability {} should be rejected",
+                probability
+            );
+        }
     }
 }
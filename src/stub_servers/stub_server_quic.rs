@@ -0,0 +1,320 @@
+use crate::RnpStubServerConfig;
+use futures_intrusive::sync::ManualResetEvent;
+use quinn::{Connecting, Connection, Endpoint, ServerConfig, VarInt};
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+pub struct StubServerQuic {
+    config: Arc<RnpStubServerConfig>,
+    stop_event: Arc<ManualResetEvent>,
+    server_started_event: Arc<ManualResetEvent>,
+
+    next_conn_id: u32,
+    conn_stats_map: HashMap<u32, Arc<Mutex<StubServerQuicConnectionStats>>>,
+}
+
+impl StubServerQuic {
+    #[tracing::instrument(name = "Start running new QUIC stub server", level = "debug", skip(stop_event))]
+    pub fn run_new(
+        config: RnpStubServerConfig,
+        stop_event: Arc<ManualResetEvent>,
+        server_started_event: Arc<ManualResetEvent>,
+    ) -> JoinHandle<Result<(), Box<dyn Error + Send + Sync>>> {
+        return tokio::spawn(async move {
+            let mut server = StubServerQuic::new(config, stop_event, server_started_event.clone());
+
+            // In case server started failed, we always signal server started event here to keep it safe.
+            let result = server.run().await;
+            server_started_event.set();
+
+            return result;
+        });
+    }
+
+    #[tracing::instrument(name = "Creating QUIC stub server", level = "debug", skip(stop_event))]
+    fn new(config: RnpStubServerConfig, stop_event: Arc<ManualResetEvent>, server_started_event: Arc<ManualResetEvent>) -> StubServerQuic {
+        return StubServerQuic { config: Arc::new(config), stop_event, server_started_event, next_conn_id: 0, conn_stats_map: HashMap::new() };
+    }
+
+    #[tracing::instrument(name = "Running QUIC stub server loop", level = "debug", skip(self))]
+    async fn run(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let server_config = self.create_server_config()?;
+        let endpoint = Endpoint::server(server_config, self.config.server_address)?;
+        self.server_started_event.set();
+
+        println!("Rnp {} server started successfully at {}.", self.config.protocol, endpoint.local_addr()?);
+
+        let mut next_report_time = Instant::now();
+        loop {
+            tokio::select! {
+                // New connection arrived.
+                connecting = endpoint.accept() => {
+                    match connecting {
+                        Some(connecting) => self.handle_new_connection(connecting),
+                        None => {
+                            println!("QUIC endpoint is closed. Exit.");
+                            break;
+                        }
+                    }
+                }
+
+                // Report interval reached
+                _ = tokio::time::sleep_until(next_report_time) => {
+                    self.report_and_reset_conn_stats();
+                    next_report_time += self.config.report_interval;
+                }
+
+                // Stopped
+                _ = self.stop_event.wait() => {
+                    break;
+                }
+            }
+        }
+
+        endpoint.close(VarInt::from_u32(0), b"Server stopped");
+        return Ok(());
+    }
+
+    fn create_server_config(&self) -> Result<ServerConfig, Box<dyn Error + Send + Sync>> {
+        // The certificate is generated on every start, since it is only used for testing, and QUIC pings never validate it.
+        let mut server_names = vec![String::from("localhost")];
+        if !self.config.server_address.ip().is_unspecified() {
+            server_names.push(self.config.server_address.ip().to_string());
+        }
+        let certificate = rcgen::generate_simple_self_signed(server_names)?;
+        let cert_chain = vec![rustls::Certificate(certificate.serialize_der()?)];
+        let private_key = rustls::PrivateKey(certificate.serialize_private_key_der());
+
+        let crypto_builder = rustls::ServerConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(&[&rustls::version::TLS13])?
+            .with_no_client_auth();
+
+        // To close the connections on handshake, we use a resolver without any certificate, which aborts every handshake right after
+        // receiving the client hello.
+        let mut crypto = if self.config.close_on_handshake {
+            crypto_builder.with_cert_resolver(Arc::new(rustls::server::ResolvesServerCertUsingSni::new()))
+        } else {
+            crypto_builder.with_single_cert(cert_chain, private_key)?
+        };
+
+        // QUIC requires ALPN to match whenever either side specifies it, so without any ALPN on our side, all handshakes with ALPN
+        // will fail with no_application_protocol.
+        if !self.config.reject_alpn {
+            crypto.alpn_protocols = self.config.alpn_protocols.iter().map(|alpn_protocol| alpn_protocol.as_bytes().to_vec()).collect();
+        }
+
+        return Ok(ServerConfig::with_crypto(Arc::new(crypto)));
+    }
+
+    #[tracing::instrument(name = "New connection received", level = "debug", skip(self, connecting))]
+    fn handle_new_connection(&mut self, connecting: Connecting) {
+        let peer_addr = connecting.remote_address();
+        println!("New connection received: Remote = {}", peer_addr);
+
+        if self.config.close_on_handshake {
+            StubServerQuic::close_connection_on_handshake(connecting, peer_addr);
+            return;
+        }
+
+        self.start_connection_worker(connecting, peer_addr);
+    }
+
+    #[tracing::instrument(name = "Close connection on handshake", level = "debug", skip(connecting))]
+    fn close_connection_on_handshake(connecting: Connecting, peer_addr: SocketAddr) {
+        tokio::spawn(async move {
+            if let Err(e) = connecting.await {
+                println!("Connection closed on handshake: Remote = {}, Error = {}", peer_addr, e);
+            }
+        });
+    }
+
+    #[tracing::instrument(name = "Starting new connection worker", level = "debug", skip(self, connecting))]
+    fn start_connection_worker(&mut self, connecting: Connecting, peer_addr: SocketAddr) {
+        let connection_config = self.config.clone();
+        let connection_stop_event = self.stop_event.clone();
+
+        let conn_id = self.next_conn_id;
+        self.next_conn_id += 1;
+
+        let conn_stats = Arc::new(Mutex::new(StubServerQuicConnectionStats::new(&peer_addr)));
+        self.conn_stats_map.insert(conn_id, conn_stats.clone());
+
+        tokio::spawn(async move {
+            let connection = match connecting.await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    println!("Handshake failed, connection closed: Remote = {}, Error = {}", peer_addr, e);
+                    conn_stats.lock().unwrap().is_alive = false;
+                    return;
+                }
+            };
+
+            let mut worker = StubServerQuicConnection::new(conn_id, connection_config, connection, peer_addr, conn_stats);
+            tokio::select! {
+                _ = worker.run() => { return; }
+                _ = connection_stop_event.wait() => { return; }
+            }
+        });
+    }
+
+    #[tracing::instrument(name = "Report and reset connection stats", level = "debug", skip(self))]
+    fn report_and_reset_conn_stats(&mut self) {
+        if self.conn_stats_map.is_empty() {
+            return;
+        }
+
+        println!("========== Connection Stats ==========");
+        for (id, conn_stats) in &self.conn_stats_map {
+            let conn_stats = conn_stats.lock().unwrap().clone_and_clear_stats();
+            let read_bps = conn_stats.bytes_read * 8 * 1000 / (self.config.report_interval.as_millis() as u64);
+            let write_bps = conn_stats.bytes_write * 8 * 1000 / (self.config.report_interval.as_millis() as u64);
+            println!(
+                "[{}] {} => Read = {} bytes ({} bps), Write = {} bytes ({} bps), RTT = {:.2}ms",
+                id,
+                conn_stats.remote_address,
+                conn_stats.bytes_read,
+                read_bps,
+                conn_stats.bytes_write,
+                write_bps,
+                conn_stats.rtt.as_micros() as f64 / 1000.0
+            );
+        }
+        println!();
+
+        // We clean up the dead connections after reporting, otherwise we will miss the stats in the last round of report.
+        self.conn_stats_map.retain(|_, v| v.lock().unwrap().is_alive);
+    }
+}
+
+struct StubServerQuicConnection {
+    id: u32,
+    config: Arc<RnpStubServerConfig>,
+    connection: Connection,
+    remote_address: SocketAddr,
+    conn_stats: Arc<Mutex<StubServerQuicConnectionStats>>,
+}
+
+impl StubServerQuicConnection {
+    #[tracing::instrument(name = "Creating new QUIC connection worker", level = "debug", skip(connection, conn_stats))]
+    fn new(
+        id: u32,
+        config: Arc<RnpStubServerConfig>,
+        connection: Connection,
+        remote_address: SocketAddr,
+        conn_stats: Arc<Mutex<StubServerQuicConnectionStats>>,
+    ) -> StubServerQuicConnection {
+        conn_stats.lock().unwrap().connection = Some(connection.clone());
+        return StubServerQuicConnection { id, config, connection, remote_address, conn_stats };
+    }
+
+    #[tracing::instrument(name = "Running new QUIC connection worker", level = "debug", skip(self), fields(id = %self.id, remote_address = %self.remote_address))]
+    async fn run(&mut self) {
+        let alpn_protocol = self
+            .connection
+            .handshake_data()
+            .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+            .and_then(|data| data.protocol)
+            .map_or(String::from("None"), |protocol| String::from_utf8_lossy(&protocol).to_string());
+        println!("Handshake completed: Remote = {}, ALPN = {}", self.remote_address, alpn_protocol);
+
+        // Writes stop on their own once the connection is closed, so the connection is done when both sides are done.
+        let (read_result, _) = tokio::join!(self.read_from_connection(), self.write_to_connection());
+        if let Err(e) = read_result {
+            println!("Connection closed: Remote = {}, Reason = {}", self.remote_address, e);
+        }
+
+        self.conn_stats.lock().unwrap().is_alive = false;
+    }
+
+    // The streams are only drained, because the traffic is counted by the connection stats already.
+    async fn read_from_connection(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        loop {
+            let mut recv_stream = tokio::select! {
+                stream = self.connection.accept_uni() => stream?,
+                stream = self.connection.accept_bi() => stream?.1,
+            };
+
+            tokio::spawn(async move {
+                let _ = recv_stream.read_to_end(usize::MAX).await;
+            });
+        }
+    }
+
+    #[tracing::instrument(name = "QUIC connection on write", level = "debug", skip(self), fields(id = %self.id, remote_address = %self.remote_address))]
+    async fn write_to_connection(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.config.write_chunk_size == 0 {
+            return Ok(());
+        }
+
+        let mut send_stream = self.connection.open_uni().await?;
+        let write_buf = vec![0_u8; self.config.write_chunk_size];
+        let mut write_count = 0;
+        while self.config.write_count_limit == 0 || write_count < self.config.write_count_limit {
+            if !self.config.sleep_before_write.is_zero() {
+                tokio::time::sleep(self.config.sleep_before_write).await;
+            }
+
+            send_stream.write_all(&write_buf).await?;
+            write_count += 1;
+        }
+
+        send_stream.finish().await?;
+        return Ok(());
+    }
+}
+
+// QUIC connection stats are accumulated since the connection is created, so we save the total bytes that are already reported to
+// calculate the bytes in each report interval.
+#[derive(Debug, Clone)]
+struct StubServerQuicConnectionStats {
+    pub remote_address: SocketAddr,
+    pub is_alive: bool,
+    pub connection: Option<Connection>,
+    pub bytes_read: u64,
+    pub bytes_write: u64,
+    pub rtt: Duration,
+    pub reported_bytes_read: u64,
+    pub reported_bytes_write: u64,
+}
+
+impl StubServerQuicConnectionStats {
+    pub fn new(remote_address: &SocketAddr) -> StubServerQuicConnectionStats {
+        return StubServerQuicConnectionStats {
+            remote_address: *remote_address,
+            is_alive: true,
+            connection: None,
+            bytes_read: 0,
+            bytes_write: 0,
+            rtt: Duration::ZERO,
+            reported_bytes_read: 0,
+            reported_bytes_write: 0,
+        };
+    }
+
+    pub fn clone_and_clear_stats(&mut self) -> StubServerQuicConnectionStats {
+        if let Some(connection) = &self.connection {
+            let connection_stats = connection.stats();
+            self.bytes_read = connection_stats.udp_rx.bytes - self.reported_bytes_read;
+            self.bytes_write = connection_stats.udp_tx.bytes - self.reported_bytes_write;
+            self.rtt = connection_stats.path.rtt;
+        }
+
+        let stats = self.clone();
+        self.clear_stats();
+        return stats;
+    }
+
+    pub fn clear_stats(&mut self) {
+        self.reported_bytes_read += self.bytes_read;
+        self.reported_bytes_write += self.bytes_write;
+        self.bytes_read = 0;
+        self.bytes_write = 0;
+    }
+}
@@ -1,10 +1,18 @@
-use crate::RnpStubServerConfig;
+use crate::ping_clients::ping_client_one_way_latency::{
+    build_one_way_latency_response, now_in_us, ONE_WAY_LATENCY_PROBE_MAGIC, ONE_WAY_LATENCY_REQUEST_SIZE,
+};
+use crate::throughput_runner::{
+    build_throughput_upload_confirmation, parse_throughput_request, THROUGHPUT_CHUNK_SIZE, THROUGHPUT_REQUEST_MAGIC, THROUGHPUT_REQUEST_SIZE,
+};
+use crate::{RnpStubServerConfig, RnpStubServerFaultAction, ThroughputDirection};
 use futures_intrusive::sync::ManualResetEvent;
+use socket2::SockRef;
 use std::collections::HashMap;
 use std::error::Error;
 use std::io;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::io::{AsyncWriteExt, Interest};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::task::JoinHandle;
@@ -16,6 +24,8 @@ pub struct StubServerTcp {
     server_started_event: Arc<ManualResetEvent>,
 
     next_conn_id: u32,
+    accepted_conn_count: u32,
+    accept_resume_time: Instant,
     conn_stats_map: HashMap<u32, Arc<Mutex<StubServerTcpConnectionStats>>>,
 }
 
@@ -39,7 +49,15 @@ impl StubServerTcp {
 
     #[tracing::instrument(name = "Creating TCP stub server", level = "debug", skip(stop_event))]
     fn new(config: RnpStubServerConfig, stop_event: Arc<ManualResetEvent>, server_started_event: Arc<ManualResetEvent>) -> StubServerTcp {
-        return StubServerTcp { config: Arc::new(config), stop_event, server_started_event, next_conn_id: 0, conn_stats_map: HashMap::new() };
+        return StubServerTcp {
+            config: Arc::new(config),
+            stop_event,
+            server_started_event,
+            next_conn_id: 0,
+            accepted_conn_count: 0,
+            accept_resume_time: Instant::now(),
+            conn_stats_map: HashMap::new(),
+        };
     }
 
     #[tracing::instrument(name = "Running TCP stub server loop", level = "debug", skip(self))]
@@ -53,7 +71,7 @@ impl StubServerTcp {
         loop {
             tokio::select! {
                 // New connection arrived.
-                accept_result = listener.accept() => {
+                accept_result = StubServerTcp::accept_after(&listener, self.accept_resume_time) => {
                     match accept_result {
                         Ok((stream, peer_addr)) => {
                             self.handle_new_connection(stream, peer_addr).await
@@ -81,26 +99,85 @@ impl StubServerTcp {
         return Ok(());
     }
 
+    // When accept is delayed by fault rules, we simply don't call accept, so the new connections will be queued in the backlog,
+    // and new connects will start to time out after the backlog is full.
+    async fn accept_after(listener: &TcpListener, accept_resume_time: Instant) -> io::Result<(TcpStream, SocketAddr)> {
+        tokio::time::sleep_until(accept_resume_time).await;
+        return listener.accept().await;
+    }
+
     #[tracing::instrument(name = "New connection received", level = "debug", skip(self))]
     async fn handle_new_connection(&mut self, stream: TcpStream, peer_addr: SocketAddr) {
         println!("New connection received: Remote = {}", peer_addr);
 
+        self.accepted_conn_count += 1;
+        let fault_action = self.pick_fault_action();
+        match fault_action {
+            Some(RnpStubServerFaultAction::Reset) => self.reset_connection_on_close(&stream, peer_addr),
+            Some(RnpStubServerFaultAction::Hang) => {
+                self.hang_connection_on_accept(stream, peer_addr);
+                return;
+            }
+            Some(RnpStubServerFaultAction::DelayAccept(delay)) => self.delay_next_accept(delay),
+            _ => (),
+        }
+
         if self.config.close_on_accept {
-            self.close_connection_on_accept(stream, peer_addr).await;
+            let is_reset = fault_action == Some(RnpStubServerFaultAction::Reset);
+            self.close_connection_on_accept(stream, peer_addr, is_reset).await;
             return;
         }
 
-        self.start_connection_worker(stream, peer_addr).await;
+        self.start_connection_worker(stream, peer_addr, fault_action).await;
+    }
+
+    fn pick_fault_action(&self) -> Option<RnpStubServerFaultAction> {
+        return self.config.fault_rules.iter().find(|rule| rule.trigger.is_triggered(self.accepted_conn_count)).map(|rule| rule.action.clone());
+    }
+
+    // Resetting the connection right after accept races with the connect on the client side, which can fail the connect instead of
+    // failing the disconnect. Hence, we only set linger to 0 here, so the connection will be reset whenever it is closed.
+    #[tracing::instrument(name = "Reset connection on close", level = "debug", skip(self))]
+    fn reset_connection_on_close(&mut self, stream: &TcpStream, peer_addr: SocketAddr) {
+        // Since the socket is not blocked on drop with zero linger, we set it via socket2 instead of the deprecated tokio API.
+        match SockRef::from(stream).set_linger(Some(Duration::ZERO)) {
+            Ok(_) => println!("Connection will be reset instead of closed: Remote = {}", peer_addr),
+            Err(e) => println!("Failed to set linger for resetting connection: Remote = {}, Error = {}", peer_addr, e),
+        }
+    }
+
+    #[tracing::instrument(name = "Hang connection on accept", level = "debug", skip(self))]
+    fn hang_connection_on_accept(&mut self, stream: TcpStream, peer_addr: SocketAddr) {
+        println!("Connection hung on accept, no data will be read or written: Remote = {}", peer_addr);
+
+        // Hold the connection without reading it until the server stops.
+        let stop_event = self.stop_event.clone();
+        tokio::spawn(async move {
+            stop_event.wait().await;
+            drop(stream);
+        });
+    }
+
+    #[tracing::instrument(name = "Delay next accept", level = "debug", skip(self))]
+    fn delay_next_accept(&mut self, delay: Duration) {
+        println!("Delaying accepting new connections for {:?}.", delay);
+        self.accept_resume_time = Instant::now() + delay;
     }
 
     #[tracing::instrument(name = "Close connection on accept", level = "debug", skip(self))]
-    async fn close_connection_on_accept(&mut self, mut stream: TcpStream, peer_addr: SocketAddr) {
+    async fn close_connection_on_accept(&mut self, mut stream: TcpStream, peer_addr: SocketAddr, is_reset: bool) {
+        if is_reset {
+            drop(stream);
+            println!("Connection reset on accept: Remote = {}", peer_addr);
+            return;
+        }
+
         let _ = stream.shutdown().await;
         println!("Connection closed on accept: Remote = {}", peer_addr);
     }
 
     #[tracing::instrument(name = "Starting new connection worker", level = "debug", skip(self))]
-    async fn start_connection_worker(&mut self, stream: TcpStream, peer_addr: SocketAddr) {
+    async fn start_connection_worker(&mut self, stream: TcpStream, peer_addr: SocketAddr, fault_action: Option<RnpStubServerFaultAction>) {
         let stream_config = self.config.clone();
         let stream_stop_event = self.stop_event.clone();
 
@@ -111,7 +188,7 @@ impl StubServerTcp {
         self.conn_stats_map.insert(conn_id, conn_stats.clone());
 
         tokio::spawn(async move {
-            let mut worker = StubServerTcpConnection::new(conn_id, stream_config, stream, peer_addr, conn_stats);
+            let mut worker = StubServerTcpConnection::new(conn_id, stream_config, stream, peer_addr, fault_action, conn_stats);
             tokio::select! {
                 _ = worker.run() => { return; }
                 _ = stream_stop_event.wait() => { return; }
@@ -132,7 +209,7 @@ impl StubServerTcp {
             let write_bps = conn_stats.bytes_write * 8 * 1000 / (self.config.report_interval.as_millis() as usize);
             println!(
                 "[{}] {} => Read = {} bytes ({} bps), Write = {} bytes ({} bps)",
-                id, conn_stats.remote_address, read_bps, conn_stats.bytes_read, write_bps, conn_stats.bytes_write
+                id, conn_stats.remote_address, conn_stats.bytes_read, read_bps, conn_stats.bytes_write, write_bps
             );
         }
         println!();
@@ -147,7 +224,19 @@ struct StubServerTcpConnection {
     config: Arc<RnpStubServerConfig>,
     stream: TcpStream,
     remote_address: SocketAddr,
+    fault_action: Option<RnpStubServerFaultAction>,
+    total_bytes: usize,
     read_buf: Vec<u8>,
+    throughput_direction: Option<ThroughputDirection>,
+    throughput_write_buf: Vec<u8>,
+    throughput_bytes_read: usize,
+
+    // Bytes received at the beginning of the connection, which are kept until we can tell if they are a throughput request or not,
+    // since the request can be split across reads. None after the decision is made.
+    pending_throughput_request: Option<Vec<u8>>,
+
+    // Timestamp request received so far, since a request can be split across reads on long-lived connections.
+    pending_one_way_latency_request: Vec<u8>,
     conn_stats: Arc<Mutex<StubServerTcpConnectionStats>>,
 }
 
@@ -158,9 +247,24 @@ impl StubServerTcpConnection {
         config: Arc<RnpStubServerConfig>,
         stream: TcpStream,
         remote_address: SocketAddr,
+        fault_action: Option<RnpStubServerFaultAction>,
         conn_stats: Arc<Mutex<StubServerTcpConnectionStats>>,
     ) -> StubServerTcpConnection {
-        return StubServerTcpConnection { id, config, stream, remote_address, read_buf: vec![0; 4096], conn_stats };
+        return StubServerTcpConnection {
+            id,
+            config,
+            stream,
+            remote_address,
+            fault_action,
+            total_bytes: 0,
+            read_buf: vec![0; 4096],
+            throughput_direction: None,
+            throughput_write_buf: vec![],
+            throughput_bytes_read: 0,
+            pending_throughput_request: Some(Vec::with_capacity(THROUGHPUT_REQUEST_SIZE)),
+            pending_one_way_latency_request: Vec::with_capacity(ONE_WAY_LATENCY_REQUEST_SIZE),
+            conn_stats,
+        };
     }
 
     #[tracing::instrument(name = "Running new TCP connection worker", level = "debug", skip(self), fields(id = %self.id, remote_address = %self.remote_address))]
@@ -171,18 +275,21 @@ impl StubServerTcpConnection {
     }
 
     async fn run_loop(&mut self) -> Result<(), Box<dyn Error>> {
-        let mut interest = Interest::READABLE;
-        if self.config.write_chunk_size != 0 {
-            interest = interest | Interest::WRITABLE;
+        let is_half_closed = self.fault_action == Some(RnpStubServerFaultAction::HalfClose);
+        if is_half_closed {
+            self.stream.shutdown().await?;
+            println!("Connection half closed on accept, waiting for remote side to close: Remote = {}", self.remote_address);
         }
-        tracing::debug!(
-            "Running TCP connection worker loop with interest, worker_id = {}, remote_address = {}, interest = {:?}",
-            self.id,
-            self.remote_address,
-            interest
-        );
+
+        tracing::debug!("Running TCP connection worker loop, worker_id = {}, remote_address = {}", self.id, self.remote_address);
 
         loop {
+            // Writable is only waited when we have data to write, otherwise the loop will keep spinning on the writable socket.
+            let mut interest = Interest::READABLE;
+            if !is_half_closed && self.has_data_to_write() {
+                interest = interest | Interest::WRITABLE;
+            }
+
             let ready = self.stream.ready(interest).await?;
 
             if ready.is_readable() {
@@ -192,14 +299,67 @@ impl StubServerTcpConnection {
             if ready.is_writable() {
                 self.on_connection_write().await?;
             }
+
+            if let Some(RnpStubServerFaultAction::CloseAfterBytes(bytes_limit)) = self.fault_action {
+                if self.total_bytes >= bytes_limit {
+                    self.stream.shutdown().await?;
+                    println!("Connection closed after {} bytes: Remote = {}", self.total_bytes, self.remote_address);
+                    return Ok(());
+                }
+            }
         }
     }
 
+    fn has_data_to_write(&self) -> bool {
+        if self.is_sending_throughput_data() {
+            return true;
+        }
+
+        return self.config.write_chunk_size != 0 && !self.is_write_completed();
+    }
+
+    fn is_write_completed(&self) -> bool {
+        return self.config.write_count_limit != 0 && self.conn_stats.lock().unwrap().total_write_count >= self.config.write_count_limit;
+    }
+
+    fn is_sending_throughput_data(&self) -> bool {
+        return self.throughput_direction.is_some_and(|direction| direction.is_download());
+    }
+
+    // Throughput test from rnp is started by the request at the beginning of the connection. After that, the data is sent as fast as
+    // possible without the write limits in the config, and both buffers are enlarged to reduce the syscalls.
+    fn start_throughput_test(&mut self, direction: ThroughputDirection) {
+        println!("Throughput test started: Remote = {}, Direction = {}", self.remote_address, direction);
+
+        self.throughput_direction = Some(direction);
+        self.read_buf.resize(THROUGHPUT_CHUNK_SIZE, 0);
+        if direction.is_download() {
+            self.throughput_write_buf = vec![0; THROUGHPUT_CHUNK_SIZE];
+        }
+    }
+
+    // rnp shuts down the writes when the upload test is stopped, and the bytes we received are sent back as the last bytes before
+    // closing the connection, so rnp reports the bytes arrived at our side instead of the bytes buffered in its socket.
+    async fn confirm_throughput_upload(&mut self) -> Result<(), Box<dyn Error>> {
+        let uploaded_bytes = self.throughput_bytes_read.saturating_sub(THROUGHPUT_REQUEST_SIZE) as u64;
+        println!("Throughput test upload completed: Remote = {}, Received = {} bytes", self.remote_address, uploaded_bytes);
+
+        self.stream.write_all(&build_throughput_upload_confirmation(uploaded_bytes)).await?;
+        self.stream.shutdown().await?;
+        return Ok(());
+    }
+
     #[tracing::instrument(name = "TCP connection on read", level = "debug", skip(self), fields(id = %self.id, remote_address = %self.remote_address))]
     async fn on_connection_read(&mut self) -> Result<(), Box<dyn Error>> {
-        match self.stream.try_read(&mut self.read_buf) {
+        let read_result = self.stream.try_read(&mut self.read_buf);
+        let receive_time_in_us = now_in_us();
+        match read_result {
             Ok(n) => {
                 if n == 0 {
+                    if self.throughput_direction.is_some_and(|direction| direction.is_upload()) {
+                        self.confirm_throughput_upload().await?;
+                    }
+
                     if !self.config.wait_before_disconnect.is_zero() {
                         println!(
                             "Connection is half shutdown by remote side. Wait for {:?} before disconnect the connection: Remote = {}",
@@ -214,6 +374,42 @@ impl StubServerTcpConnection {
                     return Err(io::Error::new(io::ErrorKind::ConnectionAborted, error_message).into());
                 }
                 self.conn_stats.lock().unwrap().bytes_read += n;
+                self.total_bytes += n;
+                self.throughput_bytes_read += n;
+
+                if self.throughput_direction.is_some() {
+                    return Ok(());
+                }
+
+                // Data that arrived before the throughput request is ruled out needs to be handled together with this read.
+                let mut pending_data = Vec::new();
+                if let Some(mut pending_request) = self.pending_throughput_request.take() {
+                    pending_request.extend_from_slice(&self.read_buf[..n]);
+                    if pending_request.len() < THROUGHPUT_REQUEST_SIZE && is_throughput_request_prefix(&pending_request) {
+                        self.pending_throughput_request = Some(pending_request);
+                        return Ok(());
+                    }
+
+                    if let Some(direction) = parse_throughput_request(&pending_request) {
+                        self.start_throughput_test(direction);
+                        return Ok(());
+                    }
+
+                    pending_data = pending_request;
+                }
+
+                // Timestamp requests from rnp are answered right away for measuring one-way latency. On persistent connections, the
+                // requests can be coalesced in a single read or split across reads, so they are reassembled before being answered.
+                let data = if pending_data.is_empty() { &self.read_buf[..n] } else { &pending_data[..] };
+                let response = build_one_way_latency_responses(&mut self.pending_one_way_latency_request, data, receive_time_in_us);
+                if !response.is_empty() {
+                    if let Err(e) = self.stream.write_all(&response).await {
+                        println!("Error found in connection to {}, connection closed: Error = {}", self.remote_address, e);
+                        return Err(e.into());
+                    }
+                    self.conn_stats.lock().unwrap().bytes_write += response.len();
+                    self.total_bytes += response.len();
+                }
             }
             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
             Err(e) => {
@@ -227,6 +423,10 @@ impl StubServerTcpConnection {
 
     #[tracing::instrument(name = "TCP connection on write", level = "debug", skip(self), fields(id = %self.id, remote_address = %self.remote_address))]
     async fn on_connection_write(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.is_sending_throughput_data() {
+            return self.write_throughput_data();
+        }
+
         if !self.config.sleep_before_write.is_zero() {
             tokio::time::sleep(self.config.sleep_before_write).await;
         }
@@ -245,6 +445,23 @@ impl StubServerTcpConnection {
         match self.stream.try_write(&write_buf) {
             Ok(n) => {
                 self.conn_stats.lock().unwrap().bytes_write += n;
+                self.total_bytes += n;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(e) => {
+                println!("Error found in connection to {}, connection closed: Error = {}", self.remote_address, e);
+                return Err(e.into());
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn write_throughput_data(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.stream.try_write(&self.throughput_write_buf) {
+            Ok(n) => {
+                self.conn_stats.lock().unwrap().bytes_write += n;
+                self.total_bytes += n;
             }
             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
             Err(e) => {
@@ -257,6 +474,40 @@ impl StubServerTcpConnection {
     }
 }
 
+// Every complete request in the data read is answered, and the incomplete one at the end is kept for the next read. Data that
+// doesn't look like a request is drained as before, which is what other ping clients are sending.
+fn build_one_way_latency_responses(pending_request: &mut Vec<u8>, data: &[u8], receive_time_in_us: u64) -> Vec<u8> {
+    let mut responses = Vec::new();
+    for &byte in data {
+        pending_request.push(byte);
+
+        let pending_size = pending_request.len();
+        let magic_size = pending_size.min(ONE_WAY_LATENCY_PROBE_MAGIC.len());
+        if pending_request[..magic_size] != ONE_WAY_LATENCY_PROBE_MAGIC[..magic_size] {
+            // The byte breaking the magic can still be the start of the next request.
+            pending_request.clear();
+            if byte == ONE_WAY_LATENCY_PROBE_MAGIC[0] {
+                pending_request.push(byte);
+            }
+            continue;
+        }
+
+        if pending_size == ONE_WAY_LATENCY_REQUEST_SIZE {
+            if let Some(response) = build_one_way_latency_response(pending_request, receive_time_in_us, now_in_us()) {
+                responses.extend(response);
+            }
+            pending_request.clear();
+        }
+    }
+
+    return responses;
+}
+
+fn is_throughput_request_prefix(data: &[u8]) -> bool {
+    let magic_size = data.len().min(THROUGHPUT_REQUEST_MAGIC.len());
+    return data[..magic_size] == THROUGHPUT_REQUEST_MAGIC[..magic_size];
+}
+
 #[derive(Debug, Clone, PartialEq)]
 struct StubServerTcpConnectionStats {
     pub remote_address: SocketAddr,
@@ -0,0 +1,184 @@
+use crate::RnpStubServerConfig;
+use futures_intrusive::sync::ManualResetEvent;
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+pub struct StubServerUdp {
+    config: Arc<RnpStubServerConfig>,
+    stop_event: Arc<ManualResetEvent>,
+    server_started_event: Arc<ManualResetEvent>,
+
+    peer_stats_map: HashMap<SocketAddr, StubServerUdpPeerStats>,
+}
+
+impl StubServerUdp {
+    #[tracing::instrument(name = "Start running new UDP stub server", level = "debug", skip(stop_event))]
+    pub fn run_new(
+        config: RnpStubServerConfig,
+        stop_event: Arc<ManualResetEvent>,
+        server_started_event: Arc<ManualResetEvent>,
+    ) -> JoinHandle<Result<(), Box<dyn Error + Send + Sync>>> {
+        return tokio::spawn(async move {
+            let mut server = StubServerUdp::new(config, stop_event, server_started_event.clone());
+
+            // In case server started failed, we always signal server started event here to keep it safe.
+            let result = server.run().await;
+            server_started_event.set();
+
+            return result;
+        });
+    }
+
+    #[tracing::instrument(name = "Creating UDP stub server", level = "debug", skip(stop_event))]
+    fn new(config: RnpStubServerConfig, stop_event: Arc<ManualResetEvent>, server_started_event: Arc<ManualResetEvent>) -> StubServerUdp {
+        return StubServerUdp { config: Arc::new(config), stop_event, server_started_event, peer_stats_map: HashMap::new() };
+    }
+
+    #[tracing::instrument(name = "Running UDP stub server loop", level = "debug", skip(self))]
+    async fn run(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let socket = UdpSocket::bind(self.config.server_address).await?;
+        self.server_started_event.set();
+
+        println!("Rnp {} server started successfully at {}.", self.config.protocol, self.config.server_address);
+
+        // Delayed echoes are sent back to this loop when they are due, so the server keeps receiving while waiting.
+        let (delayed_echo_sender, mut delayed_echo_receiver) = mpsc::unbounded_channel::<(SocketAddr, Vec<u8>)>();
+
+        let mut read_buffer = vec![0u8; 65536];
+        let mut next_report_time = Instant::now();
+        loop {
+            tokio::select! {
+                // New datagram arrived.
+                recv_result = socket.recv_from(&mut read_buffer) => {
+                    match recv_result {
+                        Ok((read_size, peer_addr)) => {
+                            self.echo_datagram(&socket, &read_buffer[..read_size], peer_addr, &delayed_echo_sender).await;
+                        },
+
+                        // On some platforms, ICMP errors caused by previous echoes are reported on the next read, such as
+                        // port unreachable when the client has already gone. They are not fatal to the server, so we skip them.
+                        Err(e) => {
+                            tracing::debug!("Failed to receive datagram, skipping: Error = {}", e);
+                        }
+                    }
+                }
+
+                // Delayed echo is due.
+                Some((peer_addr, datagram)) = delayed_echo_receiver.recv() => {
+                    self.send_echo(&socket, &datagram, peer_addr).await;
+                }
+
+                // Report interval reached
+                _ = tokio::time::sleep_until(next_report_time) => {
+                    self.report_and_reset_peer_stats();
+                    next_report_time += self.config.report_interval;
+                }
+
+                // Stopped
+                _ = self.stop_event.wait() => {
+                    break;
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    #[tracing::instrument(name = "Echo datagram", level = "debug", skip(self, socket, datagram, delayed_echo_sender))]
+    async fn echo_datagram(
+        &mut self,
+        socket: &UdpSocket,
+        datagram: &[u8],
+        peer_addr: SocketAddr,
+        delayed_echo_sender: &mpsc::UnboundedSender<(SocketAddr, Vec<u8>)>,
+    ) {
+        let peer_stats = self.peer_stats_map.entry(peer_addr).or_insert_with(|| {
+            println!("New peer found: Remote = {}", peer_addr);
+            StubServerUdpPeerStats::new()
+        });
+        peer_stats.datagrams_read += 1;
+        peer_stats.bytes_read += datagram.len();
+
+        // Each echo is delayed on its own, so a slow echo doesn't hold back the datagrams received after it.
+        if !self.config.sleep_before_write.is_zero() {
+            let sleep_before_write = self.config.sleep_before_write;
+            let delayed_echo_sender = delayed_echo_sender.clone();
+            let datagram = datagram.to_vec();
+            tokio::spawn(async move {
+                tokio::time::sleep(sleep_before_write).await;
+                let _ = delayed_echo_sender.send((peer_addr, datagram));
+            });
+            return;
+        }
+
+        self.send_echo(socket, datagram, peer_addr).await;
+    }
+
+    async fn send_echo(&mut self, socket: &UdpSocket, datagram: &[u8], peer_addr: SocketAddr) {
+        match socket.send_to(datagram, peer_addr).await {
+            // The peer can be cleaned up in the report while its echo is delayed, and the write won't be counted in that case.
+            Ok(n) => {
+                if let Some(peer_stats) = self.peer_stats_map.get_mut(&peer_addr) {
+                    peer_stats.datagrams_write += 1;
+                    peer_stats.bytes_write += n;
+                }
+            }
+            Err(e) => println!("Failed to echo datagram to {}: Error = {}", peer_addr, e),
+        }
+    }
+
+    #[tracing::instrument(name = "Report and reset peer stats", level = "debug", skip(self))]
+    fn report_and_reset_peer_stats(&mut self) {
+        if self.peer_stats_map.is_empty() {
+            return;
+        }
+
+        // Peers are kept for one more round after going idle, so we skip the report if nobody talked to us in this round.
+        if self.peer_stats_map.values().all(|v| v.datagrams_read == 0) {
+            self.peer_stats_map.clear();
+            return;
+        }
+
+        println!("========== Peer Stats ==========");
+        for (peer_addr, peer_stats) in &self.peer_stats_map {
+            let read_bps = peer_stats.bytes_read * 8 * 1000 / (self.config.report_interval.as_millis() as usize);
+            let write_bps = peer_stats.bytes_write * 8 * 1000 / (self.config.report_interval.as_millis() as usize);
+            println!(
+                "{} => Read = {} datagrams, {} bytes ({} bps), Write = {} datagrams, {} bytes ({} bps)",
+                peer_addr, peer_stats.datagrams_read, peer_stats.bytes_read, read_bps, peer_stats.datagrams_write, peer_stats.bytes_write, write_bps
+            );
+        }
+        println!();
+
+        // UDP has no connection, so we treat the peers that are idle for a whole report interval as gone.
+        self.peer_stats_map.retain(|_, v| v.datagrams_read > 0);
+        self.peer_stats_map.values_mut().for_each(|v| v.clear_stats());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct StubServerUdpPeerStats {
+    pub datagrams_read: usize,
+    pub bytes_read: usize,
+    pub datagrams_write: usize,
+    pub bytes_write: usize,
+}
+
+impl StubServerUdpPeerStats {
+    pub fn new() -> StubServerUdpPeerStats {
+        return StubServerUdpPeerStats { datagrams_read: 0, bytes_read: 0, datagrams_write: 0, bytes_write: 0 };
+    }
+
+    pub fn clear_stats(&mut self) {
+        self.datagrams_read = 0;
+        self.bytes_read = 0;
+        self.datagrams_write = 0;
+        self.bytes_write = 0;
+    }
+}
@@ -1,9 +1,15 @@
 mod test_common;
 mod test_mocks;
 
+use futures_core::Stream;
 use futures_intrusive::sync::ManualResetEvent;
 use pretty_assertions::assert_eq;
 use rnp::*;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use test_mocks::*;
@@ -18,7 +24,7 @@ fn ping_with_rnp_core_should_work() {
     let rt = Runtime::new().unwrap();
     rt.block_on(async {
         let stop_event = Arc::new(ManualResetEvent::new(false));
-        let mut rp = PingRunnerCore::new(config, stop_event);
+        let mut rp = PingRunnerCore::new(config, stop_event).unwrap();
         rp.run_warmup_pings().await;
         rp.start_running_normal_pings();
         rp.join().await;
@@ -50,7 +56,7 @@ fn ping_with_rnp_core_stress_should_work() {
     let rt = Runtime::new().unwrap();
     rt.block_on(async move {
         let stop_event = Arc::new(ManualResetEvent::new(false));
-        let mut rp = PingRunnerCore::new(config, stop_event);
+        let mut rp = PingRunnerCore::new(config, stop_event).unwrap();
         rp.run_warmup_pings().await;
         rp.start_running_normal_pings();
         rp.join().await;
@@ -71,7 +77,7 @@ fn ping_with_rnp_core_stop_event_should_work() {
         let stop_event = Arc::new(ManualResetEvent::new(false));
         let stop_event_clone = stop_event.clone();
 
-        let mut rp = PingRunnerCore::new(config, stop_event);
+        let mut rp = PingRunnerCore::new(config, stop_event).unwrap();
         rp.run_warmup_pings().await;
 
         rp.start_running_normal_pings();
@@ -95,7 +101,7 @@ fn ping_with_rnp_core_exit_on_fail_should_work() {
     let rt = Runtime::new().unwrap();
     rt.block_on(async {
         let stop_event = Arc::new(ManualResetEvent::new(false));
-        let mut rp = PingRunnerCore::new(config, stop_event);
+        let mut rp = PingRunnerCore::new(config, stop_event).unwrap();
         rp.run_warmup_pings().await;
         rp.start_running_normal_pings();
         rp.join().await;
@@ -108,17 +114,297 @@ fn ping_with_rnp_core_exit_on_fail_should_work() {
     assert!(failed_ping_result.as_ref().unwrap().is_timed_out || !failed_ping_result.as_ref().unwrap().ping_error.is_empty());
 }
 
+#[test]
+fn ping_with_rnp_core_should_stop_on_consecutive_failures() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 1000, 0, 1);
+    config.worker_scheduler_config.stop_on_consecutive_failures = Some(2);
+
+    // Timeout and ping failure are consecutive, because preparation failure in between is not counted.
+    let stop_reason = run_mock_rnp_core(config);
+    assert_eq!(Some(PingStopReason::ConsecutiveFailures(2)), stop_reason);
+    assert!(actual_ping_results.lock().unwrap().len() < 1000);
+}
+
+#[test]
+fn ping_with_rnp_core_should_stop_on_recovery() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 1000, 0, 1);
+    config.worker_scheduler_config.stop_on_recovery = Some(3);
+
+    let stop_reason = run_mock_rnp_core(config);
+    assert_eq!(Some(PingStopReason::Recovered(3)), stop_reason);
+    assert!(actual_ping_results.lock().unwrap().len() < 1000);
+}
+
+#[test]
+fn ping_with_rnp_core_should_stop_when_duration_elapsed() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 1, 0, 1);
+    config.worker_config.ping_interval = Duration::from_millis(10);
+    config.worker_scheduler_config.ping_until_stopped = true;
+    config.worker_scheduler_config.duration = Some(Duration::from_millis(200));
+
+    let stop_reason = run_mock_rnp_core(config);
+    assert_eq!(Some(PingStopReason::DurationElapsed(Duration::from_millis(200))), stop_reason);
+    assert!(actual_ping_results.lock().unwrap().len() > 1);
+}
+
+#[test]
+fn ping_with_rnp_core_should_issue_pings_at_rate_instead_of_interval() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 20, 0, 2);
+
+    // The pings would take hours if workers still sleep for ping interval.
+    config.worker_config.ping_interval = Duration::from_secs(3600);
+    config.worker_scheduler_config.rate = Some(PingRate { pings_per_second: 200.0 });
+    config.worker_scheduler_config.arrival_distribution = PingArrivalDistribution::Poisson;
+
+    let start_time = std::time::Instant::now();
+    run_mock_rnp_core(config);
+    assert!(start_time.elapsed() < Duration::from_secs(10));
+    assert_eq!(20, actual_ping_results.lock().unwrap().len());
+}
+
+#[test]
+fn ping_with_rnp_core_should_resolve_host_name_target_once_by_default() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let resolve_count = Arc::new(AtomicU32::new(0));
+
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 6, 0, 1);
+    config.worker_config.targets = vec![PingTarget::HostName(String::from("rnp.test"), 443)];
+    config.external_ping_target_resolver = Some(Arc::new(MockPingTargetResolver::new(vec!["10.0.0.1:0".parse().unwrap()], resolve_count.clone())));
+
+    run_mock_rnp_core(config);
+
+    assert_eq!(6, actual_ping_results.lock().unwrap().len());
+    assert_eq!(1, resolve_count.load(Ordering::SeqCst));
+}
+
+#[test]
+fn ping_with_rnp_core_should_resolve_host_name_target_on_every_ping_when_enabled() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let resolve_count = Arc::new(AtomicU32::new(0));
+
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 6, 0, 1);
+    config.worker_config.targets = vec![PingTarget::HostName(String::from("rnp.test"), 443)];
+    config.worker_config.resolve_on_every_ping = true;
+    config.external_ping_target_resolver = Some(Arc::new(MockPingTargetResolver::new(vec!["10.0.0.1:0".parse().unwrap()], resolve_count.clone())));
+
+    run_mock_rnp_core(config);
+
+    assert_eq!(6, actual_ping_results.lock().unwrap().len());
+    assert_eq!(6, resolve_count.load(Ordering::SeqCst));
+}
+
+#[test]
+fn ping_with_rnp_core_should_fail_when_host_name_target_has_no_matching_address() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let resolve_count = Arc::new(AtomicU32::new(0));
+
+    // Source IP is 10.0.0.2, so IPv6 only targets cannot be reached.
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 3, 0, 1);
+    config.worker_config.targets = vec![PingTarget::HostName(String::from("rnp.test"), 443)];
+    config.external_ping_target_resolver = Some(Arc::new(MockPingTargetResolver::new(vec!["[::1]:0".parse().unwrap()], resolve_count.clone())));
+
+    run_mock_rnp_core(config);
+
+    assert_eq!(vec![MockPingClientResult::PingFailed; 3], *actual_ping_results.lock().unwrap());
+    assert_eq!(3, resolve_count.load(Ordering::SeqCst));
+}
+
+#[test]
+fn ping_with_rnp_core_should_spread_pings_across_all_targets() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let actual_ping_targets = Arc::new(Mutex::new(Vec::<SocketAddr>::new()));
+
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 4, 1, 2);
+    config.worker_config.targets =
+        vec!["10.0.0.1:443".parse().unwrap(), "10.0.0.3:443".parse().unwrap(), PingTarget::HostName(String::from("rnp.test"), 443)];
+    config.external_ping_target_resolver =
+        Some(Arc::new(MockPingTargetResolver::new(vec!["10.0.0.4:0".parse().unwrap()], Arc::new(AtomicU32::new(0)))));
+    config.extra_ping_result_processors.push(Box::new(MockPingTargetRecorder::new(actual_ping_targets.clone())));
+
+    run_mock_rnp_core(config);
+
+    // Warmup pings are counted per target as well.
+    let mut actual_ping_targets = actual_ping_targets.lock().unwrap().clone();
+    actual_ping_targets.sort();
+    let mut expected_ping_targets = Vec::<SocketAddr>::new();
+    for target in &["10.0.0.1:443", "10.0.0.3:443", "10.0.0.4:443"] {
+        expected_ping_targets.extend(vec![target.parse::<SocketAddr>().unwrap(); 5]);
+    }
+    assert_eq!(expected_ping_targets, actual_ping_targets);
+}
+
+#[test]
+fn ping_with_rnp_core_as_dto_stream_should_work() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let config = create_mock_rnp_config(actual_ping_results.clone(), 6, 3, 1);
+    let rt = Runtime::new().unwrap();
+    let (ping_result_dtos, stop_reason) = rt.block_on(async {
+        let (mut stream, handle) = PingResultStream::start_with_dtos(config).unwrap();
+
+        let mut ping_result_dtos = Vec::new();
+        while let Some(ping_result_dto) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            ping_result_dtos.push(ping_result_dto);
+        }
+
+        (ping_result_dtos, handle.join().await.unwrap())
+    });
+
+    assert_eq!(None, stop_reason);
+    assert_eq!(9, ping_result_dtos.len());
+    assert_eq!(3, ping_result_dtos.iter().filter(|dto| dto.is_warmup).count());
+    assert_eq!(
+        vec![true, false, false, true, false, false, false, true, true],
+        ping_result_dtos.iter().map(|dto| dto.is_succeeded).collect::<Vec<bool>>()
+    );
+}
+
+#[test]
+fn ping_with_rnp_core_as_ping_result_stream_should_stop_with_handle() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 1000, 0, 1);
+    config.worker_config.ping_interval = Duration::from_millis(10);
+
+    let rt = Runtime::new().unwrap();
+    let (ping_results, stop_reason) = rt.block_on(async {
+        let (mut stream, handle) = PingResultStream::start_with_ping_results(config).unwrap();
+
+        let mut ping_results = Vec::new();
+        while let Some(ping_result) = stream.next().await {
+            ping_results.push(ping_result.unwrap());
+            if ping_results.len() == 3 {
+                handle.stop();
+            }
+        }
+
+        (ping_results, handle.join().await.unwrap())
+    });
+
+    assert_eq!(None, stop_reason);
+    assert!(ping_results.len() >= 3 && ping_results.len() < 1000);
+    assert!(ping_results[0].is_succeeded());
+    assert!(ping_results[1].is_timed_out());
+    assert!(ping_results[2].is_preparation_error());
+}
+
+#[test]
+fn creating_rnp_core_with_invalid_log_path_should_fail() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 6, 0, 1);
+
+    // The log folder cannot be created, since a file with the same name exists.
+    config.result_processor_config.csv_log_path = Some(PathBuf::from("Cargo.toml/log.csv"));
+
+    let rt = Runtime::new().unwrap();
+    let result = rt.block_on(async { PingRunnerCore::new(config, Arc::new(ManualResetEvent::new(false))).map(|_| ()) });
+    assert!(matches!(result, Err(RnpError::LogFileCreationFailed { .. })));
+    assert!(actual_ping_results.lock().unwrap().is_empty());
+}
+
+#[test]
+fn creating_rnp_core_with_unsupported_protocol_should_fail() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 6, 0, 1);
+    config.worker_config.protocol = RnpSupportedProtocol::External(String::from("ICMP"));
+    config.ping_client_registry.register("ICMP", |_, _| None);
+
+    let rt = Runtime::new().unwrap();
+    let result = rt.block_on(async { PingRunnerCore::new(config, Arc::new(ManualResetEvent::new(false))).map(|_| ()) });
+    assert!(matches!(result, Err(RnpError::UnsupportedProtocol(protocol)) if protocol == "ICMP"));
+}
+
+#[test]
+fn ping_with_registered_external_protocol_should_work() {
+    test_common::initialize();
+
+    let actual_ping_results = Arc::new(Mutex::new(Vec::<MockPingClientResult>::new()));
+    let mut config = create_mock_rnp_config(actual_ping_results.clone(), 4, 0, 1);
+    config.worker_config.protocol = "mock".parse().unwrap();
+    config.worker_config.ping_client_config.protocol_options.insert(String::from("mode"), String::from("success"));
+
+    let created_client_count = Arc::new(AtomicU32::new(0));
+    let created_client_count_in_factory = created_client_count.clone();
+    config.ping_client_registry.register("MOCK", move |_, config| {
+        if config.protocol_options.get("mode").map(|mode| mode.as_str()) != Some("success") {
+            return None;
+        }
+
+        created_client_count_in_factory.fetch_add(1, Ordering::SeqCst);
+        return Some(Box::new(MockPingClient::new(config, vec![MockPingClientResult::Success(Duration::from_millis(10))])));
+    });
+
+    assert_eq!(None, run_mock_rnp_core(config));
+    assert_eq!(vec![MockPingClientResult::Success(Duration::from_millis(10)); 4], *actual_ping_results.lock().unwrap());
+    assert!(created_client_count.load(Ordering::SeqCst) > 0);
+}
+
+fn run_mock_rnp_core(config: RnpPingRunnerConfig) -> Option<PingStopReason> {
+    let rt = Runtime::new().unwrap();
+    return rt.block_on(async {
+        let stop_event = Arc::new(ManualResetEvent::new(false));
+        let mut rp = PingRunnerCore::new(config, stop_event).unwrap();
+        rp.run_warmup_pings().await;
+        rp.start_running_normal_pings();
+        rp.join().await;
+        rp.stop_reason()
+    });
+}
+
 fn create_mock_rnp_config(
     actual_ping_results: Arc<Mutex<Vec<MockPingClientResult>>>,
     ping_count: u32,
     warmup_count: u32,
     parallel_ping_count: u32,
 ) -> RnpPingRunnerConfig {
+    // Replace the built-in TCP ping client with the mock one.
+    let mut ping_client_registry = PingClientRegistry::new();
+    ping_client_registry.register("TCP", |_, config| {
+        Some(Box::new(MockPingClient::new(
+            config,
+            vec![
+                MockPingClientResult::Success(Duration::from_micros(12345)),
+                MockPingClientResult::Timeout,
+                MockPingClientResult::PreparationFailed,
+                MockPingClientResult::PingFailed,
+                MockPingClientResult::AppHandshakeFailed(Duration::from_micros(23456)),
+                MockPingClientResult::DisconnectFailed(Duration::from_micros(34567)),
+            ],
+        )))
+    });
+
     RnpPingRunnerConfig {
         worker_config: PingWorkerConfig {
             protocol: RnpSupportedProtocol::TCP,
-            target: "10.0.0.1:443".parse().unwrap(),
+            targets: vec!["10.0.0.1:443".parse().unwrap()],
             source_ip: "10.0.0.2".parse().unwrap(),
+            resolve_on_every_ping: false,
             ping_interval: Duration::from_millis(0),
             ping_client_config: PingClientConfig {
                 wait_timeout: Duration::from_millis(1000),
@@ -130,13 +416,27 @@ fn create_mock_rnp_config(
                 log_tls_key: false,
                 alpn_protocol: None,
                 use_timer_rtt: false,
+                http_method: String::from("GET"),
+                http_path: String::from("/"),
+                http_headers: vec![],
+                http_expected_status: None,
+                http_body_regex: None,
+                measure_one_way_latency: false,
+                use_persistent_connection: false,
+                protocol_options: BTreeMap::new(),
             },
         },
         worker_scheduler_config: PingWorkerSchedulerConfig {
             source_ports: PortRangeList { ranges: vec![(1024..=2048)] },
             ping_count: Some(ping_count),
+            ping_until_stopped: false,
             warmup_count,
             parallel_ping_count,
+            duration: None,
+            stop_on_consecutive_failures: None,
+            stop_on_recovery: None,
+            rate: None,
+            arrival_distribution: PingArrivalDistribution::Constant,
         },
         result_processor_config: PingResultProcessorConfig {
             common_config: PingResultProcessorCommonConfig { quiet_level: RNP_QUIET_LEVEL_NONE },
@@ -145,23 +445,16 @@ fn create_mock_rnp_config(
             csv_log_path: None,
             json_log_path: None,
             text_log_path: None,
+            html_report_path: None,
+            metrics_address: None,
             show_result_scatter: false,
             show_latency_scatter: false,
+            show_dashboard: false,
             latency_buckets: None,
+            latency_percentiles: None,
         },
-        external_ping_client_factory: Some(|_, config| {
-            Some(Box::new(MockPingClient::new(
-                config,
-                vec![
-                    MockPingClientResult::Success(Duration::from_micros(12345)),
-                    MockPingClientResult::Timeout,
-                    MockPingClientResult::PreparationFailed,
-                    MockPingClientResult::PingFailed,
-                    MockPingClientResult::AppHandshakeFailed(Duration::from_micros(23456)),
-                    MockPingClientResult::DisconnectFailed(Duration::from_micros(34567)),
-                ],
-            )))
-        }),
+        ping_client_registry,
+        external_ping_target_resolver: None,
         extra_ping_result_processors: vec![Box::new(MockPingResultProcessor::new(actual_ping_results))],
     }
 }
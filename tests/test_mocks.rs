@@ -1,10 +1,11 @@
 use async_trait::async_trait;
 use rnp::{
     PingClient, PingClientConfig, PingClientError, PingClientPingResultDetails, PingClientResult, PingClientWarning, PingResult, PingResultProcessor,
-    PingResultProcessorCommonConfig,
+    PingResultProcessorCommonConfig, PingTargetResolver,
 };
 use std::io;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -86,6 +87,25 @@ impl PingClient for MockPingClient {
     }
 }
 
+pub struct MockPingTargetResolver {
+    mock_addresses: Vec<SocketAddr>,
+    resolve_count: Arc<AtomicU32>,
+}
+
+impl MockPingTargetResolver {
+    pub fn new(mock_addresses: Vec<SocketAddr>, resolve_count: Arc<AtomicU32>) -> MockPingTargetResolver {
+        return MockPingTargetResolver { mock_addresses, resolve_count };
+    }
+}
+
+#[async_trait]
+impl PingTargetResolver for MockPingTargetResolver {
+    async fn resolve(&self, _: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        self.resolve_count.fetch_add(1, Ordering::SeqCst);
+        return Ok(self.mock_addresses.iter().map(|address| SocketAddr::new(address.ip(), port)).collect());
+    }
+}
+
 pub struct MockPingResultProcessor {
     common_config: Arc<PingResultProcessorCommonConfig>,
     results: Arc<Mutex<Vec<MockPingClientResult>>>,
@@ -127,6 +147,7 @@ impl PingResultProcessor for MockPingResultProcessor {
             match warning {
                 PingClientWarning::AppHandshakeFailed(_) => results.push(MockPingClientResult::AppHandshakeFailed(ping_result.round_trip_time())),
                 PingClientWarning::DisconnectFailed(_) => results.push(MockPingClientResult::DisconnectFailed(ping_result.round_trip_time())),
+                PingClientWarning::Reconnected(_) => panic!("Mock ping client never reconnects."),
             }
             return;
         }
@@ -139,3 +160,28 @@ impl PingResultProcessor for MockPingResultProcessor {
         println!("Ping runner shutting down. {} result received!", results.len());
     }
 }
+
+pub struct MockPingTargetRecorder {
+    common_config: Arc<PingResultProcessorCommonConfig>,
+    targets: Arc<Mutex<Vec<SocketAddr>>>,
+}
+
+impl MockPingTargetRecorder {
+    pub fn new(targets: Arc<Mutex<Vec<SocketAddr>>>) -> MockPingTargetRecorder {
+        return MockPingTargetRecorder { common_config: Arc::new(PingResultProcessorCommonConfig { quiet_level: 0 }), targets };
+    }
+}
+
+impl PingResultProcessor for MockPingTargetRecorder {
+    fn name(&self) -> &'static str {
+        return "MockPingTargetRecorder";
+    }
+
+    fn config(&self) -> &PingResultProcessorCommonConfig {
+        self.common_config.as_ref()
+    }
+
+    fn process_ping_result(&mut self, ping_result: &PingResult) {
+        self.targets.lock().unwrap().push(ping_result.target());
+    }
+}